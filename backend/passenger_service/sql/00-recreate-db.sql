@@ -3,4 +3,6 @@ DROP DATABASE IF EXISTS passenger_service_db;
 DROP USER IF EXISTS passenger_service_user;
 
 CREATE USER passenger_service_user;
-CREATE DATABASE passenger_service_db ENCODING = 'UTF-8';
\ No newline at end of file
+CREATE DATABASE passenger_service_db ENCODING = 'UTF-8';
+-- to create the extensions of the schema (pg_trgm)
+GRANT CREATE ON DATABASE passenger_service_db TO passenger_service_user;
@@ -2,7 +2,171 @@
 CREATE TABLE passenger (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     uid UUID NOT NULL,
+    tenant_id UUID,
     first_name STRING NOT NULL,
     last_name STRING NOT NULL,
-    status STRING
-);
\ No newline at end of file
+    status STRING,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_by UUID NOT NULL,
+    -- first and last names, lowercase and without accents, for the search
+    first_name_search STRING NOT NULL DEFAULT '',
+    last_name_search STRING NOT NULL DEFAULT '',
+    -- names as in a passport MRZ (ICAO 9303), e.g. MUELLER<<JOHN, for the matching
+    name_key STRING NOT NULL DEFAULT '',
+    -- profile: gender F, M or X, nationality ISO 3166-1 alpha-3, phone E.164
+    date_of_birth DATE,
+    gender STRING,
+    nationality STRING,
+    email STRING,
+    phone STRING,
+    -- soft delete: set when merged into another passenger (merged_into), the row is then hidden
+    deleted_at TIMESTAMPTZ,
+    merged_into UUID
+);
+CREATE INDEX passenger_updated_at_idx ON passenger (updated_at);
+CREATE INDEX passenger_uid_idx ON passenger (uid);
+CREATE INDEX passenger_tenant_id_idx ON passenger (tenant_id);
+CREATE INDEX passenger_name_key_idx ON passenger (name_key);
+-- trigram indexes, for the substring (LIKE) and similarity (%) search
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX passenger_first_name_search_idx ON passenger USING GIN (first_name_search gin_trgm_ops);
+CREATE INDEX passenger_last_name_search_idx ON passenger USING GIN (last_name_search gin_trgm_ops);
+
+-- passenger_merge (audit of the merges, merged_ids is comma separated)
+-- sources is the passenger each merged field comes from, passengers the rows before the merge
+CREATE TABLE passenger_merge (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    survivor_id UUID NOT NULL,
+    merged_ids STRING NOT NULL,
+    sources JSONB NOT NULL,
+    passengers JSONB NOT NULL,
+    merged_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    merged_by UUID NOT NULL
+);
+CREATE INDEX passenger_merge_survivor_id_idx ON passenger_merge (survivor_id);
+
+-- passenger_document (travel documents, moved to the survivor of a merge)
+CREATE TABLE passenger_document (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    passenger_id UUID NOT NULL REFERENCES passenger (id) ON DELETE CASCADE,
+    doc_type STRING NOT NULL,
+    number STRING NOT NULL,
+    issuing_country STRING NOT NULL,
+    expiry_date DATE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_by UUID NOT NULL
+);
+CREATE INDEX passenger_document_passenger_id_idx ON passenger_document (passenger_id);
+CREATE INDEX passenger_document_number_idx ON passenger_document (number, issuing_country);
+
+-- api_key (secrets are only stored as salted hashes)
+CREATE TABLE api_key (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name STRING NOT NULL,
+    prefix STRING NOT NULL UNIQUE,
+    key_hash STRING NOT NULL,
+    salt STRING NOT NULL,
+    scopes STRING NOT NULL,
+    tenant_id UUID,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL
+);
+
+-- users (passwords are only stored as argon2 hashes)
+CREATE TABLE users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    username STRING NOT NULL UNIQUE,
+    pwd_hash STRING NOT NULL,
+    tenant_id UUID,
+    roles STRING NOT NULL DEFAULT 'user',
+    failed_logins INT8 NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- user_session (one per login, holds the hash of the current refresh token)
+CREATE TABLE user_session (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id),
+    refresh_hash STRING NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    refreshed_at TIMESTAMPTZ
+);
+CREATE INDEX user_session_user_id_idx ON user_session (user_id);
+-- user_session_superseded (the hashes of the refresh tokens rotated out, a reuse revokes the session)
+CREATE TABLE user_session_superseded (
+    refresh_hash STRING PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES user_session (id) ON DELETE CASCADE,
+    rotated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+-- revoked_tokens (access token ids, or session ids for all the access tokens of a session)
+-- kept until the tokens would have expired anyway
+CREATE TABLE revoked_tokens (
+    jti STRING PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX revoked_tokens_expires_at_idx ON revoked_tokens (expires_at);
+
+-- idempotency_key (first response of a request with an Idempotency-Key header, per user)
+-- response is NULL while the first request is in progress
+CREATE TABLE idempotency_key (
+    user_id STRING NOT NULL,
+    request_key STRING NOT NULL,
+    request_hash STRING NOT NULL,
+    status INT8,
+    response STRING,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, request_key)
+);
+CREATE INDEX idempotency_key_expires_at_idx ON idempotency_key (expires_at);
+
+-- outbox (passenger events, written in the transaction of the change)
+-- dispatched_at is set once the event is fanned out to the webhook deliveries
+CREATE TABLE outbox (
+    id BIGSERIAL PRIMARY KEY,
+    event_type STRING NOT NULL,
+    aggregate_id UUID NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    dispatched_at TIMESTAMPTZ
+);
+CREATE INDEX outbox_pending_idx ON outbox (id) WHERE dispatched_at IS NULL;
+
+-- webhook (receivers of the outbox events, event_types is comma separated, empty for all)
+CREATE TABLE webhook (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    url STRING NOT NULL,
+    secret STRING NOT NULL,
+    event_types STRING NOT NULL DEFAULT '',
+    active BOOL NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL
+);
+
+-- webhook_delivery (one per event and webhook, the delivery log)
+-- status is pending until delivered, or dead after the last failed attempt
+CREATE TABLE webhook_delivery (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id UUID NOT NULL REFERENCES webhook (id) ON DELETE CASCADE,
+    outbox_id INT8 NOT NULL REFERENCES outbox (id),
+    status STRING NOT NULL DEFAULT 'pending',
+    attempts INT8 NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_status_code INT8,
+    last_error STRING,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    delivered_at TIMESTAMPTZ,
+    UNIQUE (webhook_id, outbox_id)
+);
+CREATE INDEX webhook_delivery_due_idx ON webhook_delivery (status, next_attempt_at);
@@ -1,3 +1,7 @@
 -- Dev seed (dummy data for development)
-INSERT INTO passenger_service_db.passenger (id, uid, first_name, last_name, status) VALUES ('4208b168-08b2-4c45-915d-c51f6f71213b', '4464cab1-74da-45c1-bcec-d9e668175ec0', 'Passenger 100', '100', 'new');
-INSERT INTO passenger_service_db.passenger (id, uid, first_name, last_name) VALUES ('b03535ad-0b98-4c8f-8b5a-66960c71392c', '7bb0d513-6c69-49bb-9b1f-9bf456467f88', 'Passenger 101', '101');
+INSERT INTO passenger_service_db.passenger (id, uid, first_name, last_name, status, created_at, created_by, updated_at, updated_by, first_name_search, last_name_search, name_key) VALUES ('4208b168-08b2-4c45-915d-c51f6f71213b', '4464cab1-74da-45c1-bcec-d9e668175ec0', 'Passenger 100', '100', 'new', '2023-04-01 10:00:00+00', '4464cab1-74da-45c1-bcec-d9e668175ec0', '2023-04-01 10:00:00+00', '4464cab1-74da-45c1-bcec-d9e668175ec0', 'passenger 100', '100', '100<<PASSENGER<100');
+INSERT INTO passenger_service_db.passenger (id, uid, first_name, last_name, created_at, created_by, updated_at, updated_by, first_name_search, last_name_search, name_key) VALUES ('b03535ad-0b98-4c8f-8b5a-66960c71392c', '7bb0d513-6c69-49bb-9b1f-9bf456467f88', 'Passenger 101', '101', '2023-04-02 10:00:00+00', '7bb0d513-6c69-49bb-9b1f-9bf456467f88', '2023-04-02 10:00:00+00', '7bb0d513-6c69-49bb-9b1f-9bf456467f88', 'passenger 101', '101', '101<<PASSENGER<101');
+
+-- Dev users, password 'welcome'
+INSERT INTO passenger_service_db.users (id, username, pwd_hash, roles) VALUES ('3cb430d0-8914-4c71-aaf9-0ed2b163eca6', 'admin', '$argon2id$v=19$m=19456,t=2,p=1$qJK2tOHBFGtsySB+GBoELA$4K7za6A3rCsYSp2WRicg1lXOZZuebEA7OhhCQhqLb0U', 'admin');
+INSERT INTO passenger_service_db.users (id, username, pwd_hash, roles) VALUES ('4464cab1-74da-45c1-bcec-d9e668175ec0', 'demo', '$argon2id$v=19$m=19456,t=2,p=1$qJK2tOHBFGtsySB+GBoELA$4K7za6A3rCsYSp2WRicg1lXOZZuebEA7OhhCQhqLb0U', 'user');
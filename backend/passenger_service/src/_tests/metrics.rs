@@ -0,0 +1,46 @@
+use super::{metrics, route_label};
+use crate::model::{init_db, PassengerDao, PassengerFilter};
+use crate::security::utx_from_token;
+
+#[test]
+fn metrics_route_label() {
+    assert_eq!("/api/passengers", route_label("/api/passengers", 200));
+    assert_eq!(
+        "/api/passengers/{id}",
+        route_label("/api/passengers/4208b168-08b2-4c45-915d-c51f6f71213b", 200)
+    );
+    assert_eq!("/api/auth/sessions/{id}", route_label("/api/auth/sessions/xyz", 400));
+    assert_eq!("/docs", route_label("/docs/swagger-ui.css", 200));
+    assert_eq!("/static", route_label("/app-bundle.js", 200));
+    assert_eq!("/", route_label("/", 200));
+    assert_eq!("/readyz", route_label("/readyz", 503));
+    assert_eq!("unmatched", route_label("/api/passengers/4208b168/whatever", 404));
+}
+
+#[tokio::test]
+async fn metrics_gather() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
+    metrics().auth_failures.with_label_values(&["token_expired"]).inc();
+
+    // -- ACTION
+    let text = metrics().gather(&db).await;
+
+    // -- CHECK
+    assert!(
+        text.contains(
+            r#"passenger_service_db_query_duration_seconds_count{operation="passenger.list",outcome="ok"} 1"#
+        ),
+        "{}",
+        text
+    );
+    assert!(text.contains(r#"passenger_service_auth_failures_total{reason="token_expired"} 1"#));
+    assert!(text.contains("passenger_service_db_pool_connections "));
+    assert!(text.contains("passenger_service_db_pool_wait_seconds "));
+    assert!(text.contains("passenger_service_http_requests_in_flight 0"));
+    assert!(text.contains("passenger_service_process_resident_memory_bytes "));
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use super::{Error, Passenger};
+use crate::web::WebErrorMessage;
+use chrono::Utc;
+use sqlx::types::Uuid;
+use warp::http::StatusCode;
+
+#[test]
+fn model_error_from_sqlx() {
+    let error = Error::from(sqlx::Error::PoolTimedOut);
+    assert!(matches!(error, Error::Sqlx(sqlx::Error::PoolTimedOut)));
+    // transparent, the message is the one of sqlx
+    assert_eq!(sqlx::Error::PoolTimedOut.to_string(), error.to_string());
+    let rejection = warp::Rejection::from(error);
+    let message = rejection.find::<WebErrorMessage>().expect("a web error message");
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, message.status);
+}
+
+#[test]
+fn model_error_from_io() {
+    let error = Error::from(std::io::Error::new(std::io::ErrorKind::NotFound, "no sql file"));
+    assert!(matches!(&error, Error::Io(io) if io.kind() == std::io::ErrorKind::NotFound));
+    assert_eq!("no sql file", error.to_string());
+}
+
+// region: Test Utils
+/// A passenger not stored, of a random user, for the tests of the model and the web.
+pub(crate) fn passenger_fx(first_name: &str, last_name: &str) -> Passenger {
+    let uid = Uuid::from_u128(rand::random());
+    Passenger {
+        id: Uuid::from_u128(rand::random()),
+        uid,
+        tenant_id: None,
+        first_name: first_name.to_string(),
+        last_name: last_name.to_string(),
+        status: None,
+        date_of_birth: None,
+        gender: None,
+        nationality: None,
+        email: None,
+        phone: None,
+        created_at: Utc::now(),
+        created_by: uid,
+        updated_at: Utc::now(),
+        updated_by: uid,
+    }
+}
+// endregion: Test Utils
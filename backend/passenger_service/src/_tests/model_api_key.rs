@@ -0,0 +1,79 @@
+use super::{ApiKeyDao, ApiKeyForCreate};
+use crate::model::db::init_db;
+use crate::security::{utx_from_token, Permission};
+use chrono::{Duration, Utc};
+
+#[tokio::test]
+async fn model_api_key_create_and_auth() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data_fx = ApiKeyForCreate {
+        name: "test - model_api_key_create_and_auth".to_string(),
+        scopes: vec![Permission::PassengerRead],
+        expires_at: None,
+    };
+    // -- ACTION
+    let created = ApiKeyDao::create(&db, &utx, data_fx).await?;
+    let key_utx = utx_from_token(&db, &created.token).await?;
+    // -- CHECK
+    assert!(created.token.starts_with(&format!("psk_{}_", created.api_key.prefix)));
+    assert!(
+        !created.api_key.key_hash.contains(&created.token[13..]),
+        "secret stored in clear"
+    );
+    assert_eq!(created.api_key.id.to_string(), key_utx.user_id);
+    assert!(key_utx.has_permission(Permission::PassengerRead));
+    assert!(!key_utx.has_permission(Permission::PassengerWrite));
+    let api_keys = ApiKeyDao::list(&db, &utx).await?;
+    assert_eq!(1, api_keys.len());
+    assert!(api_keys[0].last_used_at.is_some(), "last_used_at should be set");
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_api_key_revoked_and_expired() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let revoked = ApiKeyDao::create(
+        &db,
+        &utx,
+        ApiKeyForCreate {
+            name: "test - revoked".to_string(),
+            scopes: vec![Permission::PassengerRead],
+            expires_at: None,
+        },
+    )
+    .await?;
+    let expired = ApiKeyDao::create(
+        &db,
+        &utx,
+        ApiKeyForCreate {
+            name: "test - expired".to_string(),
+            scopes: vec![Permission::PassengerRead],
+            expires_at: Some(Utc::now() - Duration::minutes(1)),
+        },
+    )
+    .await?;
+    // -- ACTION
+    ApiKeyDao::revoke(&db, &utx, revoked.api_key.id.to_string()).await?;
+    let wrong_secret = format!("{}x", &revoked.token[..revoked.token.len() - 1]);
+    // -- CHECK
+    assert!(
+        utx_from_token(&db, &revoked.token).await.is_err(),
+        "revoked key accepted"
+    );
+    assert!(
+        utx_from_token(&db, &expired.token).await.is_err(),
+        "expired key accepted"
+    );
+    assert!(
+        utx_from_token(&db, &wrong_secret).await.is_err(),
+        "wrong secret accepted"
+    );
+    assert!(ApiKeyDao::revoke(&db, &utx, revoked.api_key.id.to_string())
+        .await
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use super::{apply, change_from_changefeed, install_notify_trigger, Change, ChangeFeedMode, CHANNEL};
+use crate::model::db::{app_db_url, init_db};
+use crate::model::{passenger_events, PassengerEventKind};
+use serde_json::json;
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+
+#[tokio::test]
+async fn model_change_feed_notify() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    install_notify_trigger(&db).await?;
+    let mut listener = PgListener::connect(&app_db_url()).await?;
+    listener.listen(CHANNEL).await?;
+    let (_, mut events) = passenger_events().subscribe(None);
+
+    // -- ACTION - a passenger inserted by another instance
+    let id: (String,) = sqlx::query_as(
+        "INSERT INTO passenger (uid, first_name, last_name, created_by, updated_by) \
+         VALUES ($1::uuid, 'Ada', 'Lovelace', $1::uuid, $1::uuid) RETURNING id::text",
+    )
+    .bind("f7a25ba8-fc87-4b6f-9297-611921ef0d7a")
+    .fetch_one(&db)
+    .await?;
+    let notification = tokio::time::timeout(Duration::from_secs(5), listener.recv()).await??;
+    let change: Change = serde_json::from_str(notification.payload())?;
+    apply(&db, change).await;
+
+    // -- CHECK - published locally (other tests may publish too)
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await??;
+        if event.passenger.id.to_string() == id.0 {
+            assert_eq!(PassengerEventKind::Created, event.kind);
+            assert_eq!("Lovelace", event.passenger.last_name);
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn model_change_feed_changefeed_value() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let passenger = json!({
+        "id": "4208b168-08b2-4c45-915d-c51f6f71213b",
+        "uid": "2096036b-9606-4405-995b-565a481344bc",
+        "tenant_id": null,
+        "first_name": "John",
+        "last_name": "Doe",
+        "status": null,
+        "created_at": "2023-04-01T10:00:00.123456+00:00",
+        "created_by": "2096036b-9606-4405-995b-565a481344bc",
+        "updated_at": "2023-04-01T10:00:00.123456+00:00",
+        "updated_by": "2096036b-9606-4405-995b-565a481344bc",
+    });
+    let value = |before: &serde_json::Value, after: &serde_json::Value| {
+        json!({ "before": before, "after": after }).to_string().into_bytes()
+    };
+    let null = serde_json::Value::Null;
+    // -- ACTION
+    let created = change_from_changefeed(&value(&null, &passenger));
+    let updated = change_from_changefeed(&value(&passenger, &passenger));
+    let deleted = change_from_changefeed(&value(&passenger, &null));
+    let wrong = change_from_changefeed(b"not json");
+    // -- CHECK
+    let op = |change: Option<Change>| change.map(|change| change.op);
+    assert_eq!(Some("insert".to_string()), op(created));
+    assert_eq!(Some("update".to_string()), op(updated));
+    let deleted = deleted.ok_or("deleted")?;
+    assert_eq!("delete", deleted.op);
+    assert_eq!("4208b168-08b2-4c45-915d-c51f6f71213b", deleted.id);
+    assert!(wrong.is_none());
+    Ok(())
+}
+
+#[test]
+fn model_change_feed_mode_parse() {
+    assert_eq!(Ok(ChangeFeedMode::Off), "off".parse());
+    assert_eq!(Ok(ChangeFeedMode::Notify), "notify".parse());
+    assert_eq!(Ok(ChangeFeedMode::Changefeed), "changefeed".parse());
+    assert!("listen".parse::<ChangeFeedMode>().is_err());
+}
@@ -1,4 +1,5 @@
-use super::init_db;
+use super::{init_db, retry_with_backoff};
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn model_db_init_db() -> Result<(), Box<dyn std::error::Error>> {
@@ -10,3 +11,42 @@ async fn model_db_init_db() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(2, result.len(), "Expected 2 passengers in db");
     Ok(())
 }
+
+#[tokio::test]
+async fn model_db_retry_with_backoff_ok() -> Result<(), Box<dyn std::error::Error>> {
+    // FIXTURE - fails twice, then succeeds
+    let mut attempts = 0;
+    // ACTION
+    let result = retry_with_backoff(Duration::from_secs(5), Duration::from_millis(10), || {
+        attempts += 1;
+        let attempt = attempts;
+        async move {
+            match attempt {
+                1 | 2 => Err("connection refused"),
+                _ => Ok(attempt),
+            }
+        }
+    })
+    .await;
+    // CHECK
+    assert_eq!(Ok(3), result);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_db_retry_with_backoff_deadline() -> Result<(), Box<dyn std::error::Error>> {
+    // FIXTURE
+    let started_at = Instant::now();
+    let mut attempts = 0;
+    // ACTION
+    let result: Result<(), &str> = retry_with_backoff(Duration::from_millis(100), Duration::from_millis(20), || {
+        attempts += 1;
+        async { Err("connection refused") }
+    })
+    .await;
+    // CHECK - 20 + 40 ms of backoff, the next 80 ms would pass the deadline
+    assert_eq!(Err("connection refused"), result);
+    assert_eq!(3, attempts);
+    assert!(started_at.elapsed() < Duration::from_millis(100));
+    Ok(())
+}
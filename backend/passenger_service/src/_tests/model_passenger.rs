@@ -1,7 +1,12 @@
-use super::{Passenger, PassengerDao, PassengerPatch};
+use super::{
+    DuplicateFilter, Passenger, PassengerDao, PassengerFilter, PassengerMergeRequest, PassengerMrzRequest,
+    PassengerPatch, PassengerSearch,
+};
 use crate::model;
 use crate::model::db::init_db;
-use crate::security::utx_from_token;
+use crate::model::OutboxDao;
+use crate::security::{utx_from_token, Role, UserCtx};
+use chrono::NaiveDate;
 
 #[tokio::test]
 async fn model_passenger_create() -> Result<(), Box<dyn std::error::Error>> {
@@ -23,6 +28,147 @@ async fn model_passenger_create() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn model_passenger_create_normalized() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data = |first_name: &str| PassengerPatch {
+        first_name: Some(first_name.to_string()),
+        last_name: Some(" Mu\u{308}ller ".to_string()),
+        ..Default::default()
+    };
+    // -- ACTION
+    let passenger = PassengerDao::create(&db, &utx, data("  Jean   Luc ")).await?;
+    let control = PassengerDao::create(&db, &utx, data("Jean\u{7}")).await;
+    let bulk = PassengerDao::create_bulk(&db, &utx, vec![data("Ada"), data(" ")]).await;
+    // -- CHECK
+    assert_eq!(
+        ("Jean Luc", "Müller"),
+        (passenger.first_name.as_str(), passenger.last_name.as_str())
+    );
+    let (name_key,): (String,) = sqlx::query_as("SELECT name_key FROM passenger WHERE id = $1")
+        .bind(passenger.id)
+        .fetch_one(&db)
+        .await?;
+    assert_eq!("MUELLER<<JEAN<LUC", name_key);
+    assert!(matches!(control, Err(model::Error::Invalid("first_name", _))));
+    assert!(matches!(bulk, Err(model::Error::Invalid("first_name", reason)) if reason.starts_with("passenger 1 ")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_create_quoted() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data = PassengerPatch {
+        first_name: Some("Dara".to_string()),
+        last_name: Some("O'Brien".to_string()),
+        ..Default::default()
+    };
+    // -- ACTION
+    let passenger = PassengerDao::create(&db, &utx, data).await?;
+    let found = PassengerDao::get(&db, &utx, passenger.id.to_string()).await?;
+    let updated = PassengerDao::update(
+        &db,
+        &utx,
+        passenger.id.to_string(),
+        PassengerPatch {
+            first_name: Some("D'Arcy".to_string()),
+            last_name: Some("O'Brien-Ng".to_string()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    // -- CHECK
+    assert_eq!("O'Brien", found.last_name);
+    assert_eq!(
+        ("D'Arcy", "O'Brien-Ng"),
+        (updated.first_name.as_str(), updated.last_name.as_str())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_create_profile() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data = PassengerPatch {
+        first_name: Some("test - model_passenger_create_profile".to_string()),
+        date_of_birth: NaiveDate::from_ymd_opt(1980, 2, 29),
+        gender: Some("f".to_string()),
+        nationality: Some("fra".to_string()),
+        email: Some("Jane.Doe@Example.COM".to_string()),
+        phone: Some("+33 6 12 34 56 78".to_string()),
+        ..Default::default()
+    };
+    // -- ACTION
+    let passenger = PassengerDao::create(&db, &utx, data.clone()).await?;
+    let invalid = PassengerDao::create(
+        &db,
+        &utx,
+        PassengerPatch {
+            phone: Some("06 12 34 56 78".to_string()),
+            ..data
+        },
+    )
+    .await;
+    // -- CHECK
+    let passenger = PassengerDao::get(&db, &utx, passenger.id.to_string()).await?;
+    assert_eq!(NaiveDate::from_ymd_opt(1980, 2, 29), passenger.date_of_birth);
+    assert_eq!(Some("F"), passenger.gender.as_deref());
+    assert_eq!(Some("FRA"), passenger.nationality.as_deref());
+    assert_eq!(Some("Jane.Doe@example.com"), passenger.email.as_deref());
+    assert_eq!(Some("+33612345678"), passenger.phone.as_deref());
+    assert!(matches!(invalid, Err(model::Error::Invalid("phone", _))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_create_with_tenant() -> Result<(), Box<dyn std::error::Error>> {
+    // FIXTURE
+    let db = init_db().await?;
+    let utx = UserCtx::new(
+        "f7a25ba8-fc87-4b6f-9297-611921ef0d7a".to_string(),
+        Some("8f5c3a5e-3d4b-4b8e-9a36-5e1c2f0b7d21".to_string()),
+        vec![Role::User],
+    );
+    let data_fx = PassengerPatch {
+        first_name: Some("test - model_passenger_create_with_tenant 1".to_string()),
+        ..Default::default()
+    };
+    // ACTION
+    let passenger_created = PassengerDao::create(&db, &utx, data_fx).await?;
+    // CHECK
+    assert_eq!(utx.tenant_id, passenger_created.tenant_id.map(|id| id.to_string()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_create_bulk() -> Result<(), Box<dyn std::error::Error>> {
+    // FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data_fx: Vec<PassengerPatch> = (1..=3)
+        .map(|i| PassengerPatch {
+            first_name: Some(format!("test - model_passenger_create_bulk {}", i)),
+            ..Default::default()
+        })
+        .collect();
+    // ACTION
+    let passengers = PassengerDao::create_bulk(&db, &utx, data_fx).await?;
+    // CHECK
+    assert_eq!(3, passengers.len());
+    assert_eq!("test - model_passenger_create_bulk 3", passengers[2].first_name);
+    let all = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
+    assert_eq!(5, all.len(), "2 seed + 3 bulk");
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn model_passenger_get_ok() -> Result<(), Box<dyn std::error::Error>> {
     // -- FIXTURE
@@ -56,13 +202,72 @@ async fn model_passenger_update_ok() -> Result<(), Box<dyn std::error::Error>> {
         PassengerDao::update(&db, &utx, passenger_fx.id.to_string(), update_data_fx.clone()).await?;
     // println!("\n\n->> {:?}", passenger_updated);
     // -- CHECK
-    let passengers = PassengerDao::list(&db, &utx).await?;
+    let passengers = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
     assert_eq!(3, passengers.len());
     assert_eq!(passenger_fx.id, passenger_updated.id);
     assert_eq!(update_data_fx.first_name.unwrap(), passenger_updated.first_name);
     Ok(())
 }
 
+#[tokio::test]
+async fn model_passenger_update_keeps_creator() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let creator_utx = utx_from_token(&db, "73b88743-0c2a-4d2c-9b43-a71a582cfbc5").await?;
+    let editor_utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data_fx = PassengerPatch {
+        first_name: Some("test - model_passenger_update_keeps_creator 1".to_string()),
+        ..Default::default()
+    };
+    let passenger_fx = PassengerDao::create(&db, &creator_utx, data_fx).await?;
+    let update_data_fx = PassengerPatch {
+        first_name: Some("test - model_passenger_update_keeps_creator 2".to_string()),
+        ..Default::default()
+    };
+    // -- ACTION
+    let passenger_updated = PassengerDao::update(&db, &editor_utx, passenger_fx.id.to_string(), update_data_fx).await?;
+    // -- CHECK
+    assert_eq!(passenger_fx.created_at, passenger_fx.updated_at);
+    assert_eq!(creator_utx.user_id, passenger_updated.uid.to_string());
+    assert_eq!(creator_utx.user_id, passenger_updated.created_by.to_string());
+    assert_eq!(passenger_fx.created_at, passenger_updated.created_at);
+    assert_eq!(editor_utx.user_id, passenger_updated.updated_by.to_string());
+    assert!(passenger_updated.updated_at > passenger_fx.updated_at);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_list_updated_since() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "73b88743-0c2a-4d2c-9b43-a71a582cfbc5").await?;
+    let filter = PassengerFilter {
+        updated_since: Some("2023-04-02T00:00:00Z".parse()?),
+        sort: Some("-updated_at".parse()?),
+    };
+    let data_fx = PassengerPatch {
+        first_name: Some("test - model_passenger_list_updated_since 1".to_string()),
+        ..Default::default()
+    };
+    let passenger_fx = PassengerDao::create(&db, &utx, data_fx).await?;
+    // -- ACTION
+    let passengers = PassengerDao::list(&db, &utx, &filter).await?;
+    // -- CHECK - seed passenger 100 is older than updated_since, newest first
+    assert_eq!(2, passengers.len());
+    assert_eq!(passenger_fx.id, passengers[0].id);
+    assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", passengers[1].id.to_string());
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_sort_wrong_column() -> Result<(), Box<dyn std::error::Error>> {
+    // -- ACTION
+    let result = "-uid; DROP TABLE passenger".parse::<super::PassengerSort>();
+    // -- CHECK
+    assert!(result.is_err(), "Should not sort by unknown columns");
+    Ok(())
+}
+
 #[tokio::test]
 async fn model_passenger_get_wrong_id() -> Result<(), Box<dyn std::error::Error>> {
     // -- FIXTURE
@@ -74,12 +279,12 @@ async fn model_passenger_get_wrong_id() -> Result<(), Box<dyn std::error::Error>
     // println!("\n\n->> {:?}", result);
     // -- CHECK
     match result {
-        Ok(_) => assert!(false, "Should not succeed"),
+        Ok(_) => panic!("Should not succeed"),
         Err(model::Error::EntityNotFound(typ, id)) => {
             assert_eq!("passenger", typ);
             assert_eq!("52188bd6-733a-4856-a10e-c59b937bb573", id);
         }
-        other_error => assert!(false, "Wrong Error {:?} ", other_error),
+        other_error => panic!("Wrong Error {:?} ", other_error),
     }
     Ok(())
 }
@@ -90,7 +295,7 @@ async fn model_passenger_list() -> Result<(), Box<dyn std::error::Error>> {
     let db = init_db().await?;
     let utx = utx_from_token(&db, "125").await?;
     // ACTION
-    let passengers = PassengerDao::list(&db, &utx).await?;
+    let passengers = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
     // CHECK
     assert_eq!(2, passengers.len());
     // println!("\n\n->> {:?}", passengers);
@@ -100,6 +305,8 @@ async fn model_passenger_list() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!("Passenger 100", passengers[0].first_name);
     assert_eq!("100", passengers[0].last_name);
     assert!(passengers[0].status == Some("new".to_string()));
+    assert_eq!("2023-04-01T10:00:00+00:00", passengers[0].created_at.to_rfc3339());
+    assert_eq!(passengers[0].uid, passengers[0].created_by);
 
     // Passenger 2
     assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", passengers[1].id.to_string());
@@ -121,7 +328,244 @@ async fn model_passenger_delete_simple() -> Result<(), Box<dyn std::error::Error
     assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", passenger.id.to_string());
     assert_eq!("Passenger 101", passenger.first_name);
     // -- CHECK - list
-    let todos: Vec<Passenger> = PassengerDao::list(&db, &utx).await?;
+    let todos: Vec<Passenger> = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
     assert_eq!(1, todos.len());
     Ok(())
 }
+
+#[tokio::test]
+async fn model_passenger_outbox() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data_fx = PassengerPatch {
+        first_name: Some("test - model_passenger_outbox".to_string()),
+        ..Default::default()
+    };
+    // -- ACTION
+    let passenger = PassengerDao::create(&db, &utx, data_fx.clone()).await?;
+    let id = passenger.id.to_string();
+    PassengerDao::update(&db, &utx, id.clone(), data_fx).await?;
+    PassengerDao::delete(&db, &utx, id.clone()).await?;
+    // failed, so no event
+    let _ = PassengerDao::update(&db, &utx, id.clone(), PassengerPatch::default()).await;
+    // -- CHECK - one event per change, in order, with the passenger
+    let events = OutboxDao::list_for(&db, id.clone()).await?;
+    let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+    assert_eq!(
+        vec!["passenger.created", "passenger.updated", "passenger.deleted"],
+        types
+    );
+    assert_eq!(id, events[2].payload["id"]);
+    assert_eq!("test - model_passenger_outbox", events[2].payload["first_name"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_search() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    for (first_name, last_name) in [("Élodie", "Lefèvre"), ("Elliot", "Page"), ("John", "Smith")] {
+        let data = PassengerPatch {
+            first_name: Some(first_name.to_string()),
+            last_name: Some(last_name.to_string()),
+            ..Default::default()
+        };
+        PassengerDao::create(&db, &utx, data).await?;
+    }
+    let search = |q: &str| PassengerSearch {
+        q: q.to_string(),
+        limit: None,
+    };
+    // -- ACTION
+    let prefix = PassengerDao::search(&db, &utx, &search("EL")).await?;
+    let accents = PassengerDao::search(&db, &utx, &search("elodie lefevre")).await?;
+    let typo = PassengerDao::search(&db, &utx, &search("smiht")).await?;
+    let none = PassengerDao::search(&db, &utx, &search("zzz")).await?;
+    // -- CHECK
+    let names = |hits: &[super::PassengerSearchHit]| -> Vec<String> {
+        hits.iter().map(|hit| hit.passenger.first_name.clone()).collect()
+    };
+    // same score, by last name
+    assert_eq!(vec!["Élodie", "Elliot"], names(&prefix));
+    assert_eq!(vec!["Élodie"], names(&accents));
+    assert_eq!(1.0, accents[0].score);
+    assert_eq!(vec!["John"], names(&typo));
+    assert!(none.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_search_prefix_first() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - more close names than candidates, all more similar than the prefix match
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    for last_name in std::iter::once("Qorvexington").chain(["Qorvez"; 6]) {
+        let data = PassengerPatch {
+            first_name: Some("Ann".to_string()),
+            last_name: Some(last_name.to_string()),
+            ..Default::default()
+        };
+        PassengerDao::create(&db, &utx, data).await?;
+    }
+    // -- ACTION - 5 candidates for 1 hit
+    let search = PassengerSearch {
+        q: "qorvex".to_string(),
+        limit: Some(1),
+    };
+    let hits = PassengerDao::search(&db, &utx, &search).await?;
+    // -- CHECK - the start of the name beats the close names
+    assert_eq!(1, hits.len());
+    assert_eq!("Qorvexington", hits[0].passenger.last_name);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_search_scan() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - the only match (a typo) with the last id, after pages of 5 others
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    for last_name in ["Quackenbush"].into_iter().chain(["Doe"; 10]) {
+        let data = PassengerPatch {
+            first_name: Some("Ann".to_string()),
+            last_name: Some(last_name.to_string()),
+            ..Default::default()
+        };
+        PassengerDao::create(&db, &utx, data).await?;
+    }
+    let sql = "UPDATE passenger SET id = 'ffffffff-ffff-ffff-ffff-ffffffffffff' WHERE last_name = 'Quackenbush'";
+    sqlx::query(sql).execute(&db).await?;
+    let search = PassengerSearch {
+        q: "quackenbsh".to_string(),
+        limit: Some(1),
+    };
+    // -- ACTION - as without trigram support, a page per candidates of the trigram search
+    let hits =
+        PassengerDao::search_scan(&db, &utx, &search.terms()?, 1, PassengerDao::SEARCH_CANDIDATES_PER_HIT).await?;
+    // -- CHECK
+    assert_eq!(1, hits.len());
+    assert_eq!("Quackenbush", hits[0].passenger.last_name);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_merge() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - created in this order, the first one without first name
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let mut passengers = Vec::new();
+    for first_name in [None, Some("John"), Some("Jon")] {
+        let data = PassengerPatch {
+            first_name: first_name.map(str::to_string),
+            last_name: Some("Smith".to_string()),
+            ..Default::default()
+        };
+        passengers.push(PassengerDao::create(&db, &utx, data).await?);
+    }
+    let ids: Vec<String> = passengers.iter().map(|passenger| passenger.id.to_string()).collect();
+
+    // -- ACTION
+    let filter = DuplicateFilter {
+        threshold: Some(0.5),
+        limit: None,
+    };
+    let ours = |duplicates: Vec<super::PassengerDuplicate>| -> Vec<super::PassengerDuplicate> {
+        duplicates
+            .into_iter()
+            .filter(|duplicate| duplicate.passengers.iter().all(|p| ids.contains(&p.id.to_string())))
+            .collect()
+    };
+    let duplicates = ours(PassengerDao::duplicates(&db, &utx, &filter).await?);
+    // -- CHECK - John and Jon first (the seed passengers may match too)
+    assert_eq!(3, duplicates.len());
+    let pair: Vec<String> = duplicates[0].passengers.iter().map(|p| p.id.to_string()).collect();
+    assert_eq!(ids[1..].to_vec(), pair);
+    assert_eq!(vec!["similar_name"], duplicates[0].reasons);
+
+    // -- ACTION
+    let request = PassengerMergeRequest {
+        passenger_ids: ids.clone(),
+        survivor_id: None,
+    };
+    let merge = PassengerDao::merge(&db, &utx, &request).await?;
+    // -- CHECK - the oldest survives, with the first name of the last updated one
+    assert_eq!(ids[0], merge.survivor.id.to_string());
+    assert_eq!("Jon", merge.survivor.first_name);
+    assert_eq!(ids[2], merge.sources["first_name"]);
+    assert_eq!(vec![ids[2].clone(), ids[1].clone()], merge.merged_ids);
+    let result = PassengerDao::get(&db, &utx, ids[1].clone()).await;
+    assert!(matches!(result, Err(model::Error::EntityNotFound("passenger", _))));
+    let listed = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
+    assert!(!listed
+        .iter()
+        .any(|passenger| merge.merged_ids.contains(&passenger.id.to_string())));
+    assert!(ours(PassengerDao::duplicates(&db, &utx, &filter).await?).is_empty());
+    // -- CHECK - soft deleted, and audited
+    let (merged_into,): (String,) = sqlx::query_as("SELECT merged_into::text FROM passenger WHERE id = $1::uuid")
+        .bind(&ids[1])
+        .fetch_one(&db)
+        .await?;
+    assert_eq!(ids[0], merged_into);
+    let (merged_ids,): (String,) = sqlx::query_as("SELECT merged_ids FROM passenger_merge WHERE id = $1")
+        .bind(merge.id)
+        .fetch_one(&db)
+        .await?;
+    assert_eq!(format!("{},{}", ids[2], ids[1]), merged_ids);
+
+    // -- ACTION - merged already
+    let result = PassengerDao::merge(&db, &utx, &request).await;
+    // -- CHECK
+    assert!(matches!(result, Err(model::Error::EntityNotFound("passenger", _))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_from_mrz() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - the passport and the ID card of the ICAO 9303 specimen
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let passport = PassengerMrzRequest {
+        mrz: "P<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<\nL898902C36NLD7408122F1204159ZE184226B<<<<<10".to_string(),
+    };
+    let id_card = PassengerMrzRequest {
+        mrz: "I<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<\nD231458907NLD7408122F1204159<<<<<<<6".to_string(),
+    };
+
+    // -- ACTION
+    let created = PassengerDao::from_mrz(&db, &utx, &passport).await?;
+    // -- CHECK
+    assert!(created.created);
+    let passenger = &created.passenger;
+    assert_eq!(
+        ("ANNA MARIA", "ERIKSSON"),
+        (passenger.first_name.as_str(), passenger.last_name.as_str())
+    );
+    assert_eq!(NaiveDate::from_ymd_opt(1974, 8, 12), passenger.date_of_birth);
+    assert_eq!(
+        (Some("F"), Some("NLD")),
+        (passenger.gender.as_deref(), passenger.nationality.as_deref())
+    );
+    assert_eq!(
+        ("passport", "L898902C3"),
+        (created.document.doc_type.as_str(), created.document.number.as_str())
+    );
+    assert_eq!(vec!["expired"], created.document.warnings);
+    let events = OutboxDao::list_for(&db, passenger.id.to_string()).await?;
+    let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+    assert_eq!(vec!["passenger.created"], types);
+
+    // -- ACTION - scanned again, then another document of the same person
+    let again = PassengerDao::from_mrz(&db, &utx, &passport).await?;
+    let matched = PassengerDao::from_mrz(&db, &utx, &id_card).await?;
+    // -- CHECK
+    assert!(!again.created);
+    assert_eq!(
+        (passenger.id, created.document.id),
+        (again.passenger.id, again.document.id)
+    );
+    assert!(!matched.created);
+    assert_eq!(passenger.id, matched.passenger.id);
+    assert_eq!("id_card", matched.document.doc_type);
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use super::{CacheBackend, MemoryBackend, PassengerCache};
+use crate::model;
+use futures::future::join_all;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(60);
+
+#[tokio::test]
+async fn model_passenger_cache_memory_lru() {
+    // -- FIXTURE
+    let backend = MemoryBackend::new(2);
+    backend.set("a", "1".to_string(), TTL).await;
+    backend.set("b", "2".to_string(), TTL).await;
+    // -- ACTION - a used, so b is the least recently used one
+    backend.get("a").await;
+    backend.set("c", "3".to_string(), TTL).await;
+    backend.set("expired", "4".to_string(), Duration::ZERO).await;
+    // -- CHECK
+    assert_eq!(None, backend.get("b").await);
+    assert_eq!(None, backend.get("expired").await);
+    assert_eq!(Some("3".to_string()), backend.get("c").await);
+    // -- ACTION
+    backend.remove_prefix("c").await;
+    // -- CHECK
+    assert_eq!(None, backend.get("c").await);
+}
+
+#[tokio::test]
+async fn model_passenger_cache_read_through() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let cache = PassengerCache::new(Box::new(MemoryBackend::new(10)), TTL);
+    let loads = AtomicUsize::new(0);
+    let load = |value: &'static str| {
+        let loads = &loads;
+        move || async move {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, model::Error>(value.to_string())
+        }
+    };
+    let key = || PassengerCache::passenger_key("42");
+
+    // -- ACTION - miss then hit
+    let first = cache.get_or_load("get", key(), load("v1")).await?;
+    let second = cache.get_or_load("get", key(), load("v2")).await?;
+    // -- CHECK
+    assert_eq!(("v1", "v1"), (first.as_str(), second.as_str()));
+    assert_eq!(1, loads.load(Ordering::SeqCst));
+
+    // -- ACTION - invalidated
+    cache.invalidate("42").await;
+    let third = cache.get_or_load("get", key(), load("v3")).await?;
+    // -- CHECK
+    assert_eq!("v3", third);
+    assert_eq!(2, loads.load(Ordering::SeqCst));
+
+    // -- ACTION - an error is not cached
+    let failed = cache
+        .get_or_load::<String, _, _>("get", PassengerCache::passenger_key("43"), || async {
+            Err(model::Error::EntityNotFound("passenger", "43".to_string()))
+        })
+        .await;
+    let loaded = cache
+        .get_or_load("get", PassengerCache::passenger_key("43"), load("v4"))
+        .await?;
+    // -- CHECK
+    assert!(failed.is_err());
+    assert_eq!("v4", loaded);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_cache_stampede() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let cache = PassengerCache::new(Box::new(MemoryBackend::new(10)), TTL);
+    let loads = AtomicUsize::new(0);
+    // -- ACTION - 20 misses at once
+    let results = join_all((0..20).map(|_| {
+        cache.get_or_load("list", PassengerCache::passengers_key("all", ":"), || async {
+            loads.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, model::Error>(vec![1, 2, 3])
+        })
+    }))
+    .await;
+    // -- CHECK - loaded once
+    assert_eq!(1, loads.load(Ordering::SeqCst));
+    for result in results {
+        assert_eq!(vec![1, 2, 3], result?);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_cache_invalidated_while_loading() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let cache = PassengerCache::new(Box::new(MemoryBackend::new(10)), TTL);
+    let key = || PassengerCache::passengers_key("all", ":");
+    // -- ACTION - a change while the list loads, the list may be the one before
+    let loaded = cache
+        .get_or_load("list", key(), || async {
+            cache.invalidate("42").await;
+            Ok::<_, model::Error>("before".to_string())
+        })
+        .await?;
+    let reloaded = cache
+        .get_or_load("list", key(), || async { Ok::<_, model::Error>("after".to_string()) })
+        .await?;
+    // -- CHECK - not stored
+    assert_eq!(("before", "after"), (loaded.as_str(), reloaded.as_str()));
+    Ok(())
+}
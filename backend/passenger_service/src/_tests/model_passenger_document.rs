@@ -0,0 +1,87 @@
+use super::{validate_number, PassengerDocumentDao, PassengerDocumentForCreate, PassengerDocumentPatch};
+use crate::model::{self, init_db, PassengerDao, PassengerPatch};
+use crate::security::utx_from_token;
+use chrono::{Duration, Utc};
+
+#[test]
+fn model_passenger_document_number() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!("L898902C3", validate_number(" l898 902c3 ")?);
+    assert!(matches!(
+        validate_number("L898-902"),
+        Err(model::Error::Invalid("number", _))
+    ));
+    assert!(matches!(
+        validate_number(&"1".repeat(21)),
+        Err(model::Error::Invalid("number", _))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_document_crud() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let data = PassengerPatch {
+        first_name: Some("test - model_passenger_document_crud".to_string()),
+        ..Default::default()
+    };
+    let passenger_id = PassengerDao::create(&db, &utx, data).await?.id.to_string();
+    let today = Utc::now().date_naive();
+    let passport = PassengerDocumentForCreate {
+        doc_type: "passport".to_string(),
+        number: "l898902c3".to_string(),
+        issuing_country: "d".to_string(),
+        expiry_date: Some(today + Duration::days(30)),
+    };
+
+    // -- ACTION
+    let document = PassengerDocumentDao::create(&db, &utx, passenger_id.clone(), passport.clone()).await?;
+    let again = PassengerDocumentDao::create(&db, &utx, passenger_id.clone(), passport.clone()).await;
+    // -- CHECK - normalized, expiring within DOCUMENT_EXPIRY_WARNING_DAYS, held once
+    assert_eq!(
+        ("L898902C3", "DEU"),
+        (document.number.as_str(), document.issuing_country.as_str())
+    );
+    assert_eq!(vec!["expires_soon"], document.warnings);
+    assert!(matches!(again, Err(model::Error::Invalid("number", _))));
+
+    // -- ACTION - expired
+    let patch = PassengerDocumentPatch {
+        expiry_date: Some(today - Duration::days(1)),
+        ..Default::default()
+    };
+    let id = document.id.to_string();
+    let updated = PassengerDocumentDao::update(&db, &utx, passenger_id.clone(), id.clone(), patch).await?;
+    // -- CHECK
+    assert_eq!(vec!["expired"], updated.warnings);
+    assert_eq!("passport", updated.doc_type);
+    let listed = PassengerDocumentDao::list(&db, &utx, passenger_id.clone()).await?;
+    assert_eq!(vec![document.id], listed.iter().map(|d| d.id).collect::<Vec<_>>());
+
+    // -- ACTION - invalid
+    let result = PassengerDocumentDao::create(
+        &db,
+        &utx,
+        passenger_id.clone(),
+        PassengerDocumentForCreate {
+            doc_type: "driving_licence".to_string(),
+            ..passport
+        },
+    )
+    .await;
+    // -- CHECK
+    assert!(matches!(result, Err(model::Error::Invalid("doc_type", _))));
+
+    // -- ACTION - deleted with its passenger
+    PassengerDao::delete(&db, &utx, passenger_id.clone()).await?;
+    let result = PassengerDocumentDao::get(&db, &utx, passenger_id, id).await;
+    // -- CHECK
+    assert!(matches!(result, Err(model::Error::EntityNotFound("passenger", _))));
+    let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM passenger_document WHERE id = $1")
+        .bind(document.id)
+        .fetch_one(&db)
+        .await?;
+    assert_eq!(0, count);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use super::{PassengerEventKind, PassengerEvents, Replay};
+use crate::model::tests::passenger_fx;
+use chrono::Utc;
+
+#[tokio::test]
+async fn model_passenger_event_publish() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let events = PassengerEvents::new(10, 100);
+    let (_, mut receiver) = events.subscribe(None);
+    // -- ACTION
+    let created = events.publish(PassengerEventKind::Created, passenger_fx("John", "Doe"));
+    let deleted = events.publish(PassengerEventKind::Deleted, passenger_fx("John", "Doe"));
+    // -- CHECK
+    assert_eq!((100, 101), (created, deleted));
+    let event = receiver.recv().await?;
+    assert_eq!((100, PassengerEventKind::Created), (event.id, event.kind));
+    let event = receiver.recv().await?;
+    assert_eq!((101, PassengerEventKind::Deleted), (event.id, event.kind));
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_event_replay() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - the buffer keeps 3 events out of 5
+    let events = PassengerEvents::new(3, 1);
+    for _ in 0..5 {
+        events.publish(PassengerEventKind::Updated, passenger_fx("John", "Doe"));
+    }
+    // -- ACTION
+    let (from_3, _) = events.subscribe(Some(3));
+    let (from_2, _) = events.subscribe(Some(2));
+    let (from_1, _) = events.subscribe(Some(1));
+    let (up_to_date, _) = events.subscribe(Some(5));
+    let (future, _) = events.subscribe(Some(42));
+    // -- CHECK
+    let ids = |replay: Replay| match replay {
+        Replay::Events(events) => Some(events.iter().map(|event| event.id).collect::<Vec<_>>()),
+        Replay::Lost { .. } => None,
+    };
+    assert_eq!(Some(vec![4, 5]), ids(from_3));
+    assert_eq!(Some(vec![3, 4, 5]), ids(from_2));
+    assert!(matches!(from_1, Replay::Lost { last_id: 5 }), "2 no longer buffered");
+    assert_eq!(Some(vec![]), ids(up_to_date));
+    assert!(matches!(future, Replay::Lost { last_id: 5 }), "from another run");
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_passenger_event_publish_once() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - the change of this instance, also read back from the change feed
+    let events = PassengerEvents::new(10, 1);
+    let passenger = passenger_fx("John", "Doe");
+    let mut updated = passenger.clone();
+    updated.updated_at = Utc::now() + chrono::Duration::seconds(1);
+    // -- ACTION
+    let created = events.publish(PassengerEventKind::Created, passenger.clone());
+    let created_again = events.publish(PassengerEventKind::Created, passenger.clone());
+    let updated = events.publish(PassengerEventKind::Updated, updated);
+    let deleted = events.publish(PassengerEventKind::Deleted, passenger);
+    // -- CHECK
+    assert_eq!((1, 1, 2, 3), (created, created_again, updated, deleted));
+    let (replay, _) = events.subscribe(Some(0));
+    assert!(matches!(replay, Replay::Events(events) if events.len() == 3));
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use super::{plan_merge, rank_duplicates, DuplicateFilter, PassengerMergeRequest};
+use crate::model::tests::passenger_fx;
+use crate::model::{self, Passenger};
+use chrono::{Duration, Utc};
+use sqlx::types::Uuid;
+use std::collections::HashMap;
+
+#[test]
+fn model_passenger_merge_request_ids() -> Result<(), Box<dyn std::error::Error>> {
+    let (a, b) = (Uuid::from_u128(1).to_string(), Uuid::from_u128(2).to_string());
+    let request = |ids: &[&str], survivor_id: Option<&str>| PassengerMergeRequest {
+        passenger_ids: ids.iter().map(|id| id.to_string()).collect(),
+        survivor_id: survivor_id.map(str::to_string),
+    };
+    // -- CHECK - the same id twice counts once
+    assert_eq!(vec![a.clone(), b.clone()], request(&[&a, &b, &a], Some(&b)).ids()?);
+    assert!(matches!(
+        request(&[&a, &a], None).ids(),
+        Err(model::Error::Invalid("passenger_ids", _))
+    ));
+    assert!(matches!(
+        request(&[&a, "nope"], None).ids(),
+        Err(model::Error::Invalid("passenger_ids", _))
+    ));
+    let other = Uuid::from_u128(3).to_string();
+    assert!(matches!(
+        request(&[&a, &b], Some(&other)).ids(),
+        Err(model::Error::Invalid("survivor_id", _))
+    ));
+    let filter = DuplicateFilter {
+        threshold: Some(1.5),
+        limit: None,
+    };
+    assert!(matches!(filter.threshold(), Err(model::Error::Invalid("threshold", _))));
+    Ok(())
+}
+
+#[test]
+fn model_passenger_merge_rank_duplicates() {
+    // -- FIXTURE
+    let passengers = [
+        aged_fx("John", "Smith", 0),
+        aged_fx("Jon", "Smith", 1),
+        aged_fx("JOHN", "Smíth", 2),
+        aged_fx("Smith", "John", 3),
+        aged_fx("Jane", "Doe", 4),
+    ];
+    let ids: Vec<Uuid> = passengers.iter().map(|passenger| passenger.id).collect();
+    let by_id: HashMap<Uuid, Passenger> = passengers
+        .into_iter()
+        .map(|passenger| (passenger.id, passenger))
+        .collect();
+    let pairs = [(ids[0], ids[1]), (ids[2], ids[0]), (ids[0], ids[3]), (ids[0], ids[4])];
+    // -- ACTION
+    let duplicates = rank_duplicates(&by_id, &pairs, 0.6, 10);
+    // -- CHECK - Jane Doe is not a duplicate
+    let mut reasons: Vec<&str> = duplicates
+        .iter()
+        .map(|duplicate| duplicate.reasons[0].as_str())
+        .collect();
+    assert_eq!(Some("similar_name"), reasons.pop());
+    reasons.sort();
+    assert_eq!(vec!["same_name", "swapped_names"], reasons);
+    assert_eq!((1.0, 1.0), (duplicates[0].score, duplicates[1].score));
+    assert!(duplicates[2].score < 1.0);
+    // -- CHECK - the oldest first
+    assert_eq!(ids[0], duplicates[0].passengers[0].id);
+    // -- ACTION
+    let top = rank_duplicates(&by_id, &pairs, 0.6, 1);
+    // -- CHECK
+    assert_eq!(1, top.len());
+}
+
+#[test]
+fn model_passenger_merge_plan() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - the survivor has no first name, the newest other passenger gives it
+    let mut oldest = aged_fx("untitled", "Smith", 0);
+    oldest.status = Some("active".to_string());
+    let older = aged_fx("Jon", "Smyth", 1);
+    let mut newest = aged_fx("John", "Smith", 2);
+    newest.updated_at = Utc::now() + Duration::hours(1);
+    newest.email = Some("john.smith@example.com".to_string());
+    let ids = [oldest.id, older.id, newest.id];
+    // -- ACTION
+    let plan = plan_merge(vec![newest.clone(), older.clone(), oldest.clone()], None)?;
+    // -- CHECK
+    assert_eq!(oldest.id, plan.survivor.id);
+    assert_eq!(("John", "Smith"), (plan.first_name.as_str(), plan.last_name.as_str()));
+    assert_eq!(Some("active".to_string()), plan.status);
+    assert_eq!(Some("john.smith@example.com".to_string()), plan.email);
+    assert_eq!(None, plan.phone);
+    assert_eq!(ids[2].to_string(), plan.sources["first_name"]);
+    assert_eq!(ids[0].to_string(), plan.sources["last_name"]);
+    assert_eq!(
+        vec![ids[2], ids[1]],
+        plan.merged.iter().map(|p| p.id).collect::<Vec<_>>()
+    );
+
+    // -- ACTION - survivor chosen
+    let plan = plan_merge(vec![oldest.clone(), older.clone()], Some(&ids[1].to_string()))?;
+    // -- CHECK
+    assert_eq!(older.id, plan.survivor.id);
+    assert_eq!(("Jon", "Smyth"), (plan.first_name.as_str(), plan.last_name.as_str()));
+    assert_eq!(Some("active".to_string()), plan.status);
+
+    // -- ACTION - across tenants
+    let mut other_tenant = older;
+    other_tenant.tenant_id = Some(Uuid::from_u128(rand::random()));
+    let result = plan_merge(vec![oldest, other_tenant], None);
+    // -- CHECK
+    assert!(matches!(result, Err(model::Error::Invalid("passenger_ids", _))));
+    Ok(())
+}
+
+// region: Test Utils
+// created a day ago, plus `age` minutes
+fn aged_fx(first_name: &str, last_name: &str, age: i64) -> Passenger {
+    let created_at = Utc::now() - Duration::days(1) + Duration::minutes(age);
+    Passenger {
+        created_at,
+        updated_at: created_at,
+        ..passenger_fx(first_name, last_name)
+    }
+}
+// endregion: Test Utils
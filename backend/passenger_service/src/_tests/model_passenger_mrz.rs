@@ -0,0 +1,96 @@
+use super::{check_digit, parse_mrz, CheckDigitFailure};
+use crate::model;
+use chrono::NaiveDate;
+
+// the ICAO 9303 specimens, issued by the Netherlands instead of Utopia
+const TD1: &str = "I<NLDD231458907<<<<<<<<<<<<<<<\n7408122F1204159NLD<<<<<<<<<<<6\nERIKSSON<<ANNA<MARIA<<<<<<<<<<";
+const TD2: &str = "I<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<\nD231458907NLD7408122F1204159<<<<<<<6";
+const TD3: &str = "P<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<\nL898902C36NLD7408122F1204159ZE184226B<<<<<10";
+
+#[test]
+fn model_passenger_mrz_check_digit() {
+    assert_eq!('6', check_digit("L898902C3"));
+    assert_eq!('2', check_digit("740812"));
+    assert_eq!('0', check_digit("<<<<<<"));
+}
+
+#[test]
+fn model_passenger_mrz_parse() -> Result<(), Box<dyn std::error::Error>> {
+    // -- ACTION
+    let td3 = parse_mrz(TD3)?;
+    // -- CHECK
+    assert_eq!("passport", td3.doc_type);
+    assert_eq!(
+        ("ERIKSSON", Some("ANNA MARIA")),
+        (td3.last_name.as_str(), td3.first_name.as_deref())
+    );
+    assert_eq!(
+        ("L898902C3", "NLD", "NLD"),
+        (
+            td3.number.as_str(),
+            td3.issuing_country.as_str(),
+            td3.nationality.as_str()
+        )
+    );
+    assert_eq!(NaiveDate::from_ymd_opt(1974, 8, 12), td3.date_of_birth);
+    assert_eq!(NaiveDate::from_ymd_opt(2012, 4, 15), td3.expiry_date);
+    assert_eq!("F", td3.sex);
+
+    // -- ACTION - the ID cards, indented and lowercase as typed
+    let td1 = parse_mrz(&format!("  {}\n", TD1.to_lowercase()))?;
+    let td2 = parse_mrz(TD2)?;
+    // -- CHECK
+    for id_card in [&td1, &td2] {
+        assert_eq!("id_card", id_card.doc_type);
+        assert_eq!("D23145890", id_card.number);
+        assert_eq!(td3.date_of_birth, id_card.date_of_birth);
+        assert_eq!(td3.first_name, id_card.first_name);
+    }
+
+    // -- ACTION - German passport, `D` and no personal number
+    let german =
+        parse_mrz("P<D<<MUSTERMANN<<ERIKA<<<<<<<<<<<<<<<<<<<<<<\nC01X00T478D<<6408125F2702283<<<<<<<<<<<<<<<4")?;
+    // -- CHECK
+    assert_eq!(
+        ("DEU", "DEU"),
+        (german.issuing_country.as_str(), german.nationality.as_str())
+    );
+    assert_eq!(NaiveDate::from_ymd_opt(2027, 2, 28), german.expiry_date);
+
+    // -- ACTION - TD1 number longer than 9 characters
+    let long =
+        parse_mrz("I<NLDABC123456<78902<<<<<<<<<<\n7408122F1204159NLD<<<<<<<<<<<4\nERIKSSON<<ANNA<<<<<<<<<<<<<<<<")?;
+    // -- CHECK
+    assert_eq!("ABC1234567890", long.number);
+    Ok(())
+}
+
+#[test]
+fn model_passenger_mrz_invalid() {
+    // -- ACTION - number and date of birth mistyped, the composite check fails too
+    let result = parse_mrz(&TD3.replace("L898902C36NLD740812", "L898902C46NLD740912"));
+    // -- CHECK
+    let Err(model::Error::CheckDigits(failures)) = result else {
+        panic!("check digits not verified: {:?}", result);
+    };
+    let fields: Vec<&str> = failures.iter().map(|failure| failure.field.as_str()).collect();
+    assert_eq!(vec!["number", "date_of_birth", "composite"], fields);
+    assert_eq!(
+        CheckDigitFailure {
+            field: "number".to_string(),
+            expected: '7',
+            found: '6'
+        },
+        failures[0]
+    );
+
+    assert!(matches!(parse_mrz(&TD3[..80]), Err(model::Error::Invalid("mrz", _))));
+    assert!(matches!(
+        parse_mrz(&TD3.replace('<', " ")),
+        Err(model::Error::Invalid("mrz", _))
+    ));
+    assert!(matches!(
+        parse_mrz(&TD2.replace("I<NLD", "I<UTO")),
+        Err(model::Error::Invalid("issuing_country", _))
+    ));
+}
@@ -0,0 +1,47 @@
+use super::{case, name_key, normalize_name, transliterate, NameCase};
+use crate::model;
+
+#[test]
+fn model_passenger_name_normalize() -> Result<(), Box<dyn std::error::Error>> {
+    // whitespace trimmed and collapsed, NFC (o + combining diaeresis is ö)
+    assert_eq!("Jöhn Paul", normalize_name("first_name", " \tJo\u{308}hn \n  Paul ")?);
+    assert!(matches!(
+        normalize_name("first_name", "Jo\u{0}hn"),
+        Err(model::Error::Invalid("first_name", _))
+    ));
+    assert!(matches!(
+        normalize_name("last_name", " \u{a0} "),
+        Err(model::Error::Invalid("last_name", _))
+    ));
+    assert!(matches!(
+        normalize_name("last_name", &"a".repeat(101)),
+        Err(model::Error::Invalid("last_name", _))
+    ));
+    Ok(())
+}
+
+#[test]
+fn model_passenger_name_case() {
+    assert_eq!("jOHN o'brien", case("jOHN o'brien", NameCase::Preserve));
+    assert_eq!("JÖRG-ÅSA", case("jörg-åsa", NameCase::Upper));
+    assert_eq!("John O'Brien Jean-Luc", case("jOHN o'BRIEN jean-luc", NameCase::Title));
+    assert_eq!(Ok(NameCase::Title), "title".parse());
+    assert!("lower".parse::<NameCase>().is_err());
+}
+
+#[test]
+fn model_passenger_name_transliterate() {
+    // ICAO 9303 part 3 examples
+    assert_eq!("MUELLER<LUEDENSCHEIDT", transliterate("Müller-Lüdenscheidt"));
+    assert_eq!("OBRIEN", transliterate("O'Brien"));
+    assert_eq!("AANGSTROEM", transliterate("Ångström"));
+    assert_eq!("GROSS", transliterate("Groß"));
+    assert_eq!("ELODIE<GARCIA", transliterate(" Élodie  García "));
+    assert_eq!("IVANOV", transliterate("Иванов"));
+    assert_eq!("SHCHUKINA<IULIIA", transliterate("Щукина Юлия"));
+    assert_eq!("PASSENGER<100", transliterate("Passenger 100"));
+    assert_eq!("", transliterate("-'"));
+    // last names first
+    assert_eq!("MUELLER<<JOHN<PAUL", name_key("John Paul", "Müller"));
+    assert_eq!(name_key("Jöhn", "Mueller"), name_key("Joehn", "Müller"));
+}
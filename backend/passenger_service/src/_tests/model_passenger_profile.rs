@@ -0,0 +1,60 @@
+use super::{country, date_of_birth, email, gender, phone, COUNTRIES, ICAO_CODES};
+use crate::model;
+use chrono::{Duration, NaiveDate, Utc};
+
+#[test]
+fn model_passenger_profile_country() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(COUNTRIES.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(ICAO_CODES.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!("FRA", country("nationality", " fra ")?);
+    assert_eq!("UNO", country("nationality", "UNO")?);
+    // Germany in the travel documents
+    assert_eq!("DEU", country("nationality", "D")?);
+    assert!(matches!(
+        country("issuing_country", "FR"),
+        Err(model::Error::Invalid("issuing_country", _))
+    ));
+    Ok(())
+}
+
+#[test]
+fn model_passenger_profile_fields() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!("X", gender(" x")?);
+    assert!(matches!(gender("U"), Err(model::Error::Invalid("gender", _))));
+
+    let birthday = NaiveDate::from_ymd_opt(1980, 2, 29).unwrap();
+    assert_eq!(birthday, date_of_birth(birthday)?);
+    let tomorrow = Utc::now().date_naive() + Duration::days(1);
+    assert!(matches!(
+        date_of_birth(tomorrow),
+        Err(model::Error::Invalid("date_of_birth", _))
+    ));
+    let too_old = NaiveDate::from_ymd_opt(1850, 1, 1).unwrap();
+    assert!(date_of_birth(too_old).is_err());
+
+    assert_eq!("John.Doe@example.com", email(" John.Doe@EXAMPLE.com ")?);
+    for invalid in [
+        "john",
+        "@example.com",
+        "john@example",
+        "john@@example.com",
+        "john doe@example.com",
+        "john@.com",
+    ] {
+        assert!(
+            matches!(email(invalid), Err(model::Error::Invalid("email", _))),
+            "{}",
+            invalid
+        );
+    }
+
+    assert_eq!("+33612345678", phone("+33 6 12-34.56 (78)")?);
+    for invalid in ["0612345678", "+0612345678", "+3361234567890123", "+33 6 12 AB", "+"] {
+        assert!(
+            matches!(phone(invalid), Err(model::Error::Invalid("phone", _))),
+            "{}",
+            invalid
+        );
+    }
+    Ok(())
+}
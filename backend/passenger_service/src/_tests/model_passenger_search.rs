@@ -0,0 +1,87 @@
+use super::{rank, search_key, similarity, Highlight, PassengerSearch};
+use crate::model;
+use crate::model::tests::passenger_fx;
+
+#[test]
+fn model_passenger_search_key() {
+    assert_eq!("elodie", search_key("Élodie"));
+    assert_eq!("muller-luscher", search_key("MÜLLER-Lüscher"));
+    assert_eq!("francois", search_key("François"));
+}
+
+#[test]
+fn model_passenger_search_similarity() {
+    // same values as pg_trgm
+    assert_eq!(1.0, similarity("smith", "smith"));
+    assert!((similarity("smiht", "smith") - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(0.0, similarity("smith", "doe"));
+}
+
+#[test]
+fn model_passenger_search_terms() -> Result<(), Box<dyn std::error::Error>> {
+    let search = |q: &str| PassengerSearch {
+        q: q.to_string(),
+        limit: None,
+    };
+    assert_eq!(vec!["jose", "garcia"], search(" José  GARCÍA, jose ").terms()?);
+    assert!(matches!(search(" - ").terms(), Err(model::Error::Invalid("q", _))));
+    assert!(matches!(
+        search(&(0..10).map(|i| format!("w{} ", i)).collect::<String>()).terms(),
+        Err(model::Error::Invalid("q", _))
+    ));
+    let limit = PassengerSearch {
+        limit: Some(0),
+        ..search("jo")
+    };
+    assert!(matches!(limit.limit(), Err(model::Error::Invalid("limit", _))));
+    Ok(())
+}
+
+#[test]
+fn model_passenger_search_rank() {
+    // -- FIXTURE
+    let passengers = vec![
+        passenger_fx("Joanna", "Smithers"),
+        passenger_fx("José", "Smith"),
+        passenger_fx("John", "Smiht"),
+        passenger_fx("Jane", "Doe"),
+    ];
+    let terms = vec!["jo".to_string(), "smith".to_string()];
+    // -- ACTION
+    let hits = rank(passengers, &terms, 10);
+    // -- CHECK - whole word first, then prefix, then typo; Jane Doe does not match
+    let names: Vec<&str> = hits.iter().map(|hit| hit.passenger.last_name.as_str()).collect();
+    assert_eq!(vec!["Smith", "Smithers", "Smiht"], names);
+    assert!(hits[0].score > hits[1].score && hits[1].score > hits[2].score);
+    assert!(hits.iter().all(|hit| hit.score <= 1.0));
+    // -- CHECK - highlights, in chars of the names
+    assert_eq!(
+        vec![highlight("first_name", 0, 2), highlight("last_name", 0, 5)],
+        hits[0].highlights
+    );
+    assert_eq!(
+        vec![highlight("first_name", 0, 2), highlight("last_name", 0, 5)],
+        hits[1].highlights
+    );
+}
+
+#[test]
+fn model_passenger_search_rank_accents() {
+    // -- FIXTURE
+    let passengers = vec![passenger_fx("Zoë", "Ångström")];
+    // -- ACTION
+    let hits = rank(passengers, &["angs".to_string()], 10);
+    // -- CHECK
+    assert_eq!(1, hits.len());
+    assert_eq!(vec![highlight("last_name", 0, 4)], hits[0].highlights);
+}
+
+// region: Test Utils
+fn highlight(field: &str, start: usize, end: usize) -> Highlight {
+    Highlight {
+        field: field.to_string(),
+        start,
+        end,
+    }
+}
+// endregion: Test Utils
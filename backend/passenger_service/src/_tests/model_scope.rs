@@ -0,0 +1,131 @@
+use super::Scope;
+use crate::model::Passenger;
+use crate::security::{Role, UserCtx};
+use chrono::Utc;
+use sqlbuilder::SqlBuilder;
+use sqlx::types::Uuid;
+
+const USER_ID: &str = "4464cab1-74da-45c1-bcec-d9e668175ec0";
+const TENANT_ID: &str = "8f5c3a5e-3d4b-4b8e-9a36-5e1c2f0b7d21";
+
+#[test]
+fn model_scope_owner() {
+    // -- FIXTURE
+    let utx = utx_fx(None, Role::User);
+    let sql = SqlBuilder::new().select_from("passenger");
+    // -- ACTION
+    let sql = Scope::Owner.apply(sql, &utx).build();
+    // -- CHECK
+    assert_eq!(format!("SELECT * FROM passenger WHERE uid = '{}'", USER_ID), sql);
+}
+
+#[test]
+fn model_scope_tenant() {
+    // -- FIXTURE
+    let utx = utx_fx(Some(TENANT_ID), Role::User);
+    let sql = SqlBuilder::new()
+        .select_from("passenger")
+        .where_clause("id = {}", "b03535ad-0b98-4c8f-8b5a-66960c71392c".to_string());
+    // -- ACTION
+    let sql = Scope::Tenant.apply(sql, &utx).build();
+    // -- CHECK
+    assert_eq!(
+        format!(
+            "SELECT * FROM passenger WHERE id = 'b03535ad-0b98-4c8f-8b5a-66960c71392c' AND tenant_id = '{}'",
+            TENANT_ID
+        ),
+        sql
+    );
+}
+
+#[test]
+fn model_scope_tenant_without_tenant() {
+    // -- FIXTURE
+    let utx = utx_fx(None, Role::User);
+    let sql = SqlBuilder::new().delete_from("passenger");
+    // -- ACTION
+    let sql = Scope::Tenant.apply(sql, &utx).build();
+    // -- CHECK - falls back to the owner
+    assert_eq!(
+        format!("DELETE FROM passenger  WHERE uid = '{}' RETURNING *", USER_ID),
+        sql
+    );
+}
+
+#[test]
+fn model_scope_admin_and_off() {
+    // -- FIXTURE
+    let admin = utx_fx(Some(TENANT_ID), Role::Admin);
+    let user = utx_fx(Some(TENANT_ID), Role::User);
+    // -- ACTION
+    let admin_sql = Scope::Tenant
+        .apply(SqlBuilder::new().select_from("passenger"), &admin)
+        .build();
+    let off_sql = Scope::Off
+        .apply(SqlBuilder::new().select_from("passenger"), &user)
+        .build();
+    // -- CHECK
+    assert_eq!("SELECT * FROM passenger", admin_sql);
+    assert_eq!("SELECT * FROM passenger", off_sql);
+}
+
+#[test]
+fn model_scope_allows() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let own = passenger_fx(USER_ID, Some(TENANT_ID))?;
+    let other = passenger_fx(
+        "2096036b-9606-4405-995b-565a481344bc",
+        Some("07e4a7cd-8a3b-4a8e-8f0b-2b6a8f1d2c11"),
+    )?;
+    let user = utx_fx(Some(TENANT_ID), Role::User);
+    let admin = utx_fx(Some(TENANT_ID), Role::Admin);
+    // -- CHECK - same rows as `apply`
+    assert!(Scope::Owner.allows(&user, &own));
+    assert!(!Scope::Owner.allows(&user, &other));
+    assert!(Scope::Tenant.allows(&user, &own));
+    assert!(!Scope::Tenant.allows(&user, &other));
+    assert!(Scope::Off.allows(&user, &other));
+    assert!(Scope::Tenant.allows(&admin, &other));
+    Ok(())
+}
+
+#[test]
+fn model_scope_partition() {
+    // -- FIXTURE
+    let user = utx_fx(None, Role::User);
+    let tenant_user = utx_fx(Some(TENANT_ID), Role::User);
+    let admin = utx_fx(Some(TENANT_ID), Role::Admin);
+    // -- CHECK
+    assert_eq!("all", Scope::Off.partition(&tenant_user));
+    assert_eq!(format!("owner:{}", USER_ID), Scope::Owner.partition(&tenant_user));
+    assert_eq!(format!("tenant:{}", TENANT_ID), Scope::Tenant.partition(&tenant_user));
+    assert_eq!(format!("owner:{}", USER_ID), Scope::Tenant.partition(&user));
+    assert_eq!("all", Scope::Tenant.partition(&admin));
+}
+
+// region: Test Utils
+fn passenger_fx(uid: &str, tenant_id: Option<&str>) -> Result<Passenger, sqlx::types::uuid::Error> {
+    let uid = Uuid::parse_str(uid)?;
+    Ok(Passenger {
+        id: Uuid::from_u128(rand::random()),
+        uid,
+        tenant_id: tenant_id.map(Uuid::parse_str).transpose()?,
+        first_name: "John".to_string(),
+        last_name: "Doe".to_string(),
+        status: None,
+        date_of_birth: None,
+        gender: None,
+        nationality: None,
+        email: None,
+        phone: None,
+        created_at: Utc::now(),
+        created_by: uid,
+        updated_at: Utc::now(),
+        updated_by: uid,
+    })
+}
+
+fn utx_fx(tenant_id: Option<&str>, role: Role) -> UserCtx {
+    UserCtx::new(USER_ID.to_string(), tenant_id.map(String::from), vec![role])
+}
+// endregion: Test Utils
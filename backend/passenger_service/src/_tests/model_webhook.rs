@@ -0,0 +1,92 @@
+use super::{WebhookDao, WebhookDeliveryDao, WebhookForCreate, WebhookPatch};
+use crate::model::db::init_db;
+use crate::model::{self, PassengerDao, PassengerPatch};
+use crate::security::{Role, UserCtx};
+
+#[tokio::test]
+async fn model_webhook_create_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = admin_utx_fx();
+    let data_fx = |url: &str, event_types: &[&str], secret: Option<&str>| WebhookForCreate {
+        url: url.to_string(),
+        event_types: event_types.iter().map(|typ| typ.to_string()).collect(),
+        secret: secret.map(String::from),
+    };
+    // -- ACTION
+    let wrong_scheme = WebhookDao::create(&db, &utx, data_fx("ftp://example.com/hook", &[], None)).await;
+    let wrong_type = WebhookDao::create(&db, &utx, data_fx("http://example.com", &["passenger.moved"], None)).await;
+    let short_secret = WebhookDao::create(&db, &utx, data_fx("http://example.com", &[], Some("short"))).await;
+    let created = WebhookDao::create(&db, &utx, data_fx("http://example.com", &[], None)).await?;
+    // -- CHECK
+    assert!(
+        matches!(wrong_scheme, Err(model::Error::Invalid("url", _))),
+        "{:?}",
+        wrong_scheme
+    );
+    assert!(
+        matches!(wrong_type, Err(model::Error::Invalid("event_types", _))),
+        "{:?}",
+        wrong_type
+    );
+    assert!(
+        matches!(short_secret, Err(model::Error::Invalid("secret", _))),
+        "{:?}",
+        short_secret
+    );
+    assert!(created.secret.starts_with("whsec_"));
+    assert_eq!(created.secret, created.webhook.secret);
+    assert!(created.webhook.active);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_webhook_fan_out() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - one webhook for all the events, one for the deletes, one inactive
+    let db = init_db().await?;
+    let utx = admin_utx_fx();
+    let webhook_fx = |event_types: &[&str]| WebhookForCreate {
+        url: "http://127.0.0.1:9/hook".to_string(),
+        event_types: event_types.iter().map(|typ| typ.to_string()).collect(),
+        secret: None,
+    };
+    let all = WebhookDao::create(&db, &utx, webhook_fx(&[])).await?.webhook;
+    let deletes = WebhookDao::create(&db, &utx, webhook_fx(&["passenger.deleted"]))
+        .await?
+        .webhook;
+    let inactive = WebhookDao::create(&db, &utx, webhook_fx(&[])).await?.webhook;
+    let patch = WebhookPatch {
+        active: Some(false),
+        ..Default::default()
+    };
+    WebhookDao::update(&db, inactive.id.to_string(), patch).await?;
+    PassengerDao::create(&db, &utx, PassengerPatch::default()).await?;
+
+    // -- ACTION
+    let dispatched = WebhookDeliveryDao::fan_out(&db, 10).await?;
+    let dispatched_again = WebhookDeliveryDao::fan_out(&db, 10).await?;
+
+    // -- CHECK - each event once, to the webhooks accepting it
+    assert_eq!(1, dispatched);
+    assert_eq!(0, dispatched_again);
+    let deliveries = WebhookDeliveryDao::list(&db, all.id.to_string(), None).await?;
+    assert_eq!(1, deliveries.len());
+    assert_eq!(WebhookDeliveryDao::PENDING, deliveries[0].status);
+    assert!(WebhookDeliveryDao::list(&db, deletes.id.to_string(), None)
+        .await?
+        .is_empty());
+    assert!(WebhookDeliveryDao::list(&db, inactive.id.to_string(), None)
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+// region: Test Utils
+fn admin_utx_fx() -> UserCtx {
+    UserCtx::new(
+        "f7a25ba8-fc87-4b6f-9297-611921ef0d7a".to_string(),
+        None,
+        vec![Role::Admin],
+    )
+}
+// endregion: Test Utils
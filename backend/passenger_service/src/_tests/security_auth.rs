@@ -0,0 +1,161 @@
+use super::{login, logout, refresh, LoginPayload, RefreshPayload};
+use crate::model::{init_db, Db, User, UserDao, UserForCreate, UserSessionDao};
+use crate::security::{utx_from_token, Error, Permission, Role};
+use chrono::Utc;
+use sqlx::types::Uuid;
+
+#[tokio::test]
+async fn security_auth_login_ok() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let user = user_fx(&db, "test-login-ok", vec![Role::Editor]).await?;
+    // -- ACTION
+    let tokens = login(&db, payload_fx("test-login-ok", "welcome")).await?;
+    let utx = utx_from_token(&db, &tokens.access_token).await?;
+    // -- CHECK
+    assert!(tokens.refresh_token.starts_with("prt_"));
+    assert_eq!(user.id.to_string(), utx.user_id);
+    assert!(utx.session_id.is_some());
+    assert!(utx.has_permission(Permission::PassengerWrite));
+    assert!(!utx.has_permission(Permission::PassengerDelete));
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_auth_login_fail() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    user_fx(&db, "test-login-fail", vec![Role::User]).await?;
+    // -- ACTION & CHECK
+    let wrong_pwd = login(&db, payload_fx("test-login-fail", "nope")).await;
+    assert!(matches!(wrong_pwd, Err(Error::LoginFail)), "{:?}", wrong_pwd);
+    let unknown = login(&db, payload_fx("test-nobody", "welcome")).await;
+    assert!(matches!(unknown, Err(Error::LoginFail)), "{:?}", unknown);
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_auth_lockout() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    user_fx(&db, "test-lockout", vec![Role::User]).await?;
+    // -- ACTION
+    for _ in 0..4 {
+        assert!(matches!(
+            login(&db, payload_fx("test-lockout", "nope")).await,
+            Err(Error::LoginFail)
+        ));
+    }
+    let fifth = login(&db, payload_fx("test-lockout", "nope")).await;
+    // -- CHECK - locked, even with the right password
+    assert!(matches!(fifth, Err(Error::AccountLocked)), "{:?}", fifth);
+    let right_pwd = login(&db, payload_fx("test-lockout", "welcome")).await;
+    assert!(matches!(right_pwd, Err(Error::AccountLocked)), "{:?}", right_pwd);
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_auth_lockout_concurrent() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    user_fx(&db, "test-lockout-concurrent", vec![Role::User]).await?;
+    // -- ACTION - the failures of the max count all, even at once
+    let attempts = (0..5).map(|_| login(&db, payload_fx("test-lockout-concurrent", "nope")));
+    let results = futures::future::join_all(attempts).await;
+    // -- CHECK - exactly the last one locks the account
+    let locked = results
+        .iter()
+        .filter(|result| matches!(result, Err(Error::AccountLocked)))
+        .count();
+    assert_eq!(1, locked, "{:?}", results);
+    let right_pwd = login(&db, payload_fx("test-lockout-concurrent", "welcome")).await;
+    assert!(matches!(right_pwd, Err(Error::AccountLocked)), "{:?}", right_pwd);
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_auth_refresh_and_logout() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    user_fx(&db, "test-refresh", vec![Role::User]).await?;
+    let tokens = login(&db, payload_fx("test-refresh", "welcome")).await?;
+    let refresh_fx = |refresh_token: &str| RefreshPayload {
+        refresh_token: refresh_token.to_string(),
+    };
+
+    // -- ACTION - refresh
+    let refreshed = refresh(&db, refresh_fx(&tokens.refresh_token)).await?;
+    // -- CHECK - same session, new refresh token (the old one replayed, see security_auth_refresh_twice)
+    let utx = utx_from_token(&db, &refreshed.access_token).await?;
+    let first_utx = utx_from_token(&db, &tokens.access_token).await?;
+    assert_eq!(first_utx.session_id, utx.session_id);
+    assert_ne!(tokens.refresh_token, refreshed.refresh_token);
+
+    // -- ACTION - logout
+    let session = logout(&db, &utx).await?;
+    // -- CHECK - the refresh token is revoked with the session
+    assert!(session.and_then(|session| session.revoked_at).is_some());
+    assert!(refresh(&db, refresh_fx(&refreshed.refresh_token)).await.is_err());
+    // -- CHECK - and so are its access tokens, before they expire
+    let revoked = utx_from_token(&db, &refreshed.access_token).await;
+    assert!(matches!(revoked, Err(Error::TokenRevoked)), "{:?}", revoked);
+    assert!(utx_from_token(&db, &tokens.access_token).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_auth_refresh_twice() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    user_fx(&db, "test-refresh-twice", vec![Role::User]).await?;
+    let tokens = login(&db, payload_fx("test-refresh-twice", "welcome")).await?;
+    let refresh_fx = || RefreshPayload {
+        refresh_token: tokens.refresh_token.clone(),
+    };
+
+    // -- ACTION - refreshed, then the rotated out refresh token replayed
+    let refreshed = refresh(&db, refresh_fx()).await?;
+    let reused = refresh(&db, refresh_fx()).await;
+    // -- CHECK - the session is revoked, with the access tokens of the last refresh
+    assert!(matches!(reused, Err(Error::RefreshTokenReused)), "{:?}", reused);
+    let revoked = utx_from_token(&db, &refreshed.access_token).await;
+    assert!(matches!(revoked, Err(Error::TokenRevoked)), "{:?}", revoked);
+    let last = refresh(
+        &db,
+        RefreshPayload {
+            refresh_token: refreshed.refresh_token.clone(),
+        },
+    )
+    .await;
+    assert!(matches!(last, Err(Error::InvalidToken(_))), "{:?}", last);
+
+    // -- ACTION - rotating from a refresh token already rotated out
+    let tokens = login(&db, payload_fx("test-refresh-twice", "welcome")).await?;
+    let utx = utx_from_token(&db, &tokens.access_token).await?;
+    let session_id = Uuid::parse_str(&utx.session_id.unwrap_or_default())?;
+    let rotated = UserSessionDao::rotate(&db, session_id, "stale", "new".to_string(), Utc::now()).await?;
+    // -- CHECK - no session matches
+    assert!(rotated.is_none());
+    assert!(refresh(&db, refresh_fx()).await.is_err());
+    Ok(())
+}
+
+// region: Test Utils
+async fn user_fx(db: &Db, username: &str, roles: Vec<Role>) -> Result<User, Box<dyn std::error::Error>> {
+    let utx = utx_from_token(db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
+    let data = UserForCreate {
+        username: username.to_string(),
+        pwd: "welcome".to_string(),
+        tenant_id: None,
+        roles,
+    };
+    Ok(UserDao::create(db, &utx, data).await?)
+}
+
+fn payload_fx(username: &str, pwd: &str) -> LoginPayload {
+    LoginPayload {
+        username: username.to_string(),
+        pwd: pwd.to_string(),
+    }
+}
+// endregion: Test Utils
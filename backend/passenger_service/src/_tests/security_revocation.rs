@@ -0,0 +1,48 @@
+use super::{gc, is_revoked, reload, revoke};
+use crate::model::{init_db, RevokedTokenDao};
+use chrono::{Duration, Utc};
+
+#[tokio::test]
+async fn security_revocation_revoke() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    // -- ACTION
+    revoke(&db, "jti-revoked", Utc::now() + Duration::minutes(5)).await?;
+    // -- CHECK
+    assert!(is_revoked(&db, &["jti-other", "jti-revoked"]).await?);
+    assert!(!is_revoked(&db, &["jti-other"]).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_revocation_reload() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - revoked by another replica, straight in the database
+    let db = init_db().await?;
+    reload(&db).await?;
+    RevokedTokenDao::create(&db, "jti-elsewhere", Utc::now() + Duration::minutes(5)).await?;
+    // -- ACTION
+    reload(&db).await?;
+    // -- CHECK
+    assert!(is_revoked(&db, &["jti-elsewhere"]).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn security_revocation_gc() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    revoke(&db, "jti-expired", Utc::now() - Duration::minutes(1)).await?;
+    revoke(&db, "jti-active", Utc::now() + Duration::minutes(5)).await?;
+    // -- ACTION
+    let deleted = gc(&db).await?;
+    // -- CHECK
+    assert_eq!(1, deleted);
+    assert!(!is_revoked(&db, &["jti-expired"]).await?);
+    assert!(is_revoked(&db, &["jti-active"]).await?);
+    let active = RevokedTokenDao::list_active(&db).await?;
+    assert_eq!(
+        vec!["jti-active"],
+        active.iter().map(|r| r.jti.as_str()).collect::<Vec<_>>()
+    );
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use super::{decode, encode, Claims};
+use crate::security::{Error, Role};
+use chrono::{Duration, Utc};
+
+const KEY: &[u8] = b"test-key-test-key-test-key-test-";
+
+fn claims_fx(exp: i64) -> Claims {
+    Claims {
+        sub: "f7a25ba8-fc87-4b6f-9297-611921ef0d7a".to_string(),
+        tid: None,
+        sid: "0c9c1a5e-8a8f-4bd7-9b8a-6f4a2d3e1b00".to_string(),
+        jti: "jti-1".to_string(),
+        roles: vec![Role::Editor],
+        exp,
+    }
+}
+
+#[test]
+fn security_token_encode_decode() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let claims = claims_fx((Utc::now() + Duration::minutes(5)).timestamp());
+    // -- ACTION
+    let token = encode(&claims, KEY)?;
+    let decoded = decode(&token, KEY)?;
+    // -- CHECK
+    assert!(token.starts_with("pat_"));
+    assert_eq!(claims, decoded);
+    Ok(())
+}
+
+#[test]
+fn security_token_tampered() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let token = encode(&claims_fx((Utc::now() + Duration::minutes(5)).timestamp()), KEY)?;
+    let admin_claims = Claims {
+        roles: vec![Role::Admin],
+        ..claims_fx((Utc::now() + Duration::minutes(5)).timestamp())
+    };
+    let admin_token = encode(&admin_claims, KEY)?;
+    let (_, signature) = token.split_once('.').unwrap();
+    let (admin_part, _) = admin_token.split_once('.').unwrap();
+    let forged = format!("{}.{}", admin_part, signature);
+    // -- ACTION & CHECK
+    assert!(matches!(decode(&forged, KEY), Err(Error::InvalidToken(_))));
+    assert!(matches!(decode(&token, b"other-key"), Err(Error::InvalidToken(_))));
+    assert!(matches!(decode("pat_garbage", KEY), Err(Error::InvalidToken(_))));
+    Ok(())
+}
+
+#[test]
+fn security_token_expired() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let token = encode(&claims_fx((Utc::now() - Duration::seconds(1)).timestamp()), KEY)?;
+    // -- ACTION & CHECK
+    assert!(matches!(decode(&token, KEY), Err(Error::TokenExpired)));
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use super::{new_tracer_provider, remote_parent};
+use opentelemetry::trace::TracerProvider;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use warp::http::HeaderMap;
+use warp::Filter;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn telemetry_otlp_export() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - collector stand-in, keeping the posted traces
+    let received: Arc<Mutex<Vec<Value>>> = Default::default();
+    let collector = warp::path!("v1" / "traces")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map({
+            let received = received.clone();
+            move |traces: Value| {
+                received.lock().unwrap().push(traces);
+                warp::reply::json(&serde_json::json!({}))
+            }
+        });
+    let (addr, server) = warp::serve(collector).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let provider = new_tracer_provider(&format!("http://{}", addr))?;
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let mut headers = HeaderMap::new();
+    headers.insert("traceparent", format!("00-{}-00f067aa0ba902b7-01", TRACE_ID).parse()?);
+
+    // -- ACTION - a request span continuing the caller trace, with a query span
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", method = "GET");
+        let _ = span.set_parent(remote_parent(&headers));
+        span.in_scope(|| {
+            let sql = "SELECT * FROM passenger WHERE id = '4208b168'";
+            tracing::info_span!("db.query", db.statement = %sqlbuilder::template(sql)).in_scope(|| {});
+        });
+    });
+    let provider_flush = provider.clone();
+    tokio::task::spawn_blocking(move || provider_flush.force_flush()).await??;
+
+    // -- CHECK
+    let received = received.lock().unwrap();
+    let spans: Vec<Value> = received
+        .iter()
+        .flat_map(|traces| traces["resourceSpans"].as_array().cloned().unwrap_or_default())
+        .flat_map(|resource| resource["scopeSpans"].as_array().cloned().unwrap_or_default())
+        .flat_map(|scope| scope["spans"].as_array().cloned().unwrap_or_default())
+        .collect();
+    let names: Vec<&str> = spans.iter().filter_map(|span| span["name"].as_str()).collect();
+    assert!(names.contains(&"request") && names.contains(&"db.query"), "{:?}", names);
+    for span in &spans {
+        assert_eq!(TRACE_ID, span["traceId"].as_str().unwrap_or_default().to_lowercase());
+    }
+    let query = spans.iter().find(|span| span["name"] == "db.query").unwrap();
+    let statement = query["attributes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|attribute| attribute["key"] == "db.statement")
+        .map(|attribute| attribute["value"]["stringValue"].clone());
+    assert_eq!(Some(Value::from("SELECT * FROM passenger WHERE id = $1")), statement);
+
+    Ok(())
+}
+
+#[test]
+fn telemetry_sql_template() {
+    assert_eq!(
+        "UPDATE passenger SET first_name = $1 WHERE id = $2 AND revoked_at IS NULL",
+        sqlbuilder::template("UPDATE passenger SET first_name = 'O''Neil' WHERE id = 'x' AND revoked_at IS NULL")
+    );
+    assert_eq!("SELECT 1", sqlbuilder::template("SELECT 1"));
+}
@@ -0,0 +1,21 @@
+use super::SecurityAddon;
+use serde_json::{json, to_value};
+use utoipa::openapi::{ComponentsBuilder, OpenApiBuilder};
+use utoipa::Modify;
+
+#[test]
+fn web_security_addon_header() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let mut openapi = OpenApiBuilder::new()
+        .components(Some(ComponentsBuilder::new().build()))
+        .build();
+    // -- ACTION
+    SecurityAddon.modify(&mut openapi);
+    // -- CHECK
+    // the scheme sends the token in the header the server reads
+    let scheme = &to_value(&openapi)?["components"]["securitySchemes"]["X-Auth-Token"];
+    assert_eq!(&json!("apiKey"), &scheme["type"]);
+    assert_eq!(&json!("header"), &scheme["in"]);
+    assert_eq!(&json!("X-Auth-Token"), &scheme["name"]);
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use super::require;
+use crate::metrics::metrics;
+use crate::model::init_db;
+use crate::security::Permission;
+use crate::web::handle_rejection;
+use anyhow::Result;
+use std::sync::Arc;
+use warp::Filter;
+
+#[tokio::test]
+async fn web_filter_auth_require_ok() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = require(db, Permission::PassengerDelete).map(|_| "ok");
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .reply(&filter.recover(handle_rejection))
+        .await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_auth_require_forbidden() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = require(db, Permission::Admin).map(|_| "ok");
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .reply(&filter.recover(handle_rejection))
+        .await;
+    // -- CHECK
+    assert_eq!(403, resp.status(), "http status");
+    assert_eq!(
+        1,
+        metrics().auth_failures.with_label_values(&["permission_denied"]).get()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_auth_require_missing_token() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = require(db, Permission::PassengerRead).map(|_| "ok");
+    // -- ACTION
+    let resp = warp::test::request().reply(&filter.recover(handle_rejection)).await;
+    // -- CHECK
+    assert_eq!(400, resp.status(), "http status");
+    assert_eq!(1, metrics().auth_failures.with_label_values(&["missing_token"]).get());
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::{negotiate, with_compression, Encoding};
+use anyhow::Result;
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder};
+use tokio::io::AsyncReadExt;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+fn json_reply(
+    size: usize,
+) -> impl Filter<Extract = (Response,), Error = std::convert::Infallible> + Clone + Send + Sync + 'static {
+    warp::any().map(move || warp::reply::json(&vec!["passenger"; size]).into_response())
+}
+
+#[test]
+fn web_filter_compression_negotiate() -> Result<()> {
+    assert_eq!(Some(Encoding::Brotli), negotiate("gzip, deflate, br"));
+    assert_eq!(Some(Encoding::Gzip), negotiate("gzip"));
+    assert_eq!(Some(Encoding::Gzip), negotiate("br;q=0.5, gzip;q=0.8"));
+    assert_eq!(Some(Encoding::Gzip), negotiate("br;q=0, *"));
+    assert_eq!(Some(Encoding::Brotli), negotiate("*"));
+    assert_eq!(None, negotiate("identity"));
+    assert_eq!(None, negotiate("gzip;q=0"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_compression_gzip() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_compression(true, json_reply(1000));
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("Accept-Encoding", "gzip")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!("gzip", resp.headers()["content-encoding"]);
+    assert_eq!("Accept-Encoding", resp.headers()["vary"]);
+    let mut body = String::new();
+    GzipDecoder::new(resp.body().as_ref()).read_to_string(&mut body).await?;
+    let passengers: Vec<String> = serde_json::from_str(&body)?;
+    assert_eq!(1000, passengers.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_compression_brotli() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_compression(true, json_reply(1000));
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("Accept-Encoding", "gzip, br")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!("br", resp.headers()["content-encoding"]);
+    let mut body = String::new();
+    BrotliDecoder::new(resp.body().as_ref())
+        .read_to_string(&mut body)
+        .await?;
+    assert!(body.len() > resp.body().len(), "compressed");
+    assert!(body.starts_with("[\"passenger\""));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_compression_skipped() -> Result<()> {
+    // -- FIXTURE
+    let events = warp::any().map(|| {
+        warp::http::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(warp::hyper::Body::from("data: x\n\n".repeat(500)))
+            .unwrap()
+    });
+    // -- ACTION
+    let small = warp::test::request()
+        .header("Accept-Encoding", "gzip")
+        .reply(&with_compression(true, json_reply(2)))
+        .await;
+    let not_accepted = warp::test::request()
+        .reply(&with_compression(true, json_reply(1000)))
+        .await;
+    let disabled = warp::test::request()
+        .header("Accept-Encoding", "gzip")
+        .reply(&with_compression(false, json_reply(1000)))
+        .await;
+    let events = warp::test::request()
+        .header("Accept-Encoding", "gzip")
+        .reply(&with_compression(true, events))
+        .await;
+    // -- CHECK
+    for resp in [small, not_accepted, disabled, events] {
+        assert!(!resp.headers().contains_key("content-encoding"));
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use super::{with_cors, Cors};
+use anyhow::Result;
+use std::time::Duration;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+fn cors(origins: &[&str], allow_credentials: bool) -> Option<std::sync::Arc<Cors>> {
+    let origins: Vec<String> = origins.iter().map(|origin| origin.to_string()).collect();
+    let methods = vec!["GET".to_string(), "POST".to_string()];
+    let headers = vec!["Content-Type".to_string(), "X-Auth-Token".to_string()];
+    Cors::new(
+        &origins,
+        &methods,
+        &headers,
+        allow_credentials,
+        Duration::from_secs(600),
+    )
+}
+
+fn ok() -> impl Filter<Extract = (Response,), Error = std::convert::Infallible> + Clone + Send + Sync + 'static {
+    warp::any().map(|| "ok".into_response())
+}
+
+#[tokio::test]
+async fn web_filter_cors_allowed_origin() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&["https://app.example.com"], true), ok());
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("Origin", "https://app.example.com")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    let headers = resp.headers();
+    assert_eq!("https://app.example.com", headers["access-control-allow-origin"]);
+    assert_eq!("true", headers["access-control-allow-credentials"]);
+    assert_eq!("Origin", headers["vary"]);
+    assert!(headers["access-control-expose-headers"]
+        .to_str()?
+        .contains("X-Request-Id"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_cors_other_origin() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&["https://app.example.com"], false), ok());
+    // -- ACTION
+    let other = warp::test::request()
+        .header("Origin", "https://evil.example.com")
+        .reply(&filter)
+        .await;
+    let same_origin = warp::test::request().reply(&filter).await;
+    // -- CHECK - the response is still given, the browser keeps it from the other origin
+    assert_eq!(200, other.status(), "http status");
+    assert!(!other.headers().contains_key("access-control-allow-origin"));
+    assert_eq!(200, same_origin.status(), "http status");
+    assert!(!same_origin.headers().contains_key("access-control-allow-origin"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_cors_any_origin() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&["*"], false), ok());
+    // -- ACTION
+    let resp = warp::test::request()
+        .header("Origin", "https://any.example.com")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!("*", resp.headers()["access-control-allow-origin"]);
+    assert!(!resp.headers().contains_key("access-control-allow-credentials"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_cors_preflight_ok() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&["https://app.example.com"], false), ok());
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("OPTIONS")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type, x-auth-token")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!(204, resp.status(), "http status");
+    let headers = resp.headers();
+    assert_eq!("https://app.example.com", headers["access-control-allow-origin"]);
+    assert_eq!("GET, POST", headers["access-control-allow-methods"]);
+    assert_eq!("content-type, x-auth-token", headers["access-control-allow-headers"]);
+    assert_eq!("600", headers["access-control-max-age"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_cors_preflight_forbidden() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&["https://app.example.com"], false), ok());
+    let preflight = |origin: &str, method: &str, headers: &str| {
+        warp::test::request()
+            .method("OPTIONS")
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .header("Access-Control-Request-Headers", headers)
+    };
+    // -- ACTION
+    let other_origin = preflight("https://evil.example.com", "POST", "content-type")
+        .reply(&filter)
+        .await;
+    let other_method = preflight("https://app.example.com", "DELETE", "content-type")
+        .reply(&filter)
+        .await;
+    let other_header = preflight("https://app.example.com", "POST", "x-custom")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    for resp in [other_origin, other_method, other_header] {
+        assert_eq!(403, resp.status(), "http status");
+        assert!(!resp.headers().contains_key("access-control-allow-origin"));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_cors_disabled() -> Result<()> {
+    // -- FIXTURE
+    let filter = with_cors(cors(&[], false), ok());
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("OPTIONS")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "POST")
+        .reply(&filter)
+        .await;
+    // -- CHECK - the request goes to the routes
+    assert_eq!(200, resp.status(), "http status");
+    assert!(!resp.headers().contains_key("access-control-allow-origin"));
+    Ok(())
+}
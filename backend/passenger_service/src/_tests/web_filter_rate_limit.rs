@@ -0,0 +1,169 @@
+use super::{rate_limit, with_rate_limit_headers, MemoryBackend, Quota, RateLimitBackend, RateLimiter};
+use crate::config::config;
+use crate::model::{init_db, ApiKeyDao, ApiKeyForCreate};
+use crate::security::token::{encode, Claims};
+use crate::security::{utx_from_token, Permission, Role};
+use crate::web::handle_rejection;
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use warp::Filter;
+
+#[test]
+fn web_rate_limit_quota_parse() {
+    assert_eq!(Ok(Quota::new(300, 60)), "300/60".parse());
+    assert_eq!(Duration::from_secs(60), Quota::new(300, 60).period);
+    for wrong in ["300", "0/60", "300/0", "a/60", "300/1m"] {
+        assert!(wrong.parse::<Quota>().is_err(), "'{}' accepted", wrong);
+    }
+}
+
+#[tokio::test]
+async fn web_rate_limit_memory_backend() {
+    // -- FIXTURE
+    let backend = MemoryBackend::default();
+    let quota = Quota::new(2, 60);
+    // -- ACTION
+    let first = backend.take("a", &quota).await;
+    let second = backend.take("a", &quota).await;
+    let third = backend.take("a", &quota).await;
+    let other = backend.take("b", &quota).await;
+    // -- CHECK
+    assert!(first.allowed && second.allowed && !third.allowed && other.allowed);
+    assert_eq!((2, 1, 30), (first.limit, first.remaining, first.reset_secs));
+    assert_eq!((0, 60), (second.remaining, second.reset_secs));
+    assert_eq!(30, third.retry_after_secs, "one token every 30s");
+}
+
+#[tokio::test]
+async fn web_rate_limit_filter() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let limiter = RateLimiter::new(Box::<MemoryBackend>::default(), Quota::new(2, 60), Quota::new(1, 60));
+    let apis = rate_limit(db, Some(Arc::new(limiter)))
+        .and(warp::any().map(|| "ok"))
+        .map(with_rate_limit_headers)
+        .recover(handle_rejection);
+    let get = |token: &str| {
+        warp::test::request()
+            .method("GET")
+            .header("X-Auth-Token", token)
+            .path("/api/passengers")
+    };
+    let (user_a, user_b) = (access_token_fx("user-a")?, access_token_fx("user-b")?);
+
+    // -- ACTION & CHECK - reads of the user, until over the quota
+    let resp = get(&user_a).reply(&apis).await;
+    assert_eq!(200, resp.status(), "http status");
+    assert_eq!("2", resp.headers()["RateLimit-Limit"]);
+    assert_eq!("1", resp.headers()["RateLimit-Remaining"]);
+    get(&user_a).reply(&apis).await;
+    let resp = get(&user_a).reply(&apis).await;
+    assert_eq!(429, resp.status(), "http status");
+    assert_eq!("30", resp.headers()["Retry-After"]);
+    assert_eq!("0", resp.headers()["RateLimit-Remaining"]);
+
+    // -- ACTION & CHECK - writes and other users have their own buckets
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &user_a)
+        .path("/api/passengers")
+        .reply(&apis)
+        .await;
+    assert_eq!(200, resp.status(), "http status");
+    assert_eq!("1", resp.headers()["RateLimit-Limit"]);
+    assert_eq!(200, get(&user_b).reply(&apis).await.status(), "http status");
+
+    // -- ACTION & CHECK - anonymous requests are keyed by IP
+    for (ip, status) in [
+        ("10.0.0.1", 200),
+        ("10.0.0.1", 200),
+        ("10.0.0.1", 429),
+        ("10.0.0.2", 200),
+    ] {
+        let resp = warp::test::request()
+            .method("GET")
+            .remote_addr(format!("{}:4000", ip).parse()?)
+            .path("/api/passengers")
+            .reply(&apis)
+            .await;
+        assert_eq!(status, resp.status(), "http status for {}", ip);
+    }
+
+    // -- ACTION & CHECK - invalid credentials are keyed by IP, changing them on each request
+    // does not get a new bucket
+    for (token, status) in [
+        ("psk_abcdefgh_0123456789012345678901234567890123456789", 200),
+        ("psk_hgfedcba_0123456789012345678901234567890123456789", 200),
+        ("psk_bcdefgha_0123456789012345678901234567890123456789", 429),
+        ("pat_eyJzdWIiOiJ1c2VyLWMifQ.forged", 429),
+    ] {
+        let resp = get(token).remote_addr("10.0.0.3:4000".parse()?).reply(&apis).await;
+        assert_eq!(status, resp.status(), "http status for {}", token);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_rate_limit_api_keys() -> Result<()> {
+    // -- FIXTURE - two API keys, called from the same address
+    let db = Arc::new(init_db().await?);
+    let utx = utx_from_token(&db, "f7a25ba8-fc87-4b6f-9297-611921ef0d7a").await?;
+    let mut tokens = Vec::new();
+    for name in ["test - rate limit a", "test - rate limit b"] {
+        let data = ApiKeyForCreate {
+            name: name.to_string(),
+            scopes: vec![Permission::PassengerRead],
+            expires_at: None,
+        };
+        tokens.push(ApiKeyDao::create(&db, &utx, data).await?.token);
+    }
+    let limiter = RateLimiter::new(Box::<MemoryBackend>::default(), Quota::new(1, 60), Quota::new(1, 60));
+    let apis = rate_limit(db, Some(Arc::new(limiter)))
+        .and(warp::any().map(|| "ok"))
+        .map(with_rate_limit_headers)
+        .recover(handle_rejection);
+    // -- ACTION & CHECK - a bucket per key
+    for (token, status) in [(&tokens[0], 200), (&tokens[1], 200), (&tokens[0], 429)] {
+        let resp = warp::test::request()
+            .method("GET")
+            .header("X-Auth-Token", token)
+            .remote_addr("10.0.0.4:4000".parse()?)
+            .path("/api/passengers")
+            .reply(&apis)
+            .await;
+        assert_eq!(status, resp.status(), "http status");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_rate_limit_off() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let apis = rate_limit(db, None)
+        .and(warp::any().map(|| "ok"))
+        .map(with_rate_limit_headers);
+    // -- ACTION
+    let resp = warp::test::request().path("/api/passengers").reply(&apis).await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    assert!(!resp.headers().contains_key("RateLimit-Limit"));
+    Ok(())
+}
+
+// region:    Test Utils
+fn access_token_fx(user_id: &str) -> Result<String> {
+    let claims = Claims {
+        sub: user_id.to_string(),
+        tid: None,
+        sid: format!("session-{}", user_id),
+        jti: format!("jti-{}", user_id),
+        roles: vec![Role::User],
+        exp: (Utc::now() + ChronoDuration::minutes(5)).timestamp(),
+    };
+    Ok(encode(&claims, &config().auth_token_key)?)
+}
+// endregion: Test Utils
@@ -0,0 +1,39 @@
+use super::{headers, security_headers};
+use anyhow::Result;
+use std::time::Duration;
+use warp::Filter;
+
+#[test]
+fn web_filter_security_headers_headers() -> Result<()> {
+    // -- ACTION
+    let with_hsts = headers("default-src 'self'", Duration::from_secs(3600));
+    let without = headers("", Duration::ZERO);
+    // -- CHECK
+    assert_eq!("default-src 'self'", with_hsts["content-security-policy"]);
+    assert_eq!("nosniff", with_hsts["x-content-type-options"]);
+    assert_eq!("DENY", with_hsts["x-frame-options"]);
+    assert_eq!("no-referrer", with_hsts["referrer-policy"]);
+    assert_eq!(
+        "max-age=3600; includeSubDomains",
+        with_hsts["strict-transport-security"]
+    );
+    assert!(!without.contains_key("content-security-policy"));
+    assert!(!without.contains_key("strict-transport-security"));
+    assert_eq!("nosniff", without["x-content-type-options"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_security_headers_no_tls() -> Result<()> {
+    // -- FIXTURE
+    let filter = warp::any().map(|| "ok").with(security_headers(false));
+    // -- ACTION
+    let resp = warp::test::request().reply(&filter).await;
+    // -- CHECK - no HSTS over plain http
+    assert_eq!(200, resp.status(), "http status");
+    assert!(resp.headers()["content-security-policy"]
+        .to_str()?
+        .contains("frame-ancestors 'none'"));
+    assert!(!resp.headers().contains_key("strict-transport-security"));
+    Ok(())
+}
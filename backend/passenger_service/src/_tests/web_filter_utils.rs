@@ -0,0 +1,78 @@
+use super::{json_body, parse_json};
+use crate::model::PassengerPatch;
+use crate::web::{handle_rejection, Error};
+use anyhow::Result;
+use serde_json::Value;
+use std::str::from_utf8;
+use warp::Filter;
+
+#[test]
+fn web_filter_utils_parse_json_path() -> Result<()> {
+    // -- ACTION
+    let wrong_type = parse_json::<Vec<PassengerPatch>>(br#"[{"status": "new"}, {"status": 5}]"#);
+    let unknown_field = parse_json::<PassengerPatch>(br#"{"status": "new", "statuss": "new"}"#);
+    let syntax = parse_json::<PassengerPatch>(br#"{"status": "new""#);
+
+    // -- CHECK
+    match wrong_type {
+        Err(Error::FailJsonBody { path, reason }) => {
+            assert_eq!("[1].status", path);
+            assert!(
+                reason.starts_with("invalid type: integer `5`, expected a string"),
+                "{}",
+                reason
+            );
+        }
+        other => panic!("expected FailJsonBody, got {:?}", other),
+    }
+    match unknown_field {
+        Err(Error::FailJsonBody { path, reason }) => {
+            assert_eq!("statuss", path);
+            assert!(reason.starts_with("unknown field `statuss`"), "{}", reason);
+        }
+        other => panic!("expected FailJsonBody, got {:?}", other),
+    }
+    assert!(matches!(syntax, Err(Error::FailJsonBody { .. })));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_filter_utils_json_body() -> Result<()> {
+    // -- FIXTURE
+    let filter = json_body::<PassengerPatch>(64)
+        .map(|patch: PassengerPatch| patch.get_status())
+        .recover(handle_rejection);
+
+    // -- ACTION
+    let ok = warp::test::request()
+        .method("POST")
+        .body(r#"{"status": "boarded"}"#)
+        .reply(&filter)
+        .await;
+    let wrong = warp::test::request()
+        .method("POST")
+        .body(r#"{"status": true}"#)
+        .reply(&filter)
+        .await;
+    let too_large = warp::test::request()
+        .method("POST")
+        .body(format!(r#"{{"status": "{}"}}"#, "x".repeat(100)))
+        .reply(&filter)
+        .await;
+
+    // -- CHECK
+    assert_eq!(200, ok.status(), "http status");
+    assert_eq!("boarded", from_utf8(ok.body())?);
+    assert_eq!(400, wrong.status(), "http status");
+    let body: Value = serde_json::from_slice(wrong.body())?;
+    assert_eq!("web::Error", body["errorMessage"]);
+    assert_eq!("status", body["details"]["path"]);
+    assert!(body["details"]["reason"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("expected a string"));
+    assert_eq!(413, too_large.status(), "http status");
+    let body: Value = serde_json::from_slice(too_large.body())?;
+    assert_eq!(64, body["details"]["limit"]);
+    Ok(())
+}
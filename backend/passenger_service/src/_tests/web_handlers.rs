@@ -1,8 +1,12 @@
 use super::handlers;
-use crate::model::{init_db, Passenger, PassengerDao};
+use crate::model::{
+    init_db, Passenger, PassengerDao, PassengerFilter, PassengerFromMrz, PassengerMerge, PassengerPatch,
+    PassengerSearch, PassengerSearchHit,
+};
 use crate::security::utx_from_token;
 use crate::web::handle_rejection;
 use anyhow::{Context, Result};
+use chrono::NaiveDate;
 use serde::Deserialize;
 use serde_json::{from_str, from_value, json, Value};
 use std::{str::from_utf8, sync::Arc};
@@ -34,6 +38,32 @@ async fn web_handlers_list() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn web_handlers_list_updated_since() -> Result<()> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let db = Arc::new(db);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let response = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/passengers?updated_since=2023-04-01T12:00:00Z&sort=-updated_at")
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let passengers: Vec<Passenger> = extract_body_data(response)?;
+    assert_eq!(1, passengers.len(), "number of passengers");
+    assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", passengers[0].id.to_string());
+    assert_eq!(
+        "7bb0d513-6c69-49bb-9b1f-9bf456467f88",
+        passengers[0].updated_by.to_string()
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn web_passenger_get_ok() -> Result<()> {
     // -- FIXTURE
@@ -64,6 +94,27 @@ async fn web_passenger_get_ok() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn web_passenger_get_not_found() -> Result<()> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let db = Arc::new(db);
+    let passsenger_apis = handlers("api", db).recover(handle_rejection);
+
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/passengers/52188bd6-733a-4856-a10e-c59b937bb573")
+        .reply(&passsenger_apis)
+        .await;
+
+    // -- CHECK - status
+    assert_eq!(404, resp.status(), "http status");
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn web_passenger_create_ok() -> Result<()> {
     // -- FIXTURE
@@ -134,6 +185,99 @@ async fn web_passenger_update_ok() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn web_passenger_update_keeps_profile() -> Result<()> {
+    // -- FIXTURE - a passenger with a profile
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let token = "3cb430d0-8914-4c71-aaf9-0ed2b163eca6";
+    let utx = utx_from_token(&db, token).await?;
+    let data = PassengerPatch {
+        first_name: Some("test - web_passenger_update_keeps_profile".to_string()),
+        last_name: Some("Doe".to_string()),
+        status: Some("checked in".to_string()),
+        date_of_birth: NaiveDate::from_ymd_opt(1980, 2, 29),
+        gender: Some("f".to_string()),
+        nationality: Some("fra".to_string()),
+        email: Some("jane.doe@example.com".to_string()),
+        phone: Some("+33612345678".to_string()),
+        ..Default::default()
+    };
+    let created = PassengerDao::create(&db, &utx, data).await?;
+    // -- ACTION - only the email
+    let resp = warp::test::request()
+        .method("PATCH")
+        .header("X-Auth-Token", token)
+        .path(&format!("/api/passengers/{}", created.id))
+        .json(&json!({ "email": "jane@example.com" }))
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK - the other fields unchanged
+    assert_eq!(200, resp.status(), "http status");
+    let passenger: Passenger = extract_body_data(resp)?;
+    assert_eq!(Some("jane@example.com"), passenger.email.as_deref());
+    assert_eq!(created.first_name, passenger.first_name);
+    assert_eq!(created.last_name, passenger.last_name);
+    assert_eq!(created.status, passenger.status);
+    assert_eq!(created.date_of_birth, passenger.date_of_birth);
+    assert_eq!(created.gender, passenger.gender);
+    assert_eq!(created.nationality, passenger.nationality);
+    assert_eq!(created.phone, passenger.phone);
+
+    // -- ACTION - only the last name
+    let resp = warp::test::request()
+        .method("PATCH")
+        .header("X-Auth-Token", token)
+        .path(&format!("/api/passengers/{}", created.id))
+        .json(&json!({ "last_name": "Dupontel" }))
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK - found by both names
+    assert_eq!(200, resp.status(), "http status");
+    let passenger: Passenger = extract_body_data(resp)?;
+    assert_eq!(
+        ("Dupontel", &created.first_name),
+        (passenger.last_name.as_str(), &passenger.first_name)
+    );
+    let search = PassengerSearch {
+        q: "dupontel keeps profile".to_string(),
+        limit: Some(100),
+    };
+    let hits = PassengerDao::search(&db, &utx, &search).await?;
+    assert!(hits.iter().any(|hit| hit.passenger.id == created.id));
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_passenger_update_unknown_field() -> Result<()> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let db = Arc::new(db);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let body = json!({
+        "first_name": "James Tiberius",
+        "firstname": "James"
+    });
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("PATCH")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/passengers/4208b168-08b2-4c45-915d-c51f6f71213b")
+        .json(&body)
+        .reply(&passenger_apis)
+        .await;
+
+    // -- CHECK - rejected, with the field at fault
+    assert_eq!(400, resp.status(), "http status");
+    let body: Value = from_str(from_utf8(resp.body())?)?;
+    assert_eq!("firstname", body["details"]["path"]);
+    let utx = utx_from_token(&db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
+    let passenger = PassengerDao::get(&db, &utx, "4208b168-08b2-4c45-915d-c51f6f71213b".to_string()).await?;
+    assert_ne!("James Tiberius", passenger.first_name, "not updated");
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn web_todo_delete_ok() -> Result<()> {
     // -- FIXTURE
@@ -162,7 +306,7 @@ async fn web_todo_delete_ok() -> Result<()> {
 
     // -- CHECK - list .len() should be 1
     let utx = utx_from_token(&db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
-    let passengers = PassengerDao::list(&db, &utx).await?;
+    let passengers = PassengerDao::list(&db, &utx, &PassengerFilter::default()).await?;
     assert_eq!(1, passengers.len(), "passengers length");
     assert_eq!(
         "b03535ad-0b98-4c8f-8b5a-66960c71392c",
@@ -173,6 +317,128 @@ async fn web_todo_delete_ok() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn web_passenger_search() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let response = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/passengers/search?q=passenger%20101")
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK - the seed passenger 101 first, 100 matches "passenger" only
+    assert_eq!(response.status(), 200, "http status");
+    let hits: Vec<PassengerSearchHit> = extract_body_data(response)?;
+    assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", hits[0].passenger.id.to_string());
+    assert_eq!(1.0, hits[0].score);
+    assert!(hits.iter().all(|hit| hit.passenger.first_name.starts_with("Passenger")));
+
+    // -- ACTION - no word
+    let response = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/passengers/search?q=%20")
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 400, "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_passenger_merge() -> Result<()> {
+    // -- FIXTURE - the seed passengers 100 and 101
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let request = |method: &str, path: &str| {
+        warp::test::request()
+            .method(method)
+            .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+            .path(path)
+    };
+    // -- ACTION
+    let response = request("GET", "/api/passengers/duplicates?threshold=0.5")
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let duplicates: Value = extract_body_data(response)?;
+    assert_eq!(1, duplicates.as_array().map(Vec::len).unwrap_or_default());
+
+    // -- ACTION
+    let response = request("POST", "/api/passengers/merge")
+        .json(&json!({
+            "passenger_ids": ["4208b168-08b2-4c45-915d-c51f6f71213b", "b03535ad-0b98-4c8f-8b5a-66960c71392c"],
+            "survivor_id": "b03535ad-0b98-4c8f-8b5a-66960c71392c",
+        }))
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let merge: PassengerMerge = extract_body_data(response)?;
+    assert_eq!("b03535ad-0b98-4c8f-8b5a-66960c71392c", merge.survivor.id.to_string());
+    assert_eq!(Some("new"), merge.survivor.status.as_deref());
+    let response = request("GET", "/api/passengers/4208b168-08b2-4c45-915d-c51f6f71213b")
+        .reply(&passenger_apis)
+        .await;
+    assert_eq!(response.status(), 404, "http status");
+
+    // -- ACTION - a single passenger
+    let response = request("POST", "/api/passengers/merge")
+        .json(&json!({ "passenger_ids": ["b03535ad-0b98-4c8f-8b5a-66960c71392c"] }))
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 400, "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_passenger_from_mrz() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let request = |mrz: &str| {
+        warp::test::request()
+            .method("POST")
+            .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+            .path("/api/passengers/from-mrz")
+            .json(&json!({ "mrz": mrz }))
+    };
+    let mrz = "P<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<\nL898902C36NLD7408122F1204159ZE184226B<<<<<10";
+
+    // -- ACTION - expiry date mistyped
+    let response = request(&mrz.replace("F120415", "F120416")).reply(&passenger_apis).await;
+    // -- CHECK - the fields to read again
+    assert_eq!(response.status(), 422, "http status");
+    let body: Value = from_str(from_utf8(response.body())?)?;
+    assert_eq!(
+        json!([
+            {"field": "expiry_date", "expected": "0", "found": "9"},
+            {"field": "composite", "expected": "3", "found": "0"},
+        ]),
+        body["details"]["check_digits"]
+    );
+
+    // -- ACTION
+    let response = request(mrz).reply(&passenger_apis).await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let from_mrz: PassengerFromMrz = extract_body_data(response)?;
+    assert!(from_mrz.created);
+    assert_eq!("ERIKSSON", from_mrz.passenger.last_name);
+    assert_eq!("L898902C3", from_mrz.document.number);
+
+    // -- ACTION - one line missing
+    let response = request(&mrz[..44]).reply(&passenger_apis).await;
+    // -- CHECK
+    assert_eq!(response.status(), 400, "http status");
+    Ok(())
+}
+
 // region: Web Test Utils
 fn extract_body_data<D>(resp: Response<Bytes>) -> Result<D>
 where
@@ -0,0 +1,110 @@
+use super::handlers;
+use crate::model::{init_db, ApiKey, ApiKeyCreated, ApiKeyDao, ApiKeyForCreate};
+use crate::security::{utx_from_token, Permission};
+use crate::web::handle_rejection;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{from_str, from_value, json, Value};
+use std::{str::from_utf8, sync::Arc};
+use warp::hyper::body::Bytes;
+use warp::hyper::Response;
+use warp::Filter;
+
+#[tokio::test]
+async fn web_api_key_create_list_revoke() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let api_key_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let admin_token = admin_token_fx(&db).await?;
+    let body = json!({
+        "name": "flight_service",
+        "scopes": ["passenger:read", "passenger:write"],
+    });
+
+    // -- ACTION - create
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &admin_token)
+        .path("/api/admin/api-keys")
+        .json(&body)
+        .reply(&api_key_apis)
+        .await;
+    // -- CHECK - create
+    assert_eq!(200, resp.status(), "http status");
+    let created: ApiKeyCreated = extract_body_data(resp)?;
+    assert_eq!("passenger:read,passenger:write", created.api_key.scopes);
+    let key_utx = utx_from_token(&db, &created.token).await?;
+    assert!(key_utx.has_permission(Permission::PassengerWrite));
+
+    // -- ACTION - list
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", &admin_token)
+        .path("/api/admin/api-keys")
+        .reply(&api_key_apis)
+        .await;
+    // -- CHECK - list, without secrets
+    assert_eq!(200, resp.status(), "http status");
+    let body = from_utf8(resp.body())?;
+    assert!(!body.contains("key_hash") && !body.contains(&created.token));
+    let api_keys: Vec<ApiKey> = from_value(from_str::<Value>(body)?["data"].take())?;
+    assert_eq!(2, api_keys.len());
+
+    // -- ACTION - revoke
+    let resp = warp::test::request()
+        .method("DELETE")
+        .header("X-Auth-Token", &admin_token)
+        .path(&format!("/api/admin/api-keys/{}", created.api_key.id))
+        .reply(&api_key_apis)
+        .await;
+    // -- CHECK - revoke
+    assert_eq!(200, resp.status(), "http status");
+    assert!(utx_from_token(&db, &created.token).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_api_key_forbidden() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let api_key_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/admin/api-keys")
+        .reply(&api_key_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(403, resp.status(), "http status");
+    Ok(())
+}
+
+// region: Web Test Utils
+async fn admin_token_fx(db: &crate::model::Db) -> Result<String> {
+    let utx = utx_from_token(db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
+    let data = ApiKeyForCreate {
+        name: "test - admin".to_string(),
+        scopes: vec![Permission::Admin],
+        expires_at: None,
+    };
+    Ok(ApiKeyDao::create(db, &utx, data).await?.token)
+}
+
+fn extract_body_data<D>(resp: Response<Bytes>) -> Result<D>
+where
+    for<'de> D: Deserialize<'de>,
+{
+    // parse the body as serde_json::Value
+    let body = from_utf8(resp.body())?;
+    let mut body: Value =
+        from_str(body).with_context(|| format!("Cannot parse resp.body to JSON. resp.body: '{}'", body))?;
+    // extract the data
+    let data = body["data"].take();
+    // deserialize the data to D
+    let data: D = from_value(data)?;
+    Ok(data)
+}
+
+// endregion: Web Test Utils
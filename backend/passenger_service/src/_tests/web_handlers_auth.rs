@@ -0,0 +1,137 @@
+use super::handlers;
+use crate::model::{init_db, UserSession};
+use crate::security::auth::AuthTokens;
+use crate::web::handle_rejection;
+use anyhow::Result;
+use serde_json::{from_str, from_value, json, Value};
+use std::{str::from_utf8, sync::Arc};
+use warp::Filter;
+
+#[tokio::test]
+async fn web_auth_login_refresh_logout() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let auth_apis = handlers("api", db.clone()).recover(handle_rejection);
+
+    // -- ACTION - login with the seed user
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/auth/login")
+        .json(&json!({"username": "demo", "pwd": "welcome"}))
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK - login
+    assert_eq!(200, resp.status(), "http status");
+    let tokens: AuthTokens = from_value(from_str::<Value>(from_utf8(resp.body())?)?["data"].take())?;
+
+    // -- ACTION - refresh
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/auth/refresh")
+        .json(&json!({ "refresh_token": tokens.refresh_token }))
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK - refresh
+    assert_eq!(200, resp.status(), "http status");
+    let tokens: AuthTokens = from_value(from_str::<Value>(from_utf8(resp.body())?)?["data"].take())?;
+
+    // -- ACTION - logout
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &tokens.access_token)
+        .path("/api/auth/logout")
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK - logout, then refresh is refused
+    assert_eq!(200, resp.status(), "http status");
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/auth/refresh")
+        .json(&json!({ "refresh_token": tokens.refresh_token }))
+        .reply(&auth_apis)
+        .await;
+    assert_eq!(401, resp.status(), "http status");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_auth_sessions_list_revoke() -> Result<()> {
+    // -- FIXTURE - two logins of the seed user
+    let db = Arc::new(init_db().await?);
+    let auth_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/api/auth/login")
+            .json(&json!({"username": "demo", "pwd": "welcome"}))
+            .reply(&auth_apis)
+            .await;
+        tokens.push(from_value::<AuthTokens>(
+            from_str::<Value>(from_utf8(resp.body())?)?["data"].take(),
+        )?);
+    }
+
+    // -- ACTION - list
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", &tokens[0].access_token)
+        .path("/api/auth/sessions")
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK - list, newest first
+    assert_eq!(200, resp.status(), "http status");
+    let sessions: Vec<UserSession> = from_value(from_str::<Value>(from_utf8(resp.body())?)?["data"].take())?;
+    assert_eq!(2, sessions.len());
+
+    // -- ACTION - revoke the other session
+    let resp = warp::test::request()
+        .method("DELETE")
+        .header("X-Auth-Token", &tokens[0].access_token)
+        .path(&format!("/api/auth/sessions/{}", sessions[0].id))
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK - its access token is rejected, not the current one
+    assert_eq!(200, resp.status(), "http status");
+    for (token, status) in [(&tokens[1].access_token, 401), (&tokens[0].access_token, 200)] {
+        let resp = warp::test::request()
+            .method("GET")
+            .header("X-Auth-Token", token)
+            .path("/api/auth/sessions")
+            .reply(&auth_apis)
+            .await;
+        assert_eq!(status, resp.status(), "http status");
+    }
+
+    // -- ACTION - revoke a session of another user
+    let resp = warp::test::request()
+        .method("DELETE")
+        .header("X-Auth-Token", "f7a25ba8-fc87-4b6f-9297-611921ef0d7a")
+        .path(&format!("/api/auth/sessions/{}", sessions[1].id))
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(404, resp.status(), "http status");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_auth_login_wrong_pwd() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let auth_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/auth/login")
+        .json(&json!({"username": "demo", "pwd": "wrong"}))
+        .reply(&auth_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(401, resp.status(), "http status");
+    let body: Value = from_str(from_utf8(resp.body())?)?;
+    assert_eq!("security::Error", body["errorMessage"]);
+    Ok(())
+}
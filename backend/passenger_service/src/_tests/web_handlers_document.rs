@@ -0,0 +1,101 @@
+use super::handlers;
+use crate::model::{init_db, PassengerDocument};
+use crate::web::handle_rejection;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{from_str, from_value, json, Value};
+use std::{str::from_utf8, sync::Arc};
+use warp::hyper::body::Bytes;
+use warp::hyper::Response;
+use warp::Filter;
+
+#[tokio::test]
+async fn web_passenger_documents() -> Result<()> {
+    // -- FIXTURE - the seed passenger 100
+    let db = Arc::new(init_db().await?);
+    let document_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let request = |method: &str, path: &str| {
+        warp::test::request()
+            .method(method)
+            .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+            .path(path)
+    };
+    let documents_path = "/api/passengers/4208b168-08b2-4c45-915d-c51f6f71213b/documents";
+
+    // -- ACTION
+    let response = request("POST", documents_path)
+        .json(&json!({
+            "doc_type": "id_card",
+            "number": "D23145890",
+            "issuing_country": "UTO",
+        }))
+        .reply(&document_apis)
+        .await;
+    // -- CHECK - not a country
+    assert_eq!(response.status(), 400, "http status");
+
+    // -- ACTION
+    let response = request("POST", documents_path)
+        .json(&json!({
+            "doc_type": "id_card",
+            "number": "D23145890",
+            "issuing_country": "NLD",
+            "expiry_date": "2099-01-01",
+        }))
+        .reply(&document_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let document: PassengerDocument = extract_body_data(response)?;
+    assert!(document.warnings.is_empty());
+    let document_path = format!("{}/{}", documents_path, document.id);
+
+    // -- ACTION
+    let response = request("PATCH", &document_path)
+        .json(&json!({ "number": "X1234567" }))
+        .reply(&document_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let document: PassengerDocument = extract_body_data(response)?;
+    assert_eq!("X1234567", document.number);
+
+    // -- ACTION
+    let response = request("GET", documents_path).reply(&document_apis).await;
+    // -- CHECK
+    assert_eq!(response.status(), 200, "http status");
+    let documents: Vec<PassengerDocument> = extract_body_data(response)?;
+    assert_eq!(1, documents.len());
+
+    // -- ACTION
+    let response = request("DELETE", &document_path).reply(&document_apis).await;
+    assert_eq!(response.status(), 200, "http status");
+    let response = request("GET", &document_path).reply(&document_apis).await;
+    // -- CHECK
+    assert_eq!(response.status(), 404, "http status");
+
+    // -- ACTION - unknown passenger
+    let response = request("GET", "/api/passengers/00000000-0000-0000-0000-000000000000/documents")
+        .reply(&document_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(response.status(), 404, "http status");
+    Ok(())
+}
+
+// region: Web Test Utils
+fn extract_body_data<D>(resp: Response<Bytes>) -> Result<D>
+where
+    for<'de> D: Deserialize<'de>,
+{
+    // parse the body as serde_json::Value
+    let body = from_utf8(resp.body())?;
+    let mut body: Value =
+        from_str(body).with_context(|| format!("Cannot parse resp.body to JSON. resp.body: '{}'", body))?;
+    // extract the data
+    let data = body["data"].take();
+    // deserialize the data to D
+    let data: D = from_value(data)?;
+    Ok(data)
+}
+// endregion: Web Test Utils
@@ -0,0 +1,193 @@
+use super::{events, handlers, token_ended};
+use crate::config::config;
+use crate::model::tests::passenger_fx;
+use crate::model::{
+    init_db, passenger_events, Passenger, PassengerDao, PassengerEventKind, PassengerEvents, PassengerPatch,
+};
+use crate::security::token::{encode, Claims};
+use crate::security::{revocation, utx_from_token, Role, UserCtx};
+use crate::web::handle_rejection;
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use futures::StreamExt;
+use sqlx::types::Uuid;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::{Filter, Reply};
+
+const TOKEN: &str = "3cb430d0-8914-4c71-aaf9-0ed2b163eca6";
+
+#[tokio::test]
+async fn web_handlers_events_stream() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = handlers("api", db.clone()).map(Reply::into_response);
+    // -- ACTION - the token in the query, as EventSource does
+    let resp = warp::test::request()
+        .path(&format!("/api/passengers/events?access_token={}", TOKEN))
+        .filter(&filter)
+        .await
+        .map_err(|ex| anyhow::anyhow!("{:?}", ex))?;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    assert_eq!("text/event-stream", resp.headers()["content-type"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_events_missing_token() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let resp = warp::test::request()
+        .path("/api/passengers/events")
+        .reply(&filter)
+        .await;
+    // -- CHECK
+    assert_eq!(400, resp.status(), "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_events_published_by_dao() -> Result<()> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let utx = utx_from_token(&db, TOKEN).await?;
+    let mut stream = Box::pin(events(passenger_events(), utx.clone(), None));
+    // -- ACTION
+    let patch = PassengerPatch {
+        first_name: Some("Leonard".to_string()),
+        ..Default::default()
+    };
+    let passenger = PassengerDao::create(&db, &utx, patch).await?;
+    PassengerDao::delete(&db, &utx, passenger.id.to_string()).await?;
+    // -- CHECK
+    let created = next_event(&mut stream).await?;
+    assert!(created.contains("event:created\n"), "{}", created);
+    assert!(created.contains(&passenger.id.to_string()), "{}", created);
+    let deleted = next_event(&mut stream).await?;
+    assert!(deleted.contains("event:deleted\n"), "{}", deleted);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_events_replay_and_scope() -> Result<()> {
+    // -- FIXTURE - 3 events, the second of another user
+    let hub = PassengerEvents::new(2, 1);
+    let user = UserCtx::new(Uuid::from_u128(rand::random()).to_string(), None, vec![Role::User]);
+    let other = UserCtx::new(Uuid::from_u128(rand::random()).to_string(), None, vec![Role::User]);
+    hub.publish(PassengerEventKind::Created, user_passenger_fx(&user));
+    hub.publish(PassengerEventKind::Created, user_passenger_fx(&other));
+    hub.publish(PassengerEventKind::Updated, user_passenger_fx(&user));
+    // -- ACTION
+    let mut resumed = Box::pin(events(&hub, user.clone(), Some(1)));
+    let mut lost = Box::pin(events(&hub, user.clone(), Some(0)));
+    // -- CHECK - without scope, the other user's event is in the feed
+    let event = next_event(&mut resumed).await?;
+    if crate::config::config().passenger_scope == crate::model::Scope::Off {
+        assert!(
+            event.contains("event:created\n") && event.contains("\nid:2\n"),
+            "{}",
+            event
+        );
+        let event = next_event(&mut resumed).await?;
+        assert!(
+            event.contains("event:updated\n") && event.contains("\nid:3\n"),
+            "{}",
+            event
+        );
+    } else {
+        assert!(
+            event.contains("event:updated\n") && event.contains("\nid:3\n"),
+            "{}",
+            event
+        );
+    }
+    let event = next_event(&mut lost).await?;
+    assert!(
+        event.contains("event:reset\n") && event.contains("\nid:3\n"),
+        "{}",
+        event
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_events_token_expired() -> Result<()> {
+    // -- FIXTURE - a token expiring in 2 seconds
+    let db = Arc::new(init_db().await?);
+    let token = access_token_fx(ChronoDuration::seconds(2))?;
+    let utx = utx_from_token(&db, &token).await?;
+    // -- ACTION
+    let ended = tokio::time::timeout(
+        Duration::from_secs(5),
+        token_ended(db.clone(), Some(token), utx.expires_at, Duration::from_secs(60)),
+    )
+    .await;
+    // -- CHECK
+    assert!(ended.is_ok(), "the stream should end at the token expiration");
+    assert!(Utc::now() >= utx.expires_at.unwrap(), "not before the expiration");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_events_token_revoked() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let token = access_token_fx(ChronoDuration::minutes(5))?;
+    let utx = utx_from_token(&db, &token).await?;
+    let check = Duration::from_millis(50);
+    let ended = token_ended(db.clone(), Some(token.clone()), utx.expires_at, check);
+    tokio::pin!(ended);
+    // -- CHECK - runs while the token is valid
+    assert!(
+        tokio::time::timeout(check * 4, &mut ended).await.is_err(),
+        "ended with a valid token"
+    );
+    // -- ACTION - logout
+    let session_id = utx.session_id.clone().unwrap();
+    revocation::revoke(&db, &session_id, utx.expires_at.unwrap()).await?;
+    // -- CHECK
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), &mut ended).await.is_ok(),
+        "the stream should end once the session is revoked"
+    );
+    Ok(())
+}
+
+// region: Test Utils
+async fn next_event<S>(stream: &mut S) -> Result<String>
+where
+    S: futures::Stream<Item = Result<warp::sse::Event, std::convert::Infallible>> + Unpin,
+{
+    let event = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("stream ended"))??;
+    Ok(event.to_string())
+}
+
+fn access_token_fx(valid_for: ChronoDuration) -> Result<String> {
+    let id = Uuid::from_u128(rand::random()).to_string();
+    let claims = Claims {
+        sub: TOKEN.to_string(),
+        tid: None,
+        sid: format!("session-{}", id),
+        jti: format!("jti-{}", id),
+        roles: vec![Role::User],
+        exp: (Utc::now() + valid_for).timestamp(),
+    };
+    Ok(encode(&claims, &config().auth_token_key)?)
+}
+
+// a passenger of the user
+fn user_passenger_fx(utx: &UserCtx) -> Passenger {
+    let uid = Uuid::parse_str(&utx.user_id).unwrap();
+    Passenger {
+        uid,
+        created_by: uid,
+        updated_by: uid,
+        ..passenger_fx("John", "Doe")
+    }
+}
+// endregion: Test Utils
@@ -0,0 +1,64 @@
+use super::{handlers, set_shutting_down};
+use crate::model::init_db;
+use anyhow::Result;
+use serde_json::{from_slice, Value};
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn web_health_healthz() -> Result<()> {
+    // -- FIXTURE - no database needed
+    let db = Arc::new(PgPoolOptions::new().connect_lazy("postgresql://nobody@127.0.0.1:1/none")?);
+    // -- ACTION
+    let resp = warp::test::request().path("/healthz").reply(&handlers(db)).await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    let body: Value = from_slice(resp.body())?;
+    assert_eq!("ok", body["status"]);
+    assert_eq!(true, body["checks"]["process"]["ok"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_health_readyz_ready_then_shutting_down() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let health_apis = handlers(db);
+
+    // -- ACTION - ready
+    let resp = warp::test::request().path("/readyz").reply(&health_apis).await;
+    // -- CHECK - ready
+    assert_eq!(200, resp.status(), "http status");
+    let body: Value = from_slice(resp.body())?;
+    assert_eq!("ready", body["status"]);
+    assert_eq!(true, body["checks"]["database"]["ok"]);
+    assert!(body["checks"]["database"]["latency_ms"].is_number());
+    let applied = &body["checks"]["migrations"]["details"]["applied"];
+    assert_eq!("01-create-schema.sql", applied[0], "{}", body);
+    assert_eq!(0, body["checks"]["migrations"]["details"]["failed_statements"]);
+
+    // -- ACTION - shutting down
+    set_shutting_down();
+    let resp = warp::test::request().path("/readyz").reply(&health_apis).await;
+    // -- CHECK - not ready, the database is still fine
+    assert_eq!(503, resp.status(), "http status");
+    let body: Value = from_slice(resp.body())?;
+    assert_eq!("not_ready", body["status"]);
+    assert_eq!(true, body["checks"]["database"]["ok"]);
+    assert_eq!(false, body["checks"]["shutdown"]["ok"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_health_readyz_database_down() -> Result<()> {
+    // -- FIXTURE - nothing listens on port 1
+    let db = Arc::new(PgPoolOptions::new().connect_lazy("postgresql://nobody@127.0.0.1:1/none")?);
+    // -- ACTION
+    let resp = warp::test::request().path("/readyz").reply(&handlers(db)).await;
+    // -- CHECK
+    assert_eq!(503, resp.status(), "http status");
+    let body: Value = from_slice(resp.body())?;
+    assert_eq!(false, body["checks"]["database"]["ok"]);
+    assert!(body["checks"]["database"]["error"].is_string());
+    Ok(())
+}
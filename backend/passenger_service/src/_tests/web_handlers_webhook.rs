@@ -0,0 +1,150 @@
+use super::handlers;
+use crate::model::{init_db, ApiKeyDao, ApiKeyForCreate, Webhook, WebhookCreated, WebhookDelivery};
+use crate::security::{utx_from_token, Permission};
+use crate::web::handle_rejection;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{from_str, from_value, json, Value};
+use std::{str::from_utf8, sync::Arc};
+use warp::hyper::body::Bytes;
+use warp::hyper::Response;
+use warp::Filter;
+
+#[tokio::test]
+async fn web_webhook_create_list_update() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let webhook_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let admin_token = admin_token_fx(&db).await?;
+    let body = json!({
+        "url": "https://flight.example.com/hooks/passengers",
+        "event_types": ["passenger.created", "passenger.deleted"],
+    });
+
+    // -- ACTION - create
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &admin_token)
+        .path("/api/webhooks")
+        .json(&body)
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK - create, with the secret
+    assert_eq!(200, resp.status(), "http status");
+    let created: WebhookCreated = extract_body_data(resp)?;
+    assert_eq!("passenger.created,passenger.deleted", created.webhook.event_types);
+    assert!(created.secret.starts_with("whsec_"));
+
+    // -- ACTION - list
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", &admin_token)
+        .path("/api/webhooks")
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK - list, without the secret
+    assert_eq!(200, resp.status(), "http status");
+    assert!(!from_utf8(resp.body())?.contains(&created.secret));
+    let webhooks: Vec<Webhook> = extract_body_data(resp)?;
+    assert_eq!(1, webhooks.len());
+
+    // -- ACTION - pause
+    let resp = warp::test::request()
+        .method("PATCH")
+        .header("X-Auth-Token", &admin_token)
+        .path(&format!("/api/webhooks/{}", created.webhook.id))
+        .json(&json!({ "active": false }))
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    let webhook: Webhook = extract_body_data(resp)?;
+    assert!(!webhook.active);
+
+    // -- ACTION - delivery log
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", &admin_token)
+        .path(&format!("/api/webhooks/{}/deliveries?status=dead", created.webhook.id))
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(200, resp.status(), "http status");
+    let deliveries: Vec<WebhookDelivery> = extract_body_data(resp)?;
+    assert!(deliveries.is_empty());
+
+    // -- ACTION - retry of an unknown delivery
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &admin_token)
+        .path(&format!("/api/webhooks/{}/deliveries/42/retry", created.webhook.id))
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(404, resp.status(), "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_webhook_create_invalid() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let webhook_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let admin_token = admin_token_fx(&db).await?;
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", &admin_token)
+        .path("/api/webhooks")
+        .json(&json!({ "url": "not a url" }))
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(400, resp.status(), "http status");
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_webhook_forbidden() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let webhook_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("GET")
+        .header("X-Auth-Token", "3cb430d0-8914-4c71-aaf9-0ed2b163eca6")
+        .path("/api/webhooks")
+        .reply(&webhook_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(403, resp.status(), "http status");
+    Ok(())
+}
+
+// region: Web Test Utils
+async fn admin_token_fx(db: &crate::model::Db) -> Result<String> {
+    let utx = utx_from_token(db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
+    let data = ApiKeyForCreate {
+        name: "test - admin".to_string(),
+        scopes: vec![Permission::Admin],
+        expires_at: None,
+    };
+    Ok(ApiKeyDao::create(db, &utx, data).await?.token)
+}
+
+fn extract_body_data<D>(resp: Response<Bytes>) -> Result<D>
+where
+    for<'de> D: Deserialize<'de>,
+{
+    // parse the body as serde_json::Value
+    let body = from_utf8(resp.body())?;
+    let mut body: Value =
+        from_str(body).with_context(|| format!("Cannot parse resp.body to JSON. resp.body: '{}'", body))?;
+    // extract the data
+    let data = body["data"].take();
+    // deserialize the data to D
+    let data: D = from_value(data)?;
+    Ok(data)
+}
+
+// endregion: Web Test Utils
@@ -0,0 +1,196 @@
+use super::{handlers, Connection, Subscription};
+use crate::model::tests::passenger_fx;
+use crate::model::{init_db, PassengerEvent, PassengerEventKind};
+use crate::security::{Role, UserCtx};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use warp::test::WsClient;
+
+const TOKEN: &str = "3cb430d0-8914-4c71-aaf9-0ed2b163eca6";
+
+#[tokio::test]
+async fn web_handlers_ws_auth_and_crud() -> Result<()> {
+    // -- FIXTURE - authenticated by the first request
+    let db = Arc::new(init_db().await?);
+    let mut client = warp::test::ws().path("/api/ws").handshake(handlers("api", db)).await?;
+    let denied = call(&mut client, 1, "passenger.list", json!({})).await?;
+    let auth = call(&mut client, 2, "auth", json!({ "token": TOKEN })).await?;
+
+    // -- ACTION
+    let created = call(&mut client, 3, "passenger.create", json!({ "first_name": "Hoshi" })).await?;
+    let id = created["result"]["id"].as_str().unwrap_or_default().to_string();
+    let updated = call(
+        &mut client,
+        4,
+        "passenger.update",
+        json!({ "id": id, "data": { "status": "boarded" } }),
+    )
+    .await?;
+    let got = call(&mut client, 5, "passenger.get", json!({ "id": id })).await?;
+    let deleted = call(&mut client, 6, "passenger.delete", json!({ "id": id })).await?;
+    let missing = call(&mut client, 7, "passenger.get", json!({ "id": id })).await?;
+
+    // -- CHECK
+    assert_eq!(-32001, denied["error"]["code"], "{}", denied);
+    assert_eq!(TOKEN, auth["result"]["user_id"], "{}", auth);
+    assert_eq!("Hoshi", created["result"]["first_name"], "{}", created);
+    assert_eq!("boarded", updated["result"]["status"], "{}", updated);
+    assert_eq!(id, got["result"]["id"], "{}", got);
+    assert_eq!(id, deleted["result"]["id"], "{}", deleted);
+    assert_eq!(-32004, missing["error"]["code"], "{}", missing);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_ws_errors() -> Result<()> {
+    // -- FIXTURE - authenticated by the query parameter
+    let db = Arc::new(init_db().await?);
+    let path = format!("/api/ws?access_token={}", TOKEN);
+    let mut client = warp::test::ws().path(&path).handshake(handlers("api", db)).await?;
+
+    // -- ACTION
+    client.send_text("{not json").await;
+    let parse_error = recv(&mut client).await?;
+    let unknown = call(&mut client, 1, "passenger.fly", json!({})).await?;
+    let invalid = call(
+        &mut client,
+        2,
+        "passenger.create",
+        json!({ "first_name": "Travis", "rank": 2 }),
+    )
+    .await?;
+    let pong = call(&mut client, 3, "ping", Value::Null).await?;
+
+    // -- CHECK
+    assert_eq!(-32700, parse_error["error"]["code"], "{}", parse_error);
+    assert_eq!(-32601, unknown["error"]["code"], "{}", unknown);
+    assert_eq!(-32602, invalid["error"]["code"], "{}", invalid);
+    assert_eq!("rank", invalid["error"]["data"]["path"], "{}", invalid);
+    assert_eq!("pong", pong["result"], "{}", pong);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_ws_subscribe_filtered() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let filter = handlers("api", db);
+    let mut watcher = warp::test::ws()
+        .path("/api/ws")
+        .header("X-Auth-Token", TOKEN)
+        .handshake(filter.clone())
+        .await?;
+    let mut writer = warp::test::ws()
+        .path("/api/ws")
+        .header("X-Auth-Token", TOKEN)
+        .handshake(filter)
+        .await?;
+    let subscribed = call(&mut watcher, 1, "subscribe", json!({ "status": "pending" })).await?;
+    let subscription = subscribed["result"]["subscription"].clone();
+
+    // -- ACTION - another client creates 2 passengers, one pending
+    call(
+        &mut writer,
+        1,
+        "passenger.create",
+        json!({ "first_name": "Malcolm", "status": "new" }),
+    )
+    .await?;
+    let pending = call(
+        &mut writer,
+        2,
+        "passenger.create",
+        json!({ "first_name": "Reed", "status": "pending" }),
+    )
+    .await?;
+
+    // -- CHECK - only the pending one is notified
+    let event = recv(&mut watcher).await?;
+    assert_eq!("passenger.event", event["method"], "{}", event);
+    assert_eq!(subscription, event["params"]["subscription"]);
+    assert_eq!("created", event["params"]["event"]["kind"]);
+    assert_eq!(pending["result"]["id"], event["params"]["event"]["passenger"]["id"]);
+    let unsubscribed = call(&mut watcher, 2, "unsubscribe", json!({ "subscription": subscription })).await?;
+    assert_eq!(true, unsubscribed["result"], "{}", unsubscribed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_ws_invalid_token() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    // -- ACTION
+    let mut client = warp::test::ws()
+        .path("/api/ws")
+        .header("X-Auth-Token", "pat_garbage")
+        .handshake(handlers("api", db))
+        .await?;
+    // -- CHECK - closed by the server
+    let closed = tokio::time::timeout(Duration::from_secs(5), client.recv_closed()).await?;
+    assert!(closed.is_ok(), "{:?}", closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_handlers_ws_backpressure() -> Result<()> {
+    // -- FIXTURE - room for one message only, the client does not read
+    let db = Arc::new(init_db().await?);
+    let (out, mut out_rx) = mpsc::channel(1);
+    let utx = UserCtx::new(TOKEN.to_string(), None, vec![Role::Admin]);
+    let mut conn = Connection {
+        db,
+        token: Some(TOKEN.to_string()),
+        utx: Some(utx),
+        subscriptions: HashMap::from([(7, Subscription::default())]),
+        next_subscription: 8,
+        out,
+        in_flight: Arc::new(Semaphore::new(1)),
+        reset_pending: false,
+    };
+
+    // -- ACTION
+    conn.notify(&event_fx(1));
+    conn.notify(&event_fx(2));
+    let first = out_rx.recv().await.ok_or_else(|| anyhow!("no message"))?;
+    conn.notify(&event_fx(3));
+    let reset = out_rx.recv().await.ok_or_else(|| anyhow!("no message"))?;
+
+    // -- CHECK - the second is dropped, then the reset takes the room of the third
+    let first: Value = serde_json::from_str(first.to_str().unwrap_or_default())?;
+    assert_eq!(1, first["params"]["event"]["id"], "{}", first);
+    let reset: Value = serde_json::from_str(reset.to_str().unwrap_or_default())?;
+    assert_eq!("passenger.reset", reset["method"], "{}", reset);
+    assert_eq!(7, reset["params"]["subscription"], "{}", reset);
+    assert!(out_rx.try_recv().is_err(), "the third is dropped too");
+    assert!(conn.reset_pending, "so another reset is owed");
+    Ok(())
+}
+
+// region: Test Utils
+fn event_fx(id: u64) -> PassengerEvent {
+    let passenger = passenger_fx("Phlox", "Doe");
+    PassengerEvent {
+        id,
+        kind: PassengerEventKind::Created,
+        passenger,
+    }
+}
+
+async fn call(client: &mut WsClient, id: u64, method: &str, params: Value) -> Result<Value> {
+    let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    client.send_text(request.to_string()).await;
+    recv(client).await
+}
+
+async fn recv(client: &mut WsClient) -> Result<Value> {
+    let message = tokio::time::timeout(Duration::from_secs(5), client.recv()).await??;
+    let text = message
+        .to_str()
+        .map_err(|_| anyhow!("not a text message: {:?}", message))?;
+    Ok(serde_json::from_str(text)?)
+}
+// endregion: Test Utils
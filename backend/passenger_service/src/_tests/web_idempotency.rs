@@ -0,0 +1,201 @@
+use super::{idempotent, request_hash};
+use crate::model::{init_db, IdempotencyKeyDao, Passenger, PassengerDao, PassengerFilter};
+use crate::security::utx_from_token;
+use crate::web::handle_rejection;
+use crate::web::handlers::{handlers, json_response};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde_json::{from_slice, from_value, json, Value};
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+const TOKEN: &str = "f7a25ba8-fc87-4b6f-9297-611921ef0d7a";
+
+#[tokio::test]
+async fn web_idempotency_replay() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let create = |key: &str, first_name: &str| {
+        warp::test::request()
+            .method("POST")
+            .header("X-Auth-Token", TOKEN)
+            .header("Idempotency-Key", key)
+            .path("/api/passengers")
+            .json(&json!({ "first_name": first_name }))
+    };
+
+    // -- ACTION - first request, then a retry
+    let first = create("key-1", "test - replay").reply(&passenger_apis).await;
+    let retry = create("key-1", "test - replay").reply(&passenger_apis).await;
+    // -- CHECK - same response, created once
+    assert_eq!(200, first.status(), "http status");
+    assert_eq!(200, retry.status(), "http status");
+    assert!(!first.headers().contains_key("Idempotent-Replayed"));
+    assert_eq!("true", retry.headers()["Idempotent-Replayed"]);
+    assert_eq!(first.body(), retry.body());
+    assert_eq!(3, passenger_count(&db).await?, "2 seed + 1 created");
+
+    // -- ACTION & CHECK - same key, other body
+    let resp = create("key-1", "test - other").reply(&passenger_apis).await;
+    assert_eq!(422, resp.status(), "http status");
+
+    // -- ACTION & CHECK - other key, or no key
+    assert_eq!(
+        200,
+        create("key-2", "test - replay").reply(&passenger_apis).await.status()
+    );
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", TOKEN)
+        .path("/api/passengers")
+        .json(&json!({ "first_name": "test - replay" }))
+        .reply(&passenger_apis)
+        .await;
+    assert_eq!(200, resp.status(), "http status");
+    assert_eq!(5, passenger_count(&db).await?, "2 seed + 3 created");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_idempotency_concurrent() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    let create = || {
+        warp::test::request()
+            .method("POST")
+            .header("X-Auth-Token", TOKEN)
+            .header("Idempotency-Key", "key-concurrent")
+            .path("/api/passengers/bulk")
+            .json(&json!([{ "first_name": "test - bulk 1" }, { "first_name": "test - bulk 2" }]))
+            .reply(&passenger_apis)
+    };
+    // -- ACTION
+    let (first, second, third) = tokio::join!(create(), create(), create());
+    // -- CHECK - one bulk create, the same response for all
+    for resp in [&first, &second, &third] {
+        assert_eq!(200, resp.status(), "http status");
+    }
+    let created: Vec<Passenger> = from_value(from_slice::<Value>(first.body())?["data"].take())?;
+    assert_eq!(2, created.len());
+    assert_eq!(first.body(), second.body());
+    assert_eq!(first.body(), third.body());
+    assert_eq!(4, passenger_count(&db).await?, "2 seed + 2 created");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_idempotency_failed_not_stored() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let utx = utx_from_token(&db, TOKEN).await?;
+    let key = || Some("key-failed".to_string());
+    // -- ACTION
+    let failed = idempotent(&db, &utx, key(), "test", &1, || async {
+        Err::<Json, _>(warp::reject::not_found())
+    })
+    .await;
+    let retried = idempotent(&db, &utx, key(), "test", &1, || async { json_response("ok") }).await;
+    // -- CHECK - the retry runs, the key was released by the failure
+    assert!(failed.is_err());
+    let retried = retried.map_err(|ex| anyhow::anyhow!("{:?}", ex))?;
+    assert_eq!(200, retried.status());
+    assert!(!retried.headers().contains_key("Idempotent-Replayed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_idempotency_abandoned_taken_over() -> Result<()> {
+    // -- FIXTURE - a key claimed long ago, by a request which never completed
+    let db = Arc::new(init_db().await?);
+    let utx = utx_from_token(&db, TOKEN).await?;
+    let expires_at = Utc::now() + Duration::hours(24);
+    IdempotencyKeyDao::claim(
+        &db,
+        &utx.user_id,
+        "key-abandoned",
+        &request_hash("test", &1),
+        expires_at,
+    )
+    .await?;
+    sqlx::query(
+        "UPDATE idempotency_key SET created_at = now() - INTERVAL '1 hour' WHERE request_key = 'key-abandoned'",
+    )
+    .execute(db.as_ref())
+    .await?;
+    // -- ACTION
+    let retried = idempotent(&db, &utx, Some("key-abandoned".to_string()), "test", &1, || async {
+        json_response("ok")
+    })
+    .await;
+    // -- CHECK - the retry runs, instead of waiting for the lost response
+    let retried = retried.map_err(|ex| anyhow::anyhow!("{:?}", ex))?;
+    assert_eq!(200, retried.status());
+    assert!(!retried.headers().contains_key("Idempotent-Replayed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_idempotency_dropped_released() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let utx = utx_from_token(&db, TOKEN).await?;
+    let key = || Some("key-dropped".to_string());
+    // -- ACTION - the request is dropped while its handler runs (client gone, timeout)
+    let dropped = idempotent(&db, &utx, key(), "test", &1, || {
+        std::future::pending::<Result<Json, warp::Rejection>>()
+    });
+    let timed_out = tokio::time::timeout(std::time::Duration::from_millis(200), dropped).await;
+    // -- CHECK - the key is released right away, not after its lease
+    assert!(timed_out.is_err());
+    let mut released = false;
+    for _ in 0..50 {
+        if IdempotencyKeyDao::get(&db, &utx.user_id, "key-dropped")
+            .await?
+            .is_none()
+        {
+            released = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(released, "key still claimed");
+    let retried = idempotent(&db, &utx, key(), "test", &1, || async { json_response("ok") }).await;
+    assert_eq!(200, retried.map_err(|ex| anyhow::anyhow!("{:?}", ex))?.status());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_idempotency_wrong_key() -> Result<()> {
+    // -- FIXTURE
+    let db = Arc::new(init_db().await?);
+    let passenger_apis = handlers("api", db.clone()).recover(handle_rejection);
+    // -- ACTION
+    let resp = warp::test::request()
+        .method("POST")
+        .header("X-Auth-Token", TOKEN)
+        .header("Idempotency-Key", "")
+        .path("/api/passengers")
+        .json(&json!({ "first_name": "test - empty key" }))
+        .reply(&passenger_apis)
+        .await;
+    // -- CHECK
+    assert_eq!(400, resp.status(), "http status");
+    assert_eq!(2, passenger_count(&db).await?);
+
+    Ok(())
+}
+
+// region: Test Utils
+async fn passenger_count(db: &crate::model::Db) -> Result<usize> {
+    let utx = utx_from_token(db, "3cb430d0-8914-4c71-aaf9-0ed2b163eca6").await?;
+    Ok(PassengerDao::list(db, &utx, &PassengerFilter::default()).await?.len())
+}
+// endregion: Test Utils
@@ -0,0 +1,326 @@
+use super::{bind, https_redirect, serve, BindAddr, Tls, TlsClientAuth, TlsFiles};
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_rustls::TlsConnector;
+use warp::http::{Request, StatusCode};
+use warp::hyper::client::conn;
+use warp::hyper::Body;
+use warp::Filter;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+#[test]
+fn web_server_bind_addr_parse() {
+    assert_eq!(Ok(BindAddr::localhost()), "127.0.0.1".parse());
+    assert_eq!(
+        "[::]:9090",
+        "::".parse::<BindAddr>().unwrap().socket_addr(9090).to_string()
+    );
+    assert_eq!(
+        "[::1]:9090",
+        "[::1]".parse::<BindAddr>().unwrap().socket_addr(9090).to_string()
+    );
+    assert_eq!(
+        "0.0.0.0:8080",
+        "0.0.0.0:8080"
+            .parse::<BindAddr>()
+            .unwrap()
+            .socket_addr(9090)
+            .to_string()
+    );
+    assert!("localhost:8080".parse::<BindAddr>().is_err());
+}
+
+#[tokio::test]
+async fn web_server_ipv4_and_ipv6_listeners() -> TestResult {
+    // -- FIXTURE
+    let listeners = bind(&["127.0.0.1:0".parse()?, "[::1]:0".parse()?])?;
+    let addrs: Vec<SocketAddr> = listeners
+        .iter()
+        .map(|listener| listener.local_addr())
+        .collect::<Result<_, _>>()?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(serve(
+        listeners,
+        None,
+        warp::service(ok_route()),
+        async { shutdown_rx.await.unwrap_or_default() },
+        Duration::from_secs(5),
+    ));
+
+    // -- ACTION / CHECK
+    for addr in addrs {
+        assert_eq!(StatusCode::OK, http_get(addr).await?, "{}", addr);
+    }
+    shutdown_tx.send(()).unwrap();
+    server.await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_server_drains_in_flight() -> TestResult {
+    // -- FIXTURE - a slow request in flight when shutting down
+    let listeners = bind(&["127.0.0.1:0".parse()?])?;
+    let addr = listeners[0].local_addr()?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(serve(
+        listeners,
+        None,
+        warp::service(slow_route(Duration::from_millis(300))),
+        async { shutdown_rx.await.unwrap_or_default() },
+        Duration::from_secs(5),
+    ));
+    let request = tokio::spawn(http_get(addr));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // -- ACTION
+    shutdown_tx.send(()).unwrap();
+
+    // -- CHECK - the request completes, then the server ends and refuses the new connections
+    assert_eq!(StatusCode::OK, request.await?.map_err(|ex| ex.to_string())?);
+    server.await?;
+    assert!(http_get(addr).await.is_err(), "should not accept after shutdown");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_server_drain_timeout() -> TestResult {
+    // -- FIXTURE - a request longer than the drain timeout
+    let listeners = bind(&["127.0.0.1:0".parse()?])?;
+    let addr = listeners[0].local_addr()?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(serve(
+        listeners,
+        None,
+        warp::service(slow_route(Duration::from_secs(30))),
+        async { shutdown_rx.await.unwrap_or_default() },
+        Duration::from_millis(200),
+    ));
+    let request = tokio::spawn(http_get(addr));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // -- ACTION
+    let started_at = Instant::now();
+    shutdown_tx.send(()).unwrap();
+
+    // -- CHECK - the server ends at the drain timeout, the request is aborted
+    server.await?;
+    assert!(started_at.elapsed() < Duration::from_secs(2));
+    assert!(request.await?.is_err(), "the request should be aborted");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_server_tls_reload() -> TestResult {
+    // -- FIXTURE
+    let pki = Pki::new("tls_reload")?;
+    let files = pki.server_files("server-1", None, TlsClientAuth::Required)?;
+    let tls = Arc::new(Tls::load(files)?);
+    let (addr, _shutdown) = serve_tls(tls.clone())?;
+    let first = https_get(addr, &pki, None).await?;
+
+    // -- ACTION - new certificate in the same files, then a broken key
+    pki.server_files("server-2", None, TlsClientAuth::Required)?;
+    tls.reload()?;
+    let second = https_get(addr, &pki, None).await?;
+    std::fs::write(pki.dir.join("server.key"), "not a key")?;
+    let reload_broken = tls.reload();
+
+    // -- CHECK - the new certificate is served, the broken one is not taken
+    assert_eq!(StatusCode::OK, first.0);
+    assert_eq!(StatusCode::OK, second.0);
+    assert_ne!(first.1, second.1, "the certificate should have changed");
+    assert!(reload_broken.is_err());
+    assert_eq!(second.1, https_get(addr, &pki, None).await?.1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_server_mtls() -> TestResult {
+    // -- FIXTURE
+    let pki = Pki::new("mtls")?;
+    let client = pki.client_cert()?;
+    let required = pki.server_files("server", Some(pki.ca_path()), TlsClientAuth::Required)?;
+    let (required_addr, _shutdown_required) = serve_tls(Arc::new(Tls::load(required)?))?;
+    let optional = pki.server_files("server", Some(pki.ca_path()), TlsClientAuth::Optional)?;
+    let (optional_addr, _shutdown_optional) = serve_tls(Arc::new(Tls::load(optional)?))?;
+
+    // -- ACTION / CHECK - required
+    assert_eq!(StatusCode::OK, https_get(required_addr, &pki, Some(&client)).await?.0);
+    assert!(
+        https_get(required_addr, &pki, None).await.is_err(),
+        "no client certificate"
+    );
+    // -- ACTION / CHECK - optional
+    assert_eq!(StatusCode::OK, https_get(optional_addr, &pki, Some(&client)).await?.0);
+    assert_eq!(StatusCode::OK, https_get(optional_addr, &pki, None).await?.0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_server_https_redirect() -> TestResult {
+    // -- FIXTURE
+    let redirect = https_redirect(8443);
+
+    // -- ACTION
+    let resp = warp::test::request()
+        .path("/api/passengers?sort=-updated_at")
+        .header("host", "example.com:8080")
+        .reply(&redirect)
+        .await;
+    let resp_default_port = warp::test::request()
+        .path("/")
+        .header("host", "[::1]:80")
+        .reply(&https_redirect(443))
+        .await;
+
+    // -- CHECK
+    assert_eq!(308, resp.status());
+    assert_eq!(
+        "https://example.com:8443/api/passengers?sort=-updated_at",
+        resp.headers()["location"]
+    );
+    assert_eq!("https://[::1]/", resp_default_port.headers()["location"]);
+
+    Ok(())
+}
+
+// region:    Test Utils
+fn ok_route() -> impl Filter<Extract = (&'static str,), Error = Infallible> + Clone {
+    warp::any().map(|| "ok")
+}
+
+fn slow_route(delay: Duration) -> impl Filter<Extract = (&'static str,), Error = Infallible> + Clone {
+    warp::any().then(move || async move {
+        tokio::time::sleep(delay).await;
+        "done"
+    })
+}
+
+// Serves `ok_route` over TLS on a free port, until the sender is dropped.
+fn serve_tls(tls: Arc<Tls>) -> Result<(SocketAddr, oneshot::Sender<()>), Box<dyn std::error::Error>> {
+    let listeners = bind(&["127.0.0.1:0".parse()?])?;
+    let addr = listeners[0].local_addr()?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let shutdown = async {
+        let _ = shutdown_rx.await;
+    };
+    tokio::spawn(serve(
+        listeners,
+        Some(tls),
+        warp::service(ok_route()),
+        shutdown,
+        Duration::from_secs(1),
+    ));
+    Ok((addr, shutdown_tx))
+}
+
+async fn http_get(addr: SocketAddr) -> Result<StatusCode, warp::hyper::Error> {
+    let uri = format!("http://{}/", addr).parse().unwrap();
+    Ok(warp::hyper::Client::new().get(uri).await?.status())
+}
+
+// Returns the status and the server certificate.
+async fn https_get(
+    addr: SocketAddr,
+    pki: &Pki,
+    client_cert: Option<&(String, String)>,
+) -> Result<(StatusCode, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(pki.ca_path())?)?;
+    let builder = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots);
+    let client_config = match client_cert {
+        Some((cert, key)) => builder.with_client_auth_cert(
+            vec![CertificateDer::from_pem_slice(cert.as_bytes())?],
+            PrivateKeyDer::from_pem_slice(key.as_bytes())?,
+        )?,
+        None => builder.with_no_client_auth(),
+    };
+    let stream = TcpStream::connect(addr).await?;
+    let stream = TlsConnector::from(Arc::new(client_config))
+        .connect(ServerName::try_from("localhost")?, stream)
+        .await?;
+    let server_cert = stream.get_ref().1.peer_certificates().unwrap_or_default()[0].to_vec();
+    let (mut sender, connection) = conn::handshake(stream).await?;
+    tokio::spawn(connection);
+    let resp = sender
+        .send_request(Request::get("/").header("host", "localhost").body(Body::empty())?)
+        .await?;
+    Ok((resp.status(), server_cert))
+}
+
+// Test CA, issuing the server and client certificates, in a temp dir.
+struct Pki {
+    dir: PathBuf,
+    ca: CertifiedIssuer<'static, KeyPair>,
+}
+
+impl Pki {
+    fn new(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = std::env::temp_dir().join(format!("passenger_service_{}_{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        let mut params = CertificateParams::new(Vec::<String>::new())?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(params, KeyPair::generate()?)?;
+        std::fs::write(dir.join("ca.pem"), ca.pem())?;
+        Ok(Pki { dir, ca })
+    }
+
+    fn ca_path(&self) -> PathBuf {
+        self.dir.join("ca.pem")
+    }
+
+    // Writes a new server certificate for `localhost`.
+    fn server_files(
+        &self,
+        common_name: &str,
+        client_ca: Option<PathBuf>,
+        client_auth: TlsClientAuth,
+    ) -> Result<TlsFiles, Box<dyn std::error::Error>> {
+        let (cert, key) = self.issue(common_name)?;
+        std::fs::write(self.dir.join("server.pem"), cert)?;
+        std::fs::write(self.dir.join("server.key"), key)?;
+        Ok(TlsFiles {
+            cert: self.dir.join("server.pem"),
+            key: self.dir.join("server.key"),
+            client_ca,
+            client_auth,
+        })
+    }
+
+    fn client_cert(&self) -> Result<(String, String), Box<dyn std::error::Error>> {
+        self.issue("flight_service")
+    }
+
+    fn issue(&self, common_name: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+        let mut params = CertificateParams::new(vec!["localhost".to_string()])?;
+        params.distinguished_name.push(rcgen::DnType::CommonName, common_name);
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &self.ca)?;
+        Ok((cert.pem(), key.serialize_pem()))
+    }
+}
+
+impl Drop for Pki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+// endregion: Test Utils
@@ -0,0 +1,59 @@
+use super::{timed, traced, ClientAddr, HEADER_REQUEST_ID};
+use crate::metrics::metrics;
+use warp::http::Request;
+use warp::hyper::Body;
+use warp::Filter;
+
+#[tokio::test]
+async fn web_trace_request_id() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE - echoes the client address set by `traced`
+    let routes = warp::ext::optional::<ClientAddr>()
+        .map(|client_addr: Option<ClientAddr>| format!("{:?}", client_addr.map(|addr| addr.0)));
+    let service = warp::service(routes);
+    let remote_addr = Some("10.0.0.1:4000".parse()?);
+
+    // -- ACTION - with and without a request id
+    let req = Request::get("/api/passengers")
+        .header(HEADER_REQUEST_ID, "req-1")
+        .body(Body::empty())?;
+    let propagated = traced(service, remote_addr, req).await?;
+    let req = Request::get("/api/passengers").body(Body::empty())?;
+    let generated = traced(service, remote_addr, req).await?;
+
+    // -- CHECK
+    assert_eq!("req-1", propagated.headers()[HEADER_REQUEST_ID]);
+    let generated_id = generated.headers()[HEADER_REQUEST_ID].to_str()?;
+    assert!(uuid::Uuid::parse_str(generated_id).is_ok(), "{}", generated_id);
+    let body = warp::hyper::body::to_bytes(propagated.into_body()).await?;
+    assert_eq!("Some(10.0.0.1:4000)", std::str::from_utf8(&body)?);
+    let requests = metrics()
+        .http_requests
+        .with_label_values(&["GET", "/api/passengers", "200"]);
+    assert_eq!(2, requests.get());
+    assert_eq!(0, metrics().http_requests_in_flight.get());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_trace_timed() -> Result<(), Box<dyn std::error::Error>> {
+    // -- FIXTURE
+    let timeout = std::time::Duration::from_millis(50);
+    let fast = async { Ok(warp::http::Response::new(Body::from("ok"))) };
+    let slow = async {
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        Ok(warp::http::Response::new(Body::from("ok")))
+    };
+
+    // -- ACTION
+    let fast = timed(timeout, fast).await?;
+    let slow = timed(timeout, slow).await?;
+
+    // -- CHECK
+    assert_eq!(200, fast.status());
+    assert_eq!(504, slow.status());
+    let body = warp::hyper::body::to_bytes(slow.into_body()).await?;
+    assert_eq!(r#"{"errorMessage":"web::Error"}"#, std::str::from_utf8(&body)?);
+
+    Ok(())
+}
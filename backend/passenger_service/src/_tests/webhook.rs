@@ -0,0 +1,176 @@
+use super::Dispatcher;
+use crate::model::{init_db, PassengerDao, PassengerPatch, WebhookDao, WebhookDeliveryDao, WebhookForCreate};
+use crate::security::webhook::signature;
+use crate::security::{Role, UserCtx};
+use anyhow::Result;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use warp::http::{HeaderMap, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::Filter;
+
+#[tokio::test]
+async fn webhook_dispatch_signed() -> Result<()> {
+    // -- FIXTURE
+    let db = init_db().await?;
+    let receiver = Receiver::start(200);
+    let utx = admin_utx_fx();
+    let created = WebhookDao::create(&db, &utx, webhook_fx(receiver.addr)).await?;
+    let passenger = PassengerDao::create(&db, &utx, PassengerPatch::default()).await?;
+    let dispatcher = Dispatcher::new(Duration::from_secs(2), 3, Duration::ZERO, Duration::ZERO, 10)?;
+
+    // -- ACTION
+    dispatcher.run_once(&db).await?;
+    dispatcher.run_once(&db).await?;
+
+    // -- CHECK - received once, signed with the webhook secret
+    let requests = receiver.requests();
+    assert_eq!(1, requests.len(), "delivered once");
+    let (headers, body) = &requests[0];
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    let timestamp: i64 = header("X-Webhook-Timestamp").parse()?;
+    assert_eq!(
+        signature(&created.secret, timestamp, body),
+        header("X-Webhook-Signature")
+    );
+    assert_eq!("passenger.created", header("X-Webhook-Event"));
+    let body: Value = serde_json::from_slice(body)?;
+    assert_eq!("passenger.created", body["type"]);
+    assert_eq!(header("X-Webhook-Id"), body["id"].to_string());
+    assert_eq!(passenger.id.to_string(), body["data"]["id"]);
+    // -- CHECK - delivery log
+    let deliveries = WebhookDeliveryDao::list(&db, created.webhook.id.to_string(), None).await?;
+    assert_eq!(WebhookDeliveryDao::DELIVERED, deliveries[0].status);
+    assert_eq!(1, deliveries[0].attempts);
+    assert_eq!(Some(200), deliveries[0].last_status_code);
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhook_dispatch_retry_then_dead() -> Result<()> {
+    // -- FIXTURE - a receiver failing, 2 attempts without backoff
+    let db = init_db().await?;
+    let receiver = Receiver::start(500);
+    let utx = admin_utx_fx();
+    let webhook = WebhookDao::create(&db, &utx, webhook_fx(receiver.addr)).await?.webhook;
+    PassengerDao::create(&db, &utx, PassengerPatch::default()).await?;
+    let dispatcher = Dispatcher::new(Duration::from_secs(2), 2, Duration::ZERO, Duration::ZERO, 10)?;
+
+    // -- ACTION - first attempt
+    dispatcher.run_once(&db).await?;
+    // -- CHECK - pending, for a retry
+    let deliveries = WebhookDeliveryDao::list(&db, webhook.id.to_string(), None).await?;
+    assert_eq!(WebhookDeliveryDao::PENDING, deliveries[0].status);
+    assert_eq!(Some(500), deliveries[0].last_status_code);
+
+    // -- ACTION - last attempt
+    dispatcher.run_once(&db).await?;
+    dispatcher.run_once(&db).await?;
+    // -- CHECK - dead, no more attempts
+    assert_eq!(2, receiver.requests().len());
+    let dead = WebhookDeliveryDao::list(&db, webhook.id.to_string(), Some("dead".to_string())).await?;
+    assert_eq!(1, dead.len());
+    assert_eq!(2, dead[0].attempts);
+    assert_eq!(Some("HTTP 500 Internal Server Error".to_string()), dead[0].last_error);
+
+    // -- ACTION - retried, the receiver still failing
+    let retried = WebhookDeliveryDao::retry(&db, webhook.id.to_string(), dead[0].id).await?;
+    dispatcher.run_once(&db).await?;
+    // -- CHECK - the attempts kept, another window of 2
+    assert_eq!(WebhookDeliveryDao::PENDING, retried.status);
+    assert_eq!(2, retried.attempts);
+    let deliveries = WebhookDeliveryDao::list(&db, webhook.id.to_string(), None).await?;
+    assert_eq!(WebhookDeliveryDao::PENDING, deliveries[0].status);
+    assert_eq!(3, deliveries[0].attempts);
+
+    // -- ACTION - the receiver fixed
+    receiver.status.store(204, Ordering::SeqCst);
+    dispatcher.run_once(&db).await?;
+    // -- CHECK
+    let deliveries = WebhookDeliveryDao::list(&db, webhook.id.to_string(), None).await?;
+    assert_eq!(WebhookDeliveryDao::DELIVERED, deliveries[0].status);
+    assert_eq!(4, deliveries[0].attempts);
+    assert_eq!(4, receiver.requests().len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhook_dispatch_unreachable() -> Result<()> {
+    // -- FIXTURE - nothing listens on the port of a dropped listener
+    let db = init_db().await?;
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let utx = admin_utx_fx();
+    let webhook = WebhookDao::create(&db, &utx, webhook_fx(addr)).await?.webhook;
+    PassengerDao::create(&db, &utx, PassengerPatch::default()).await?;
+    let dispatcher = Dispatcher::new(Duration::from_secs(2), 5, Duration::from_secs(60), Duration::ZERO, 10)?;
+
+    // -- ACTION
+    dispatcher.run_once(&db).await?;
+
+    // -- CHECK - the connection error recorded, retried later
+    let deliveries = WebhookDeliveryDao::list(&db, webhook.id.to_string(), None).await?;
+    assert_eq!(WebhookDeliveryDao::PENDING, deliveries[0].status);
+    assert_eq!(None, deliveries[0].last_status_code);
+    assert!(deliveries[0].last_error.is_some());
+    Ok(())
+}
+
+// region: Test Utils
+type Requests = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+// Local stand-in of a webhook receiver, answering with `status`.
+struct Receiver {
+    addr: SocketAddr,
+    status: Arc<AtomicU16>,
+    requests: Requests,
+}
+
+impl Receiver {
+    fn start(status: u16) -> Self {
+        let status = Arc::new(AtomicU16::new(status));
+        let requests: Requests = Arc::default();
+        let route = {
+            let (status, requests) = (status.clone(), requests.clone());
+            warp::post()
+                .and(warp::header::headers_cloned())
+                .and(warp::body::bytes())
+                .map(move |headers: HeaderMap, body: Bytes| {
+                    requests.lock().unwrap().push((headers, body));
+                    let status = StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap();
+                    warp::reply::with_status(warp::reply(), status)
+                })
+        };
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Receiver { addr, status, requests }
+    }
+
+    fn requests(&self) -> Vec<(HeaderMap, Bytes)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn webhook_fx(addr: SocketAddr) -> WebhookForCreate {
+    WebhookForCreate {
+        url: format!("http://{}/hooks/passengers", addr),
+        event_types: vec!["passenger.created".to_string()],
+        secret: None,
+    }
+}
+
+fn admin_utx_fx() -> UserCtx {
+    UserCtx::new(
+        "f7a25ba8-fc87-4b6f-9297-611921ef0d7a".to_string(),
+        None,
+        vec![Role::Admin],
+    )
+}
+// endregion: Test Utils
@@ -0,0 +1,336 @@
+use crate::model::{ChangeFeedMode, NameCase, Scope};
+use crate::security::Role;
+use crate::telemetry::LogFormat;
+use crate::web::{BindAddr, Quota, TlsClientAuth};
+use chrono::Duration;
+use rand::RngCore;
+use std::env;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+use thiserror::Error as ThisError;
+
+// region:    Config
+const DEFAULT_CORS_METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+const DEFAULT_CORS_HEADERS: &[&str] = &["content-type", "x-auth-token", "idempotency-key", "x-request-id"];
+// the frontend and the swagger ui (/docs) use inline styles, the swagger ui data: images
+const DEFAULT_CSP: &str =
+    "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; frame-ancestors 'none'";
+
+/// Service configuration, read once from the environment.
+#[derive(Debug)]
+pub struct Config {
+    /// Row-level scoping applied by the DAOs (`PASSENGER_SCOPE` = off | owner | tenant).
+    pub passenger_scope: Scope,
+    /// User ids holding the admin role (`ADMIN_USER_IDS`, comma separated).
+    pub admin_user_ids: Vec<String>,
+    /// Role of the other users (`DEFAULT_USER_ROLE` = viewer | editor | user).
+    pub default_user_role: Role,
+    /// Accept any token as a user id, without a login (`AUTH_DEV_TOKENS`). Dev only, off by
+    /// default outside of the tests.
+    pub auth_dev_tokens: bool,
+    /// Key signing the access tokens (`AUTH_TOKEN_KEY`). Random per process when not set,
+    /// so it must be set to share tokens across replicas and restarts.
+    pub auth_token_key: Vec<u8>,
+    /// Access token lifetime (`AUTH_ACCESS_TTL_SECS`).
+    pub auth_access_ttl: Duration,
+    /// Refresh token lifetime, i.e. max session idle time (`AUTH_REFRESH_TTL_SECS`).
+    pub auth_refresh_ttl: Duration,
+    /// Failed logins in a row before the account is locked (`AUTH_MAX_FAILED_LOGINS`).
+    pub auth_max_failed_logins: i64,
+    /// How long a locked account stays locked (`AUTH_LOCKOUT_SECS`).
+    pub auth_lockout: Duration,
+    /// How often the revocation list cache is reloaded from the database (`AUTH_REVOCATION_SYNC_SECS`).
+    pub auth_revocation_sync: StdDuration,
+    /// How often the expired revocation entries are deleted (`AUTH_REVOCATION_GC_SECS`).
+    pub auth_revocation_gc: StdDuration,
+    /// Per client rate limiting of the API (`RATE_LIMIT_ENABLED`).
+    pub rate_limit_enabled: bool,
+    /// Quota of the reads, GET and HEAD (`RATE_LIMIT_READ` = <requests>/<seconds>).
+    pub rate_limit_read: Quota,
+    /// Quota of the writes, the other methods (`RATE_LIMIT_WRITE` = <requests>/<seconds>).
+    pub rate_limit_write: Quota,
+    /// How long the response of an `Idempotency-Key` request is kept (`IDEMPOTENCY_TTL_SECS`).
+    pub idempotency_ttl: Duration,
+    /// How often the expired idempotency keys are deleted (`IDEMPOTENCY_GC_SECS`).
+    pub idempotency_gc: StdDuration,
+    /// Log output (`LOG_FORMAT` = pretty | json).
+    pub log_format: LogFormat,
+    /// Log filter, e.g. `info` or `passenger_service=debug,info` (`LOG_LEVEL`). The sqlx logs are
+    /// off by default, they hold the values of the statements.
+    pub log_level: String,
+    /// Continue the trace of the callers from their `traceparent` header (`TRACE_PROPAGATION`).
+    pub trace_propagation: bool,
+    /// OTLP/HTTP collector to export the spans to, e.g. `http://localhost:4318` (`OTLP_ENDPOINT`).
+    pub otlp_endpoint: Option<String>,
+    /// Serve the Prometheus metrics on `GET /metrics` (`METRICS_ENABLED`).
+    pub metrics_enabled: bool,
+    /// How long `/readyz` waits for the database `SELECT 1` (`READINESS_DB_TIMEOUT_MS`).
+    pub readiness_db_timeout: StdDuration,
+    /// How long the startup retries to reach the database before giving up (`DB_CONNECT_DEADLINE_SECS`).
+    pub db_connect_deadline: StdDuration,
+    /// First wait between the startup retries, doubled after each failure (`DB_CONNECT_BACKOFF_MS`).
+    pub db_connect_backoff: StdDuration,
+    /// How long the in-flight requests may take to finish once shutting down (`SHUTDOWN_DRAIN_TIMEOUT_SECS`).
+    pub shutdown_drain_timeout: StdDuration,
+    /// Addresses to listen on, IPv4 or IPv6, with or without port (`BIND_ADDRS`, comma separated).
+    /// Without port, the one given on the command line.
+    pub bind_addrs: Vec<BindAddr>,
+    /// PEM certificate chain, enables TLS on all the listeners (`TLS_CERT_PATH`).
+    /// Reloaded with the key on SIGHUP.
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key of the certificate (`TLS_KEY_PATH`).
+    pub tls_key_path: Option<PathBuf>,
+    /// PEM CA certificates verifying the client certificates, enables mTLS (`TLS_CLIENT_CA_PATH`).
+    pub tls_client_ca_path: Option<PathBuf>,
+    /// Whether the clients must present a certificate with mTLS (`TLS_CLIENT_AUTH` = required | optional).
+    pub tls_client_auth: TlsClientAuth,
+    /// Plain HTTP listener redirecting to HTTPS, e.g. `0.0.0.0:80` (`HTTPS_REDIRECT_ADDR`).
+    pub https_redirect_addr: Option<SocketAddr>,
+    /// Origins allowed to call the API cross-origin, `*` for any (`CORS_ALLOWED_ORIGINS`,
+    /// comma separated). None by default, same-origin only.
+    pub cors_allowed_origins: Vec<String>,
+    /// Methods allowed cross-origin (`CORS_ALLOWED_METHODS`, comma separated).
+    pub cors_allowed_methods: Vec<String>,
+    /// Request headers allowed cross-origin (`CORS_ALLOWED_HEADERS`, comma separated).
+    pub cors_allowed_headers: Vec<String>,
+    /// Allow the cross-origin requests with credentials, cookies or TLS client certificates
+    /// (`CORS_ALLOW_CREDENTIALS`). Not with the `*` origin.
+    pub cors_allow_credentials: bool,
+    /// How long the browsers may cache a preflight response (`CORS_MAX_AGE_SECS`).
+    pub cors_max_age: StdDuration,
+    /// Content-Security-Policy of all the responses (`SECURITY_CSP`).
+    pub security_csp: String,
+    /// Strict-Transport-Security max age, sent with TLS only, 0 to not send it (`SECURITY_HSTS_MAX_AGE_SECS`).
+    pub security_hsts_max_age: StdDuration,
+    /// gzip or brotli compression of the responses, per `Accept-Encoding` (`COMPRESSION_ENABLED`).
+    pub compression_enabled: bool,
+    /// Largest JSON request body, 413 above (`MAX_BODY_BYTES`).
+    pub max_body_bytes: u64,
+    /// Largest JSON request body of the bulk routes (`MAX_BULK_BODY_BYTES`).
+    pub max_bulk_body_bytes: u64,
+    /// How long a request may take until its response starts, 504 after (`HANDLER_TIMEOUT_MS`).
+    pub handler_timeout: StdDuration,
+    /// Passenger events kept for the `Last-Event-ID` resume of the change feed (`EVENTS_BUFFER_SIZE`).
+    pub events_buffer_size: usize,
+    /// Interval of the WebSocket pings, a client silent for two is disconnected (`WS_PING_INTERVAL_SECS`).
+    pub ws_ping_interval: StdDuration,
+    /// Messages queued to a WebSocket client, the events beyond are replaced by a reset (`WS_SEND_BUFFER`).
+    pub ws_send_buffer: usize,
+    /// JSON-RPC requests a WebSocket client may have running at once (`WS_MAX_IN_FLIGHT`).
+    pub ws_max_in_flight: usize,
+    /// Subscriptions a WebSocket client may hold (`WS_MAX_SUBSCRIPTIONS`).
+    pub ws_max_subscriptions: usize,
+    /// Runs the webhook dispatcher, off for the instances which must not send (`WEBHOOK_DISPATCH_ENABLED`).
+    pub webhook_dispatch_enabled: bool,
+    /// How often the dispatcher looks for new events and due deliveries (`WEBHOOK_POLL_INTERVAL_MS`).
+    pub webhook_poll_interval: StdDuration,
+    /// How long a webhook receiver has to answer (`WEBHOOK_TIMEOUT_MS`).
+    pub webhook_timeout: StdDuration,
+    /// Attempts of a delivery before it is dead (`WEBHOOK_MAX_ATTEMPTS`).
+    pub webhook_max_attempts: i64,
+    /// Wait after the first failed attempt, doubled after each one (`WEBHOOK_RETRY_BACKOFF_SECS`).
+    pub webhook_retry_backoff: StdDuration,
+    /// Longest wait between two attempts (`WEBHOOK_RETRY_MAX_BACKOFF_SECS`).
+    pub webhook_retry_max_backoff: StdDuration,
+    /// Events fanned out, and deliveries sent, per poll (`WEBHOOK_BATCH_SIZE`).
+    pub webhook_batch_size: i64,
+    /// Source of the passenger changes of the other instances (`CHANGE_FEED` = off | notify | changefeed).
+    pub change_feed: ChangeFeedMode,
+    /// Caches `PassengerDao::get` and `list` in memory (`PASSENGER_CACHE_ENABLED`).
+    pub passenger_cache_enabled: bool,
+    /// Entries kept, the least recently used are dropped first (`PASSENGER_CACHE_CAPACITY`).
+    pub passenger_cache_capacity: usize,
+    /// How long an entry is kept, whatever the invalidations (`PASSENGER_CACHE_TTL_SECS`).
+    pub passenger_cache_ttl: StdDuration,
+    /// Casing of the stored passenger names (`NAME_CASE` = preserve | upper | title).
+    pub name_case: NameCase,
+    /// Longest passenger first or last name, in characters (`NAME_MAX_CHARS`).
+    pub name_max_chars: usize,
+    /// Days before its expiry a passenger document is flagged `expires_soon`
+    /// (`DOCUMENT_EXPIRY_WARNING_DAYS`).
+    pub document_expiry_warning_days: i64,
+}
+
+pub fn config() -> &'static Config {
+    static INSTANCE: OnceLock<Config> = OnceLock::new();
+
+    INSTANCE
+        .get_or_init(|| Config::load_from_env().unwrap_or_else(|ex| panic!("FATAL - while loading config - {}", ex)))
+}
+
+impl Config {
+    fn load_from_env() -> Result<Config, Error> {
+        Config {
+            passenger_scope: get_env_parse("PASSENGER_SCOPE", Scope::Off)?,
+            admin_user_ids: get_env_list("ADMIN_USER_IDS"),
+            default_user_role: get_env_parse("DEFAULT_USER_ROLE", Role::User)?,
+            auth_dev_tokens: get_env_parse("AUTH_DEV_TOKENS", cfg!(test))?,
+            auth_token_key: match env::var("AUTH_TOKEN_KEY") {
+                Ok(key) => key.into_bytes(),
+                Err(_) => {
+                    let mut key = vec![0; 32];
+                    rand::thread_rng().fill_bytes(&mut key);
+                    key
+                }
+            },
+            auth_access_ttl: Duration::seconds(get_env_parse("AUTH_ACCESS_TTL_SECS", 15 * 60)?),
+            auth_refresh_ttl: Duration::seconds(get_env_parse("AUTH_REFRESH_TTL_SECS", 7 * 24 * 3600)?),
+            auth_max_failed_logins: get_env_parse("AUTH_MAX_FAILED_LOGINS", 5)?,
+            auth_lockout: Duration::seconds(get_env_parse("AUTH_LOCKOUT_SECS", 15 * 60)?),
+            auth_revocation_sync: StdDuration::from_secs(get_env_parse("AUTH_REVOCATION_SYNC_SECS", 5)?),
+            auth_revocation_gc: StdDuration::from_secs(get_env_parse("AUTH_REVOCATION_GC_SECS", 3600)?),
+            rate_limit_enabled: get_env_parse("RATE_LIMIT_ENABLED", true)?,
+            rate_limit_read: get_env_parse("RATE_LIMIT_READ", Quota::new(300, 60))?,
+            rate_limit_write: get_env_parse("RATE_LIMIT_WRITE", Quota::new(60, 60))?,
+            idempotency_ttl: Duration::seconds(get_env_parse("IDEMPOTENCY_TTL_SECS", 24 * 3600)?),
+            idempotency_gc: StdDuration::from_secs(get_env_parse("IDEMPOTENCY_GC_SECS", 3600)?),
+            log_format: get_env_parse("LOG_FORMAT", LogFormat::Pretty)?,
+            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info,sqlx=warn".to_string()),
+            trace_propagation: get_env_parse("TRACE_PROPAGATION", false)?,
+            otlp_endpoint: env::var("OTLP_ENDPOINT").ok().filter(|endpoint| !endpoint.is_empty()),
+            metrics_enabled: get_env_parse("METRICS_ENABLED", true)?,
+            readiness_db_timeout: StdDuration::from_millis(get_env_parse("READINESS_DB_TIMEOUT_MS", 1000)?),
+            db_connect_deadline: StdDuration::from_secs(get_env_parse("DB_CONNECT_DEADLINE_SECS", 60)?),
+            db_connect_backoff: StdDuration::from_millis(get_env_parse("DB_CONNECT_BACKOFF_MS", 200)?),
+            shutdown_drain_timeout: StdDuration::from_secs(get_env_parse("SHUTDOWN_DRAIN_TIMEOUT_SECS", 30)?),
+            bind_addrs: get_env_parse_list("BIND_ADDRS", vec![BindAddr::localhost()])?,
+            tls_cert_path: env::var("TLS_CERT_PATH").ok().map(PathBuf::from),
+            tls_key_path: env::var("TLS_KEY_PATH").ok().map(PathBuf::from),
+            tls_client_ca_path: env::var("TLS_CLIENT_CA_PATH").ok().map(PathBuf::from),
+            tls_client_auth: get_env_parse("TLS_CLIENT_AUTH", TlsClientAuth::Required)?,
+            https_redirect_addr: env::var("HTTPS_REDIRECT_ADDR")
+                .ok()
+                .map(|addr| {
+                    addr.parse()
+                        .map_err(|_| Error::WrongFormat("HTTPS_REDIRECT_ADDR", addr))
+                })
+                .transpose()?,
+            cors_allowed_origins: get_env_list("CORS_ALLOWED_ORIGINS"),
+            cors_allowed_methods: get_env_list_or("CORS_ALLOWED_METHODS", DEFAULT_CORS_METHODS),
+            cors_allowed_headers: get_env_list_or("CORS_ALLOWED_HEADERS", DEFAULT_CORS_HEADERS),
+            cors_allow_credentials: get_env_parse("CORS_ALLOW_CREDENTIALS", false)?,
+            cors_max_age: StdDuration::from_secs(get_env_parse("CORS_MAX_AGE_SECS", 600)?),
+            security_csp: env::var("SECURITY_CSP").unwrap_or_else(|_| DEFAULT_CSP.to_string()),
+            security_hsts_max_age: StdDuration::from_secs(get_env_parse(
+                "SECURITY_HSTS_MAX_AGE_SECS",
+                365 * 24 * 3600,
+            )?),
+            compression_enabled: get_env_parse("COMPRESSION_ENABLED", true)?,
+            max_body_bytes: get_env_parse("MAX_BODY_BYTES", 64 * 1024)?,
+            max_bulk_body_bytes: get_env_parse("MAX_BULK_BODY_BYTES", 1024 * 1024)?,
+            handler_timeout: StdDuration::from_millis(get_env_parse("HANDLER_TIMEOUT_MS", 10_000)?),
+            events_buffer_size: get_env_parse("EVENTS_BUFFER_SIZE", 1000)?,
+            ws_ping_interval: StdDuration::from_secs(get_env_parse("WS_PING_INTERVAL_SECS", 30)?),
+            ws_send_buffer: get_env_parse("WS_SEND_BUFFER", 256)?,
+            ws_max_in_flight: get_env_parse("WS_MAX_IN_FLIGHT", 8)?,
+            ws_max_subscriptions: get_env_parse("WS_MAX_SUBSCRIPTIONS", 32)?,
+            webhook_dispatch_enabled: get_env_parse("WEBHOOK_DISPATCH_ENABLED", true)?,
+            webhook_poll_interval: StdDuration::from_millis(get_env_parse("WEBHOOK_POLL_INTERVAL_MS", 1000)?),
+            webhook_timeout: StdDuration::from_millis(get_env_parse("WEBHOOK_TIMEOUT_MS", 5000)?),
+            webhook_max_attempts: get_env_parse("WEBHOOK_MAX_ATTEMPTS", 8)?,
+            webhook_retry_backoff: StdDuration::from_secs(get_env_parse("WEBHOOK_RETRY_BACKOFF_SECS", 10)?),
+            webhook_retry_max_backoff: StdDuration::from_secs(get_env_parse("WEBHOOK_RETRY_MAX_BACKOFF_SECS", 3600)?),
+            webhook_batch_size: get_env_parse("WEBHOOK_BATCH_SIZE", 50)?,
+            change_feed: get_env_parse("CHANGE_FEED", ChangeFeedMode::Off)?,
+            passenger_cache_enabled: get_env_parse("PASSENGER_CACHE_ENABLED", false)?,
+            passenger_cache_capacity: get_env_parse("PASSENGER_CACHE_CAPACITY", 10_000)?,
+            passenger_cache_ttl: StdDuration::from_secs(get_env_parse("PASSENGER_CACHE_TTL_SECS", 30)?),
+            name_case: get_env_parse("NAME_CASE", NameCase::Preserve)?,
+            name_max_chars: get_env_parse("NAME_MAX_CHARS", 100)?,
+            document_expiry_warning_days: get_env_parse("DOCUMENT_EXPIRY_WARNING_DAYS", 180)?,
+        }
+        .validate()
+    }
+
+    // The settings only valid together.
+    fn validate(self) -> Result<Config, Error> {
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            return Err(Error::MissingWith("TLS_CERT_PATH and TLS_KEY_PATH", "each other"));
+        }
+        if self.tls_client_ca_path.is_some() && self.tls_cert_path.is_none() {
+            return Err(Error::MissingWith("TLS_CERT_PATH", "TLS_CLIENT_CA_PATH"));
+        }
+        if self.https_redirect_addr.is_some() && self.tls_cert_path.is_none() {
+            return Err(Error::MissingWith("TLS_CERT_PATH", "HTTPS_REDIRECT_ADDR"));
+        }
+        if self.cors_allow_credentials && self.cors_allowed_origins.iter().any(|origin| origin == "*") {
+            return Err(Error::WrongFormat(
+                "CORS_ALLOWED_ORIGINS",
+                "* with CORS_ALLOW_CREDENTIALS".to_string(),
+            ));
+        }
+        if self.webhook_max_attempts < 1 {
+            return Err(Error::WrongFormat(
+                "WEBHOOK_MAX_ATTEMPTS",
+                self.webhook_max_attempts.to_string(),
+            ));
+        }
+        if self.webhook_batch_size < 1 {
+            return Err(Error::WrongFormat(
+                "WEBHOOK_BATCH_SIZE",
+                self.webhook_batch_size.to_string(),
+            ));
+        }
+        if self.name_max_chars < 1 {
+            return Err(Error::WrongFormat("NAME_MAX_CHARS", self.name_max_chars.to_string()));
+        }
+        if !(0..=3650).contains(&self.document_expiry_warning_days) {
+            return Err(Error::WrongFormat(
+                "DOCUMENT_EXPIRY_WARNING_DAYS",
+                self.document_expiry_warning_days.to_string(),
+            ));
+        }
+        Ok(self)
+    }
+}
+// endregion: Config
+
+// region:    Utils
+fn get_env_parse<T: FromStr>(name: &'static str, default: T) -> Result<T, Error> {
+    match env::var(name) {
+        Ok(value) => value.parse().map_err(|_| Error::WrongFormat(name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+fn get_env_parse_list<T: FromStr>(name: &'static str, default: Vec<T>) -> Result<Vec<T>, Error> {
+    match get_env_list(name) {
+        list if list.is_empty() => Ok(default),
+        list => list
+            .into_iter()
+            .map(|value| value.parse().map_err(|_| Error::WrongFormat(name, value)))
+            .collect(),
+    }
+}
+
+fn get_env_list_or(name: &'static str, default: &[&str]) -> Vec<String> {
+    match get_env_list(name) {
+        list if list.is_empty() => default.iter().map(|item| item.to_string()).collect(),
+        list => list,
+    }
+}
+
+fn get_env_list(name: &'static str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+// endregion: Utils
+
+// region:    Error
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Config {0} has a wrong format - '{1}'")]
+    WrongFormat(&'static str, String),
+
+    #[error("Config {0} is required with {1}")]
+    MissingWith(&'static str, &'static str),
+}
+// endregion: Error
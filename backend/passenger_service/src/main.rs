@@ -1,17 +1,24 @@
+mod config;
+mod metrics;
 mod model;
 mod security;
+mod telemetry;
 mod web;
+mod webhook;
 
+use std::process::ExitCode;
 use std::{env, sync::Arc};
+use tokio::signal::unix::{signal, SignalKind};
 
-use model::init_db;
+use config::config;
+use model::init_db_with_retry;
 use web::start_web_server;
 
-const DEFAULT_WEB_FOLDER: &'static str = "web/";
+const DEFAULT_WEB_FOLDER: &str = "web/";
 const DEFAULT_WEB_PORT: u16 = 9090;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     // compute the web folder
     let mut args: Vec<String> = env::args().collect();
     let web_folder = args.pop().unwrap_or_else(|| DEFAULT_WEB_FOLDER.to_string());
@@ -21,14 +28,70 @@ async fn main() {
         .parse::<u16>()
         .unwrap_or(DEFAULT_WEB_PORT);
 
-    // get the database
-    // TODO - loop until valit database connection
-    let db = init_db().await.expect(" Can not init database.");
-    let db = Arc::new(db);
+    // load the config (fails fast on wrong values), then the logs and traces
+    let config = config();
+    let telemetry = match telemetry::init() {
+        Ok(telemetry) => telemetry,
+        Err(ex) => {
+            eprintln!("FATAL - while initializing telemetry - {}", ex);
+            return ExitCode::FAILURE;
+        }
+    };
+    tracing::info!(passenger_scope = ?config.passenger_scope, "config loaded");
+    if config.auth_dev_tokens {
+        tracing::warn!("AUTH_DEV_TOKENS is on, any user id signs in without a password - dev only");
+    }
+
+    let exit_code = run(&web_folder, web_port).await;
+    telemetry.shutdown();
+    exit_code
+}
+
+async fn run(web_folder: &str, web_port: u16) -> ExitCode {
+    // get the database, it may still be starting
+    let db = match init_db_with_retry().await {
+        Ok(db) => Arc::new(db),
+        Err(ex) => {
+            tracing::error!(cause = %ex, "FATAL - database not reachable");
+            return ExitCode::FAILURE;
+        }
+    };
+    security::revocation::start_gc(db.clone());
+    web::start_idempotency_gc(db.clone());
+    model::start_change_feed(db.clone());
+    model::start_passenger_cache_invalidation();
+    if let Err(ex) = webhook::start_dispatcher(db.clone()) {
+        tracing::error!(cause = %ex, "FATAL - webhook dispatcher not started");
+        return ExitCode::FAILURE;
+    }
+
+    // start the server, until SIGTERM or SIGINT
+    let result = start_web_server(web_folder, web_port, db.clone(), shutdown_signal()).await;
+    db.close().await;
+    match result {
+        Ok(_) => {
+            tracing::info!("server ended");
+            ExitCode::SUCCESS
+        }
+        Err(ex) => {
+            tracing::error!(cause = ?ex, "FATAL - web server failed");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // start the server
-    match start_web_server(&web_folder, web_port, db).await {
-        Ok(_) => println!("Server ended."),
-        Err(ex) => println!("ERROR - web server failed to start. Cause {:?}", ex),
+// Completes on the first SIGTERM (orchestrator stop) or SIGINT (Ctrl-C).
+async fn shutdown_signal() {
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(ex) => {
+            tracing::error!(cause = ?ex, "SIGTERM handler failed, only SIGINT stops the server");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = sigterm.recv() => tracing::info!("SIGTERM received, shutting down"),
+        _ = tokio::signal::ctrl_c() => tracing::info!("SIGINT received, shutting down"),
     }
 }
@@ -0,0 +1,154 @@
+use crate::model::Db;
+use prometheus::process_collector::ProcessCollector;
+use prometheus::{Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const NAMESPACE: &str = "passenger_service";
+// the pool wait time probe gives up after, the gauge then shows the timeout
+const POOL_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+// API path segments kept in the route label, the others are ids
+const API_SEGMENTS: &[&str] = &[
+    "api",
+    "passengers",
+    "bulk",
+    "search",
+    "duplicates",
+    "merge",
+    "from-mrz",
+    "documents",
+    "admin",
+    "api-keys",
+    "users",
+    "auth",
+    "login",
+    "refresh",
+    "logout",
+    "sessions",
+    "webhooks",
+    "deliveries",
+    "retry",
+];
+
+/// The service metrics, in their own registry (with the process metrics).
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub http_requests_in_flight: IntGauge,
+    pub db_query_duration: HistogramVec,
+    pub db_pool_connections: IntGauge,
+    pub db_pool_idle_connections: IntGauge,
+    pub db_pool_wait: Gauge,
+    pub auth_failures: IntCounterVec,
+    pub webhook_deliveries: IntCounterVec,
+    pub passenger_cache_requests: IntCounterVec,
+}
+
+pub fn metrics() -> &'static Metrics {
+    static INSTANCE: OnceLock<Metrics> = OnceLock::new();
+
+    INSTANCE.get_or_init(|| Metrics::new().unwrap_or_else(|ex| panic!("FATAL - while registering metrics - {}", ex)))
+}
+
+impl Metrics {
+    fn new() -> Result<Metrics, prometheus::Error> {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)?;
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests, by method, route and status"),
+                &["method", "route", "status"],
+            )?,
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "HTTP request latency, by method, route and status",
+                ),
+                &["method", "route", "status"],
+            )?,
+            http_requests_in_flight: IntGauge::new("http_requests_in_flight", "HTTP requests being served")?,
+            db_query_duration: HistogramVec::new(
+                HistogramOpts::new("db_query_duration_seconds", "Database query latency, by DAO operation")
+                    .buckets(vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+                &["operation", "outcome"],
+            )?,
+            db_pool_connections: IntGauge::new("db_pool_connections", "Open connections of the pool")?,
+            db_pool_idle_connections: IntGauge::new("db_pool_idle_connections", "Idle connections of the pool")?,
+            db_pool_wait: Gauge::new(
+                "db_pool_wait_seconds",
+                "Time to get a connection from the pool, probed at each scrape",
+            )?,
+            auth_failures: IntCounterVec::new(
+                Opts::new("auth_failures_total", "Failed authentications, by reason"),
+                &["reason"],
+            )?,
+            webhook_deliveries: IntCounterVec::new(
+                Opts::new("webhook_deliveries_total", "Webhook delivery attempts, by outcome"),
+                &["outcome"],
+            )?,
+            passenger_cache_requests: IntCounterVec::new(
+                Opts::new(
+                    "passenger_cache_requests_total",
+                    "Passenger cache reads, by DAO operation and outcome (hit or miss)",
+                ),
+                &["operation", "outcome"],
+            )?,
+            registry,
+        };
+        let registry = &metrics.registry;
+        registry.register(Box::new(metrics.http_requests.clone()))?;
+        registry.register(Box::new(metrics.http_request_duration.clone()))?;
+        registry.register(Box::new(metrics.http_requests_in_flight.clone()))?;
+        registry.register(Box::new(metrics.db_query_duration.clone()))?;
+        registry.register(Box::new(metrics.db_pool_connections.clone()))?;
+        registry.register(Box::new(metrics.db_pool_idle_connections.clone()))?;
+        registry.register(Box::new(metrics.db_pool_wait.clone()))?;
+        registry.register(Box::new(metrics.auth_failures.clone()))?;
+        registry.register(Box::new(metrics.webhook_deliveries.clone()))?;
+        registry.register(Box::new(metrics.passenger_cache_requests.clone()))?;
+        registry.register(Box::new(ProcessCollector::for_self()))?;
+        Ok(metrics)
+    }
+
+    /// Updates the pool stats, then returns all the metrics in the Prometheus text format.
+    pub async fn gather(&self, db: &Db) -> String {
+        self.db_pool_connections.set(db.size() as i64);
+        self.db_pool_idle_connections.set(db.num_idle() as i64);
+        let started_at = Instant::now();
+        // the connection goes back to the pool when dropped
+        let _ = tokio::time::timeout(POOL_PROBE_TIMEOUT, db.acquire()).await;
+        self.db_pool_wait.set(started_at.elapsed().as_secs_f64());
+
+        let mut buffer = Vec::new();
+        if let Err(ex) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(cause = ?ex, "metrics encoding failed");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Route of the request path, for the labels. The ids are replaced by `{id}`, so the number
+/// of routes stays bounded. For the same reason, the static files are `/static`, the docs
+/// `/docs` and the paths not found `unmatched`.
+pub fn route_label(path: &str, status: u16) -> String {
+    if status == 404 {
+        return "unmatched".to_string();
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments[0] {
+        "api" => segments
+            .iter()
+            .map(|segment| match API_SEGMENTS.contains(segment) {
+                true => format!("/{}", segment),
+                false => "/{id}".to_string(),
+            })
+            .collect(),
+        "docs" => "/docs".to_string(),
+        "api-doc.json" | "metrics" | "healthz" | "readyz" | "" => path.to_string(),
+        _ => "/static".to_string(),
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/metrics.rs"]
+mod tests;
@@ -0,0 +1,157 @@
+use super::db::{traced, Db};
+use crate::model;
+use crate::security::{api_key, Permission, UserCtx};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlbuilder::SqlBuilder;
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+// region: ApiKey Types
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    #[schema(example = "0a3a7f9c-6d1f-4a8e-9a3b-2f1c9b4e5d6f")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    #[schema(example = "flight_service")]
+    pub name: String,
+    #[schema(example = "Xk3d9QpL")]
+    pub prefix: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    #[serde(skip)]
+    pub salt: String,
+    #[schema(example = "passenger:read,passenger:write")]
+    pub scopes: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub tenant_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub created_by: Uuid,
+}
+
+impl ApiKey {
+    pub fn permissions(&self) -> Vec<Permission> {
+        self.scopes.split(',').filter_map(|scope| scope.parse().ok()).collect()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > Utc::now())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ApiKeyForCreate {
+    #[schema(example = "flight_service")]
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A new API key, along with its token which is never shown again.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyCreated {
+    pub api_key: ApiKey,
+    #[schema(example = "psk_Xk3d9QpL_4mZ...")]
+    pub token: String,
+}
+// endregion: ApiKey Types
+
+// region: ApiKeyDao
+pub struct ApiKeyDao;
+
+impl ApiKeyDao {
+    const TABLE: &'static str = "api_key";
+    const COLUMNS: &'static [&'static str] = &["name", "prefix", "key_hash", "salt", "scopes", "created_by"];
+}
+
+impl ApiKeyDao {
+    pub async fn create(db: &Db, utx: &UserCtx, data: ApiKeyForCreate) -> Result<ApiKeyCreated, model::Error> {
+        let parts = api_key::ApiKeyParts::generate();
+        let salt = api_key::new_salt();
+        let scopes: Vec<&str> = data.scopes.iter().map(Permission::as_str).collect();
+        let mut columns = Self::COLUMNS.to_vec();
+        let mut values = vec![
+            data.name,
+            parts.prefix.clone(),
+            api_key::hash_secret(&salt, &parts.secret),
+            salt,
+            scopes.join(","),
+            utx.user_id.to_string(),
+        ];
+        if let Some(tenant_id) = &utx.tenant_id {
+            columns.push("tenant_id");
+            values.push(tenant_id.clone());
+        }
+        if let Some(expires_at) = data.expires_at {
+            columns.push("expires_at");
+            values.push(expires_at.to_rfc3339());
+        }
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&columns)
+            .values(&values.iter().collect::<Vec<_>>())
+            .build();
+        let api_key = traced("api_key.create", &sql, sqlx::query_as::<_, ApiKey>(&sql).fetch_one(db)).await?;
+        Ok(ApiKeyCreated {
+            api_key,
+            token: parts.token(),
+        })
+    }
+
+    pub async fn list(db: &Db, _utx: &UserCtx) -> Result<Vec<ApiKey>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .order_by("created_at")
+            .build();
+        let api_keys = traced("api_key.list", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(api_keys)
+    }
+
+    pub async fn revoke(db: &Db, _utx: &UserCtx, id: String) -> Result<ApiKey, model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["revoked_at"], &[&Utc::now().to_rfc3339()])
+            .where_clause("id = {}", id.clone())
+            .and("revoked_at IS NULL")
+            .build();
+        let result = traced("api_key.revoke", &sql, sqlx::query_as::<_, ApiKey>(&sql).fetch_one(db)).await;
+        result.map_err(|sqlx_error| match sqlx_error {
+            sqlx::Error::RowNotFound => model::Error::EntityNotFound(Self::TABLE, id),
+            other => model::Error::Sqlx(other),
+        })
+    }
+
+    /// Finds the active API key matching the token (no user context, used for authentication).
+    pub async fn find_active(db: &Db, parts: &api_key::ApiKeyParts) -> Result<Option<ApiKey>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("prefix = {}", parts.prefix.clone())
+            .build();
+        let api_key: Option<ApiKey> =
+            traced("api_key.find_active", &sql, sqlx::query_as(&sql).fetch_optional(db)).await?;
+        Ok(api_key.filter(|api_key| {
+            api_key.is_active() && api_key::hash_secret(&api_key.salt, &parts.secret) == api_key.key_hash
+        }))
+    }
+
+    pub async fn touch(db: &Db, id: Uuid) -> Result<(), model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["last_used_at"], &[&Utc::now().to_rfc3339()])
+            .where_clause("id = {}", id.to_string())
+            .build();
+        traced("api_key.touch", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+}
+// endregion: ApiKeyDao
+
+#[cfg(test)]
+#[path = "../_tests/model_api_key.rs"]
+mod tests;
@@ -0,0 +1,220 @@
+use super::db::app_db_url;
+use super::passenger_event::{passenger_events, PassengerEventKind};
+use super::{Db, Passenger, PassengerDao};
+use crate::config::config;
+use crate::security::{Role, UserCtx};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use sqlx::{Connection, PgConnection, Row};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CHANNEL: &str = "passenger_changes";
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// A NOTIFY payload is at most 8000 bytes, a bigger row is sent as its id only.
+const SQL_NOTIFY_FUNCTION: &str = r#"
+CREATE OR REPLACE FUNCTION passenger_notify() RETURNS trigger AS $$
+DECLARE
+    rec passenger;
+    payload TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN rec := OLD; ELSE rec := NEW; END IF;
+    payload := json_build_object('op', lower(TG_OP), 'id', rec.id, 'passenger', row_to_json(rec))::text;
+    IF octet_length(payload) > 7900 THEN
+        payload := json_build_object('op', lower(TG_OP), 'id', rec.id)::text;
+    END IF;
+    PERFORM pg_notify('passenger_changes', payload);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql"#;
+const SQL_NOTIFY_TRIGGER: &str =
+    "CREATE OR REPLACE TRIGGER passenger_notify AFTER INSERT OR UPDATE OR DELETE ON passenger \
+     FOR EACH ROW EXECUTE FUNCTION passenger_notify()";
+const SQL_CHANGEFEED: &str = "EXPERIMENTAL CHANGEFEED FOR passenger WITH diff, initial_scan = 'no'";
+
+/// Source of the passenger changes made by the other instances (`CHANGE_FEED`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeFeedMode {
+    /// Only the changes of this instance are published.
+    Off,
+    /// Postgres LISTEN/NOTIFY, from a trigger on the passenger table installed at start.
+    Notify,
+    /// CockroachDB core changefeed (needs the `kv.rangefeed.enabled` cluster setting).
+    Changefeed,
+}
+
+impl FromStr for ChangeFeedMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ChangeFeedMode::Off),
+            "notify" => Ok(ChangeFeedMode::Notify),
+            "changefeed" => Ok(ChangeFeedMode::Changefeed),
+            other => Err(format!("Unknown change feed '{}'", other)),
+        }
+    }
+}
+
+/// A change read from the database, the passenger may be missing when too big for the feed.
+#[derive(Debug, Deserialize)]
+struct Change {
+    op: String,
+    id: String,
+    passenger: Option<PassengerRow>,
+}
+
+/// A passenger row, a soft deleted one (merged) has a `deleted_at`.
+#[derive(Debug, Deserialize)]
+struct PassengerRow {
+    #[serde(flatten)]
+    passenger: Passenger,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Follows the database changes of the passenger table in the background, and publishes them
+/// to the local `passenger_events`, so the subscribers of every instance see every change.
+/// The ones of this instance are published twice, `publish` keeps the first.
+/// Reconnects with a backoff; the changes made while disconnected are lost.
+pub fn start(db: Arc<Db>) {
+    let mode = config().change_feed;
+    if mode == ChangeFeedMode::Off {
+        tracing::info!("change feed off");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+        while !db.is_closed() {
+            let mut connected = false;
+            let result = match mode {
+                ChangeFeedMode::Notify => listen(&db, &mut connected).await,
+                ChangeFeedMode::Changefeed => follow(&db, &mut connected).await,
+                ChangeFeedMode::Off => return,
+            };
+            if connected {
+                backoff = MIN_BACKOFF;
+            }
+            let cause = result
+                .err()
+                .map(|ex| ex.to_string())
+                .unwrap_or_else(|| "connection lost".to_string());
+            tracing::warn!(
+                ?mode,
+                cause,
+                retry_in_ms = backoff.as_millis() as u64,
+                "change feed disconnected"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+/// Installs the trigger notifying the passenger changes on `passenger_changes`.
+pub async fn install_notify_trigger(db: &Db) -> Result<(), sqlx::Error> {
+    sqlx::query(SQL_NOTIFY_FUNCTION).execute(db).await?;
+    sqlx::query(SQL_NOTIFY_TRIGGER).execute(db).await?;
+    Ok(())
+}
+
+// Returns Ok once the connection is lost.
+async fn listen(db: &Db, connected: &mut bool) -> Result<(), sqlx::Error> {
+    install_notify_trigger(db).await?;
+    // a connection of its own, not to hold one of the pool
+    let mut listener = PgListener::connect(&app_db_url()).await?;
+    listener.listen(CHANNEL).await?;
+    *connected = true;
+    tracing::info!(channel = CHANNEL, "change feed listening");
+    while let Some(notification) = listener.try_recv().await? {
+        match serde_json::from_str::<Change>(notification.payload()) {
+            Ok(change) => apply(db, change).await,
+            Err(ex) => tracing::warn!(cause = %ex, "change feed - notification ignored"),
+        }
+    }
+    Ok(())
+}
+
+// Returns Ok once the changefeed ends.
+async fn follow(db: &Db, connected: &mut bool) -> Result<(), sqlx::Error> {
+    // a connection of its own, the changefeed runs until the connection is closed
+    let mut conn = PgConnection::connect(&app_db_url()).await?;
+    let mut rows = sqlx::query(SQL_CHANGEFEED).fetch(&mut conn);
+    *connected = true;
+    tracing::info!("change feed following");
+    // rows of (table, key, value), the value is {"after": row, "before": row} (diff)
+    while let Some(row) = rows.try_next().await? {
+        let value: Vec<u8> = row.try_get("value")?;
+        match change_from_changefeed(&value) {
+            Some(change) => apply(db, change).await,
+            None => tracing::warn!("change feed - row ignored"),
+        }
+    }
+    Ok(())
+}
+
+fn change_from_changefeed(value: &[u8]) -> Option<Change> {
+    #[derive(Deserialize)]
+    struct Value {
+        after: Option<PassengerRow>,
+        before: Option<PassengerRow>,
+    }
+    let value: Value = serde_json::from_slice(value).ok()?;
+    let (op, passenger) = match (value.before, value.after) {
+        (None, Some(after)) => ("insert", after),
+        (Some(_), Some(after)) => ("update", after),
+        (Some(before), None) => ("delete", before),
+        (None, None) => return None,
+    };
+    Some(Change {
+        op: op.to_string(),
+        id: passenger.passenger.id.to_string(),
+        passenger: Some(passenger),
+    })
+}
+
+async fn apply(db: &Db, change: Change) {
+    let kind = match change.op.as_str() {
+        "insert" => PassengerEventKind::Created,
+        "update" => PassengerEventKind::Updated,
+        "delete" => PassengerEventKind::Deleted,
+        op => {
+            tracing::warn!(op, "change feed - unknown operation ignored");
+            return;
+        }
+    };
+    let passenger = match (change.passenger, kind) {
+        // the passenger is hidden from now on
+        (Some(row), PassengerEventKind::Updated) if row.deleted_at.is_some() => {
+            passenger_events().publish(PassengerEventKind::Deleted, row.passenger);
+            return;
+        }
+        (Some(row), _) => row.passenger,
+        (None, PassengerEventKind::Deleted) => {
+            tracing::warn!(id = change.id, "change feed - deleted passenger too big, not published");
+            return;
+        }
+        // too big for the feed, read it back (it may have changed since, the next change will follow)
+        (None, _) => match PassengerDao::get(db, &feed_utx(), change.id.clone()).await {
+            Ok(passenger) => passenger,
+            Err(ex) => {
+                tracing::warn!(id = change.id, cause = %ex, "change feed - passenger not read");
+                return;
+            }
+        },
+    };
+    passenger_events().publish(kind, passenger);
+}
+
+// The passengers of every user and tenant.
+fn feed_utx() -> UserCtx {
+    UserCtx::new("change-feed".to_string(), None, vec![Role::Admin])
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_change_feed.rs"]
+mod tests;
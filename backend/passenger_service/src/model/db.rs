@@ -1,11 +1,17 @@
 #![allow(dead_code)]
 #![allow(unused)]
 
+use crate::config::config;
+use crate::metrics::metrics;
+use serde::Serialize;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{Pool, Postgres};
 use std::fs;
+use std::future::Future;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 
 pub type Db = Pool<Postgres>;
 
@@ -24,8 +30,39 @@ const PG_APP_MAX_CONN: u32 = 5;
 // sql files
 const SQL_DIR: &str = "sql/";
 const SQL_RECREATE: &str = "sql/00-recreate-db.sql";
+// startup retries
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
 // endregion db connection constants
 
+/// `init_db`, retried with an exponential backoff while the database is not up yet, until the
+/// `db_connect_deadline`. Returns the last error after the deadline.
+pub async fn init_db_with_retry() -> Result<Db, sqlx::Error> {
+    let config = config();
+    retry_with_backoff(config.db_connect_deadline, config.db_connect_backoff, init_db).await
+}
+
+async fn retry_with_backoff<T, E, F, Fut>(deadline: Duration, backoff: Duration, mut f: F) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let give_up_at = Instant::now() + deadline;
+    let mut backoff = backoff;
+    for attempt in 1.. {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(ex) if Instant::now() + backoff >= give_up_at => return Err(ex),
+            Err(ex) => {
+                tracing::warn!(attempt, cause = %ex, retry_in_ms = backoff.as_millis() as u64, "database not ready");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+    unreachable!("the attempts are unbounded")
+}
+
 pub async fn init_db() -> Result<Db, sqlx::Error> {
     // Create the db with PG_ROOT (dev only)
     {
@@ -44,19 +81,21 @@ pub async fn init_db() -> Result<Db, sqlx::Error> {
     // Run the app sql files
     let app_db = new_db_pool(PG_HOST, PG_APP_DB, PG_APP_USER, PG_PORT_DB, PG_APP_PWD, PG_SSL_MODE, 1).await?;
     let mut paths: Vec<PathBuf> = fs::read_dir(SQL_DIR)?
-        .into_iter()
         .filter_map(|e| e.ok())
         .map(|e| e.path())
         .collect();
     paths.sort();
+    let mut migrations = MigrationState::default();
     for path in paths {
         if let Some(path) = path.to_str() {
             // only sql files and not the recreate
             if path.ends_with(".sql") && path != SQL_RECREATE {
-                pexec(&app_db, &path).await?;
+                migrations.failed_statements += pexec(&app_db, path).await?;
+                migrations.applied.push(path.trim_start_matches(SQL_DIR).to_string());
             }
         }
     }
+    *MIGRATIONS.write().unwrap() = Some(migrations);
     // return app db
     new_db_pool(
         PG_HOST,
@@ -70,23 +109,58 @@ pub async fn init_db() -> Result<Db, sqlx::Error> {
     .await
 }
 
-async fn pexec(db: &Db, file: &str) -> Result<(), sqlx::Error> {
+/// Runs the query in a `db.query` span (child of the request span), with the DAO operation and
+/// the SQL template, and records its latency in the `db_query_duration_seconds` metric.
+pub(crate) async fn traced<T, E, F>(operation: &'static str, sql: &str, query: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let span = tracing::info_span!("db.query", db.operation = operation, db.statement = %sqlbuilder::template(sql));
+    let started_at = Instant::now();
+    let result = query.instrument(span).await;
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    metrics()
+        .db_query_duration
+        .with_label_values(&[operation, outcome])
+        .observe(started_at.elapsed().as_secs_f64());
+    result
+}
+
+/// The app sql files run by the last `init_db`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MigrationState {
+    pub applied: Vec<String>,
+    /// Statements which failed, the schema may be incomplete when not 0.
+    pub failed_statements: usize,
+}
+
+static MIGRATIONS: RwLock<Option<MigrationState>> = RwLock::new(None);
+
+/// None until `init_db` ran the app sql files.
+pub fn migration_state() -> Option<MigrationState> {
+    MIGRATIONS.read().unwrap().clone()
+}
+
+// Returns the number of failed statements.
+async fn pexec(db: &Db, file: &str) -> Result<usize, sqlx::Error> {
     // Read the file
     let content = fs::read_to_string(file).map_err(|ex| {
-        println!("ERROR reading file: {} (cause: {:?}", file, ex);
+        tracing::error!(file, cause = ?ex, "reading sql file failed");
         ex
     })?;
     // TODO: make the split more sql proof
     let sqls: Vec<&str> = content.split(";").collect();
+    let mut failed = 0;
     for sql in sqls {
-        match sqlx::query(&sql).execute(db).await {
+        match sqlx::query(sql).execute(db).await {
             Ok(_) => {}
             Err(ex) => {
-                println!("WARNING - pexec - Sql file '{}' FAILED cause: {}", file, ex);
+                tracing::warn!(file, cause = %ex, "pexec - sql file failed");
+                failed += 1;
             }
         }
     }
-    Ok(())
+    Ok(failed)
 }
 
 async fn new_db_pool(
@@ -98,10 +172,7 @@ async fn new_db_pool(
     sslmode: &str,
     max_con: u32,
 ) -> Result<Db, sqlx::Error> {
-    let db_url = format!(
-        "postgresql://{}:{}@{}:{}/{}?sslmode={}",
-        user, pwd, host, port, db, sslmode
-    );
+    let db_url = db_url(host, db, user, port, pwd, sslmode);
 
     PgPoolOptions::new()
         .max_connections(max_con)
@@ -110,6 +181,18 @@ async fn new_db_pool(
         .await
 }
 
+/// URL of the app db, for the connections outside of the pool (e.g. a `PgListener`).
+pub(super) fn app_db_url() -> String {
+    db_url(PG_HOST, PG_APP_DB, PG_APP_USER, PG_PORT_DB, PG_APP_PWD, PG_SSL_MODE)
+}
+
+fn db_url(host: &str, db: &str, user: &str, port: &str, pwd: &str, sslmode: &str) -> String {
+    format!(
+        "postgresql://{}:{}@{}:{}/{}?sslmode={}",
+        user, pwd, host, port, db, sslmode
+    )
+}
+
 #[cfg(test)]
 #[path = "../_tests/model_db.rs"]
 mod tests;
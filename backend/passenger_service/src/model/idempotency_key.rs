@@ -0,0 +1,145 @@
+use super::db::{traced, Db};
+use crate::model;
+use chrono::{DateTime, Duration, Utc};
+use sqlbuilder::{FormatSqlValue, SqlBuilder};
+
+// Postgres (and CockroachDB) error code of a unique constraint violation
+const UNIQUE_VIOLATION: &str = "23505";
+
+// region: IdempotencyKey Types
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct IdempotencyKey {
+    /// Hash of the request first sent with the key.
+    pub request_hash: String,
+    /// Status and body of the response, None while the first request is in progress.
+    pub status: Option<i64>,
+    pub response: Option<String>,
+    /// When the first request claimed the key.
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl IdempotencyKey {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Whether the first request is still in progress after its `lease`, i.e. it is gone
+    /// without releasing the key (process crashed).
+    pub fn is_abandoned(&self, lease: Duration) -> bool {
+        self.response.is_none() && self.created_at + lease <= Utc::now()
+    }
+}
+// endregion: IdempotencyKey Types
+
+// region: IdempotencyKeyDao
+pub struct IdempotencyKeyDao;
+
+impl IdempotencyKeyDao {
+    const TABLE: &'static str = "idempotency_key";
+}
+
+impl IdempotencyKeyDao {
+    /// Claims the key for a new request. Returns None if the user already used the key.
+    pub async fn claim(
+        db: &Db,
+        user_id: &str,
+        request_key: &str,
+        request_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyKey>, model::Error> {
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&["user_id", "request_key", "request_hash", "expires_at"])
+            .values(&[
+                &user_id.to_string(),
+                &request_key.to_string(),
+                &request_hash.to_string(),
+                &expires_at.to_rfc3339(),
+            ])
+            .build();
+        match traced("idempotency_key.claim", &sql, sqlx::query_as(&sql).fetch_one(db)).await {
+            Ok(claimed) => Ok(Some(claimed)),
+            Err(sqlx::Error::Database(ex)) if ex.code().as_deref() == Some(UNIQUE_VIOLATION) => Ok(None),
+            Err(ex) => Err(ex.into()),
+        }
+    }
+
+    pub async fn get(db: &Db, user_id: &str, request_key: &str) -> Result<Option<IdempotencyKey>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("user_id = {}", user_id.to_string())
+            .and_where("request_key = {}", request_key.to_string())
+            .build();
+        let idempotency_key = traced("idempotency_key.get", &sql, sqlx::query_as(&sql).fetch_optional(db)).await?;
+        Ok(idempotency_key)
+    }
+
+    /// Stores the response of the request which claimed the key at `claimed_at`.
+    pub async fn complete(
+        db: &Db,
+        user_id: &str,
+        request_key: &str,
+        claimed_at: DateTime<Utc>,
+        status: u16,
+        response: &str,
+    ) -> Result<(), model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["status", "response"], &[&status.to_string(), &response.to_string()])
+            .where_clause("user_id = {}", user_id.to_string())
+            .and_where("request_key = {}", request_key.to_string())
+            .and_where("created_at = {}", claimed_at.to_rfc3339())
+            .build();
+        traced("idempotency_key.complete", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+
+    /// Releases the key claimed at `claimed_at`, while its request is in progress, e.g. when it
+    /// failed, so it can be retried. A key claimed again in between is left alone.
+    pub async fn release(
+        db: &Db,
+        user_id: &str,
+        request_key: &str,
+        claimed_at: DateTime<Utc>,
+    ) -> Result<(), model::Error> {
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("user_id = {}", user_id.to_string())
+            .and_where("request_key = {}", request_key.to_string())
+            .and_where("created_at = {}", claimed_at.to_rfc3339())
+            .and("response IS NULL")
+            .build();
+        traced("idempotency_key.release", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+
+    /// Releases the key if it is expired, or abandoned by its request (see
+    /// `IdempotencyKey::is_abandoned`), so it can be claimed again.
+    pub async fn release_stale(db: &Db, user_id: &str, request_key: &str, lease: Duration) -> Result<(), model::Error> {
+        let now = Utc::now();
+        let stale = format!(
+            "(expires_at <= {} OR (response IS NULL AND created_at <= {{}}))",
+            now.to_rfc3339().format_sql_value()
+        );
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("user_id = {}", user_id.to_string())
+            .and_where("request_key = {}", request_key.to_string())
+            .and_where(&stale, (now - lease).to_rfc3339())
+            .build();
+        traced("idempotency_key.release_stale", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+
+    /// Deletes the expired keys, returns how many were deleted.
+    pub async fn delete_expired(db: &Db) -> Result<u64, model::Error> {
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("expires_at <= {}", Utc::now().to_rfc3339())
+            .build();
+        let result = traced("idempotency_key.delete_expired", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(result.rows_affected())
+    }
+}
+// endregion: IdempotencyKeyDao
@@ -1,12 +1,54 @@
 use thiserror::Error as ThisError;
 
+mod api_key;
+mod change_feed;
 mod db;
+mod idempotency_key;
+mod outbox;
 mod passenger;
+mod passenger_cache;
+mod passenger_document;
+mod passenger_event;
+mod passenger_merge;
+mod passenger_mrz;
+mod passenger_name;
+mod passenger_profile;
+mod passenger_search;
+mod revoked_token;
+mod scope;
+mod user;
+mod user_session;
+mod webhook;
 
 // re-export to the outside world
+pub use api_key::{ApiKey, ApiKeyCreated, ApiKeyDao, ApiKeyForCreate};
+pub use change_feed::{start as start_change_feed, ChangeFeedMode};
+#[cfg(test)]
 pub use db::init_db;
 pub use db::Db;
-pub use passenger::{Passenger, PassengerDao, PassengerPatch};
+pub use db::{init_db_with_retry, migration_state};
+pub use idempotency_key::{IdempotencyKey, IdempotencyKeyDao};
+pub use outbox::OutboxDao;
+pub use passenger::{Passenger, PassengerDao, PassengerFilter, PassengerPatch};
+pub use passenger_cache::start_invalidation as start_passenger_cache_invalidation;
+pub use passenger_document::{
+    PassengerDocument, PassengerDocumentDao, PassengerDocumentForCreate, PassengerDocumentPatch,
+};
+#[cfg(test)]
+pub use passenger_event::PassengerEventKind;
+pub use passenger_event::{passenger_events, PassengerEvent, PassengerEvents, Replay};
+pub use passenger_merge::{DuplicateFilter, PassengerDuplicate, PassengerMerge, PassengerMergeRequest};
+pub use passenger_mrz::{CheckDigitFailure, PassengerFromMrz, PassengerMrzRequest};
+pub use passenger_name::NameCase;
+pub use passenger_search::{Highlight, PassengerSearch, PassengerSearchHit};
+pub use revoked_token::RevokedTokenDao;
+pub use scope::Scope;
+pub use user::{User, UserDao, UserForCreate};
+pub use user_session::{UserSession, UserSessionDao};
+pub use webhook::{
+    Webhook, WebhookCreated, WebhookDao, WebhookDelivery, WebhookDeliveryDao, WebhookDeliveryFilter, WebhookForCreate,
+    WebhookPatch,
+};
 
 // region:    Error
 #[derive(ThisError, Debug)]
@@ -14,10 +56,23 @@ pub enum Error {
     #[error("Entity Not Found - {0}[{1}] ")]
     EntityNotFound(&'static str, String),
     #[error(transparent)]
-    SqlxError(#[from] sqlx::Error),
+    Sqlx(#[from] sqlx::Error),
 
     #[error(transparent)]
-    IOError(#[from] std::io::Error),
+    Io(#[from] std::io::Error),
+
+    #[error("Fail to hash password")]
+    FailHashPwd,
+
+    #[error("Invalid {0} - {1}")]
+    Invalid(&'static str, String),
+
+    #[error("Invalid check digits - {}", .0.iter().map(|failure| failure.field.as_str()).collect::<Vec<_>>().join(", "))]
+    CheckDigits(Vec<CheckDigitFailure>),
 }
 
 // endregion: Error
+
+#[cfg(test)]
+#[path = "../_tests/model.rs"]
+pub(crate) mod tests;
@@ -0,0 +1,100 @@
+use super::db::{traced, Db};
+use super::passenger_event::PassengerEventKind;
+use super::Passenger;
+use crate::model;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlbuilder::SqlBuilder;
+use sqlx::{Postgres, Transaction};
+
+// region: Outbox Types
+/// A change, stored in the transaction of the change itself, so it is never lost nor sent for a
+/// change rolled back.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct OutboxEvent {
+    pub id: i64,
+    /// e.g. `passenger.created`
+    pub event_type: String,
+    /// The passenger after the change (before it, when deleted).
+    pub payload: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The event types of the outbox, the ones a webhook can subscribe to.
+pub const OUTBOX_EVENT_TYPES: &[&str] = &["passenger.created", "passenger.updated", "passenger.deleted"];
+
+pub fn event_type(kind: PassengerEventKind) -> String {
+    format!("passenger.{}", kind.as_str())
+}
+// endregion: Outbox Types
+
+// region: OutboxDao
+pub struct OutboxDao;
+
+impl OutboxDao {
+    const TABLE: &'static str = "outbox";
+}
+
+impl OutboxDao {
+    /// Adds the event of the passenger change, to be committed along the change.
+    pub(super) async fn insert(
+        tx: &mut Transaction<'_, Postgres>,
+        kind: PassengerEventKind,
+        passenger: &Passenger,
+    ) -> Result<(), model::Error> {
+        let payload =
+            serde_json::to_string(passenger).map_err(|ex| model::Error::Invalid("payload", ex.to_string()))?;
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&["event_type", "aggregate_id", "payload"])
+            .values(&[&event_type(kind), &passenger.id.to_string(), &payload])
+            .build();
+        traced("outbox.insert", &sql, sqlx::query(&sql).execute(&mut *tx)).await?;
+        Ok(())
+    }
+
+    /// Marks up to `limit` events as dispatched, oldest first, and returns them. Runs in the
+    /// transaction creating their deliveries, so a concurrent dispatcher does not get them too.
+    pub(super) async fn claim_pending(
+        tx: &mut Transaction<'_, Postgres>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEvent>, model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["dispatched_at"], &[&Utc::now().to_rfc3339()])
+            .where_clause(
+                "id IN (SELECT id FROM outbox WHERE dispatched_at IS NULL ORDER BY id LIMIT {})",
+                limit,
+            )
+            .build();
+        let mut events: Vec<OutboxEvent> =
+            traced("outbox.claim_pending", &sql, sqlx::query_as(&sql).fetch_all(&mut *tx)).await?;
+        events.sort_by_key(|event| event.id);
+        Ok(events)
+    }
+
+    pub async fn get(db: &Db, id: i64) -> Result<OutboxEvent, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("id = {}", id)
+            .build();
+        let result = traced("outbox.get", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        result.map_err(|sqlx_error| match sqlx_error {
+            sqlx::Error::RowNotFound => model::Error::EntityNotFound(Self::TABLE, id.to_string()),
+            other => model::Error::Sqlx(other),
+        })
+    }
+
+    /// The events of a passenger, oldest first.
+    #[cfg(test)]
+    pub async fn list_for(db: &Db, aggregate_id: String) -> Result<Vec<OutboxEvent>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("aggregate_id = {}", aggregate_id)
+            .order_by("id")
+            .build();
+        let events = traced("outbox.list_for", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(events)
+    }
+}
+// endregion: OutboxDao
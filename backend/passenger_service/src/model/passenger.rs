@@ -1,14 +1,31 @@
 use std::fmt;
 use std::str::FromStr;
 
-use super::db::Db;
+use super::db::{traced, Db};
+use super::outbox::OutboxDao;
+use super::passenger_cache::{passenger_cache, PassengerCache};
+use super::passenger_document::{PassengerDocumentDao, PassengerDocumentForCreate};
+use super::passenger_event::{passenger_events, PassengerEventKind};
+use super::passenger_merge::{
+    plan_merge, rank_duplicates, DuplicateFilter, PassengerDuplicate, PassengerMerge, PassengerMergeRequest,
+    PLACEHOLDER_NAME,
+};
+use super::passenger_mrz::{parse_mrz, PassengerFromMrz, PassengerMrzRequest};
+use super::passenger_name::{name_key, normalize_name};
+use super::passenger_profile as profile;
+use super::passenger_search::{rank, search_key, PassengerSearch, PassengerSearchHit};
+use crate::config::config;
 use crate::model;
 use crate::security::UserCtx;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
-use sqlbuilder::SqlBuilder;
+use sqlbuilder::{FormatSqlValue, SqlBuilder};
 use sqlx::types::Uuid;
-use utoipa::ToSchema;
+use sqlx::Row;
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use utoipa::{IntoParams, ToSchema};
 
 // region: use  Passenger Types
 #[serde_as]
@@ -20,14 +37,41 @@ pub struct Passenger {
     #[schema(example = "2096036b-9606-4405-995b-565a481344bc")]
     #[serde_as(as = "DisplayFromStr")]
     pub uid: Uuid,
+    #[schema(example = "8f5c3a5e-3d4b-4b8e-9a36-5e1c2f0b7d21")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub tenant_id: Option<Uuid>,
     #[schema(example = "John")]
     pub first_name: String,
     #[schema(example = "Doe")]
     pub last_name: String,
     #[schema(example = "new")]
     pub status: Option<String>,
+    #[schema(example = "1980-02-29")]
+    pub date_of_birth: Option<NaiveDate>,
+    /// `F`, `M` or `X` (unspecified), as in the travel documents
+    #[schema(example = "F")]
+    pub gender: Option<String>,
+    /// ISO 3166-1 alpha-3 code
+    #[schema(example = "FRA")]
+    pub nationality: Option<String>,
+    #[schema(example = "john.doe@example.com")]
+    pub email: Option<String>,
+    /// E.164
+    #[schema(example = "+33612345678")]
+    pub phone: Option<String>,
+    #[schema(example = "2023-04-01T10:00:00Z")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "2096036b-9606-4405-995b-565a481344bc")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub created_by: Uuid,
+    #[schema(example = "2023-04-01T10:00:00Z")]
+    pub updated_at: DateTime<Utc>,
+    #[schema(example = "2096036b-9606-4405-995b-565a481344bc")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub updated_by: Uuid,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Status {
     Active,
@@ -65,12 +109,19 @@ impl fmt::Display for Status {
     }
 }
 
-#[derive(Default, Clone, Debug, Deserialize)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PassengerPatch {
+    #[allow(dead_code)]
     pub uid: Option<String>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub status: Option<String>,
+    pub date_of_birth: Option<NaiveDate>,
+    pub gender: Option<String>,
+    pub nationality: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
 }
 
 impl PassengerPatch {
@@ -85,6 +136,85 @@ impl PassengerPatch {
     pub fn get_status(&self) -> String {
         self.status.clone().unwrap_or_else(|| "new".to_string())
     }
+
+    /// The patch with its names as stored (see `normalize_name`) and its profile fields
+    /// validated (see `passenger_profile`).
+    pub fn normalized(self) -> Result<Self, model::Error> {
+        Ok(PassengerPatch {
+            first_name: self
+                .first_name
+                .map(|name| normalize_name("first_name", &name))
+                .transpose()?,
+            last_name: self
+                .last_name
+                .map(|name| normalize_name("last_name", &name))
+                .transpose()?,
+            date_of_birth: self.date_of_birth.map(profile::date_of_birth).transpose()?,
+            gender: self.gender.map(|gender| profile::gender(&gender)).transpose()?,
+            nationality: self
+                .nationality
+                .map(|code| profile::country("nationality", &code))
+                .transpose()?,
+            email: self.email.map(|email| profile::email(&email)).transpose()?,
+            phone: self.phone.map(|phone| profile::phone(&phone)).transpose()?,
+            ..self
+        })
+    }
+}
+
+/// Query parameters of the passenger list.
+#[serde_as]
+#[derive(Default, Clone, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PassengerFilter {
+    /// Only passengers updated at or after this instant (RFC 3339)
+    #[param(example = "2023-04-01T10:00:00Z")]
+    pub updated_since: Option<DateTime<Utc>>,
+    /// Sort column, prefixed with `-` for descending order (e.g. `-updated_at`)
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[param(value_type = Option<String>, example = "-updated_at")]
+    pub sort: Option<PassengerSort>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PassengerSort {
+    pub column: &'static str,
+    pub descending: bool,
+}
+
+impl FromStr for PassengerSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, descending) = match s.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (s, false),
+        };
+        match PassengerDao::SORTABLE.iter().find(|column| **column == name) {
+            Some(column) => Ok(PassengerSort { column, descending }),
+            None => Err(format!("Cannot sort passengers by '{}'", name)),
+        }
+    }
+}
+
+impl PassengerSort {
+    /// ORDER BY expression, ending with the id so equal values come back in a stable order.
+    fn order_by(&self) -> String {
+        let direction = if self.descending { " DESC" } else { "" };
+        match self.column {
+            "id" => format!("id{}", direction),
+            column => format!("{}{}, id", column, direction),
+        }
+    }
+}
+
+impl fmt::Display for PassengerSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.descending {
+            true => write!(f, "-{}", self.column),
+            false => write!(f, "{}", self.column),
+        }
+    }
 }
 
 // endregion:  Passenger Types
@@ -94,95 +224,580 @@ pub struct PassengerDao;
 
 impl PassengerDao {
     const TABLE: &'static str = "passenger";
-    const COLUMNS: &'static [&'static str] = &["uid", "first_name", "last_name", "status"];
+    const COLUMNS: &'static [&'static str] = &[
+        "uid",
+        "first_name",
+        "last_name",
+        "status",
+        "created_at",
+        "created_by",
+        "updated_at",
+        "updated_by",
+        "first_name_search",
+        "last_name_search",
+        "name_key",
+        "date_of_birth",
+        "gender",
+        "nationality",
+        "email",
+        "phone",
+    ];
+    // uid, created_at and created_by are only written once, on create
+    const UPDATE_COLUMNS: &'static [&'static str] = &[
+        "first_name",
+        "last_name",
+        "status",
+        "updated_at",
+        "updated_by",
+        "first_name_search",
+        "last_name_search",
+        "name_key",
+        "date_of_birth",
+        "gender",
+        "nationality",
+        "email",
+        "phone",
+    ];
+    // candidates read from the trigram index per hit returned, ranked in `rank`
+    const SEARCH_CANDIDATES_PER_HIT: i64 = 5;
+    // passengers read at once by the search without trigram support
+    const SEARCH_SCAN_PAGE: i64 = 1000;
+    // candidate pairs read for the duplicates, ranked in `rank_duplicates`
+    const MAX_DUPLICATE_CANDIDATES: i64 = 10_000;
+    /// The (table, column) holding passenger ids, moved to the survivor of a merge. The outbox
+    /// keeps the ids of its events, they are history.
+    const REFERENCES: &'static [(&'static str, &'static str)] =
+        &[("passenger", "merged_into"), ("passenger_document", "passenger_id")];
+    const SORTABLE: &'static [&'static str] = &["id", "first_name", "last_name", "created_at", "updated_at"];
 }
 
 impl PassengerDao {
     pub async fn create(db: &Db, utx: &UserCtx, data: PassengerPatch) -> Result<Passenger, model::Error> {
-        let sql = SqlBuilder::new()
-            .insert_into(Self::TABLE) // Start the INSERT statement and specify the table name
-            .columns(Self::COLUMNS) // Specify the columns to insert into
-            .values(&[
-                &utx.user_id.to_string(),
-                &data.get_first_name(),
-                &data.get_last_name(),
-                &data.get_status(),
-            ])
-            .build();
+        let data = data.normalized()?;
+        let sql = Self::insert_sql(utx, &data);
         let query = sqlx::query_as::<_, Passenger>(&sql);
-        let passenger = query.fetch_one(db).await?;
+        let mut tx = db.begin().await?;
+        let passenger = traced("passenger.create", &sql, query.fetch_one(&mut tx)).await?;
+        OutboxDao::insert(&mut tx, PassengerEventKind::Created, &passenger).await?;
+        tx.commit().await?;
+        invalidate_cache(&passenger).await;
+        passenger_events().publish(PassengerEventKind::Created, passenger.clone());
         Ok(passenger)
     }
 
-    // Create from bulk (array of PassengerPatch)
-    // pub async fn create_bulk(
-    //     db: &Db,
-    //     utx: &UserCtx,
-    //     data: Vec<PassengerPatch>,
-    // ) -> Result<Vec<Passenger>, model::Error> {
-    //     let mut passengers: Vec<Passenger> = Vec::new();
-    //     for passenger in data {
-    //         let sql = SqlBuilder::new()
-    //             .insert_into(Self::TABLE) // Start the INSERT statement and specify the table name
-    //             .columns(Self::COLUMNS) // Specify the columns to insert into
-    //             .values(&[
-    //                 &utx.user_id.to_string(),
-    //                 &passenger.get_first_name(),
-    //                 &passenger.get_last_name(),
-    //                 &passenger.get_status(),
-    //             ])
-    //             .build();
-    //         let query = sqlx::query_as::<_, (Passenger)>(&sql);
-    //         let passenger = query.fetch_one(db).await?;
-    //         passengers.push(passenger);
-    //     }
-    //     Ok(passengers)
-    // }
-
-    pub async fn get(db: &Db, _utx: &UserCtx, id: String) -> Result<Passenger, model::Error> {
+    /// Creates all the passengers, or none of them.
+    pub async fn create_bulk(
+        db: &Db,
+        utx: &UserCtx,
+        data: Vec<PassengerPatch>,
+    ) -> Result<Vec<Passenger>, model::Error> {
+        // all checked before the first insert
+        let data = data
+            .into_iter()
+            .enumerate()
+            .map(|(index, passenger)| {
+                passenger.normalized().map_err(|ex| match ex {
+                    model::Error::Invalid(field, reason) => {
+                        model::Error::Invalid(field, format!("passenger {} - {}", index, reason))
+                    }
+                    other => other,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tx = db.begin().await?;
+        let mut passengers: Vec<Passenger> = Vec::with_capacity(data.len());
+        for passenger in data {
+            let sql = Self::insert_sql(utx, &passenger);
+            let query = sqlx::query_as::<_, Passenger>(&sql);
+            let passenger = traced("passenger.create_bulk", &sql, query.fetch_one(&mut tx)).await?;
+            OutboxDao::insert(&mut tx, PassengerEventKind::Created, &passenger).await?;
+            passengers.push(passenger);
+        }
+        tx.commit().await?;
+        for passenger in &passengers {
+            invalidate_cache(passenger).await;
+            passenger_events().publish(PassengerEventKind::Created, passenger.clone());
+        }
+        Ok(passengers)
+    }
+
+    pub async fn get(db: &Db, utx: &UserCtx, id: String) -> Result<Passenger, model::Error> {
         let sql = SqlBuilder::new()
             .select_from(Self::TABLE)
             .where_clause("id = {}", id.clone())
-            .build();
+            .and("deleted_at IS NULL");
+        let Some(cache) = passenger_cache() else {
+            return Self::fetch_one(db, Self::scoped(sql, utx), id).await;
+        };
+        // cached for all the users, the scope is checked on each read
+        let key = PassengerCache::passenger_key(&id);
+        let passenger = cache
+            .get_or_load("get", key, || Self::fetch_one(db, sql, id.clone()))
+            .await?;
+        match config().passenger_scope.allows(utx, &passenger) {
+            true => Ok(passenger),
+            false => Err(model::Error::EntityNotFound(Self::TABLE, id)),
+        }
+    }
+
+    async fn fetch_one(db: &Db, sql: SqlBuilder, id: String) -> Result<Passenger, model::Error> {
+        let sql = sql.build();
         let query = sqlx::query_as::<_, Passenger>(&sql);
-        let result = query.fetch_one(db).await;
+        let result = traced("passenger.get", &sql, query.fetch_one(db)).await;
         handle_fetch_one_result(result, Self::TABLE, id)
     }
 
+    /// Updates the fields in the patch, the others are kept.
     pub async fn update(db: &Db, utx: &UserCtx, id: String, data: PassengerPatch) -> Result<Passenger, model::Error> {
+        let data = data.normalized()?;
+        let mut tx = db.begin().await?;
+        let mut columns = vec!["updated_at", "updated_by"];
+        let mut values = vec![Utc::now().to_rfc3339(), utx.user_id.to_string()];
+        // the search keys are of both names, the one left out is read (locked) in the transaction
+        if data.first_name.is_some() || data.last_name.is_some() {
+            let sql = SqlBuilder::new()
+                .select_from(Self::TABLE)
+                .where_clause("id = {}", id.clone());
+            let sql = format!("{} FOR UPDATE", Self::visible(sql, utx).build());
+            let query = sqlx::query_as::<_, Passenger>(&sql);
+            let result = traced("passenger.update", &sql, query.fetch_one(&mut tx)).await;
+            let current = handle_fetch_one_result(result, Self::TABLE, id.clone())?;
+            let first_name = data.first_name.clone().unwrap_or(current.first_name);
+            let last_name = data.last_name.clone().unwrap_or(current.last_name);
+            columns.extend(["first_name_search", "last_name_search", "name_key"]);
+            values.extend([
+                search_key(&first_name),
+                search_key(&last_name),
+                name_key(&first_name, &last_name),
+            ]);
+        }
+        for (column, value) in [
+            ("first_name", data.first_name),
+            ("last_name", data.last_name),
+            ("status", data.status),
+            ("date_of_birth", data.date_of_birth.map(|date| date.to_string())),
+            ("gender", data.gender),
+            ("nationality", data.nationality),
+            ("email", data.email),
+            ("phone", data.phone),
+        ] {
+            if let Some(value) = value {
+                columns.push(column);
+                values.push(value);
+            }
+        }
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&columns, &values.iter().collect::<Vec<_>>())
+            .where_clause("id = {}", id.clone());
+        let sql = Self::visible(sql, utx).build();
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let result = traced("passenger.update", &sql, query.fetch_one(&mut tx)).await;
+        let passenger = handle_fetch_one_result(result, Self::TABLE, id)?;
+        OutboxDao::insert(&mut tx, PassengerEventKind::Updated, &passenger).await?;
+        tx.commit().await?;
+        invalidate_cache(&passenger).await;
+        passenger_events().publish(PassengerEventKind::Updated, passenger.clone());
+        Ok(passenger)
+    }
+
+    pub async fn delete(db: &Db, utx: &UserCtx, id: String) -> Result<Passenger, model::Error> {
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("id = {}", id.clone());
+        let sql = Self::visible(sql, utx).build();
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let mut tx = db.begin().await?;
+        let result = traced("passenger.delete", &sql, query.fetch_one(&mut tx)).await;
+        let passenger = handle_fetch_one_result(result, Self::TABLE, id)?;
+        OutboxDao::insert(&mut tx, PassengerEventKind::Deleted, &passenger).await?;
+        tx.commit().await?;
+        invalidate_cache(&passenger).await;
+        passenger_events().publish(PassengerEventKind::Deleted, passenger.clone());
+        Ok(passenger)
+    }
+
+    pub async fn list(db: &Db, utx: &UserCtx, filter: &PassengerFilter) -> Result<Vec<Passenger>, model::Error> {
+        let Some(cache) = passenger_cache() else {
+            return Self::fetch_all(db, utx, filter).await;
+        };
+        let query = format!(
+            "{}:{}",
+            filter.updated_since.map(|at| at.to_rfc3339()).unwrap_or_default(),
+            filter.sort.as_ref().map(PassengerSort::to_string).unwrap_or_default()
+        );
+        let key = PassengerCache::passengers_key(&config().passenger_scope.partition(utx), &query);
+        cache
+            .get_or_load("list", key, || Self::fetch_all(db, utx, filter))
+            .await
+    }
+
+    async fn fetch_all(db: &Db, utx: &UserCtx, filter: &PassengerFilter) -> Result<Vec<Passenger>, model::Error> {
+        let mut sql = Self::visible(SqlBuilder::new().select_from(Self::TABLE), utx);
+        if let Some(updated_since) = filter.updated_since {
+            sql = sql.and_where("updated_at >= {}", updated_since.to_rfc3339());
+        }
+        let order_by = filter.sort.as_ref().map(PassengerSort::order_by);
+        let sql = sql.order_by(order_by.as_deref().unwrap_or("id")).build();
+        let query = sqlx::query_as(&sql);
+        let passengers = traced("passenger.list", &sql, query.fetch_all(db)).await?;
+        Ok(passengers)
+    }
+
+    /// Passengers matching all the words of the query, the most relevant first. Candidates come
+    /// from the trigram indexes of the folded names, at most `SEARCH_CANDIDATES_PER_HIT` per hit,
+    /// those with words starting with the terms first, then the most similar. Without trigram
+    /// support, all the passengers of the user are ranked, see `search_scan`.
+    pub async fn search(
+        db: &Db,
+        utx: &UserCtx,
+        search: &PassengerSearch,
+    ) -> Result<Vec<PassengerSearchHit>, model::Error> {
+        let terms = search.terms()?;
+        let limit = search.limit()?;
+        if !has_trigrams(db).await {
+            return Self::search_scan(db, utx, &terms, limit, Self::SEARCH_SCAN_PAGE).await;
+        }
+        let mut sql = Self::visible(SqlBuilder::new().select_from(Self::TABLE), utx);
+        let mut prefixes = Vec::with_capacity(terms.len());
+        let mut relevance = Vec::with_capacity(terms.len());
+        for term in &terms {
+            sql = sql.and_where(
+                "(first_name_search LIKE '%' || {} || '%' OR last_name_search LIKE '%' || {} || '%' \
+                 OR first_name_search % {} OR last_name_search % {})",
+                term.clone(),
+            );
+            // the terms are alphanumeric words, nothing to escape in the pattern
+            let word_start = format!("(^|[^[:alnum:]]){}", term).format_sql_value();
+            prefixes.push(format!(
+                "(CASE WHEN first_name_search ~ {} OR last_name_search ~ {} THEN 1 ELSE 0 END)",
+                word_start, word_start
+            ));
+            let term = term.format_sql_value();
+            relevance.push(format!(
+                "greatest(similarity(first_name_search, {}), similarity(last_name_search, {}))",
+                term, term
+            ));
+        }
+        let sql = sql
+            .order_by(&format!(
+                "{} DESC, {} DESC, id",
+                prefixes.join(" + "),
+                relevance.join(" + ")
+            ))
+            .limit(limit * Self::SEARCH_CANDIDATES_PER_HIT)
+            .build();
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let candidates = traced("passenger.search", &sql, query.fetch_all(db)).await?;
+        Ok(rank(candidates, &terms, limit))
+    }
+
+    // Ranks the passengers of the user `page` at a time, keeping the best `limit` of them, so the
+    // typos are found without the trigram indexes, at the cost of reading them all.
+    async fn search_scan(
+        db: &Db,
+        utx: &UserCtx,
+        terms: &[String],
+        limit: i64,
+        page: i64,
+    ) -> Result<Vec<PassengerSearchHit>, model::Error> {
+        let mut best: Vec<Passenger> = Vec::new();
+        let mut after: Option<Uuid> = None;
+        loop {
+            let mut sql = Self::visible(SqlBuilder::new().select_from(Self::TABLE), utx);
+            if let Some(after) = after {
+                sql = sql.and_where("id > {}", after.to_string());
+            }
+            let sql = sql.order_by("id").limit(page).build();
+            let query = sqlx::query_as::<_, Passenger>(&sql);
+            let passengers = traced("passenger.search", &sql, query.fetch_all(db)).await?;
+            after = match passengers.len() as i64 == page {
+                true => passengers.last().map(|passenger| passenger.id),
+                false => None,
+            };
+            best.extend(passengers);
+            best = rank(best, terms, limit).into_iter().map(|hit| hit.passenger).collect();
+            if after.is_none() {
+                return Ok(rank(best, terms, limit));
+            }
+        }
+    }
+
+    /// Pairs of passengers that may be the same person, the most likely first. Candidates share
+    /// a last name close to the other's first or last name (trigram indexes), or equal to it when
+    /// the database has no trigram support; they are scored in `rank_duplicates`. The passengers
+    /// created without a last name are not candidates.
+    pub async fn duplicates(
+        db: &Db,
+        utx: &UserCtx,
+        filter: &DuplicateFilter,
+    ) -> Result<Vec<PassengerDuplicate>, model::Error> {
+        let threshold = filter.threshold()?;
+        let limit = filter.limit()?;
+        let visible = Self::visible(SqlBuilder::new().select_from(Self::TABLE), utx).build();
+        let matches = match has_trigrams(db).await {
+            true => "%",
+            false => "=",
+        };
+        // b read through the trigram indexes, and kept when visible as well
+        let sql = format!(
+            "WITH visible AS ({visible}) \
+             SELECT a.id, b.id FROM visible a JOIN passenger b ON b.id > a.id \
+             AND (b.last_name_search {matches} a.last_name_search OR b.first_name_search {matches} a.last_name_search) \
+             AND b.id IN (SELECT id FROM visible) \
+             WHERE a.last_name_search <> {} \
+             LIMIT {}",
+            PLACEHOLDER_NAME.to_string().format_sql_value(),
+            Self::MAX_DUPLICATE_CANDIDATES
+        );
+        let query = sqlx::query_as::<_, (Uuid, Uuid)>(&sql);
+        let pairs = traced("passenger.duplicates", &sql, query.fetch_all(db)).await?;
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<String> = pairs.iter().flat_map(|(a, b)| [a.to_string(), b.to_string()]).collect();
+        let sql = Self::visible(SqlBuilder::new().select_from(Self::TABLE).and(&in_ids("id", &ids)), utx).build();
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let passengers: HashMap<Uuid, Passenger> = traced("passenger.duplicates", &sql, query.fetch_all(db))
+            .await?
+            .into_iter()
+            .map(|passenger| (passenger.id, passenger))
+            .collect();
+        Ok(rank_duplicates(&passengers, &pairs, threshold, limit))
+    }
+
+    /// Merges the passengers into one of them, in one transaction: the survivor gets the fields
+    /// chosen by `plan_merge`, the references to the others move to it, the others are deleted
+    /// (soft, `merged_into` the survivor) and the merge is recorded in `passenger_merge`.
+    pub async fn merge(
+        db: &Db,
+        utx: &UserCtx,
+        request: &PassengerMergeRequest,
+    ) -> Result<PassengerMerge, model::Error> {
+        let ids = request.ids()?;
+        let mut tx = db.begin().await?;
+        // locked until the commit, a concurrent change of them waits for the merge
+        let sql = Self::visible(SqlBuilder::new().select_from(Self::TABLE).and(&in_ids("id", &ids)), utx).build();
+        let sql = format!("{} FOR UPDATE", sql);
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let passengers = traced("passenger.merge", &sql, query.fetch_all(&mut tx)).await?;
+        if let Some(missing) = ids
+            .iter()
+            .find(|id| !passengers.iter().any(|passenger| passenger.id.to_string() == **id))
+        {
+            return Err(model::Error::EntityNotFound(Self::TABLE, missing.clone()));
+        }
+        let before =
+            serde_json::to_string(&passengers).map_err(|ex| model::Error::Invalid("passengers", ex.to_string()))?;
+        let plan = plan_merge(passengers, request.survivor_id.as_deref())?;
+        let now = Utc::now().to_rfc3339();
+        let survivor_id = plan.survivor.id.to_string();
+        let merged_ids: Vec<String> = plan.merged.iter().map(|passenger| passenger.id.to_string()).collect();
+
         let sql = SqlBuilder::new()
             .update(Self::TABLE)
             .set_columns_and_values(
-                Self::COLUMNS,
+                Self::UPDATE_COLUMNS,
                 &[
-                    &utx.user_id.to_string(),
-                    &data.get_first_name(),
-                    &data.get_last_name(),
-                    &data.get_status(),
+                    &Some(plan.first_name.clone()),
+                    &Some(plan.last_name.clone()),
+                    &plan.status.clone(),
+                    &Some(now.clone()),
+                    &Some(utx.user_id.to_string()),
+                    &Some(search_key(&plan.first_name)),
+                    &Some(search_key(&plan.last_name)),
+                    &Some(name_key(&plan.first_name, &plan.last_name)),
+                    &plan.date_of_birth.clone(),
+                    &plan.gender.clone(),
+                    &plan.nationality.clone(),
+                    &plan.email.clone(),
+                    &plan.phone.clone(),
                 ],
             )
-            .where_clause("id = {}", id.clone())
+            .where_clause("id = {}", survivor_id.clone())
             .build();
         let query = sqlx::query_as::<_, Passenger>(&sql);
-        let result = query.fetch_one(db).await;
-        handle_fetch_one_result(result, Self::TABLE, id)
-    }
+        let survivor = traced("passenger.merge", &sql, query.fetch_one(&mut tx)).await?;
+
+        for (table, column) in Self::REFERENCES {
+            let sql = SqlBuilder::new()
+                .update(table)
+                .set_columns_and_values(&[*column], &[&survivor_id])
+                .and(&in_ids(column, &merged_ids))
+                .build();
+            traced("passenger.merge", &sql, sqlx::query(&sql).execute(&mut tx)).await?;
+        }
 
-    pub async fn delete(db: &Db, _utx: &UserCtx, id: String) -> Result<Passenger, model::Error> {
         let sql = SqlBuilder::new()
-            .delete_from(Self::TABLE)
-            .where_clause("id = {}", id.clone())
+            .update(Self::TABLE)
+            .set_columns_and_values(
+                &["deleted_at", "merged_into", "updated_at", "updated_by"],
+                &[&now, &survivor_id, &now, &utx.user_id.to_string()],
+            )
+            .and(&in_ids("id", &merged_ids))
             .build();
         let query = sqlx::query_as::<_, Passenger>(&sql);
-        let result = query.fetch_one(db).await;
-        handle_fetch_one_result(result, Self::TABLE, id)
+        let merged = traced("passenger.merge", &sql, query.fetch_all(&mut tx)).await?;
+
+        let sources =
+            serde_json::to_string(&plan.sources).map_err(|ex| model::Error::Invalid("sources", ex.to_string()))?;
+        let sql = SqlBuilder::new()
+            .insert_into("passenger_merge")
+            .columns(&[
+                "survivor_id",
+                "merged_ids",
+                "sources",
+                "passengers",
+                "merged_at",
+                "merged_by",
+            ])
+            .values(&[
+                &survivor_id,
+                &merged_ids.join(","),
+                &sources,
+                &before,
+                &now,
+                &utx.user_id.to_string(),
+            ])
+            .build();
+        let record = traced("passenger.merge", &sql, sqlx::query(&sql).fetch_one(&mut tx)).await?;
+
+        OutboxDao::insert(&mut tx, PassengerEventKind::Updated, &survivor).await?;
+        for passenger in &merged {
+            OutboxDao::insert(&mut tx, PassengerEventKind::Deleted, passenger).await?;
+        }
+        tx.commit().await?;
+        invalidate_cache(&survivor).await;
+        passenger_events().publish(PassengerEventKind::Updated, survivor.clone());
+        for passenger in merged {
+            invalidate_cache(&passenger).await;
+            passenger_events().publish(PassengerEventKind::Deleted, passenger);
+        }
+        Ok(PassengerMerge {
+            id: record.try_get("id")?,
+            survivor,
+            merged_ids,
+            sources: plan.sources,
+            merged_at: record.try_get("merged_at")?,
+            merged_by: utx.user_id.to_string(),
+        })
     }
 
-    pub async fn list(db: &Db, _utx: &UserCtx) -> Result<Vec<Passenger>, model::Error> {
-        let sql = SqlBuilder::new().select_from(Self::TABLE).order_by("id").build();
-        let query = sqlx::query_as(&sql);
-        let passengers = query.fetch_all(db).await?;
-        Ok(passengers)
+    /// The passenger of the MRZ of a travel document (see `parse_mrz`), with the document: the
+    /// passenger holding the document already, else the oldest one with the same `name_key` and
+    /// date of birth, given the document, else a new passenger, created with the document in one
+    /// transaction.
+    pub async fn from_mrz(
+        db: &Db,
+        utx: &UserCtx,
+        request: &PassengerMrzRequest,
+    ) -> Result<PassengerFromMrz, model::Error> {
+        let mrz = parse_mrz(&request.mrz)?;
+        let document = PassengerDocumentForCreate {
+            doc_type: mrz.doc_type.to_string(),
+            number: mrz.number.clone(),
+            issuing_country: mrz.issuing_country.clone(),
+            expiry_date: mrz.expiry_date,
+        };
+
+        for held in PassengerDocumentDao::find_held(db, &document).await? {
+            match Self::get(db, utx, held.passenger_id.to_string()).await {
+                Ok(passenger) => {
+                    return Ok(PassengerFromMrz {
+                        passenger,
+                        document: held,
+                        created: false,
+                    })
+                }
+                Err(model::Error::EntityNotFound(..)) => continue,
+                Err(other) => return Err(other),
+            }
+        }
+
+        let first_name = mrz.first_name.clone().unwrap_or_default();
+        if let Some(date_of_birth) = mrz.date_of_birth {
+            let sql = SqlBuilder::new()
+                .select_from(Self::TABLE)
+                .where_clause("name_key = {}", name_key(&first_name, &mrz.last_name))
+                .and_where("date_of_birth = {}", date_of_birth.to_string());
+            let sql = Self::visible(sql, utx).order_by("created_at, id").limit(1).build();
+            let query = sqlx::query_as::<_, Passenger>(&sql);
+            if let Some(passenger) = traced("passenger.from_mrz", &sql, query.fetch_optional(db)).await? {
+                let document = PassengerDocumentDao::create(db, utx, passenger.id.to_string(), document).await?;
+                return Ok(PassengerFromMrz {
+                    passenger,
+                    document,
+                    created: false,
+                });
+            }
+        }
+
+        let data = PassengerPatch {
+            first_name: mrz.first_name,
+            last_name: Some(mrz.last_name),
+            date_of_birth: mrz.date_of_birth,
+            gender: Some(mrz.sex),
+            nationality: Some(mrz.nationality),
+            ..Default::default()
+        };
+        // the passenger, its document and its event, or none of them
+        let sql = Self::insert_sql(utx, &data.normalized()?);
+        let query = sqlx::query_as::<_, Passenger>(&sql);
+        let mut tx = db.begin().await?;
+        let passenger = traced("passenger.from_mrz", &sql, query.fetch_one(&mut tx)).await?;
+        let document = PassengerDocumentDao::insert(&mut tx, utx, passenger.id.to_string(), document).await?;
+        OutboxDao::insert(&mut tx, PassengerEventKind::Created, &passenger).await?;
+        tx.commit().await?;
+        invalidate_cache(&passenger).await;
+        passenger_events().publish(PassengerEventKind::Created, passenger.clone());
+        Ok(PassengerFromMrz {
+            passenger,
+            document,
+            created: true,
+        })
+    }
+
+    fn insert_sql(utx: &UserCtx, data: &PassengerPatch) -> String {
+        let now = Utc::now().to_rfc3339();
+        let mut columns = Self::COLUMNS.to_vec();
+        let mut values = vec![
+            Some(utx.user_id.to_string()),
+            Some(data.get_first_name()),
+            Some(data.get_last_name()),
+            Some(data.get_status()),
+            Some(now.clone()),
+            Some(utx.user_id.to_string()),
+            Some(now),
+            Some(utx.user_id.to_string()),
+            Some(search_key(&data.get_first_name())),
+            Some(search_key(&data.get_last_name())),
+            Some(name_key(&data.get_first_name(), &data.get_last_name())),
+            data.date_of_birth.map(|date| date.to_string()),
+            data.gender.clone(),
+            data.nationality.clone(),
+            data.email.clone(),
+            data.phone.clone(),
+        ];
+        // the passenger belongs to the tenant of its creator
+        if let Some(tenant_id) = &utx.tenant_id {
+            columns.push("tenant_id");
+            values.push(Some(tenant_id.clone()));
+        }
+        SqlBuilder::new()
+            .insert_into(Self::TABLE) // Start the INSERT statement and specify the table name
+            .columns(&columns) // Specify the columns to insert into
+            .values(&values.iter().collect::<Vec<_>>())
+            .build()
+    }
+
+    // Restricts the statement to the rows the user is allowed to access.
+    fn scoped(sql: SqlBuilder, utx: &UserCtx) -> SqlBuilder {
+        config().passenger_scope.apply(sql, utx)
+    }
+
+    // Restricts the statement to the rows the user is allowed to access, and not deleted.
+    fn visible(sql: SqlBuilder, utx: &UserCtx) -> SqlBuilder {
+        Self::scoped(sql.and("deleted_at IS NULL"), utx)
     }
 }
 
@@ -196,9 +811,37 @@ fn handle_fetch_one_result(
 ) -> Result<Passenger, model::Error> {
     result.map_err(|sqlx_error| match sqlx_error {
         sqlx::Error::RowNotFound => model::Error::EntityNotFound(typ, id.to_string()),
-        other => model::Error::SqlxError(other),
+        other => model::Error::Sqlx(other),
     })
 }
+
+// `column IN (ids)`, the ids quoted.
+fn in_ids(column: &str, ids: &[String]) -> String {
+    let ids: Vec<String> = ids.iter().map(FormatSqlValue::format_sql_value).collect();
+    format!("{} IN ({})", column, ids.join(", "))
+}
+
+// Whether `similarity()` and `%` exist (pg_trgm, CockroachDB), checked once.
+async fn has_trigrams(db: &Db) -> bool {
+    static HAS_TRIGRAMS: OnceCell<bool> = OnceCell::const_new();
+
+    *HAS_TRIGRAMS
+        .get_or_init(|| async {
+            let result = sqlx::query("SELECT similarity('a', 'a')").execute(db).await;
+            if let Err(ex) = &result {
+                tracing::warn!(cause = %ex, "no trigram support, the passenger search scans the passengers");
+            }
+            result.is_ok()
+        })
+        .await
+}
+
+// Before the event is published, so a subscriber reading the passenger back gets the new one.
+async fn invalidate_cache(passenger: &Passenger) {
+    if let Some(cache) = passenger_cache() {
+        cache.invalidate(&passenger.id.to_string()).await;
+    }
+}
 // endregion: Utils
 
 #[cfg(test)]
@@ -0,0 +1,261 @@
+use super::passenger_event::passenger_events;
+use crate::config::config;
+use crate::metrics::metrics;
+use crate::model;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+const KEY_PASSENGER: &str = "passenger:";
+const KEY_PASSENGERS: &str = "passengers:";
+
+// region:    Backend
+/// Where the cached values (JSON) live. In memory by default, a shared store (e.g. Redis) would
+/// share them across replicas. A backend failing should log and behave as a miss.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn set(&self, key: &str, value: String, ttl: Duration);
+    async fn remove(&self, key: &str);
+    async fn remove_prefix(&self, prefix: &str);
+}
+
+/// LRU of at most `capacity` entries, each one expiring after its ttl.
+pub struct MemoryBackend {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<String, Entry>,
+    /// Keys by last use, the least recently used first.
+    by_use: BTreeMap<u64, String>,
+    uses: u64,
+}
+
+struct Entry {
+    value: String,
+    expires_at: Instant,
+    used: u64,
+}
+
+impl MemoryBackend {
+    pub fn new(capacity: usize) -> Self {
+        MemoryBackend {
+            capacity: capacity.max(1),
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl Entries {
+    fn next_use(&mut self) -> u64 {
+        self.uses += 1;
+        self.uses
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.by_key.remove(key) {
+            self.by_use.remove(&entry.used);
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let used = entries.next_use();
+        let Entries { by_key, by_use, .. } = &mut *entries;
+        let entry = by_key.get_mut(key)?;
+        if entry.expires_at <= Instant::now() {
+            entries.remove(key);
+            return None;
+        }
+        by_use.remove(&entry.used);
+        by_use.insert(used, key.to_string());
+        entry.used = used;
+        Some(entry.value.clone())
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(key);
+        let used = entries.next_use();
+        let expires_at = Instant::now() + ttl;
+        entries.by_key.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at,
+                used,
+            },
+        );
+        entries.by_use.insert(used, key.to_string());
+        while entries.by_key.len() > self.capacity {
+            match entries.by_use.pop_first() {
+                Some((_, key)) => entries.by_key.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    async fn remove_prefix(&self, prefix: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.by_key.retain(|key, _| !key.starts_with(prefix));
+        entries.by_use.retain(|_, key| !key.starts_with(prefix));
+    }
+}
+// endregion: Backend
+
+// region:    Cache
+/// Read-through cache of `PassengerDao::get` and `list`, invalidated on each passenger change.
+///
+/// A passenger is cached once for all the users, its scope is checked on each read; a list
+/// is cached per scope partition (see `Scope::partition`). The concurrent misses of a key
+/// wait for the first one to load it.
+pub struct PassengerCache {
+    backend: Box<dyn CacheBackend>,
+    ttl: Duration,
+    /// Bumped on each invalidation, a value loaded before is not stored.
+    generation: AtomicU64,
+    loading: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// None when the cache is off.
+pub fn passenger_cache() -> Option<&'static PassengerCache> {
+    static INSTANCE: OnceLock<Option<PassengerCache>> = OnceLock::new();
+
+    INSTANCE
+        .get_or_init(|| {
+            let config = config();
+            config.passenger_cache_enabled.then(|| {
+                PassengerCache::new(
+                    Box::new(MemoryBackend::new(config.passenger_cache_capacity)),
+                    config.passenger_cache_ttl,
+                )
+            })
+        })
+        .as_ref()
+}
+
+impl PassengerCache {
+    pub fn new(backend: Box<dyn CacheBackend>, ttl: Duration) -> Self {
+        PassengerCache {
+            backend,
+            ttl,
+            generation: AtomicU64::new(0),
+            loading: Mutex::default(),
+        }
+    }
+
+    pub fn passenger_key(id: &str) -> String {
+        format!("{}{}", KEY_PASSENGER, id)
+    }
+
+    /// `query` identifies the filter and sort of the list.
+    pub fn passengers_key(partition: &str, query: &str) -> String {
+        format!("{}{}:{}", KEY_PASSENGERS, partition, query)
+    }
+
+    /// The cached value of the key, else the one of `load`, stored. The errors are not.
+    /// `operation` labels the metrics.
+    pub async fn get_or_load<T, F, Fut>(&self, operation: &'static str, key: String, load: F) -> Result<T, model::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, model::Error>>,
+    {
+        if let Some(value) = self.lookup(&key).await {
+            record(operation, "hit");
+            return Ok(value);
+        }
+        let lock = self.loading.lock().unwrap().entry(key.clone()).or_default().clone();
+        let _loading = lock.lock().await;
+        // loaded by the request we waited for
+        if let Some(value) = self.lookup(&key).await {
+            record(operation, "hit");
+            return Ok(value);
+        }
+        record(operation, "miss");
+        let generation = self.generation.load(Ordering::SeqCst);
+        let result = load().await;
+        if let Ok(value) = &result {
+            if generation == self.generation.load(Ordering::SeqCst) {
+                match serde_json::to_string(value) {
+                    Ok(json) => self.backend.set(&key, json, self.ttl).await,
+                    Err(ex) => tracing::warn!(key, cause = %ex, "passenger cache - value not stored"),
+                }
+            }
+        }
+        // the waiting requests hold the lock, the next ones read the cache
+        self.loading.lock().unwrap().remove(&key);
+        result
+    }
+
+    /// Drops the passenger, and all the lists (it may enter or leave any of them).
+    pub async fn invalidate(&self, id: &str) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.backend.remove(&Self::passenger_key(id)).await;
+        self.backend.remove_prefix(KEY_PASSENGERS).await;
+    }
+
+    pub async fn invalidate_all(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.backend.remove_prefix(KEY_PASSENGER).await;
+        self.backend.remove_prefix(KEY_PASSENGERS).await;
+    }
+
+    async fn lookup<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let json = self.backend.get(key).await?;
+        match serde_json::from_str(&json) {
+            Ok(value) => Some(value),
+            Err(ex) => {
+                tracing::warn!(key, cause = %ex, "passenger cache - value ignored");
+                None
+            }
+        }
+    }
+}
+
+fn record(operation: &'static str, outcome: &'static str) {
+    metrics()
+        .passenger_cache_requests
+        .with_label_values(&[operation, outcome])
+        .inc();
+}
+
+/// Invalidates the cache on the passenger events too, for the changes of the other instances
+/// (see `CHANGE_FEED`); the DAO already invalidates on the changes of this one.
+pub fn start_invalidation() {
+    let Some(cache) = passenger_cache() else {
+        return;
+    };
+    let (_, mut events) = passenger_events().subscribe(None);
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => cache.invalidate(&event.passenger.id.to_string()).await,
+                // some changes are unknown
+                Err(RecvError::Lagged(_)) => cache.invalidate_all().await,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+// endregion: Cache
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_cache.rs"]
+mod tests;
@@ -0,0 +1,359 @@
+use super::db::{traced, Db};
+use super::passenger_profile::country;
+use super::PassengerDao;
+use crate::config::config;
+use crate::model;
+use crate::security::UserCtx;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlbuilder::SqlBuilder;
+use sqlx::types::Uuid;
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
+
+const DOC_TYPES: &[&str] = &["passport", "id_card", "visa", "residence_permit"];
+const MAX_NUMBER_CHARS: usize = 20;
+
+// region: PassengerDocument Types
+/// A travel document of a passenger.
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PassengerDocument {
+    #[schema(example = "5d0c6a1e-7f3b-4c2d-9e8f-0a1b2c3d4e5f")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    #[schema(example = "4208b168-08b2-4c45-915d-c51f6f71213b")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub passenger_id: Uuid,
+    /// `passport`, `id_card`, `visa` or `residence_permit`
+    #[schema(example = "passport")]
+    pub doc_type: String,
+    #[schema(example = "L898902C3")]
+    pub number: String,
+    /// ISO 3166-1 alpha-3 code, or one of the ICAO 9303 codes (e.g. `UNO`)
+    #[schema(example = "FRA")]
+    pub issuing_country: String,
+    #[schema(example = "2031-04-15")]
+    pub expiry_date: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub created_by: Uuid,
+    pub updated_at: DateTime<Utc>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub updated_by: Uuid,
+    /// `expired`, or `expires_soon` (within `DOCUMENT_EXPIRY_WARNING_DAYS`), as of the request
+    #[sqlx(default)]
+    #[serde(default)]
+    #[schema(example = json!(["expires_soon"]))]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PassengerDocumentForCreate {
+    #[schema(example = "passport")]
+    pub doc_type: String,
+    /// Spaces removed, uppercase
+    #[schema(example = "L898902C3")]
+    pub number: String,
+    #[schema(example = "FRA")]
+    pub issuing_country: String,
+    #[schema(example = "2031-04-15")]
+    pub expiry_date: Option<NaiveDate>,
+}
+
+impl PassengerDocumentForCreate {
+    /// The document with its type, number and issuing country checked and as stored.
+    fn validated(self) -> Result<Self, model::Error> {
+        Ok(PassengerDocumentForCreate {
+            doc_type: validate_doc_type(&self.doc_type)?,
+            number: validate_number(&self.number)?,
+            issuing_country: country("issuing_country", &self.issuing_country)?,
+            expiry_date: self.expiry_date,
+        })
+    }
+}
+
+#[derive(Default, Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PassengerDocumentPatch {
+    pub doc_type: Option<String>,
+    pub number: Option<String>,
+    pub issuing_country: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+impl PassengerDocument {
+    fn with_warnings(mut self) -> Self {
+        let today = Utc::now().date_naive();
+        let soon = today + Duration::days(config().document_expiry_warning_days);
+        self.warnings = match self.expiry_date {
+            Some(expiry_date) if expiry_date < today => vec!["expired".to_string()],
+            Some(expiry_date) if expiry_date <= soon => vec!["expires_soon".to_string()],
+            _ => Vec::new(),
+        };
+        self
+    }
+}
+// endregion: PassengerDocument Types
+
+// region: PassengerDocumentDao
+pub struct PassengerDocumentDao;
+
+impl PassengerDocumentDao {
+    const TABLE: &'static str = "passenger_document";
+}
+
+/// The documents of a passenger the user can see, the passenger not found otherwise.
+impl PassengerDocumentDao {
+    pub async fn list(db: &Db, utx: &UserCtx, passenger_id: String) -> Result<Vec<PassengerDocument>, model::Error> {
+        PassengerDao::get(db, utx, passenger_id.clone()).await?;
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("passenger_id = {}", passenger_id)
+            .order_by("created_at, id")
+            .build();
+        let documents: Vec<PassengerDocument> =
+            traced("passenger_document.list", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(documents.into_iter().map(PassengerDocument::with_warnings).collect())
+    }
+
+    pub async fn get(
+        db: &Db,
+        utx: &UserCtx,
+        passenger_id: String,
+        id: String,
+    ) -> Result<PassengerDocument, model::Error> {
+        PassengerDao::get(db, utx, passenger_id.clone()).await?;
+        Self::fetch_one(db, passenger_id, id).await
+    }
+
+    pub async fn create(
+        db: &Db,
+        utx: &UserCtx,
+        passenger_id: String,
+        data: PassengerDocumentForCreate,
+    ) -> Result<PassengerDocument, model::Error> {
+        PassengerDao::get(db, utx, passenger_id.clone()).await?;
+        let data = data.validated()?;
+        Self::check_unique(
+            db,
+            &passenger_id,
+            None,
+            &data.doc_type,
+            &data.number,
+            &data.issuing_country,
+        )
+        .await?;
+        let sql = Self::insert_sql(utx, passenger_id, &data);
+        let query = sqlx::query_as::<_, PassengerDocument>(&sql);
+        let document = traced("passenger_document.create", &sql, query.fetch_one(db)).await?;
+        Ok(document.with_warnings())
+    }
+
+    /// Adds the document to a passenger created in the same transaction, which has no other
+    /// document to check it against.
+    pub(super) async fn insert(
+        tx: &mut Transaction<'_, Postgres>,
+        utx: &UserCtx,
+        passenger_id: String,
+        data: PassengerDocumentForCreate,
+    ) -> Result<PassengerDocument, model::Error> {
+        let sql = Self::insert_sql(utx, passenger_id, &data.validated()?);
+        let query = sqlx::query_as::<_, PassengerDocument>(&sql);
+        let document = traced("passenger_document.insert", &sql, query.fetch_one(&mut *tx)).await?;
+        Ok(document.with_warnings())
+    }
+
+    pub async fn update(
+        db: &Db,
+        utx: &UserCtx,
+        passenger_id: String,
+        id: String,
+        data: PassengerDocumentPatch,
+    ) -> Result<PassengerDocument, model::Error> {
+        let document = Self::get(db, utx, passenger_id.clone(), id.clone()).await?;
+        let doc_type = data.doc_type.as_deref().map(validate_doc_type).transpose()?;
+        let number = data.number.as_deref().map(validate_number).transpose()?;
+        let issuing_country = data
+            .issuing_country
+            .as_deref()
+            .map(|code| country("issuing_country", code))
+            .transpose()?;
+        Self::check_unique(
+            db,
+            &passenger_id,
+            Some(&id),
+            doc_type.as_ref().unwrap_or(&document.doc_type),
+            number.as_ref().unwrap_or(&document.number),
+            issuing_country.as_ref().unwrap_or(&document.issuing_country),
+        )
+        .await?;
+        let mut columns = vec!["updated_at", "updated_by"];
+        let mut values = vec![Utc::now().to_rfc3339(), utx.user_id.clone()];
+        for (column, value) in [
+            ("doc_type", doc_type),
+            ("number", number),
+            ("issuing_country", issuing_country),
+            ("expiry_date", data.expiry_date.map(|date| date.to_string())),
+        ] {
+            if let Some(value) = value {
+                columns.push(column);
+                values.push(value);
+            }
+        }
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&columns, &values.iter().collect::<Vec<_>>())
+            .where_clause("id = {}", id.clone())
+            .and_where("passenger_id = {}", passenger_id)
+            .build();
+        let result = traced("passenger_document.update", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        Ok(handle_fetch_one_result(result, Self::TABLE, id)?.with_warnings())
+    }
+
+    pub async fn delete(
+        db: &Db,
+        utx: &UserCtx,
+        passenger_id: String,
+        id: String,
+    ) -> Result<PassengerDocument, model::Error> {
+        PassengerDao::get(db, utx, passenger_id.clone()).await?;
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("id = {}", id.clone())
+            .and_where("passenger_id = {}", passenger_id)
+            .build();
+        let result = traced("passenger_document.delete", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        Ok(handle_fetch_one_result(result, Self::TABLE, id)?.with_warnings())
+    }
+
+    /// The documents with these type, number and issuing country, of any passenger, the oldest
+    /// first; the caller checks their passenger is visible.
+    pub(super) async fn find_held(
+        db: &Db,
+        data: &PassengerDocumentForCreate,
+    ) -> Result<Vec<PassengerDocument>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("doc_type = {}", data.doc_type.clone())
+            .and_where("number = {}", data.number.clone())
+            .and_where("issuing_country = {}", data.issuing_country.clone())
+            .order_by("created_at, id")
+            .build();
+        let documents: Vec<PassengerDocument> =
+            traced("passenger_document.find_held", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(documents.into_iter().map(PassengerDocument::with_warnings).collect())
+    }
+
+    async fn fetch_one(db: &Db, passenger_id: String, id: String) -> Result<PassengerDocument, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("id = {}", id.clone())
+            .and_where("passenger_id = {}", passenger_id)
+            .build();
+        let result = traced("passenger_document.get", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        Ok(handle_fetch_one_result(result, Self::TABLE, id)?.with_warnings())
+    }
+
+    // A passenger holds a document once, `except` being the one updated.
+    fn insert_sql(utx: &UserCtx, passenger_id: String, data: &PassengerDocumentForCreate) -> String {
+        SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&[
+                "passenger_id",
+                "doc_type",
+                "number",
+                "issuing_country",
+                "expiry_date",
+                "created_by",
+                "updated_by",
+            ])
+            .values(&[
+                &Some(passenger_id),
+                &Some(data.doc_type.clone()),
+                &Some(data.number.clone()),
+                &Some(data.issuing_country.clone()),
+                &data.expiry_date.map(|date| date.to_string()),
+                &Some(utx.user_id.clone()),
+                &Some(utx.user_id.clone()),
+            ])
+            .build()
+    }
+
+    async fn check_unique(
+        db: &Db,
+        passenger_id: &str,
+        except: Option<&str>,
+        doc_type: &str,
+        number: &str,
+        issuing_country: &str,
+    ) -> Result<(), model::Error> {
+        let mut sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("passenger_id = {}", passenger_id.to_string())
+            .and_where("doc_type = {}", doc_type.to_string())
+            .and_where("number = {}", number.to_string())
+            .and_where("issuing_country = {}", issuing_country.to_string());
+        if let Some(id) = except {
+            sql = sql.and_where("id <> {}", id.to_string());
+        }
+        let sql = sql.build();
+        let query = sqlx::query_as::<_, PassengerDocument>(&sql);
+        match traced("passenger_document.check_unique", &sql, query.fetch_optional(db)).await? {
+            Some(_) => Err(model::Error::Invalid(
+                "number",
+                "already a document of the passenger".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+// endregion: PassengerDocumentDao
+
+// region: Utils
+fn validate_doc_type(doc_type: &str) -> Result<String, model::Error> {
+    match DOC_TYPES.contains(&doc_type) {
+        true => Ok(doc_type.to_string()),
+        false => Err(model::Error::Invalid(
+            "doc_type",
+            format!("not one of {}", DOC_TYPES.join(", ")),
+        )),
+    }
+}
+
+/// Uppercase letters and digits, the spaces removed.
+pub(super) fn validate_number(number: &str) -> Result<String, model::Error> {
+    let number: String = number
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    if number.is_empty() || number.chars().count() > MAX_NUMBER_CHARS {
+        return Err(model::Error::Invalid(
+            "number",
+            format!("not 1 to {} characters", MAX_NUMBER_CHARS),
+        ));
+    }
+    match number.chars().all(|c| c.is_ascii_alphanumeric()) {
+        true => Ok(number),
+        false => Err(model::Error::Invalid("number", "only letters and digits".to_string())),
+    }
+}
+
+fn handle_fetch_one_result(
+    result: Result<PassengerDocument, sqlx::Error>,
+    typ: &'static str,
+    id: String,
+) -> Result<PassengerDocument, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound(typ, id.to_string()),
+        other => model::Error::Sqlx(other),
+    })
+}
+// endregion: Utils
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_document.rs"]
+mod tests;
@@ -0,0 +1,143 @@
+use super::Passenger;
+use crate::config::config;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::broadcast;
+
+/// What happened to the passenger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassengerEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl PassengerEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PassengerEventKind::Created => "created",
+            PassengerEventKind::Updated => "updated",
+            PassengerEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// A change of a passenger, with the passenger as it is after it (before it, when deleted).
+#[derive(Debug, Clone, Serialize)]
+pub struct PassengerEvent {
+    /// Increasing, also across restarts.
+    pub id: u64,
+    pub kind: PassengerEventKind,
+    pub passenger: Passenger,
+}
+
+/// The events missed by a subscriber since its last event.
+#[derive(Debug)]
+pub enum Replay {
+    Events(Vec<Arc<PassengerEvent>>),
+    /// Some are no longer buffered (or from before a restart), the subscriber must reload
+    /// the passengers. `last_id` is the latest event published.
+    Lost {
+        last_id: u64,
+    },
+}
+
+/// In-process feed of the passenger changes, published by `PassengerDao`. The latest events
+/// are kept in a bounded buffer, for the subscribers coming back after a disconnection.
+pub struct PassengerEvents {
+    sender: broadcast::Sender<Arc<PassengerEvent>>,
+    state: Mutex<State>,
+}
+
+struct State {
+    next_id: u64,
+    buffer: VecDeque<Arc<PassengerEvent>>,
+    capacity: usize,
+}
+
+pub fn passenger_events() -> &'static PassengerEvents {
+    static INSTANCE: OnceLock<PassengerEvents> = OnceLock::new();
+
+    // the ids start at the start time, so the ones of a previous run are older
+    INSTANCE.get_or_init(|| {
+        let first_id = Utc::now().timestamp_micros().max(1) as u64;
+        PassengerEvents::new(config().events_buffer_size, first_id)
+    })
+}
+
+impl PassengerEvents {
+    /// Keeps the last `capacity` events, the ids start at `first_id`.
+    pub fn new(capacity: usize, first_id: u64) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        PassengerEvents {
+            sender,
+            state: Mutex::new(State {
+                next_id: first_id,
+                buffer: VecDeque::with_capacity(capacity),
+                capacity,
+            }),
+        }
+    }
+
+    /// Publishes the change, unless already published (it may come from this instance and from
+    /// the change feed, in any order). Returns the id of the event.
+    pub fn publish(&self, kind: PassengerEventKind, passenger: Passenger) -> u64 {
+        // under the lock, so the ids are sent in order and `subscribe` sees no gap
+        let mut state = self.state.lock().unwrap();
+        let published = state.buffer.iter().rev().find(|event| {
+            event.kind == kind
+                && event.passenger.id == passenger.id
+                && event.passenger.updated_at == passenger.updated_at
+        });
+        if let Some(published) = published {
+            return published.id;
+        }
+        let event = Arc::new(PassengerEvent {
+            id: state.next_id,
+            kind,
+            passenger,
+        });
+        state.next_id += 1;
+        if state.buffer.len() == state.capacity {
+            state.buffer.pop_front();
+        }
+        state.buffer.push_back(event.clone());
+        // no subscriber is not an error
+        let _ = self.sender.send(event.clone());
+        event.id
+    }
+
+    /// The events after `last_id` (none without), and the receiver of the next ones.
+    pub fn subscribe(&self, last_id: Option<u64>) -> (Replay, broadcast::Receiver<Arc<PassengerEvent>>) {
+        let state = self.state.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let replay = match last_id {
+            None => Replay::Events(Vec::new()),
+            Some(last_id) => {
+                let first_id = state.buffer.front().map(|event| event.id).unwrap_or(state.next_id);
+                match last_id < state.next_id && last_id + 1 >= first_id {
+                    true => Replay::Events(
+                        state
+                            .buffer
+                            .iter()
+                            .filter(|event| event.id > last_id)
+                            .cloned()
+                            .collect(),
+                    ),
+                    false => Replay::Lost {
+                        last_id: state.next_id - 1,
+                    },
+                }
+            }
+        };
+        (replay, receiver)
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_event.rs"]
+mod tests;
@@ -0,0 +1,275 @@
+use super::passenger_name::name_key;
+use super::passenger_search::{search_key, similarity};
+use super::Passenger;
+use crate::model;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlx::types::Uuid;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::{IntoParams, ToSchema};
+
+/// The names of a passenger created without them (see `PassengerPatch`), not a value to keep.
+pub(super) const PLACEHOLDER_NAME: &str = "untitled";
+const DEFAULT_THRESHOLD: f64 = 0.6;
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+const MAX_MERGED: usize = 20;
+
+// region:    Duplicate Types
+/// Query parameters of the duplicate candidates.
+#[derive(Default, Clone, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicateFilter {
+    /// Lowest score of a pair, from 0 to 1, 0.6 by default
+    #[param(example = 0.6)]
+    pub threshold: Option<f64>,
+    /// Pairs returned, 50 by default, 200 at most
+    #[param(example = 50)]
+    pub limit: Option<i64>,
+}
+
+/// Two passengers that may be the same person.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PassengerDuplicate {
+    /// The pair, the oldest first
+    pub passengers: Vec<Passenger>,
+    /// Likelihood of a duplicate, from 0 to 1 (same names once normalized or transliterated)
+    #[schema(example = 0.643)]
+    pub score: f64,
+    /// Why the pair matched: `same_name`, `similar_name`, `swapped_names`
+    #[schema(example = json!(["similar_name"]))]
+    pub reasons: Vec<String>,
+}
+
+impl DuplicateFilter {
+    pub(super) fn threshold(&self) -> Result<f64, model::Error> {
+        match self.threshold.unwrap_or(DEFAULT_THRESHOLD) {
+            threshold if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+            threshold => Err(model::Error::Invalid(
+                "threshold",
+                format!("{} not in 0..=1", threshold),
+            )),
+        }
+    }
+
+    pub(super) fn limit(&self) -> Result<i64, model::Error> {
+        match self.limit.unwrap_or(DEFAULT_LIMIT) {
+            limit if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+            limit => Err(model::Error::Invalid(
+                "limit",
+                format!("{} not in 1..={}", limit, MAX_LIMIT),
+            )),
+        }
+    }
+}
+// endregion: Duplicate Types
+
+// region:    Merge Types
+/// The passengers to merge into one of them.
+#[derive(Default, Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PassengerMergeRequest {
+    /// All the passengers merged, the survivor included (2 to 20)
+    #[schema(example = json!(["4208b168-08b2-4c45-915d-c51f6f71213b", "9b2e6a0c-51f4-4f57-a1c2-7a3f0f1d8e44"]))]
+    pub passenger_ids: Vec<String>,
+    /// The passenger kept, one of `passenger_ids`; the oldest one by default
+    #[schema(example = "4208b168-08b2-4c45-915d-c51f6f71213b")]
+    pub survivor_id: Option<String>,
+}
+
+/// A merge done, as recorded in the `passenger_merge` audit table.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PassengerMerge {
+    #[schema(example = "0c1f3f8e-2a49-4d7e-8a8e-3d6c0b1a9f20")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    /// The survivor, after the merge
+    pub survivor: Passenger,
+    /// The other passengers, now deleted (`merged_into` the survivor)
+    #[schema(example = json!(["9b2e6a0c-51f4-4f57-a1c2-7a3f0f1d8e44"]))]
+    pub merged_ids: Vec<String>,
+    /// The passenger each field of the survivor comes from
+    #[schema(example = json!({"first_name": "9b2e6a0c-51f4-4f57-a1c2-7a3f0f1d8e44", "last_name": "4208b168-08b2-4c45-915d-c51f6f71213b", "status": "4208b168-08b2-4c45-915d-c51f6f71213b"}))]
+    pub sources: BTreeMap<String, String>,
+    #[schema(example = "2023-04-01T10:00:00Z")]
+    pub merged_at: DateTime<Utc>,
+    #[schema(example = "2096036b-9606-4405-995b-565a481344bc")]
+    pub merged_by: String,
+}
+
+impl PassengerMergeRequest {
+    /// The distinct ids to merge, checked.
+    pub(super) fn ids(&self) -> Result<Vec<String>, model::Error> {
+        let mut ids: Vec<String> = Vec::with_capacity(self.passenger_ids.len());
+        for id in &self.passenger_ids {
+            let id = Uuid::parse_str(id)
+                .map_err(|_| model::Error::Invalid("passenger_ids", format!("'{}' is not a UUID", id)))?
+                .to_string();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        if !(2..=MAX_MERGED).contains(&ids.len()) {
+            return Err(model::Error::Invalid(
+                "passenger_ids",
+                format!("{} distinct passengers, not in 2..={}", ids.len(), MAX_MERGED),
+            ));
+        }
+        if let Some(survivor_id) = &self.survivor_id {
+            if !Uuid::parse_str(survivor_id).is_ok_and(|id| ids.contains(&id.to_string())) {
+                return Err(model::Error::Invalid(
+                    "survivor_id",
+                    "not one of passenger_ids".to_string(),
+                ));
+            }
+        }
+        Ok(ids)
+    }
+}
+// endregion: Merge Types
+
+// region:    Scoring
+/// The pairs scoring at least `threshold`, the most likely first, at most `limit`.
+/// Works on any candidate pairs, from the trigram indexes or the exact names.
+pub(super) fn rank_duplicates(
+    passengers: &HashMap<Uuid, Passenger>,
+    pairs: &[(Uuid, Uuid)],
+    threshold: f64,
+    limit: i64,
+) -> Vec<PassengerDuplicate> {
+    let mut duplicates: Vec<PassengerDuplicate> = pairs
+        .iter()
+        .filter_map(|(a, b)| {
+            let (a, b) = (passengers.get(a)?, passengers.get(b)?);
+            let (score, reasons) = score_pair(a, b);
+            let mut pair = vec![a.clone(), b.clone()];
+            pair.sort_by_key(|passenger| (passenger.created_at, passenger.id));
+            (score >= threshold).then_some(PassengerDuplicate {
+                passengers: pair,
+                score,
+                reasons,
+            })
+        })
+        .collect();
+    duplicates.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.passengers[0].id.cmp(&b.passengers[0].id))
+            .then_with(|| a.passengers[1].id.cmp(&b.passengers[1].id))
+    });
+    duplicates.truncate(limit as usize);
+    duplicates
+}
+
+// The mean similarity of the first and the last names, compared as stored in the `*_search`
+// columns; the names swapped (first name entered as the last one) count as well. The names
+// transliterated the same (Müller, Mueller) are the same.
+fn score_pair(a: &Passenger, b: &Passenger) -> (f64, Vec<String>) {
+    if name_key(&a.first_name, &a.last_name) == name_key(&b.first_name, &b.last_name) {
+        return (1.0, vec!["same_name".to_string()]);
+    }
+    let (a_first, a_last) = (search_key(&a.first_name), search_key(&a.last_name));
+    let (b_first, b_last) = (search_key(&b.first_name), search_key(&b.last_name));
+    let same = (similarity(&a_first, &b_first) + similarity(&a_last, &b_last)) / 2.0;
+    let swapped = (similarity(&a_first, &b_last) + similarity(&a_last, &b_first)) / 2.0;
+    let (score, reason) = match (same, swapped) {
+        (same, swapped) if swapped > same => (swapped, "swapped_names"),
+        (same, _) if a_first == b_first && a_last == b_last => (same, "same_name"),
+        (same, _) => (same, "similar_name"),
+    };
+    ((score * 1000.0).round() / 1000.0, vec![reason.to_string()])
+}
+// endregion: Scoring
+
+// region:    Merge Rules
+/// The survivor and the values it gets from the merge.
+#[derive(Debug)]
+pub(super) struct MergePlan {
+    pub survivor: Passenger,
+    pub merged: Vec<Passenger>,
+    pub first_name: String,
+    pub last_name: String,
+    pub status: Option<String>,
+    pub date_of_birth: Option<String>,
+    pub gender: Option<String>,
+    pub nationality: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    /// The passenger each field comes from.
+    pub sources: BTreeMap<String, String>,
+}
+
+/// Applies the merge rules to the passengers (all of them, the survivor included):
+/// - they all belong to the same tenant,
+/// - the survivor is `survivor_id`, else the oldest passenger,
+/// - each field keeps the value of the survivor, unless it has none (no status or profile
+///   field, or the `untitled` placeholder name): then it takes the one of the most recently updated passenger
+///   having one.
+pub(super) fn plan_merge(passengers: Vec<Passenger>, survivor_id: Option<&str>) -> Result<MergePlan, model::Error> {
+    if passengers.windows(2).any(|pair| pair[0].tenant_id != pair[1].tenant_id) {
+        return Err(model::Error::Invalid(
+            "passenger_ids",
+            "passengers of different tenants".to_string(),
+        ));
+    }
+    let mut passengers = passengers;
+    passengers.sort_by_key(|passenger| (passenger.created_at, passenger.id));
+    let survivor_index = match survivor_id {
+        Some(id) => passengers
+            .iter()
+            .position(|passenger| passenger.id.to_string() == id.to_lowercase())
+            .ok_or_else(|| model::Error::EntityNotFound("passenger", id.to_string()))?,
+        None => 0,
+    };
+    let survivor = passengers.remove(survivor_index);
+    let mut merged = passengers;
+    merged.sort_by_key(|passenger| Reverse((passenger.updated_at, passenger.id)));
+
+    let mut sources = BTreeMap::new();
+    let mut pick = |field: &str, value: fn(&Passenger) -> Option<String>| {
+        let (source, value) = std::iter::once(&survivor)
+            .chain(merged.iter())
+            .find_map(|passenger| value(passenger).map(|value| (passenger, value)))
+            .map(|(passenger, value)| (passenger.id, Some(value)))
+            .unwrap_or((survivor.id, None));
+        sources.insert(field.to_string(), source.to_string());
+        value
+    };
+    let first_name = pick("first_name", |passenger| name(&passenger.first_name));
+    let last_name = pick("last_name", |passenger| name(&passenger.last_name));
+    let status = pick("status", |passenger| {
+        passenger.status.clone().filter(|status| !status.is_empty())
+    });
+    let date_of_birth = pick("date_of_birth", |passenger| {
+        passenger.date_of_birth.map(|date| date.to_string())
+    });
+    let gender = pick("gender", |passenger| passenger.gender.clone());
+    let nationality = pick("nationality", |passenger| passenger.nationality.clone());
+    let email = pick("email", |passenger| passenger.email.clone());
+    let phone = pick("phone", |passenger| passenger.phone.clone());
+    Ok(MergePlan {
+        first_name: first_name.unwrap_or_else(|| survivor.first_name.clone()),
+        last_name: last_name.unwrap_or_else(|| survivor.last_name.clone()),
+        status,
+        date_of_birth,
+        gender,
+        nationality,
+        email,
+        phone,
+        sources,
+        survivor,
+        merged,
+    })
+}
+
+fn name(name: &str) -> Option<String> {
+    (!name.trim().is_empty() && name != PLACEHOLDER_NAME).then(|| name.to_string())
+}
+// endregion: Merge Rules
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_merge.rs"]
+mod tests;
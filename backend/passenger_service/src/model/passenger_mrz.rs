@@ -0,0 +1,290 @@
+use super::passenger_document::validate_number;
+use super::passenger_name::FILLER;
+use super::passenger_profile::country;
+use super::{Passenger, PassengerDocument};
+use crate::model;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// region:    MRZ Types
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PassengerMrzRequest {
+    /// The machine readable zone of a travel document, a line per row (TD1: 3 x 30 characters,
+    /// TD2: 2 x 36, TD3: 2 x 44)
+    #[schema(example = "P<NLDERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<\nL898902C36NLD7408122F1204159ZE184226B<<<<<10")]
+    pub mrz: String,
+}
+
+/// The passenger of a travel document, found or created, and the document.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PassengerFromMrz {
+    pub passenger: Passenger,
+    pub document: PassengerDocument,
+    /// Whether the passenger was created, or found (holding the document, or with the same names
+    /// and date of birth)
+    pub created: bool,
+}
+
+/// A check digit of the MRZ not matching its field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CheckDigitFailure {
+    /// `number`, `date_of_birth`, `expiry_date`, `personal_number` or `composite`
+    #[schema(example = "number")]
+    pub field: String,
+    #[schema(example = "6")]
+    pub expected: char,
+    #[schema(example = "5")]
+    pub found: char,
+}
+
+/// The fields of a travel document read from its MRZ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Mrz {
+    pub doc_type: &'static str,
+    pub issuing_country: String,
+    /// The primary identifier
+    pub last_name: String,
+    /// The secondary identifier, none when the document has a single name
+    pub first_name: Option<String>,
+    pub number: String,
+    pub nationality: String,
+    /// None when unknown (`<` in the MRZ)
+    pub date_of_birth: Option<NaiveDate>,
+    /// `F`, `M` or `X`
+    pub sex: String,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+// The fields as in the MRZ, with their check digits.
+struct Fields<'a> {
+    code: &'a str,
+    issuing_country: &'a str,
+    names: &'a str,
+    number: String,
+    number_check: char,
+    nationality: &'a str,
+    date_of_birth: &'a str,
+    date_of_birth_check: char,
+    sex: char,
+    expiry_date: &'a str,
+    expiry_date_check: char,
+    // TD3 passports only
+    personal_number: Option<(&'a str, char)>,
+    // the visas have none
+    composite: Option<(String, char)>,
+}
+// endregion: MRZ Types
+
+// region:    Parsing
+/// Reads the MRZ of a travel document (ICAO 9303 parts 4 to 6): TD1 ID cards, TD2 ID cards and
+/// visas, TD3 passports and visas. The lines are trimmed and uppercased. The check digits are
+/// all verified, their failures reported together (`CheckDigits`) before the fields are read.
+pub(super) fn parse_mrz(text: &str) -> Result<Mrz, model::Error> {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| line.trim().to_uppercase())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if let Some(c) = lines
+        .iter()
+        .flat_map(|line| line.chars())
+        .find(|c| !(c.is_ascii_uppercase() || c.is_ascii_digit() || *c == FILLER))
+    {
+        return Err(model::Error::Invalid("mrz", format!("'{}' is not an MRZ character", c)));
+    }
+    let lengths: Vec<usize> = lines.iter().map(String::len).collect();
+    let fields = match lengths.as_slice() {
+        [30, 30, 30] => td1(&lines[0], &lines[1], &lines[2]),
+        [36, 36] => td2_td3(&lines[0], &lines[1], 36),
+        [44, 44] => td2_td3(&lines[0], &lines[1], 44),
+        _ => {
+            return Err(model::Error::Invalid(
+                "mrz",
+                format!(
+                    "lines of {:?} characters, not TD1 (3 x 30), TD2 (2 x 36) nor TD3 (2 x 44)",
+                    lengths
+                ),
+            ))
+        }
+    };
+    verify(&fields)?;
+
+    let doc_type = match fields.code.chars().next() {
+        Some('P') => "passport",
+        Some('V') => "visa",
+        Some('I' | 'A' | 'C') => "id_card",
+        _ => {
+            return Err(model::Error::Invalid(
+                "mrz",
+                format!("'{}' is not a document code", fields.code),
+            ))
+        }
+    };
+    let (last_name, first_name) = names(fields.names);
+    Ok(Mrz {
+        doc_type,
+        issuing_country: country("issuing_country", fields.issuing_country.trim_matches(FILLER))?,
+        last_name,
+        first_name,
+        number: validate_number(fields.number.trim_end_matches(FILLER))?,
+        nationality: country("nationality", fields.nationality.trim_matches(FILLER))?,
+        date_of_birth: date("date_of_birth", fields.date_of_birth, true)?,
+        sex: match fields.sex {
+            'F' | 'M' => fields.sex.to_string(),
+            'X' | FILLER => "X".to_string(),
+            other => return Err(model::Error::Invalid("sex", format!("'{}' is not F, M or <", other))),
+        },
+        expiry_date: date("expiry_date", fields.expiry_date, false)?,
+    })
+}
+
+fn td1<'a>(line1: &'a str, line2: &'a str, line3: &'a str) -> Fields<'a> {
+    // a number longer than 9 characters goes on in the optional data, its check digit last
+    let (number, number_check) = match &line1[14..15] {
+        "<" => {
+            let rest = line1[15..].split(FILLER).next().unwrap_or_default();
+            match rest.char_indices().last() {
+                Some((index, check)) => (format!("{}{}", &line1[5..14], &rest[..index]), check),
+                None => (line1[5..14].to_string(), FILLER),
+            }
+        }
+        _ => (line1[5..14].to_string(), char_at(line1, 14)),
+    };
+    Fields {
+        code: &line1[0..2],
+        issuing_country: &line1[2..5],
+        names: line3,
+        number,
+        number_check,
+        nationality: &line2[15..18],
+        date_of_birth: &line2[0..6],
+        date_of_birth_check: char_at(line2, 6),
+        sex: char_at(line2, 7),
+        expiry_date: &line2[8..14],
+        expiry_date_check: char_at(line2, 14),
+        personal_number: None,
+        composite: Some((
+            format!("{}{}{}{}", &line1[5..30], &line2[0..7], &line2[8..15], &line2[18..29]),
+            char_at(line2, 29),
+        )),
+    }
+}
+
+// TD2 and TD3 differ by their length, and the personal number of the TD3 passports.
+fn td2_td3<'a>(line1: &'a str, line2: &'a str, length: usize) -> Fields<'a> {
+    let visa = line1.starts_with('V');
+    let passport = length == 44 && !visa;
+    Fields {
+        code: &line1[0..2],
+        issuing_country: &line1[2..5],
+        names: &line1[5..],
+        number: line2[0..9].to_string(),
+        number_check: char_at(line2, 9),
+        nationality: &line2[10..13],
+        date_of_birth: &line2[13..19],
+        date_of_birth_check: char_at(line2, 19),
+        sex: char_at(line2, 20),
+        expiry_date: &line2[21..27],
+        expiry_date_check: char_at(line2, 27),
+        personal_number: passport.then(|| (&line2[28..42], char_at(line2, 42))),
+        composite: (!visa).then(|| {
+            (
+                format!("{}{}{}", &line2[0..10], &line2[13..20], &line2[21..length - 1]),
+                char_at(line2, length - 1),
+            )
+        }),
+    }
+}
+
+fn verify(fields: &Fields) -> Result<(), model::Error> {
+    let mut checks = vec![
+        ("number", fields.number.as_str(), fields.number_check),
+        ("date_of_birth", fields.date_of_birth, fields.date_of_birth_check),
+        ("expiry_date", fields.expiry_date, fields.expiry_date_check),
+    ];
+    if let Some((personal_number, check)) = fields.personal_number {
+        // an empty personal number may have `<` as check digit
+        if !(check == FILLER && personal_number.chars().all(|c| c == FILLER)) {
+            checks.push(("personal_number", personal_number, check));
+        }
+    }
+    if let Some((composite, check)) = &fields.composite {
+        checks.push(("composite", composite, *check));
+    }
+    let failures: Vec<CheckDigitFailure> = checks
+        .into_iter()
+        .filter_map(|(field, value, found)| {
+            let expected = check_digit(value);
+            (expected != found).then(|| CheckDigitFailure {
+                field: field.to_string(),
+                expected,
+                found,
+            })
+        })
+        .collect();
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(model::Error::CheckDigits(failures)),
+    }
+}
+
+/// The check digit of an MRZ field: the sum of its character values (digits, `A` = 10 to
+/// `Z` = 35, `<` = 0) weighted 7, 3, 1 repeatedly, modulo 10.
+pub(super) fn check_digit(value: &str) -> char {
+    let sum: u32 = value
+        .chars()
+        .zip([7, 3, 1].iter().cycle())
+        .map(|(c, weight)| {
+            let value = match c {
+                '0'..='9' => c as u32 - '0' as u32,
+                'A'..='Z' => c as u32 - 'A' as u32 + 10,
+                _ => 0,
+            };
+            value * weight
+        })
+        .sum();
+    char::from_digit(sum % 10, 10).unwrap_or('0')
+}
+
+// The primary and secondary identifiers, `<<` between them and `<` between their words.
+fn names(field: &str) -> (String, Option<String>) {
+    let field = field.trim_end_matches(FILLER);
+    let (last_name, first_name) = match field.split_once("<<") {
+        Some((last_name, first_name)) => (last_name, Some(first_name)),
+        None => (field, None),
+    };
+    let words = |name: &str| {
+        name.split(FILLER)
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    (words(last_name), first_name.map(words).filter(|name| !name.is_empty()))
+}
+
+// YYMMDD, unknown when it has fillers. The births are in the past, the expiries within 50 years.
+fn date(field: &'static str, yymmdd: &str, past: bool) -> Result<Option<NaiveDate>, model::Error> {
+    if yymmdd.contains(FILLER) {
+        return Ok(None);
+    }
+    let invalid = || model::Error::Invalid(field, format!("'{}' is not a date (YYMMDD)", yymmdd));
+    let number = |range: std::ops::Range<usize>| yymmdd[range].parse::<u32>().map_err(|_| invalid());
+    let (yy, month, day) = (number(0..2)?, number(2..4)?, number(4..6)?);
+    let this_year = Utc::now().year();
+    let mut year = this_year / 100 * 100 + yy as i32;
+    if (past && year > this_year) || (!past && year > this_year + 50) {
+        year -= 100;
+    }
+    NaiveDate::from_ymd_opt(year, month, day).map(Some).ok_or_else(invalid)
+}
+
+fn char_at(line: &str, index: usize) -> char {
+    line[index..].chars().next().unwrap_or(FILLER)
+}
+// endregion: Parsing
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_mrz.rs"]
+mod tests;
@@ -0,0 +1,167 @@
+use crate::config::config;
+use crate::model;
+use std::str::FromStr;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+/// Separator of the name parts in a machine readable zone (MRZ).
+pub const FILLER: char = '<';
+
+/// Casing of the stored names (`NAME_CASE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameCase {
+    /// As entered.
+    Preserve,
+    /// `JOHN`, as printed in the travel documents.
+    Upper,
+    /// `John`, each part of the name capitalized (`Jean-Luc`, `O'Brien`), the rest lowercase.
+    Title,
+}
+
+impl FromStr for NameCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(NameCase::Preserve),
+            "upper" => Ok(NameCase::Upper),
+            "title" => Ok(NameCase::Title),
+            other => Err(format!("Unknown name case '{}'", other)),
+        }
+    }
+}
+
+// region:    Normalization
+/// The name as stored: whitespace trimmed and collapsed to single spaces, Unicode NFC, cased
+/// per `NAME_CASE`. Rejects the blank names, the control characters and the names longer than
+/// `NAME_MAX_CHARS`.
+pub fn normalize_name(field: &'static str, name: &str) -> Result<String, model::Error> {
+    if name.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return Err(model::Error::Invalid(
+            field,
+            "control characters not allowed".to_string(),
+        ));
+    }
+    let name: String = name.split_whitespace().collect::<Vec<_>>().join(" ").nfc().collect();
+    if name.is_empty() {
+        return Err(model::Error::Invalid(field, "blank".to_string()));
+    }
+    let max_chars = config().name_max_chars;
+    if name.chars().count() > max_chars {
+        return Err(model::Error::Invalid(
+            field,
+            format!("longer than {} characters", max_chars),
+        ));
+    }
+    Ok(case(&name, config().name_case))
+}
+
+fn case(name: &str, name_case: NameCase) -> String {
+    match name_case {
+        NameCase::Preserve => name.to_string(),
+        NameCase::Upper => name.to_uppercase(),
+        NameCase::Title => {
+            let mut cased = String::with_capacity(name.len());
+            let mut start = true;
+            for c in name.chars() {
+                match start {
+                    true => cased.extend(c.to_uppercase()),
+                    false => cased.extend(c.to_lowercase()),
+                }
+                start = !c.is_alphanumeric();
+            }
+            cased
+        }
+    }
+}
+// endregion: Normalization
+
+// region:    ICAO 9303
+/// The names as in the MRZ of a passport (ICAO 9303 part 3): transliterated to `A-Z`, the
+/// last name (primary identifier) then the first names, separated by `<<`, the parts of each
+/// separated by `<`. Stored in `name_key`, equal for the names written differently.
+pub fn name_key(first_name: &str, last_name: &str) -> String {
+    let first_name = transliterate(first_name);
+    match first_name.is_empty() {
+        true => transliterate(last_name),
+        false => format!("{}{}{}{}", transliterate(last_name), FILLER, FILLER, first_name),
+    }
+}
+
+/// The name in uppercase `A-Z` and digits, its parts separated by `<`: the letters with
+/// diacritics lose them, except the ones ICAO 9303 spells out (`Ä` → `AE`, `ß` → `SS`...),
+/// the Cyrillic letters are transliterated, apostrophes are dropped and the other characters
+/// (spaces, hyphens) separate the parts.
+pub fn transliterate(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_uppercase) {
+        match c {
+            // apostrophes, and the soft sign
+            '\'' | '’' | 'ʼ' | 'Ь' => continue,
+            c => match spelled(c) {
+                Some(letters) => key.push_str(letters),
+                None => decompose_canonical(c, |c| {
+                    if c.is_ascii_alphanumeric() {
+                        key.push(c);
+                    } else if !is_combining_mark(c) && !key.ends_with(FILLER) {
+                        key.push(FILLER);
+                    }
+                }),
+            },
+        }
+    }
+    key.trim_matches(FILLER).to_string()
+}
+
+// The letters ICAO 9303 transliterates to several ones, or not to their base letter.
+fn spelled(c: char) -> Option<&'static str> {
+    let letters = match c {
+        'Ä' | 'Æ' => "AE",
+        'Å' => "AA",
+        'Ö' | 'Ø' | 'Œ' => "OE",
+        'Ü' => "UE",
+        'ẞ' => "SS",
+        'Þ' => "TH",
+        'Ð' | 'Đ' => "D",
+        'Ł' => "L",
+        'Ħ' => "H",
+        // Cyrillic
+        'А' => "A",
+        'Б' => "B",
+        'В' => "V",
+        'Г' | 'Ґ' => "G",
+        'Д' => "D",
+        'Е' | 'Ё' | 'Э' => "E",
+        'Є' => "IE",
+        'Ж' => "ZH",
+        'З' => "Z",
+        'И' | 'Й' | 'І' | 'Ї' => "I",
+        'К' => "K",
+        'Л' => "L",
+        'М' => "M",
+        'Н' => "N",
+        'О' => "O",
+        'П' => "P",
+        'Р' => "R",
+        'С' => "S",
+        'Т' => "T",
+        'У' => "U",
+        'Ф' => "F",
+        'Х' => "KH",
+        'Ц' => "TS",
+        'Ч' => "CH",
+        'Ш' => "SH",
+        'Щ' => "SHCH",
+        'Ъ' => "IE",
+        'Ы' => "Y",
+        'Ю' => "IU",
+        'Я' => "IA",
+        _ => return None,
+    };
+    Some(letters)
+}
+// endregion: ICAO 9303
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_name.rs"]
+mod tests;
@@ -0,0 +1,123 @@
+use crate::model;
+use chrono::{Months, NaiveDate, Utc};
+
+/// ISO 3166-1 alpha-3 codes of the countries.
+const COUNTRIES: &[&str] = &[
+    "ABW", "AFG", "AGO", "AIA", "ALA", "ALB", "AND", "ARE", "ARG", "ARM", "ASM", "ATA", "ATF", "ATG", "AUS", "AUT",
+    "AZE", "BDI", "BEL", "BEN", "BES", "BFA", "BGD", "BGR", "BHR", "BHS", "BIH", "BLM", "BLR", "BLZ", "BMU", "BOL",
+    "BRA", "BRB", "BRN", "BTN", "BVT", "BWA", "CAF", "CAN", "CCK", "CHE", "CHL", "CHN", "CIV", "CMR", "COD", "COG",
+    "COK", "COL", "COM", "CPV", "CRI", "CUB", "CUW", "CXR", "CYM", "CYP", "CZE", "DEU", "DJI", "DMA", "DNK", "DOM",
+    "DZA", "ECU", "EGY", "ERI", "ESH", "ESP", "EST", "ETH", "FIN", "FJI", "FLK", "FRA", "FRO", "FSM", "GAB", "GBR",
+    "GEO", "GGY", "GHA", "GIB", "GIN", "GLP", "GMB", "GNB", "GNQ", "GRC", "GRD", "GRL", "GTM", "GUF", "GUM", "GUY",
+    "HKG", "HMD", "HND", "HRV", "HTI", "HUN", "IDN", "IMN", "IND", "IOT", "IRL", "IRN", "IRQ", "ISL", "ISR", "ITA",
+    "JAM", "JEY", "JOR", "JPN", "KAZ", "KEN", "KGZ", "KHM", "KIR", "KNA", "KOR", "KWT", "LAO", "LBN", "LBR", "LBY",
+    "LCA", "LIE", "LKA", "LSO", "LTU", "LUX", "LVA", "MAC", "MAF", "MAR", "MCO", "MDA", "MDG", "MDV", "MEX", "MHL",
+    "MKD", "MLI", "MLT", "MMR", "MNE", "MNG", "MNP", "MOZ", "MRT", "MSR", "MTQ", "MUS", "MWI", "MYS", "MYT", "NAM",
+    "NCL", "NER", "NFK", "NGA", "NIC", "NIU", "NLD", "NOR", "NPL", "NRU", "NZL", "OMN", "PAK", "PAN", "PCN", "PER",
+    "PHL", "PLW", "PNG", "POL", "PRI", "PRK", "PRT", "PRY", "PSE", "PYF", "QAT", "REU", "ROU", "RUS", "RWA", "SAU",
+    "SDN", "SEN", "SGP", "SGS", "SHN", "SJM", "SLB", "SLE", "SLV", "SMR", "SOM", "SPM", "SRB", "SSD", "STP", "SUR",
+    "SVK", "SVN", "SWE", "SWZ", "SXM", "SYC", "SYR", "TCA", "TCD", "TGO", "THA", "TJK", "TKL", "TKM", "TLS", "TON",
+    "TTO", "TUN", "TUR", "TUV", "TWN", "TZA", "UGA", "UKR", "UMI", "URY", "USA", "UZB", "VAT", "VCT", "VEN", "VGB",
+    "VIR", "VNM", "VUT", "WLF", "WSM", "YEM", "ZAF", "ZMB", "ZWE",
+];
+
+/// The codes ICAO 9303 part 3 adds for the travel documents: British nationals, Kosovo,
+/// organizations (EU, UN...), stateless persons and refugees.
+const ICAO_CODES: &[&str] = &[
+    "EUE", "GBD", "GBN", "GBO", "GBP", "GBS", "RKS", "UNA", "UNK", "UNO", "XBA", "XCC", "XCE", "XCO", "XDC", "XEC",
+    "XES", "XIM", "XMP", "XOM", "XPO", "XXA", "XXB", "XXC", "XXX",
+];
+
+/// The sex codes of the travel documents (ICAO 9303): female, male, unspecified.
+const GENDERS: &[&str] = &["F", "M", "X"];
+
+const MAX_AGE_MONTHS: u32 = 130 * 12;
+const MAX_EMAIL_CHARS: usize = 254;
+
+// region:    Validation
+/// The country code in uppercase, ISO 3166-1 alpha-3 or one of the ICAO 9303 codes;
+/// `D` (Germany in the travel documents) is `DEU`.
+pub fn country(field: &'static str, code: &str) -> Result<String, model::Error> {
+    let code = code.trim().to_uppercase();
+    if code == "D" {
+        return Ok("DEU".to_string());
+    }
+    match COUNTRIES.contains(&code.as_str()) || ICAO_CODES.contains(&code.as_str()) {
+        true => Ok(code),
+        false => Err(model::Error::Invalid(
+            field,
+            format!("'{}' is not an ISO 3166-1 alpha-3 code", code),
+        )),
+    }
+}
+
+pub(super) fn gender(gender: &str) -> Result<String, model::Error> {
+    let gender = gender.trim().to_uppercase();
+    match GENDERS.contains(&gender.as_str()) {
+        true => Ok(gender),
+        false => Err(model::Error::Invalid(
+            "gender",
+            format!("not one of {}", GENDERS.join(", ")),
+        )),
+    }
+}
+
+/// Not in the future, nor more than 130 years ago.
+pub(super) fn date_of_birth(date: NaiveDate) -> Result<NaiveDate, model::Error> {
+    let today = Utc::now().date_naive();
+    let oldest = today
+        .checked_sub_months(Months::new(MAX_AGE_MONTHS))
+        .unwrap_or(NaiveDate::MIN);
+    match (oldest..=today).contains(&date) {
+        true => Ok(date),
+        false => Err(model::Error::Invalid(
+            "date_of_birth",
+            format!("{} not in {}..={}", date, oldest, today),
+        )),
+    }
+}
+
+/// A single `@` between a local part and a domain with a dot, no whitespace; the domain is
+/// lowercased.
+pub(super) fn email(email: &str) -> Result<String, model::Error> {
+    let email = email.trim();
+    let invalid = |reason: &str| Err(model::Error::Invalid("email", reason.to_string()));
+    if email.chars().count() > MAX_EMAIL_CHARS {
+        return invalid("too long");
+    }
+    if email.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return invalid("whitespace not allowed");
+    }
+    let Some((local, domain)) = email.split_once('@') else {
+        return invalid("no @");
+    };
+    let labels: Vec<&str> = domain.split('.').collect();
+    if local.is_empty() || domain.contains('@') || labels.len() < 2 || labels.iter().any(|label| label.is_empty()) {
+        return invalid("not an address");
+    }
+    Ok(format!("{}@{}", local, domain.to_lowercase()))
+}
+
+/// E.164: `+`, then up to 15 digits, the first not 0. The spaces, dots, dashes and
+/// parentheses of the usual notations are removed.
+pub(super) fn phone(phone: &str) -> Result<String, model::Error> {
+    let phone: String = phone
+        .chars()
+        .filter(|c| !(c.is_whitespace() || matches!(c, '.' | '-' | '(' | ')')))
+        .collect();
+    let valid = phone.strip_prefix('+').is_some_and(|digits| {
+        (2..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) && !digits.starts_with('0')
+    });
+    match valid {
+        true => Ok(phone),
+        false => Err(model::Error::Invalid(
+            "phone",
+            "not an E.164 number (e.g. +33612345678)".to_string(),
+        )),
+    }
+}
+// endregion: Validation
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_profile.rs"]
+mod tests;
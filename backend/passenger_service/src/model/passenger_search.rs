@@ -0,0 +1,233 @@
+use super::Passenger;
+use crate::model;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use utoipa::{IntoParams, ToSchema};
+
+/// Lowest trigram similarity of a fuzzy match, the default `pg_trgm.similarity_threshold`.
+const SIMILARITY_THRESHOLD: f64 = 0.3;
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+const MAX_QUERY_CHARS: usize = 200;
+const MAX_TERMS: usize = 8;
+
+// region:    Search Types
+/// Query parameters of the passenger search.
+#[derive(Default, Clone, Debug, Deserialize, IntoParams)]
+pub struct PassengerSearch {
+    /// Words of the first or last names, in any order. Each one matches the start of a name
+    /// word, or a name close to it (typos); case and accents are ignored.
+    #[param(example = "jo smit")]
+    pub q: String,
+    /// Hits returned, 20 by default, 100 at most
+    #[param(example = 20)]
+    pub limit: Option<i64>,
+}
+
+/// A passenger matching the search.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PassengerSearchHit {
+    pub passenger: Passenger,
+    /// Relevance, from 0 to 1 (every word of the query is a word of the names)
+    #[schema(example = 0.875)]
+    pub score: f64,
+    /// The parts of the names matched by the query words
+    pub highlights: Vec<Highlight>,
+}
+
+/// A matched part of a name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Highlight {
+    /// `first_name` or `last_name`
+    #[schema(example = "last_name")]
+    pub field: String,
+    /// Offset of the first matched character (Unicode scalar values)
+    #[schema(example = 0)]
+    pub start: usize,
+    /// Offset after the last matched character
+    #[schema(example = 4)]
+    pub end: usize,
+}
+
+impl PassengerSearch {
+    /// The distinct folded words of `q`, at least one.
+    pub(super) fn terms(&self) -> Result<Vec<String>, model::Error> {
+        if self.q.chars().count() > MAX_QUERY_CHARS {
+            return Err(model::Error::Invalid(
+                "q",
+                format!("longer than {} characters", MAX_QUERY_CHARS),
+            ));
+        }
+        let (folded, _) = fold(&self.q);
+        let mut terms: Vec<String> = Vec::new();
+        for (start, end) in words(&folded) {
+            let term: String = folded.chars().skip(start).take(end - start).collect();
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        match terms.len() {
+            0 => Err(model::Error::Invalid("q", "no word to search".to_string())),
+            n if n > MAX_TERMS => Err(model::Error::Invalid("q", format!("more than {} words", MAX_TERMS))),
+            _ => Ok(terms),
+        }
+    }
+
+    pub(super) fn limit(&self) -> Result<i64, model::Error> {
+        match self.limit.unwrap_or(DEFAULT_LIMIT) {
+            limit if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+            limit => Err(model::Error::Invalid(
+                "limit",
+                format!("{} not in 1..={}", limit, MAX_LIMIT),
+            )),
+        }
+    }
+}
+// endregion: Search Types
+
+// region:    Ranking
+/// The name as stored in the `*_search` columns: lowercase, without accents.
+pub fn search_key(name: &str) -> String {
+    fold(name).0
+}
+
+/// The passengers matching all the terms, the most relevant first, at most `limit`.
+/// Works on any candidates, from the trigram index or a plain scan.
+pub(super) fn rank(passengers: Vec<Passenger>, terms: &[String], limit: i64) -> Vec<PassengerSearchHit> {
+    let mut hits: Vec<PassengerSearchHit> = passengers
+        .into_iter()
+        .filter_map(|passenger| score(passenger, terms))
+        .collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.passenger.last_name.cmp(&b.passenger.last_name))
+            .then_with(|| a.passenger.first_name.cmp(&b.passenger.first_name))
+            .then_with(|| a.passenger.id.cmp(&b.passenger.id))
+    });
+    hits.truncate(limit as usize);
+    hits
+}
+
+// None when a term matches none of the names.
+fn score(passenger: Passenger, terms: &[String]) -> Option<PassengerSearchHit> {
+    let fields = [
+        ("first_name", fold(&passenger.first_name)),
+        ("last_name", fold(&passenger.last_name)),
+    ];
+    let mut total = 0.0;
+    let mut highlights = Vec::new();
+    for term in terms {
+        let (score, field, origins, (start, end)) = fields
+            .iter()
+            .flat_map(|(field, (folded, origins))| {
+                let chars: Vec<char> = folded.chars().collect();
+                words(folded).into_iter().filter_map(move |(start, end)| {
+                    let word: String = chars[start..end].iter().collect();
+                    match_word(term, &word).map(|(score, len)| (score, *field, origins, (start, start + len)))
+                })
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        total += score;
+        let highlight = Highlight {
+            field: field.to_string(),
+            start: origins[start],
+            end: origins[end - 1] + 1,
+        };
+        if !highlights.contains(&highlight) {
+            highlights.push(highlight);
+        }
+    }
+    highlights.sort_by(|a, b| (&a.field, a.start).cmp(&(&b.field, b.start)));
+    let score = (total / terms.len() as f64 * 1000.0).round() / 1000.0;
+    Some(PassengerSearchHit {
+        passenger,
+        score,
+        highlights,
+    })
+}
+
+// The score of the term for the word, and the number of chars of the word matched.
+// Whole word 1, start of the word 0.5 to 1 with the part matched, else the similarity (< 1).
+fn match_word(term: &str, word: &str) -> Option<(f64, usize)> {
+    let word_len = word.chars().count();
+    if term == word {
+        return Some((1.0, word_len));
+    }
+    if let Some(rest) = word.strip_prefix(term) {
+        let term_len = word_len - rest.chars().count();
+        return Some((0.5 + 0.5 * term_len as f64 / word_len as f64, term_len));
+    }
+    let similarity = similarity(term, word);
+    (similarity >= SIMILARITY_THRESHOLD).then_some((similarity * 0.9, word_len))
+}
+
+/// Trigram similarity of two names, as `similarity()` of pg_trgm: the shared trigrams of their
+/// words padded with 2 spaces before and 1 after, over all their trigrams.
+pub(super) fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.intersection(&b).count();
+    let all = a.len() + b.len() - shared;
+    match all {
+        0 => 0.0,
+        all => shared as f64 / all as f64,
+    }
+}
+
+fn trigrams(name: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut trigrams = HashSet::new();
+    for (start, end) in words(name) {
+        let padded: Vec<char> = "  "
+            .chars()
+            .chain(chars[start..end].iter().copied())
+            .chain(" ".chars())
+            .collect();
+        trigrams.extend(padded.windows(3).map(|window| [window[0], window[1], window[2]]));
+    }
+    trigrams
+}
+
+// Lowercase without the combining marks (accents), with the index in `s` of the char each
+// folded char comes from.
+fn fold(s: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(s.len());
+    let mut origins = Vec::with_capacity(s.len());
+    for (index, c) in s.chars().enumerate() {
+        decompose_canonical(c, |c| {
+            if !is_combining_mark(c) {
+                for c in c.to_lowercase() {
+                    folded.push(c);
+                    origins.push(index);
+                }
+            }
+        });
+    }
+    (folded, origins)
+}
+
+// The (start, end) char offsets of the alphanumeric runs.
+fn words(s: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in s.chars().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(from)) => {
+                words.push((from, index));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some(from) = start {
+        words.push((from, s.chars().count()));
+    }
+    words
+}
+// endregion: Ranking
+
+#[cfg(test)]
+#[path = "../_tests/model_passenger_search.rs"]
+mod tests;
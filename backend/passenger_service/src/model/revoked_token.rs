@@ -0,0 +1,59 @@
+use super::db::{traced, Db};
+use crate::model;
+use chrono::{DateTime, Utc};
+use sqlbuilder::SqlBuilder;
+
+// region: RevokedToken Types
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RevokedToken {
+    /// Token id (`jti`), or session id (`sid`) to revoke all the access tokens of a session.
+    pub jti: String,
+    /// When the revoked token(s) expire, after which the entry is useless.
+    pub expires_at: DateTime<Utc>,
+}
+// endregion: RevokedToken Types
+
+// region: RevokedTokenDao
+pub struct RevokedTokenDao;
+
+impl RevokedTokenDao {
+    const TABLE: &'static str = "revoked_tokens";
+}
+
+impl RevokedTokenDao {
+    pub async fn create(db: &Db, jti: &str, expires_at: DateTime<Utc>) -> Result<RevokedToken, model::Error> {
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&["jti", "expires_at"])
+            .values(&[&jti.to_string(), &expires_at.to_rfc3339()])
+            .build();
+        let revoked = traced(
+            "revoked_token.create",
+            &sql,
+            sqlx::query_as::<_, RevokedToken>(&sql).fetch_one(db),
+        )
+        .await?;
+        Ok(revoked)
+    }
+
+    /// Lists the entries still protecting from a not yet expired token.
+    pub async fn list_active(db: &Db) -> Result<Vec<RevokedToken>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("expires_at > {}", Utc::now().to_rfc3339())
+            .build();
+        let revoked = traced("revoked_token.list_active", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(revoked)
+    }
+
+    /// Deletes the expired entries, returns how many were deleted.
+    pub async fn delete_expired(db: &Db) -> Result<u64, model::Error> {
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("expires_at <= {}", Utc::now().to_rfc3339())
+            .build();
+        let result = traced("revoked_token.delete_expired", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(result.rows_affected())
+    }
+}
+// endregion: RevokedTokenDao
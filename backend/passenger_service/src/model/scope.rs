@@ -0,0 +1,73 @@
+use super::Passenger;
+use crate::security::UserCtx;
+use sqlbuilder::SqlBuilder;
+use std::str::FromStr;
+
+/// Row-level scoping of the passenger rows.
+///
+/// Applied by the DAO to every query, so a row outside of the caller's scope
+/// is reported as not found. Admins are never scoped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Every authenticated user can access every row.
+    Off,
+    /// Rows are restricted to their owner (`uid`).
+    Owner,
+    /// Rows are restricted to the caller's tenant (`tenant_id`).
+    /// Callers without a tenant fall back to the owner scope.
+    Tenant,
+}
+
+impl Scope {
+    pub fn apply(&self, sql: SqlBuilder, utx: &UserCtx) -> SqlBuilder {
+        if utx.is_admin() {
+            return sql;
+        }
+        match (self, &utx.tenant_id) {
+            (Scope::Off, _) => sql,
+            (Scope::Tenant, Some(tenant_id)) => sql.and_where("tenant_id = {}", tenant_id.clone()),
+            (Scope::Owner, _) | (Scope::Tenant, None) => sql.and_where("uid = {}", utx.user_id.clone()),
+        }
+    }
+
+    /// Same as `apply`, for a passenger already loaded (e.g. a change event).
+    pub fn allows(&self, utx: &UserCtx, passenger: &Passenger) -> bool {
+        if utx.is_admin() {
+            return true;
+        }
+        match (self, &utx.tenant_id) {
+            (Scope::Off, _) => true,
+            (Scope::Tenant, Some(tenant_id)) => passenger.tenant_id.is_some_and(|id| id.to_string() == *tenant_id),
+            (Scope::Owner, _) | (Scope::Tenant, None) => passenger.uid.to_string() == utx.user_id,
+        }
+    }
+
+    /// Key of the rows `apply` lets the user see, the users with the same key see the same rows.
+    pub fn partition(&self, utx: &UserCtx) -> String {
+        if utx.is_admin() {
+            return "all".to_string();
+        }
+        match (self, &utx.tenant_id) {
+            (Scope::Off, _) => "all".to_string(),
+            (Scope::Tenant, Some(tenant_id)) => format!("tenant:{}", tenant_id),
+            (Scope::Owner, _) | (Scope::Tenant, None) => format!("owner:{}", utx.user_id),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Scope::Off),
+            "owner" => Ok(Scope::Owner),
+            "tenant" => Ok(Scope::Tenant),
+            other => Err(format!("Unknown scope '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_scope.rs"]
+mod tests;
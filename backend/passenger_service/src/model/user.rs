@@ -0,0 +1,133 @@
+use super::db::{traced, Db};
+use crate::model;
+use crate::security::{pwd, Role, UserCtx};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlbuilder::{FormatSqlValue, SqlBuilder};
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+// region: User Types
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct User {
+    #[schema(example = "d2b4c1a6-3f5e-4e7b-8a9c-0b1d2e3f4a5b")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    #[schema(example = "demo")]
+    pub username: String,
+    #[serde(skip)]
+    pub pwd_hash: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub tenant_id: Option<Uuid>,
+    #[schema(example = "user")]
+    pub roles: String,
+    pub failed_logins: i64,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    pub fn roles(&self) -> Vec<Role> {
+        self.roles
+            .split(',')
+            .filter_map(|role| role.trim().parse().ok())
+            .collect()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|locked_until| locked_until > Utc::now())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct UserForCreate {
+    #[schema(example = "demo")]
+    pub username: String,
+    #[schema(example = "welcome")]
+    pub pwd: String,
+    pub tenant_id: Option<String>,
+    pub roles: Vec<Role>,
+}
+// endregion: User Types
+
+// region: UserDao
+pub struct UserDao;
+
+impl UserDao {
+    const TABLE: &'static str = "users";
+}
+
+impl UserDao {
+    pub async fn create(db: &Db, _utx: &UserCtx, data: UserForCreate) -> Result<User, model::Error> {
+        let roles: Vec<&str> = data.roles.iter().map(Role::as_str).collect();
+        let mut columns = vec!["username", "pwd_hash", "roles"];
+        let pwd_hash = pwd::hash_pwd(&data.pwd).map_err(|_| model::Error::FailHashPwd)?;
+        let mut values = vec![data.username, pwd_hash, roles.join(",")];
+        if let Some(tenant_id) = data.tenant_id {
+            columns.push("tenant_id");
+            values.push(tenant_id);
+        }
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&columns)
+            .values(&values.iter().collect::<Vec<_>>())
+            .build();
+        let user = traced("user.create", &sql, sqlx::query_as::<_, User>(&sql).fetch_one(db)).await?;
+        Ok(user)
+    }
+
+    /// Finds a user for the login (no user context yet).
+    pub async fn find_by_username(db: &Db, username: &str) -> Result<Option<User>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("username = {}", username.to_string())
+            .build();
+        let user = traced("user.find_by_username", &sql, sqlx::query_as(&sql).fetch_optional(db)).await?;
+        Ok(user)
+    }
+
+    pub async fn find_by_id(db: &Db, id: Uuid) -> Result<Option<User>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("id = {}", id.to_string())
+            .build();
+        let user = traced("user.find_by_id", &sql, sqlx::query_as(&sql).fetch_optional(db)).await?;
+        Ok(user)
+    }
+
+    /// Counts a failed login, in one statement so concurrent failures all count. Reaching
+    /// `max_failed_logins` locks the account until `locked_until`, the count starting again.
+    pub async fn record_failed_login(
+        db: &Db,
+        id: Uuid,
+        max_failed_logins: i64,
+        locked_until: DateTime<Utc>,
+    ) -> Result<User, model::Error> {
+        let sql = format!(
+            "UPDATE {table} SET \
+             failed_logins = CASE WHEN failed_logins + 1 >= {max} THEN 0 ELSE failed_logins + 1 END, \
+             locked_until = CASE WHEN failed_logins + 1 >= {max} THEN {locked_until} ELSE locked_until END \
+             WHERE id = {id} RETURNING *",
+            table = Self::TABLE,
+            max = max_failed_logins,
+            locked_until = locked_until.to_rfc3339().format_sql_value(),
+            id = id.to_string().format_sql_value(),
+        );
+        let user = traced("user.record_failed_login", &sql, sqlx::query_as(&sql).fetch_one(db)).await?;
+        Ok(user)
+    }
+
+    /// Clears the failed login count, after a successful login.
+    pub async fn reset_failed_logins(db: &Db, id: Uuid) -> Result<(), model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["failed_logins"], &[&0])
+            .where_clause("id = {}", id.to_string())
+            .build();
+        traced("user.reset_failed_logins", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+}
+// endregion: UserDao
@@ -0,0 +1,187 @@
+use super::db::{traced, Db};
+use crate::model;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlbuilder::{FormatSqlValue, SqlBuilder};
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+// region: UserSession Types
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserSession {
+    #[schema(example = "5b1d5c1e-2f7a-4c3e-8d9b-6a0e4f3c2b1a")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    #[serde_as(as = "DisplayFromStr")]
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+// endregion: UserSession Types
+
+// region: UserSessionDao
+pub struct UserSessionDao;
+
+impl UserSessionDao {
+    const TABLE: &'static str = "user_session";
+    const SUPERSEDED_TABLE: &'static str = "user_session_superseded";
+}
+
+impl UserSessionDao {
+    pub async fn create(
+        db: &Db,
+        user_id: Uuid,
+        refresh_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<UserSession, model::Error> {
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&["user_id", "refresh_hash", "expires_at"])
+            .values(&[&user_id.to_string(), &refresh_hash, &expires_at.to_rfc3339()])
+            .build();
+        let session = traced(
+            "user_session.create",
+            &sql,
+            sqlx::query_as::<_, UserSession>(&sql).fetch_one(db),
+        )
+        .await?;
+        Ok(session)
+    }
+
+    pub async fn get(db: &Db, id: Uuid) -> Result<UserSession, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("id = {}", id.to_string())
+            .build();
+        let result = traced(
+            "user_session.get",
+            &sql,
+            sqlx::query_as::<_, UserSession>(&sql).fetch_one(db),
+        )
+        .await;
+        handle_fetch_one_result(result, Self::TABLE, id)
+    }
+
+    /// Lists the active (not revoked, not expired) sessions of a user, newest first.
+    pub async fn list_active(db: &Db, user_id: Uuid) -> Result<Vec<UserSession>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("user_id = {}", user_id.to_string())
+            .and("revoked_at IS NULL")
+            .and_where("expires_at > {}", Utc::now().to_rfc3339())
+            .order_by("created_at DESC, id")
+            .build();
+        let sessions = traced("user_session.list_active", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(sessions)
+    }
+
+    /// Finds the active (not revoked, not expired) session of a refresh token hash.
+    pub async fn find_active_by_refresh_hash(db: &Db, refresh_hash: &str) -> Result<Option<UserSession>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("refresh_hash = {}", refresh_hash.to_string())
+            .and("revoked_at IS NULL")
+            .and_where("expires_at > {}", Utc::now().to_rfc3339())
+            .build();
+        let session = traced(
+            "user_session.find_active_by_refresh_hash",
+            &sql,
+            sqlx::query_as(&sql).fetch_optional(db),
+        )
+        .await?;
+        Ok(session)
+    }
+
+    /// The session a refresh token was rotated out of, revoked or expired ones too.
+    pub async fn find_by_superseded_hash(db: &Db, refresh_hash: &str) -> Result<Option<UserSession>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .and(&format!(
+                "id IN (SELECT session_id FROM {} WHERE refresh_hash = {})",
+                Self::SUPERSEDED_TABLE,
+                refresh_hash.to_string().format_sql_value()
+            ))
+            .build();
+        let session = traced(
+            "user_session.find_by_superseded_hash",
+            &sql,
+            sqlx::query_as(&sql).fetch_optional(db),
+        )
+        .await?;
+        Ok(session)
+    }
+
+    /// Replaces the refresh token of the session, so the previous one can not be used anymore;
+    /// its hash is kept, to recognize it if presented again (see `find_by_superseded_hash`).
+    /// None when the previous refresh token is not the one of the session anymore (rotated by a
+    /// concurrent refresh) or the session is revoked.
+    pub async fn rotate(
+        db: &Db,
+        id: Uuid,
+        previous_hash: &str,
+        refresh_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<UserSession>, model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(
+                &["refresh_hash", "expires_at", "refreshed_at"],
+                &[&refresh_hash, &expires_at.to_rfc3339(), &Utc::now().to_rfc3339()],
+            )
+            .where_clause("id = {}", id.to_string())
+            .and_where("refresh_hash = {}", previous_hash.to_string())
+            .and("revoked_at IS NULL")
+            .build();
+        let mut tx = db.begin().await?;
+        let session = traced(
+            "user_session.rotate",
+            &sql,
+            sqlx::query_as::<_, UserSession>(&sql).fetch_optional(&mut tx),
+        )
+        .await?;
+        if session.is_some() {
+            let sql = SqlBuilder::new()
+                .insert_into(Self::SUPERSEDED_TABLE)
+                .columns(&["refresh_hash", "session_id"])
+                .values(&[&previous_hash.to_string(), &id.to_string()])
+                .build();
+            traced("user_session.rotate", &sql, sqlx::query(&sql).execute(&mut tx)).await?;
+        }
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    pub async fn revoke(db: &Db, id: Uuid) -> Result<UserSession, model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["revoked_at"], &[&Utc::now().to_rfc3339()])
+            .where_clause("id = {}", id.to_string())
+            .and("revoked_at IS NULL")
+            .build();
+        let result = traced(
+            "user_session.revoke",
+            &sql,
+            sqlx::query_as::<_, UserSession>(&sql).fetch_one(db),
+        )
+        .await;
+        handle_fetch_one_result(result, Self::TABLE, id)
+    }
+}
+// endregion: UserSessionDao
+
+// region:    Utils
+fn handle_fetch_one_result(
+    result: Result<UserSession, sqlx::Error>,
+    typ: &'static str,
+    id: Uuid,
+) -> Result<UserSession, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound(typ, id.to_string()),
+        other => model::Error::Sqlx(other),
+    })
+}
+// endregion: Utils
@@ -0,0 +1,381 @@
+use super::db::{traced, Db};
+use super::outbox::{OutboxDao, OUTBOX_EVENT_TYPES};
+use crate::model;
+use crate::security::{webhook, UserCtx};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlbuilder::SqlBuilder;
+use sqlx::types::Uuid;
+use utoipa::{IntoParams, ToSchema};
+
+// region: Webhook Types
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    #[schema(example = "6c1f0e2a-9b7d-4e3f-8a5c-1d2e3f4a5b6c")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: Uuid,
+    #[schema(example = "https://flight.example.com/hooks/passengers")]
+    pub url: String,
+    #[serde(skip)]
+    pub secret: String,
+    /// Comma separated, empty for all the events
+    #[schema(example = "passenger.created,passenger.deleted")]
+    pub event_types: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub created_by: Uuid,
+}
+
+impl Webhook {
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.split(',').any(|accepted| accepted == event_type)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookForCreate {
+    #[schema(example = "https://flight.example.com/hooks/passengers")]
+    pub url: String,
+    /// Empty for all the events
+    #[serde(default)]
+    #[schema(example = json!(["passenger.created", "passenger.deleted"]))]
+    pub event_types: Vec<String>,
+    /// Generated when not given
+    pub secret: Option<String>,
+}
+
+#[derive(Default, Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookPatch {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+/// A new webhook, along with the secret signing its deliveries, which is never shown again.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookCreated {
+    pub webhook: Webhook,
+    #[schema(example = "whsec_4mZ...")]
+    pub secret: String,
+}
+
+/// The delivery of an outbox event to a webhook, and the outcome of its last attempt.
+#[serde_as]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub webhook_id: Uuid,
+    pub outbox_id: i64,
+    /// `pending`, `delivered`, or `dead` once out of attempts
+    #[schema(example = "delivered")]
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters of the delivery log.
+#[derive(Default, Clone, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WebhookDeliveryFilter {
+    /// Only the deliveries with this status (`pending`, `delivered` or `dead`)
+    #[param(example = "dead")]
+    pub status: Option<String>,
+}
+// endregion: Webhook Types
+
+// region: WebhookDao
+pub struct WebhookDao;
+
+impl WebhookDao {
+    const TABLE: &'static str = "webhook";
+}
+
+impl WebhookDao {
+    pub async fn create(db: &Db, utx: &UserCtx, data: WebhookForCreate) -> Result<WebhookCreated, model::Error> {
+        validate_url(&data.url)?;
+        let event_types = validate_event_types(&data.event_types)?;
+        let secret = match data.secret {
+            Some(secret) if secret.len() < webhook::MIN_SECRET_LEN => {
+                let reason = format!("at least {} characters", webhook::MIN_SECRET_LEN);
+                return Err(model::Error::Invalid("secret", reason));
+            }
+            Some(secret) => secret,
+            None => webhook::new_secret(),
+        };
+        let sql = SqlBuilder::new()
+            .insert_into(Self::TABLE)
+            .columns(&["url", "secret", "event_types", "created_by"])
+            .values(&[&data.url, &secret, &event_types, &utx.user_id])
+            .build();
+        let webhook = traced("webhook.create", &sql, sqlx::query_as::<_, Webhook>(&sql).fetch_one(db)).await?;
+        Ok(WebhookCreated { webhook, secret })
+    }
+
+    pub async fn list(db: &Db) -> Result<Vec<Webhook>, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .order_by("created_at, id")
+            .build();
+        let webhooks = traced("webhook.list", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(webhooks)
+    }
+
+    pub async fn get(db: &Db, id: String) -> Result<Webhook, model::Error> {
+        let sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("id = {}", id.clone())
+            .build();
+        let result = traced("webhook.get", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        handle_fetch_one_result(result, Self::TABLE, id)
+    }
+
+    pub async fn update(db: &Db, id: String, data: WebhookPatch) -> Result<Webhook, model::Error> {
+        let mut columns = Vec::new();
+        let mut values = Vec::new();
+        if let Some(url) = data.url {
+            validate_url(&url)?;
+            columns.push("url");
+            values.push(url);
+        }
+        if let Some(event_types) = data.event_types {
+            columns.push("event_types");
+            values.push(validate_event_types(&event_types)?);
+        }
+        if let Some(active) = data.active {
+            columns.push("active");
+            values.push(active.to_string());
+        }
+        if columns.is_empty() {
+            return Self::get(db, id).await;
+        }
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&columns, &values.iter().collect::<Vec<_>>())
+            .where_clause("id = {}", id.clone())
+            .build();
+        let result = traced("webhook.update", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        handle_fetch_one_result(result, Self::TABLE, id)
+    }
+
+    /// Deletes the webhook, along with its deliveries.
+    pub async fn delete(db: &Db, id: String) -> Result<Webhook, model::Error> {
+        let sql = SqlBuilder::new()
+            .delete_from(Self::TABLE)
+            .where_clause("id = {}", id.clone())
+            .build();
+        let result = traced("webhook.delete", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        handle_fetch_one_result(result, Self::TABLE, id)
+    }
+}
+// endregion: WebhookDao
+
+// region: WebhookDeliveryDao
+pub struct WebhookDeliveryDao;
+
+impl WebhookDeliveryDao {
+    const TABLE: &'static str = "webhook_delivery";
+    pub const PENDING: &'static str = "pending";
+    pub const DELIVERED: &'static str = "delivered";
+    pub const DEAD: &'static str = "dead";
+}
+
+impl WebhookDeliveryDao {
+    /// Creates the deliveries of up to `limit` outbox events, to the active webhooks accepting
+    /// them. Returns the number of events dispatched.
+    pub async fn fan_out(db: &Db, limit: i64) -> Result<usize, model::Error> {
+        let mut tx = db.begin().await?;
+        let events = OutboxDao::claim_pending(&mut tx, limit).await?;
+        if events.is_empty() {
+            return Ok(0);
+        }
+        let sql = SqlBuilder::new()
+            .select_from(WebhookDao::TABLE)
+            .where_clause("active = {}", "true".to_string())
+            .build();
+        let webhooks: Vec<Webhook> =
+            traced("webhook.list_active", &sql, sqlx::query_as(&sql).fetch_all(&mut tx)).await?;
+        for event in &events {
+            for webhook in webhooks.iter().filter(|webhook| webhook.accepts(&event.event_type)) {
+                let sql = SqlBuilder::new()
+                    .insert_into(Self::TABLE)
+                    .columns(&["webhook_id", "outbox_id"])
+                    .values(&[&webhook.id.to_string(), &event.id.to_string()])
+                    .build();
+                traced("webhook_delivery.create", &sql, sqlx::query(&sql).execute(&mut tx)).await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(events.len())
+    }
+
+    /// Takes up to `limit` pending deliveries due now, oldest first, the ones of the inactive
+    /// webhooks wait until they are active again. The deliveries taken are not due again
+    /// before `lease_until`, so another dispatcher does not take them meanwhile, and they are
+    /// retried if this one stops before recording their outcome.
+    pub async fn claim_due(
+        db: &Db,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>, model::Error> {
+        let due = format!(
+            "id IN (SELECT id FROM webhook_delivery WHERE status = '{}' AND next_attempt_at <= now() \
+             AND webhook_id IN (SELECT id FROM webhook WHERE active) ORDER BY next_attempt_at, id LIMIT {{}})",
+            Self::PENDING
+        );
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(&["next_attempt_at"], &[&lease_until.to_rfc3339()])
+            .where_clause(&due, limit)
+            .build();
+        let mut deliveries: Vec<WebhookDelivery> =
+            traced("webhook_delivery.claim_due", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        deliveries.sort_by_key(|delivery| delivery.id);
+        Ok(deliveries)
+    }
+
+    pub async fn delivered(db: &Db, id: i64, attempts: i64, status_code: u16) -> Result<(), model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(
+                &["status", "attempts", "last_status_code", "last_error", "delivered_at"],
+                &[
+                    &Some(Self::DELIVERED.to_string()),
+                    &Some(attempts.to_string()),
+                    &Some(status_code.to_string()),
+                    &None,
+                    &Some(Utc::now().to_rfc3339()),
+                ],
+            )
+            .where_clause("id = {}", id)
+            .build();
+        traced("webhook_delivery.delivered", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+
+    /// Records a failed attempt, to retry at `retry_at`, or dead when None.
+    pub async fn failed(
+        db: &Db,
+        id: i64,
+        attempts: i64,
+        status_code: Option<u16>,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), model::Error> {
+        let (status, next_attempt_at) = match retry_at {
+            Some(retry_at) => (Self::PENDING, retry_at),
+            None => (Self::DEAD, Utc::now()),
+        };
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(
+                &[
+                    "status",
+                    "attempts",
+                    "last_status_code",
+                    "last_error",
+                    "next_attempt_at",
+                ],
+                &[
+                    &Some(status.to_string()),
+                    &Some(attempts.to_string()),
+                    &status_code.map(|status_code| status_code.to_string()),
+                    &Some(error),
+                    &Some(next_attempt_at.to_rfc3339()),
+                ],
+            )
+            .where_clause("id = {}", id)
+            .build();
+        traced("webhook_delivery.failed", &sql, sqlx::query(&sql).execute(db)).await?;
+        Ok(())
+    }
+
+    /// The deliveries of a webhook, newest first, only the ones with `status` if given.
+    pub async fn list(
+        db: &Db,
+        webhook_id: String,
+        status: Option<String>,
+    ) -> Result<Vec<WebhookDelivery>, model::Error> {
+        let mut sql = SqlBuilder::new()
+            .select_from(Self::TABLE)
+            .where_clause("webhook_id = {}", webhook_id);
+        if let Some(status) = status {
+            sql = sql.and_where("status = {}", status);
+        }
+        let sql = sql.order_by("id DESC").build();
+        let deliveries = traced("webhook_delivery.list", &sql, sqlx::query_as(&sql).fetch_all(db)).await?;
+        Ok(deliveries)
+    }
+
+    /// Sends the delivery again now (e.g. a dead one, once the receiver is fixed). The attempts
+    /// made are kept, a dead delivery gets another `max_attempts` ones.
+    pub async fn retry(db: &Db, webhook_id: String, id: i64) -> Result<WebhookDelivery, model::Error> {
+        let sql = SqlBuilder::new()
+            .update(Self::TABLE)
+            .set_columns_and_values(
+                &["status", "next_attempt_at"],
+                &[&Self::PENDING.to_string(), &Utc::now().to_rfc3339()],
+            )
+            .where_clause("id = {}", id)
+            .and_where("webhook_id = {}", webhook_id)
+            .build();
+        let result = traced("webhook_delivery.retry", &sql, sqlx::query_as(&sql).fetch_one(db)).await;
+        result.map_err(|sqlx_error| match sqlx_error {
+            sqlx::Error::RowNotFound => model::Error::EntityNotFound(Self::TABLE, id.to_string()),
+            other => model::Error::Sqlx(other),
+        })
+    }
+}
+// endregion: WebhookDeliveryDao
+
+// region:    Utils
+fn validate_url(url: &str) -> Result<(), model::Error> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => Ok(()),
+        Ok(_) => Err(model::Error::Invalid("url", "http or https URL expected".to_string())),
+        Err(ex) => Err(model::Error::Invalid("url", ex.to_string())),
+    }
+}
+
+// Returns the event types, comma separated.
+fn validate_event_types(event_types: &[String]) -> Result<String, model::Error> {
+    match event_types
+        .iter()
+        .find(|typ| !OUTBOX_EVENT_TYPES.contains(&typ.as_str()))
+    {
+        Some(unknown) => Err(model::Error::Invalid(
+            "event_types",
+            format!(
+                "unknown '{}', expected one of {}",
+                unknown,
+                OUTBOX_EVENT_TYPES.join(", ")
+            ),
+        )),
+        None => Ok(event_types.join(",")),
+    }
+}
+
+fn handle_fetch_one_result(
+    result: Result<Webhook, sqlx::Error>,
+    typ: &'static str,
+    id: String,
+) -> Result<Webhook, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound(typ, id),
+        other => model::Error::Sqlx(other),
+    })
+}
+// endregion: Utils
+
+#[cfg(test)]
+#[path = "../_tests/model_webhook.rs"]
+mod tests;
@@ -0,0 +1,60 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+// API keys look like `psk_<prefix>_<secret>`. The prefix is stored in clear to find the key,
+// the secret only as a salted hash.
+const API_KEY_MARKER: &str = "psk_";
+const PREFIX_LEN: usize = 8;
+const SECRET_LEN: usize = 40;
+const SALT_LEN: usize = 16;
+
+pub struct ApiKeyParts {
+    pub prefix: String,
+    pub secret: String,
+}
+
+impl ApiKeyParts {
+    pub fn generate() -> Self {
+        ApiKeyParts {
+            prefix: random_string(PREFIX_LEN),
+            secret: random_string(SECRET_LEN),
+        }
+    }
+
+    /// Splits a token into its parts, or None if the token is not an API key.
+    pub fn parse(token: &str) -> Option<Self> {
+        let (prefix, secret) = token.strip_prefix(API_KEY_MARKER)?.split_once('_')?;
+        if prefix.len() != PREFIX_LEN || secret.len() != SECRET_LEN {
+            return None;
+        }
+        Some(ApiKeyParts {
+            prefix: prefix.to_string(),
+            secret: secret.to_string(),
+        })
+    }
+
+    /// The key as given to its owner (shown only once).
+    pub fn token(&self) -> String {
+        format!("{}{}_{}", API_KEY_MARKER, self.prefix, self.secret)
+    }
+}
+
+pub fn new_salt() -> String {
+    random_string(SALT_LEN)
+}
+
+pub fn hash_secret(salt: &str, secret: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(secret.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+pub fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
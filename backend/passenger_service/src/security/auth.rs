@@ -0,0 +1,179 @@
+use super::{api_key, pwd, revocation, token, Error, UserCtx};
+use crate::config::config;
+use crate::model::{self, Db, User, UserDao, UserSession, UserSessionDao};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+// Refresh tokens are opaque random strings, only their hash is stored in the session.
+const REFRESH_TOKEN_MARKER: &str = "prt_";
+const REFRESH_SECRET_LEN: usize = 48;
+
+// region: Auth Types
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct LoginPayload {
+    #[schema(example = "demo")]
+    pub username: String,
+    #[schema(example = "welcome")]
+    pub pwd: String,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct RefreshPayload {
+    pub refresh_token: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthTokens {
+    /// Token to send in the X-Auth-Token header.
+    pub access_token: String,
+    pub access_expires_at: DateTime<Utc>,
+    /// Token to get new tokens from `/api/auth/refresh`, once.
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+// endregion: Auth Types
+
+/// Checks the credentials and opens a new session.
+///
+/// Unknown users and wrong passwords fail the same way. After too many failures in a row,
+/// the account is locked for a while, even for the right password.
+pub async fn login(db: &Db, payload: LoginPayload) -> Result<AuthTokens, Error> {
+    let user = UserDao::find_by_username(db, &payload.username).await?;
+    let pwd_hash = user.as_ref().map(|user| user.pwd_hash.clone());
+    let pwd_ok = tokio::task::spawn_blocking(move || pwd::verify_pwd(&payload.pwd, pwd_hash.as_deref()))
+        .await
+        .unwrap_or(false);
+
+    let Some(user) = user else {
+        return Err(Error::LoginFail);
+    };
+    if user.is_locked() {
+        return Err(Error::AccountLocked);
+    }
+    if !pwd_ok {
+        let locked_until = Utc::now() + config().auth_lockout;
+        let user = UserDao::record_failed_login(db, user.id, config().auth_max_failed_logins, locked_until).await?;
+        return match user.is_locked() {
+            true => Err(Error::AccountLocked),
+            false => Err(Error::LoginFail),
+        };
+    }
+    if user.failed_logins > 0 {
+        UserDao::reset_failed_logins(db, user.id).await?;
+    }
+
+    let (refresh_token, refresh_hash) = new_refresh_token();
+    let refresh_expires_at = Utc::now() + config().auth_refresh_ttl;
+    let session = UserSessionDao::create(db, user.id, refresh_hash, refresh_expires_at).await?;
+    new_auth_tokens(&user, &session, refresh_token)
+}
+
+/// Exchanges a refresh token for new tokens. The refresh token is rotated, so it works only once;
+/// used again, it revokes its session.
+pub async fn refresh(db: &Db, payload: RefreshPayload) -> Result<AuthTokens, Error> {
+    let invalid = || Error::InvalidToken(REFRESH_TOKEN_MARKER.to_string());
+    let secret = payload
+        .refresh_token
+        .strip_prefix(REFRESH_TOKEN_MARKER)
+        .ok_or_else(invalid)?;
+    let previous_hash = api_key::hash_secret("", secret);
+    let Some(session) = UserSessionDao::find_active_by_refresh_hash(db, &previous_hash).await? else {
+        // rotated out already, and presented again: the token is used twice, maybe stolen
+        return match UserSessionDao::find_by_superseded_hash(db, &previous_hash).await? {
+            Some(session) => Err(revoke_reused(db, session.id).await),
+            None => Err(invalid()),
+        };
+    };
+    let user = UserDao::find_by_id(db, session.user_id).await?.ok_or_else(invalid)?;
+    if user.is_locked() {
+        return Err(Error::AccountLocked);
+    }
+
+    let (refresh_token, refresh_hash) = new_refresh_token();
+    let refresh_expires_at = Utc::now() + config().auth_refresh_ttl;
+    let rotated = UserSessionDao::rotate(db, session.id, &previous_hash, refresh_hash, refresh_expires_at).await?;
+    let Some(session) = rotated else {
+        // rotated by another refresh in between, the token is used twice: maybe stolen
+        return Err(revoke_reused(db, session.id).await);
+    };
+    new_auth_tokens(&user, &session, refresh_token)
+}
+
+/// Revokes the session of the access token, see `revoke_session`.
+/// Returns None when the user is not authenticated with a session (API key, dev token).
+pub async fn logout(db: &Db, utx: &UserCtx) -> Result<Option<UserSession>, Error> {
+    let Some(session_id) = &utx.session_id else {
+        return Ok(None);
+    };
+    let session_id = Uuid::parse_str(session_id).map_err(|_| Error::InvalidToken(session_id.clone()))?;
+    let session = revoke_session(db, utx, session_id).await?;
+    Ok(Some(session))
+}
+
+/// Lists the active sessions of the user.
+pub async fn list_sessions(db: &Db, utx: &UserCtx) -> Result<Vec<UserSession>, Error> {
+    // API keys and dev tokens may not have a user id, they have no sessions either
+    let Ok(user_id) = Uuid::parse_str(&utx.user_id) else {
+        return Ok(Vec::new());
+    };
+    Ok(UserSessionDao::list_active(db, user_id).await?)
+}
+
+/// Revokes a session of the user (or of anyone, for admins). Its refresh token can not be
+/// used anymore, and its access tokens are rejected right away.
+pub async fn revoke_session(db: &Db, utx: &UserCtx, id: Uuid) -> Result<UserSession, Error> {
+    let session = UserSessionDao::get(db, id).await?;
+    // sessions of other users are not found, so their existence does not leak
+    if session.user_id.to_string() != utx.user_id && !utx.is_admin() {
+        return Err(model::Error::EntityNotFound("user_session", id.to_string()).into());
+    }
+    revoke(db, id).await
+}
+
+// region:    Utils
+// A refresh token used twice: the session is revoked, for the legitimate client and the thief
+// alike. A session revoked in between is not found, it is revoked already.
+async fn revoke_reused(db: &Db, session_id: Uuid) -> Error {
+    match revoke(db, session_id).await {
+        Ok(_) | Err(Error::Model(model::Error::EntityNotFound(..))) => Error::RefreshTokenReused,
+        Err(ex) => ex,
+    }
+}
+
+// Revokes the session and its access tokens, which are all expired after one access ttl.
+async fn revoke(db: &Db, id: Uuid) -> Result<UserSession, Error> {
+    let session = UserSessionDao::revoke(db, id).await?;
+    revocation::revoke(db, &id.to_string(), Utc::now() + config().auth_access_ttl).await?;
+    Ok(session)
+}
+
+fn new_auth_tokens(user: &User, session: &UserSession, refresh_token: String) -> Result<AuthTokens, Error> {
+    let claims = token::Claims {
+        sub: user.id.to_string(),
+        tid: user.tenant_id.map(|id| id.to_string()),
+        sid: session.id.to_string(),
+        jti: uuid::Uuid::new_v4().to_string(),
+        roles: user.roles(),
+        exp: (Utc::now() + config().auth_access_ttl).timestamp(),
+    };
+    Ok(AuthTokens {
+        access_token: token::encode(&claims, &config().auth_token_key)?,
+        access_expires_at: claims.expires_at(),
+        refresh_token,
+        refresh_expires_at: session.expires_at,
+    })
+}
+
+// Returns the refresh token and the hash to store.
+fn new_refresh_token() -> (String, String) {
+    let secret = api_key::random_string(REFRESH_SECRET_LEN);
+    let refresh_hash = api_key::hash_secret("", &secret);
+    (format!("{}{}", REFRESH_TOKEN_MARKER, secret), refresh_hash)
+}
+// endregion: Utils
+
+#[cfg(test)]
+#[path = "../_tests/security_auth.rs"]
+mod tests;
@@ -1,16 +1,181 @@
-use crate::model::Db;
+use crate::config::config;
+use crate::model::{self, ApiKeyDao, Db};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use thiserror::Error as ThisError;
+use utoipa::ToSchema;
 
-#[derive(Debug)]
+pub mod api_key;
+pub mod auth;
+pub mod pwd;
+pub mod revocation;
+pub mod token;
+pub mod webhook;
+
+#[derive(Debug, Clone)]
 pub struct UserCtx {
     pub user_id: String,
+    pub tenant_id: Option<String>,
+    pub permissions: Vec<Permission>,
+    /// Login session, when authenticated with an access token.
+    pub session_id: Option<String>,
+    /// Expiration of the access token, the streams opened with it end then.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl UserCtx {
+    pub fn new(user_id: String, tenant_id: Option<String>, roles: Vec<Role>) -> Self {
+        let mut permissions: Vec<Permission> = roles.iter().flat_map(|role| role.permissions()).copied().collect();
+        permissions.sort();
+        permissions.dedup();
+        Self::with_permissions(user_id, tenant_id, permissions)
+    }
+
+    pub fn with_permissions(user_id: String, tenant_id: Option<String>, permissions: Vec<Permission>) -> Self {
+        UserCtx {
+            user_id,
+            tenant_id,
+            permissions,
+            session_id: None,
+            expires_at: None,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.permissions.contains(&Permission::Admin)
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.is_admin() || self.permissions.contains(&permission)
+    }
+}
+
+// region:    Roles & Permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Viewer => &[Permission::PassengerRead],
+            Role::Editor => &[Permission::PassengerRead, Permission::PassengerWrite],
+            Role::User => &[
+                Permission::PassengerRead,
+                Permission::PassengerWrite,
+                Permission::PassengerDelete,
+            ],
+            Role::Admin => &[Permission::Admin],
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum Permission {
+    #[serde(rename = "passenger:read")]
+    PassengerRead,
+    #[serde(rename = "passenger:write")]
+    PassengerWrite,
+    #[serde(rename = "passenger:delete")]
+    PassengerDelete,
+    #[serde(rename = "admin")]
+    Admin,
 }
 
-pub async fn utx_from_token(_db: &Db, token: &str) -> Result<UserCtx, Error> {
-    // todo!("Real validation needed");
-    // for now, just parse to i64
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::PassengerRead => "passenger:read",
+            Permission::PassengerWrite => "passenger:write",
+            Permission::PassengerDelete => "passenger:delete",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "passenger:read" => Ok(Permission::PassengerRead),
+            "passenger:write" => Ok(Permission::PassengerWrite),
+            "passenger:delete" => Ok(Permission::PassengerDelete),
+            "admin" => Ok(Permission::Admin),
+            other => Err(format!("Unknown permission '{}'", other)),
+        }
+    }
+}
+// endregion: Roles & Permissions
+
+pub async fn utx_from_token(db: &Db, token: &str) -> Result<UserCtx, Error> {
+    // API keys, for service to service calls
+    if let Some(parts) = api_key::ApiKeyParts::parse(token) {
+        let api_key = ApiKeyDao::find_active(db, &parts)
+            .await?
+            .ok_or_else(|| Error::InvalidToken(parts.prefix.clone()))?;
+        ApiKeyDao::touch(db, api_key.id).await?;
+        return Ok(UserCtx::with_permissions(
+            api_key.id.to_string(),
+            api_key.tenant_id.map(|id| id.to_string()),
+            api_key.permissions(),
+        ));
+    }
+
+    // Access tokens, from a login
+    if token::is_access_token(token) {
+        let claims = token::decode(token, &config().auth_token_key)?;
+        if revocation::is_revoked(db, &[&claims.jti, &claims.sid]).await? {
+            return Err(Error::TokenRevoked);
+        }
+        let expires_at = claims.expires_at();
+        let mut utx = UserCtx::new(claims.sub, claims.tid, claims.roles);
+        utx.expires_at = Some(expires_at);
+        utx.session_id = Some(claims.sid);
+        return Ok(utx);
+    }
+
+    // Dev tokens, the token is the user id
+    if !config().auth_dev_tokens {
+        return Err(Error::InvalidToken(token.chars().take(12).collect()));
+    }
     match token.parse::<String>() {
-        Ok(user_id) => Ok(UserCtx { user_id }),
+        Ok(user_id) => {
+            let role = match config().admin_user_ids.contains(&user_id) {
+                true => Role::Admin,
+                false => config().default_user_role,
+            };
+            Ok(UserCtx::new(user_id, None, vec![role]))
+        }
         Err(_) => Err(Error::InvalidToken(token.to_string())),
     }
 }
@@ -20,6 +185,45 @@ pub async fn utx_from_token(_db: &Db, token: &str) -> Result<UserCtx, Error> {
 pub enum Error {
     #[error("Invalid Token {0}")]
     InvalidToken(String),
+
+    #[error("Token expired")]
+    TokenExpired,
+
+    #[error("Token revoked")]
+    TokenRevoked,
+
+    #[error("Refresh token already used, session revoked")]
+    RefreshTokenReused,
+
+    #[error("Fail to encode token")]
+    FailEncodeToken,
+
+    #[error("Fail to hash password")]
+    FailHashPwd,
+
+    #[error("Login failed, wrong username or password")]
+    LoginFail,
+
+    #[error("Account locked after too many failed logins")]
+    AccountLocked,
+
+    #[error(transparent)]
+    Model(#[from] model::Error),
+}
+
+impl Error {
+    /// Reason of the failed authentication, for the metrics. None when not an authentication failure.
+    pub fn auth_failure_reason(&self) -> Option<&'static str> {
+        match self {
+            Error::InvalidToken(_) => Some("invalid_token"),
+            Error::TokenExpired => Some("token_expired"),
+            Error::TokenRevoked => Some("token_revoked"),
+            Error::RefreshTokenReused => Some("refresh_token_reused"),
+            Error::LoginFail => Some("login_fail"),
+            Error::AccountLocked => Some("account_locked"),
+            Error::FailEncodeToken | Error::FailHashPwd | Error::Model(_) => None,
+        }
+    }
 }
 
 // endregion: Error
@@ -0,0 +1,25 @@
+use super::Error;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+// Hash of "not-a-password", verified when the user does not exist so the response time
+// does not tell whether a username exists.
+const DUMMY_PWD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$zW2vzonyfIFEq3PYvcs9OA$4E65q4BAQHixZ9DNKUI8TO2/k8wCqoWM+PmlD2mbKmI";
+
+pub fn hash_pwd(pwd: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pwd.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| Error::FailHashPwd)
+}
+
+pub fn verify_pwd(pwd: &str, pwd_hash: Option<&str>) -> bool {
+    let Ok(hash) = PasswordHash::new(pwd_hash.unwrap_or(DUMMY_PWD_HASH)) else {
+        return false;
+    };
+    let valid = Argon2::default().verify_password(pwd.as_bytes(), &hash).is_ok();
+    valid && pwd_hash.is_some()
+}
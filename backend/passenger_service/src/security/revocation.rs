@@ -0,0 +1,87 @@
+use super::Error;
+use crate::config::config;
+use crate::model::{Db, RevokedTokenDao};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::RwLock;
+
+// In-process copy of the (small) revocation list, so checking a token does not cost a query.
+// It is reloaded from the database every `auth_revocation_sync`, to get the revocations made
+// by other replicas.
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, DateTime<Utc>>,
+    loaded_at: Option<Instant>,
+}
+
+fn cache() -> &'static RwLock<Cache> {
+    static INSTANCE: OnceLock<RwLock<Cache>> = OnceLock::new();
+    INSTANCE.get_or_init(Default::default)
+}
+
+/// Revokes a token id (`jti`) or a session id (`sid`) until `expires_at`.
+pub async fn revoke(db: &Db, id: &str, expires_at: DateTime<Utc>) -> Result<(), Error> {
+    RevokedTokenDao::create(db, id, expires_at).await?;
+    cache().write().await.entries.insert(id.to_string(), expires_at);
+    Ok(())
+}
+
+/// Returns true if any of the ids (e.g. the `jti` and `sid` of a token) is revoked.
+pub async fn is_revoked(db: &Db, ids: &[&str]) -> Result<bool, Error> {
+    let stale = cache()
+        .read()
+        .await
+        .loaded_at
+        .is_none_or(|loaded_at| loaded_at.elapsed() >= config().auth_revocation_sync);
+    if stale {
+        reload(db).await?;
+    }
+    let now = Utc::now();
+    let cache = cache().read().await;
+    Ok(ids
+        .iter()
+        .any(|id| cache.entries.get(*id).is_some_and(|expires_at| *expires_at > now)))
+}
+
+/// Replaces the cache with the active entries of the database.
+pub async fn reload(db: &Db) -> Result<(), Error> {
+    let entries = RevokedTokenDao::list_active(db)
+        .await?
+        .into_iter()
+        .map(|revoked| (revoked.jti, revoked.expires_at))
+        .collect();
+    *cache().write().await = Cache {
+        entries,
+        loaded_at: Some(Instant::now()),
+    };
+    Ok(())
+}
+
+/// Deletes the expired entries, from the database and the cache. Returns how many were deleted.
+pub async fn gc(db: &Db) -> Result<u64, Error> {
+    let deleted = RevokedTokenDao::delete_expired(db).await?;
+    let now = Utc::now();
+    cache().write().await.entries.retain(|_, expires_at| *expires_at > now);
+    Ok(deleted)
+}
+
+/// Runs `gc` every `auth_revocation_gc` in the background.
+pub fn start_gc(db: Arc<Db>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config().auth_revocation_gc);
+        loop {
+            interval.tick().await;
+            match gc(&db).await {
+                Ok(0) => (),
+                Ok(deleted) => tracing::info!(deleted, "revocation gc - expired entries deleted"),
+                Err(ex) => tracing::error!(cause = ?ex, "revocation gc failed"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+#[path = "../_tests/security_revocation.rs"]
+mod tests;
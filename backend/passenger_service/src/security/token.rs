@@ -0,0 +1,72 @@
+use super::{Error, Role};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+// Access tokens look like `pat_<claims>.<signature>`, both base64url encoded.
+// The signature is the HMAC-SHA256 of the encoded claims.
+const ACCESS_TOKEN_MARKER: &str = "pat_";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    /// user id
+    pub sub: String,
+    /// tenant id
+    pub tid: Option<String>,
+    /// session id
+    pub sid: String,
+    /// token id
+    pub jti: String,
+    pub roles: Vec<Role>,
+    /// expiration, as unix timestamp
+    pub exp: i64,
+}
+
+impl Claims {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        Utc.timestamp_opt(self.exp, 0).single().unwrap_or_default()
+    }
+}
+
+pub fn is_access_token(token: &str) -> bool {
+    token.starts_with(ACCESS_TOKEN_MARKER)
+}
+
+pub fn encode(claims: &Claims, key: &[u8]) -> Result<String, Error> {
+    let claims = serde_json::to_vec(claims).map_err(|_| Error::FailEncodeToken)?;
+    let claims = URL_SAFE_NO_PAD.encode(claims);
+    let signature = URL_SAFE_NO_PAD.encode(sign(&claims, key)?.finalize().into_bytes());
+    Ok(format!("{}{}.{}", ACCESS_TOKEN_MARKER, claims, signature))
+}
+
+/// Verifies the signature and the expiration, then returns the claims.
+pub fn decode(token: &str, key: &[u8]) -> Result<Claims, Error> {
+    let invalid = || Error::InvalidToken(token.chars().take(12).collect());
+    let (claims, signature) = token
+        .strip_prefix(ACCESS_TOKEN_MARKER)
+        .and_then(|token| token.split_once('.'))
+        .ok_or_else(invalid)?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+    sign(claims, key)?.verify_slice(&signature).map_err(|_| invalid())?;
+    let claims = URL_SAFE_NO_PAD.decode(claims).map_err(|_| invalid())?;
+    let claims: Claims = serde_json::from_slice(&claims).map_err(|_| invalid())?;
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(Error::TokenExpired);
+    }
+    Ok(claims)
+}
+
+fn sign(content: &str, key: &[u8]) -> Result<HmacSha256, Error> {
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|_| Error::FailEncodeToken)?;
+    mac.update(content.as_bytes());
+    Ok(mac)
+}
+
+#[cfg(test)]
+#[path = "../_tests/security_token.rs"]
+mod tests;
@@ -0,0 +1,25 @@
+use super::api_key::random_string;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// Webhook secrets look like `whsec_<secret>`. They are stored in clear, the dispatcher needs
+// them to sign. The `X-Webhook-Signature` header is `sha256=<hex>`, the HMAC-SHA256 with the
+// secret of `<timestamp>.<body>`, the timestamp being the `X-Webhook-Timestamp` header.
+const SECRET_MARKER: &str = "whsec_";
+const SECRET_LEN: usize = 40;
+pub const MIN_SECRET_LEN: usize = 16;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub fn new_secret() -> String {
+    format!("{}{}", SECRET_MARKER, random_string(SECRET_LEN))
+}
+
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC key of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
@@ -0,0 +1,134 @@
+use crate::config::config;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{Protocol, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::str::FromStr;
+use thiserror::Error as ThisError;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use warp::http::HeaderMap;
+
+const SERVICE_NAME: &str = "passenger_service";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Unknown log format '{}'", other)),
+        }
+    }
+}
+
+/// Keeps the OTLP exporter alive, `shutdown` flushes the spans not exported yet.
+pub struct Telemetry {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(tracer_provider) = self.tracer_provider {
+            if let Err(ex) = tracer_provider.shutdown() {
+                eprintln!("ERROR - OTLP exporter shutdown failed. Cause {:?}", ex);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber: logs to stdout (`log_format`), filtered by `log_level`,
+/// and spans to the OTLP collector when `otlp_endpoint` is set.
+pub fn init() -> Result<Telemetry, Error> {
+    let config = config();
+    let filter = EnvFilter::try_new(&config.log_level).map_err(|ex| Error::FailLogLevel(ex.to_string()))?;
+    let fmt_layer = match config.log_format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let tracer_provider = config.otlp_endpoint.as_deref().map(new_tracer_provider).transpose()?;
+    let otel_layer = tracer_provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .try_init()
+        .map_err(|ex| Error::FailInit(ex.to_string()))?;
+    Ok(Telemetry { tracer_provider })
+}
+
+/// Exports the spans to `<endpoint>/v1/traces`, with OTLP over HTTP/JSON.
+pub fn new_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, Error> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpJson)
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|ex| Error::FailOtlpExporter(ex.to_string()))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// Continues the trace of the caller (W3C `traceparent` header) when `trace_propagation` is on.
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    if !config().trace_propagation {
+        return;
+    }
+    // fails only without the OTLP layer, then there is no trace to continue
+    let _ = span.set_parent(remote_parent(headers));
+}
+
+fn remote_parent(headers: &HeaderMap) -> opentelemetry::Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// region:    Error
+#[allow(clippy::enum_variant_names)]
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Wrong log level - {0}")]
+    FailLogLevel(String),
+
+    #[error("Fail to init the OTLP exporter - {0}")]
+    FailOtlpExporter(String),
+
+    #[error("Fail to init tracing - {0}")]
+    FailInit(String),
+}
+// endregion: Error
+
+#[cfg(test)]
+#[path = "../_tests/telemetry.rs"]
+mod tests;
@@ -1,24 +1,80 @@
 use super::filter_utils::with_db;
 use crate::model::Db;
-use crate::security::{utx_from_token, UserCtx};
+use crate::security::{utx_from_token, Permission, UserCtx};
 use crate::web::Error;
+use serde::Deserialize;
 use std::sync::Arc;
 use warp::{Filter, Rejection};
 
 const HEADER_XAUTH: &str = "X-Auth-Token";
 
+/// Token of the clients that cannot set the `X-Auth-Token` header, e.g. the browser `EventSource`
+/// and `WebSocket`.
+#[derive(Debug, Default, Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+}
+
 pub fn do_auth(db: Arc<Db>) -> impl Filter<Extract = (UserCtx,), Error = warp::Rejection> + Clone {
     // warp::any().and_then(|| async { Ok::<UserCtx, warp::Rejection>(utx_from_token("123").await?) })
     warp::any()
         .and(with_db(db))
         .and(warp::header::optional(HEADER_XAUTH))
-        .and_then(|db:Arc<Db>, xauth: Option<String>| async move {
-            match xauth {
-                Some(xauth) => {
-                    let utx = utx_from_token(&db, &xauth).await?;
-                    Ok::<UserCtx, Rejection>(utx)
-                }
-                None => Err(Error::FailAuthMissingXAuth.into()),
-            }
-        })
+        .and_then(authenticate)
 }
+
+// Same as `do_auth`, the token may also be in the `access_token` query parameter.
+fn do_auth_or_query(db: Arc<Db>) -> impl Filter<Extract = (UserCtx,), Error = warp::Rejection> + Clone {
+    warp::any()
+        .and(with_db(db))
+        .and(token_or_query())
+        .and_then(authenticate)
+}
+
+/// The token of the `X-Auth-Token` header, or of the `access_token` query parameter, not checked.
+pub fn token_or_query() -> impl Filter<Extract = (Option<String>,), Error = warp::Rejection> + Clone {
+    let query = warp::query::<TokenQuery>()
+        .or(warp::any().map(TokenQuery::default))
+        .unify();
+    warp::header::optional(HEADER_XAUTH)
+        .and(query)
+        .map(|xauth: Option<String>, query: TokenQuery| xauth.or(query.access_token))
+}
+
+async fn authenticate(db: Arc<Db>, xauth: Option<String>) -> Result<UserCtx, Rejection> {
+    match xauth {
+        Some(xauth) => {
+            let utx = utx_from_token(&db, &xauth).await?;
+            tracing::Span::current().record("user_id", utx.user_id.as_str());
+            Ok::<UserCtx, Rejection>(utx)
+        }
+        None => Err(Error::FailAuthMissingXAuth.into()),
+    }
+}
+
+// Authenticates the request and checks the user holds the permission.
+pub fn require(
+    db: Arc<Db>,
+    permission: Permission,
+) -> impl Filter<Extract = (UserCtx,), Error = warp::Rejection> + Clone {
+    do_auth(db).and_then(move |utx: UserCtx| check_permission(utx, permission))
+}
+
+/// Same as `require`, for the streaming routes, where the token may be in the query.
+pub fn require_or_query(
+    db: Arc<Db>,
+    permission: Permission,
+) -> impl Filter<Extract = (UserCtx,), Error = warp::Rejection> + Clone {
+    do_auth_or_query(db).and_then(move |utx: UserCtx| check_permission(utx, permission))
+}
+
+async fn check_permission(utx: UserCtx, permission: Permission) -> Result<UserCtx, Rejection> {
+    match utx.has_permission(permission) {
+        true => Ok(utx),
+        false => Err(Error::FailAuthPermission(permission.as_str()).into()),
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_auth.rs"]
+mod tests;
@@ -0,0 +1,157 @@
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+use async_compression::Level;
+use futures::TryStreamExt;
+use std::convert::Infallible;
+use std::io;
+use tokio_util::io::{ReaderStream, StreamReader};
+use warp::http::header::{self, HeaderMap, HeaderValue};
+use warp::http::StatusCode;
+use warp::hyper::body::HttpBody;
+use warp::hyper::Body;
+use warp::reply::Response;
+use warp::Filter;
+
+// smaller bodies do not get much smaller
+const MIN_SIZE: u64 = 1024;
+// the default brotli quality (11) is too slow for on the fly compression
+const BROTLI_QUALITY: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// Compresses the responses of `filter` with brotli or gzip, as the client accepts.
+/// Does nothing when not `enabled`.
+pub fn with_compression<F>(enabled: bool, filter: F) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    warp::header::headers_cloned()
+        .and(filter)
+        .map(move |headers: HeaderMap, response: Response| {
+            let accept_encoding = headers
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok());
+            match enabled {
+                true => compress(accept_encoding, response),
+                false => response,
+            }
+        })
+}
+
+/// The preferred encoding of an `Accept-Encoding` header, brotli before gzip when equally weighted.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut any = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+    let brotli = brotli.or(any).unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    match (brotli, gzip) {
+        (brotli, gzip) if brotli > 0.0 && brotli >= gzip => Some(Encoding::Brotli),
+        (_, gzip) if gzip > 0.0 => Some(Encoding::Gzip),
+        _ => None,
+    }
+}
+
+fn compress(accept_encoding: Option<&str>, mut response: Response) -> Response {
+    if !is_compressible_type(&response) {
+        return response;
+    }
+    // the representation varies even when this one is not compressed
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    let encoding = match accept_encoding.and_then(negotiate) {
+        Some(encoding) if should_compress(&response) => encoding,
+        _ => return response,
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let reader = StreamReader::new(TryStreamExt::map_err(body, io::Error::other));
+    let body = match encoding {
+        Encoding::Brotli => Body::wrap_stream(ReaderStream::new(BrotliEncoder::with_quality(
+            reader,
+            Level::Precise(BROTLI_QUALITY),
+        ))),
+        Encoding::Gzip => Body::wrap_stream(ReaderStream::new(GzipEncoder::new(reader))),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+    Response::from_parts(parts, body)
+}
+
+// Text like content, the event streams are left alone so each event gets out as it comes.
+fn is_compressible_type(response: &Response) -> bool {
+    let content_type = match response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(content_type) => content_type.to_lowercase(),
+        None => return false,
+    };
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    match mime {
+        "text/event-stream" => false,
+        "image/svg+xml" | "application/wasm" => true,
+        _ => {
+            mime.starts_with("text/")
+                || mime.ends_with("/json")
+                || mime.ends_with("+json")
+                || mime.ends_with("/xml")
+                || mime.ends_with("+xml")
+                || mime.ends_with("/javascript")
+                || mime.ends_with("/yaml")
+        }
+    }
+}
+
+fn should_compress(response: &Response) -> bool {
+    let headers = response.headers();
+    let status = response.status();
+    // the in memory bodies know their size, the streamed ones (files) tell it in Content-Length
+    let length = response.body().size_hint().exact().or_else(|| {
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    });
+    let too_small = length.is_some_and(|length| length < MIN_SIZE);
+    !(too_small
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || status == StatusCode::PARTIAL_CONTENT
+        || headers.contains_key(header::CONTENT_ENCODING)
+        || headers.contains_key(header::CONTENT_RANGE))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_compression.rs"]
+mod tests;
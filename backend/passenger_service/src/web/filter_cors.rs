@@ -0,0 +1,159 @@
+use crate::config::config;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::http::header::{self, HeaderMap, HeaderValue};
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+// response headers the cross-origin scripts may read
+const EXPOSED_HEADERS: &str =
+    "X-Request-Id, RateLimit-Limit, RateLimit-Remaining, RateLimit-Reset, Retry-After, Idempotent-Replayed";
+
+/// Cross-origin settings. The requests from other origins get no CORS headers, so the
+/// browsers block them, the same-origin ones are not concerned.
+pub struct Cors {
+    any_origin: bool,
+    origins: HashSet<String>,
+    methods: HashSet<String>,
+    headers: HashSet<String>,
+    allow_credentials: bool,
+    allow_methods: HeaderValue,
+    allow_headers: HeaderValue,
+    max_age: HeaderValue,
+}
+
+impl Cors {
+    /// None when no origin is allowed.
+    pub fn from_config() -> Option<Arc<Cors>> {
+        let config = config();
+        Cors::new(
+            &config.cors_allowed_origins,
+            &config.cors_allowed_methods,
+            &config.cors_allowed_headers,
+            config.cors_allow_credentials,
+            config.cors_max_age,
+        )
+    }
+
+    /// `origins` may contain `*` for any origin. None when it is empty.
+    pub fn new(
+        origins: &[String],
+        methods: &[String],
+        headers: &[String],
+        allow_credentials: bool,
+        max_age: Duration,
+    ) -> Option<Arc<Cors>> {
+        if origins.is_empty() {
+            return None;
+        }
+        let methods: Vec<String> = methods.iter().map(|method| method.to_uppercase()).collect();
+        let headers: Vec<String> = headers.iter().map(|name| name.to_lowercase()).collect();
+        Some(Arc::new(Cors {
+            any_origin: origins.iter().any(|origin| origin == "*"),
+            origins: origins.iter().cloned().collect(),
+            allow_methods: header_value(&methods.join(", ")),
+            allow_headers: header_value(&headers.join(", ")),
+            methods: methods.into_iter().collect(),
+            headers: headers.into_iter().collect(),
+            allow_credentials,
+            max_age: HeaderValue::from(max_age.as_secs()),
+        }))
+    }
+
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        self.any_origin || self.origins.contains(origin)
+    }
+
+    // Answers the preflight (OPTIONS) request, 403 when the origin, method or headers are not allowed.
+    fn preflight(&self, origin: &str, method: &str, request_headers: Option<&str>) -> Response {
+        let headers_allowed = request_headers
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .all(|name| name.is_empty() || self.headers.contains(&name));
+        if !self.is_allowed_origin(origin) || !self.methods.contains(method) || !headers_allowed {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        self.add_origin_headers(origin, headers);
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, self.allow_methods.clone());
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, self.allow_headers.clone());
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, self.max_age.clone());
+        response
+    }
+
+    // Lets the browser give the response to an allowed origin.
+    fn add_headers(&self, origin: &str, headers: &mut HeaderMap) {
+        if self.is_allowed_origin(origin) {
+            self.add_origin_headers(origin, headers);
+            headers.insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static(EXPOSED_HEADERS),
+            );
+        }
+    }
+
+    fn add_origin_headers(&self, origin: &str, headers: &mut HeaderMap) {
+        let allow_origin = match self.any_origin && !self.allow_credentials {
+            true => HeaderValue::from_static("*"),
+            false => header_value(origin),
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+}
+
+/// Answers the CORS preflight requests and adds the CORS headers to the responses of `filter`.
+/// Does nothing when `cors` is None.
+pub fn with_cors<F>(
+    cors: Option<Arc<Cors>>,
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    let preflight_cors = cors.clone();
+    let preflight = warp::options()
+        .and(warp::header::<String>("origin"))
+        .and(warp::header::<String>("access-control-request-method"))
+        .and(warp::header::optional::<String>("access-control-request-headers"))
+        .and_then(move |origin: String, method: String, request_headers: Option<String>| {
+            let cors = preflight_cors.clone();
+            async move {
+                match cors {
+                    Some(cors) => Ok(cors.preflight(&origin, &method, request_headers.as_deref())),
+                    None => Err(warp::reject::not_found()),
+                }
+            }
+        });
+    let actual = warp::header::headers_cloned()
+        .and(filter)
+        .map(move |headers: HeaderMap, mut response: Response| {
+            let origin = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok());
+            if let (Some(cors), Some(origin)) = (&cors, origin) {
+                cors.add_headers(origin, response.headers_mut());
+            }
+            response
+        });
+
+    preflight.or(actual).unify()
+}
+
+// The config values are checked by the header parsing of the browsers, not here.
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_cors.rs"]
+mod tests;
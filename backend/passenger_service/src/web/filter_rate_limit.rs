@@ -0,0 +1,255 @@
+use super::filter_utils::with_db;
+use super::trace::ClientAddr;
+use crate::config::config;
+use crate::model::Db;
+use crate::security::{api_key, utx_from_token};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::{HeaderMap, HeaderValue, Method};
+use warp::{Filter, Rejection};
+
+const HEADER_XAUTH: &str = "X-Auth-Token";
+// Drop the idle buckets every so many calls, so the map does not grow forever.
+const SWEEP_EVERY: u64 = 1024;
+
+// region:    Quota
+/// Token bucket quota: `requests` per `period`, with bursts up to `requests`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub const fn new(requests: u32, secs: u64) -> Self {
+        Quota {
+            requests,
+            period: Duration::from_secs(secs),
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.requests as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// Parses `<requests>/<seconds>`, e.g. `300/60`.
+impl FromStr for Quota {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wrong_format = || format!("Wrong quota '{}', expected <requests>/<seconds>", s);
+        let (requests, secs) = s.split_once('/').ok_or_else(wrong_format)?;
+        let requests: u32 = requests.trim().parse().map_err(|_| wrong_format())?;
+        let secs: u64 = secs.trim().parse().map_err(|_| wrong_format())?;
+        if requests == 0 || secs == 0 {
+            return Err(wrong_format());
+        }
+        Ok(Quota::new(requests, secs))
+    }
+}
+// endregion: Quota
+
+// region:    Backend
+/// Outcome of taking a token, with what the `RateLimit-*` headers need.
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next token, when not allowed.
+    pub retry_after_secs: u64,
+}
+
+/// Where the buckets live. In memory by default, a shared store (e.g. Redis) would make the
+/// limits global across replicas.
+#[async_trait]
+pub trait RateLimitBackend: Send + Sync {
+    /// Takes a token from the bucket of the key, if there is one left.
+    async fn take(&self, key: &str, quota: &Quota) -> Decision;
+}
+
+#[derive(Default)]
+pub struct MemoryBackend {
+    buckets: Mutex<Buckets>,
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    calls: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket is full again, i.e. the same as no bucket.
+    full_at: Instant,
+}
+
+#[async_trait]
+impl RateLimitBackend for MemoryBackend {
+    async fn take(&self, key: &str, quota: &Quota) -> Decision {
+        let now = Instant::now();
+        let refill_per_sec = quota.refill_per_sec();
+        let capacity = quota.requests as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        buckets.calls += 1;
+        if buckets.calls.is_multiple_of(SWEEP_EVERY) {
+            buckets.by_key.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let bucket = buckets.by_key.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            full_at: now,
+        });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let reset = Duration::from_secs_f64((capacity - bucket.tokens) / refill_per_sec);
+        bucket.full_at = now + reset;
+        Decision {
+            allowed,
+            limit: quota.requests,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: reset.as_secs_f64().ceil() as u64,
+            retry_after_secs: ((1.0 - bucket.tokens).max(0.0) / refill_per_sec).ceil() as u64,
+        }
+    }
+}
+// endregion: Backend
+
+// region:    Filter
+pub struct RateLimiter {
+    backend: Box<dyn RateLimitBackend>,
+    read: Quota,
+    write: Quota,
+}
+
+impl RateLimiter {
+    pub fn new(backend: Box<dyn RateLimitBackend>, read: Quota, write: Quota) -> Self {
+        RateLimiter { backend, read, write }
+    }
+
+    /// In memory limiter with the quotas of the config, None when rate limiting is off.
+    pub fn from_config() -> Option<Self> {
+        let config = config();
+        config.rate_limit_enabled.then(|| {
+            Self::new(
+                Box::<MemoryBackend>::default(),
+                config.rate_limit_read,
+                config.rate_limit_write,
+            )
+        })
+    }
+}
+
+/// Rejects with `RateLimited` when the client is over its quota, otherwise extracts the
+/// decision so the reply can carry the `RateLimit-*` headers (see `with_rate_limit_headers`).
+/// Extracts None without a limiter.
+///
+/// Reads (GET, HEAD) and writes have their own quota and bucket. Clients are keyed by user or
+/// API key once their credential is verified, and by IP when anonymous or not verified, so a
+/// client changing a bogus credential on each request still spends the tokens of its IP.
+pub fn rate_limit(
+    db: Arc<Db>,
+    limiter: Option<Arc<RateLimiter>>,
+) -> impl Filter<Extract = (Option<Decision>,), Error = Rejection> + Clone {
+    warp::any()
+        .map(move || limiter.clone())
+        .and(with_db(db))
+        .and(warp::method())
+        .and(warp::header::optional::<String>(HEADER_XAUTH))
+        .and(client_addr())
+        .and_then(
+            |limiter: Option<Arc<RateLimiter>>,
+             db: Arc<Db>,
+             method: Method,
+             xauth: Option<String>,
+             remote: Option<SocketAddr>| async move {
+                let Some(limiter) = limiter else {
+                    return Ok(None);
+                };
+                let (group, quota) = match method {
+                    Method::GET | Method::HEAD => ("read", &limiter.read),
+                    _ => ("write", &limiter.write),
+                };
+                let key = format!("{}:{}", group, client_key(&db, xauth.as_deref(), remote).await);
+                let decision = limiter.backend.take(&key, quota).await;
+                match decision.allowed {
+                    true => Ok(Some(decision)),
+                    false => Err(warp::reject::custom(RateLimited(decision))),
+                }
+            },
+        )
+}
+
+pub fn with_rate_limit_headers(decision: Option<Decision>, reply: impl warp::Reply) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    if let Some(decision) = decision {
+        add_headers(response.headers_mut(), &decision);
+    }
+    response
+}
+
+#[derive(Debug)]
+pub struct RateLimited(pub Decision);
+impl warp::reject::Reject for RateLimited {}
+
+impl RateLimited {
+    pub fn add_headers(&self, headers: &mut HeaderMap) {
+        add_headers(headers, &self.0);
+        headers.insert("Retry-After", HeaderValue::from(self.0.retry_after_secs));
+    }
+}
+// endregion: Filter
+
+// region:    Utils
+fn client_addr() -> impl Filter<Extract = (Option<SocketAddr>,), Error = std::convert::Infallible> + Clone {
+    warp::ext::optional::<ClientAddr>()
+        .and(warp::addr::remote())
+        .map(|client_addr: Option<ClientAddr>, remote: Option<SocketAddr>| client_addr.map(|addr| addr.0).or(remote))
+}
+
+async fn client_key(db: &Db, xauth: Option<&str>, remote: Option<SocketAddr>) -> String {
+    let Some(xauth) = xauth else {
+        return ip_key(remote);
+    };
+    match utx_from_token(db, xauth).await {
+        Ok(utx) if api_key::ApiKeyParts::parse(xauth).is_some() => format!("apikey:{}", utx.user_id),
+        Ok(utx) => format!("user:{}", utx.user_id),
+        // forged, expired or revoked, counted with the other requests of the client
+        Err(_) => ip_key(remote),
+    }
+}
+
+fn ip_key(remote: Option<SocketAddr>) -> String {
+    match remote {
+        Some(remote) => format!("ip:{}", remote.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+fn add_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert("RateLimit-Limit", HeaderValue::from(decision.limit));
+    headers.insert("RateLimit-Remaining", HeaderValue::from(decision.remaining));
+    headers.insert("RateLimit-Reset", HeaderValue::from(decision.reset_secs));
+}
+// endregion: Utils
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_rate_limit.rs"]
+mod tests;
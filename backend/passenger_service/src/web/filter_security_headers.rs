@@ -0,0 +1,36 @@
+use crate::config::config;
+use std::time::Duration;
+use warp::http::header::{self, HeaderMap, HeaderValue};
+use warp::reply::with::WithHeaders;
+
+/// Adds the security headers of the config to all the responses.
+pub fn security_headers(tls: bool) -> WithHeaders {
+    let config = config();
+    let hsts_max_age = match tls {
+        true => config.security_hsts_max_age,
+        false => Duration::ZERO,
+    };
+    warp::reply::with::headers(headers(&config.security_csp, hsts_max_age))
+}
+
+// Strict-Transport-Security is sent only when `hsts_max_age` is not zero, no CSP when `csp` is empty.
+fn headers(csp: &str, hsts_max_age: Duration) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(csp) = HeaderValue::from_str(csp) {
+        if !csp.is_empty() {
+            headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+        }
+    }
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(header::REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    if !hsts_max_age.is_zero() {
+        let hsts = format!("max-age={}; includeSubDomains", hsts_max_age.as_secs());
+        headers.insert(header::STRICT_TRANSPORT_SECURITY, HeaderValue::from_str(&hsts).unwrap());
+    }
+    headers
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_security_headers.rs"]
+mod tests;
@@ -1,9 +1,68 @@
+use super::Error;
 use crate::model::Db;
+use futures::{Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
-use warp::Filter;
+use warp::{Buf, Filter, Rejection};
 
 // Just clones the Db Arc and returns it as a Filter. This will aloow to include the
 // Db in the filter chain.
 pub fn with_db(db: Arc<Db>) -> impl Filter<Extract = (Arc<Db>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || db.clone())
 }
+
+/// The JSON body, of at most `limit` bytes (413 above, also without `Content-Length`).
+/// A body that does not parse is a 400 with the JSON path and the reason (see `Error::FailJsonBody`).
+pub fn json_body<T>(limit: u64) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    warp::header::optional::<u64>("content-length")
+        .and(warp::body::stream())
+        .and_then(move |length: Option<u64>, body| async move {
+            if length.is_some_and(|length| length > limit) {
+                return Err(Error::FailBodyTooLarge(limit).into());
+            }
+            let bytes = read_body(body, limit).await?;
+            parse_json(&bytes).map_err(Rejection::from)
+        })
+}
+
+// Reads the chunks until the end, or until more than `limit` bytes.
+async fn read_body<S, B>(body: S, limit: u64) -> Result<Vec<u8>, Error>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    futures::pin_mut!(body);
+    let mut bytes = Vec::new();
+    while let Some(mut chunk) = body
+        .try_next()
+        .await
+        .map_err(|ex| Error::FailBodyRead(ex.to_string()))?
+    {
+        if (bytes.len() + chunk.remaining()) as u64 > limit {
+            return Err(Error::FailBodyTooLarge(limit));
+        }
+        while chunk.has_remaining() {
+            let part = chunk.chunk();
+            bytes.extend_from_slice(part);
+            let read = part.len();
+            chunk.advance(read);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Parses the JSON, the error tells where it failed, e.g. `[2].status`.
+pub fn parse_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(bytes);
+    serde_path_to_error::deserialize(deserializer).map_err(|ex| Error::FailJsonBody {
+        path: ex.path().to_string(),
+        reason: ex.inner().to_string(),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_filter_utils.rs"]
+mod tests;
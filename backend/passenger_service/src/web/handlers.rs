@@ -1,13 +1,18 @@
-use super::filter_auth::do_auth;
-use super::filter_utils::with_db;
+use super::filter_auth::require;
+use super::filter_utils::{json_body, with_db};
+use super::idempotency::{idempotency_key, idempotent};
 use crate::{
-    model::{Db, PassengerDao, PassengerPatch},
-    security::UserCtx,
+    config::config,
+    model::{
+        Db, DuplicateFilter, PassengerDao, PassengerFilter, PassengerMergeRequest, PassengerMrzRequest, PassengerPatch,
+        PassengerSearch,
+    },
+    security::{Permission, UserCtx},
 };
 use serde::Serialize;
 use serde_json::json;
 use std::sync::Arc;
-use warp::reply::Json;
+use warp::reply::{Json, Response};
 use warp::Filter;
 
 pub fn handlers(
@@ -15,41 +20,93 @@ pub fn handlers(
     db: Arc<Db>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     let passengers_path = warp::path(base_path).and(warp::path("passengers"));
-    // Each of our routes will have its own copy of the db Arc.
-    let common = with_db(db.clone()).and(do_auth(db.clone()));
+    // Each of our routes will have its own copy of the db Arc, and requires its own permission.
+    let common = |permission: Permission| with_db(db.clone()).and(require(db.clone(), permission));
 
     let list = passengers_path
         .and(warp::get())
         .and(warp::path::end())
-        .and(common.clone())
+        .and(common(Permission::PassengerRead))
+        .and(warp::query::<PassengerFilter>())
         .and_then(list_passengers);
 
+    let search = passengers_path
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(common(Permission::PassengerRead))
+        .and(warp::query::<PassengerSearch>())
+        .and_then(search_passengers);
+
+    let duplicates = passengers_path
+        .and(warp::path("duplicates"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(common(Permission::PassengerRead))
+        .and(warp::query::<DuplicateFilter>())
+        .and_then(passenger_duplicates);
+
+    let merge = passengers_path
+        .and(warp::path("merge"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(common(Permission::PassengerDelete))
+        .and(json_body(config().max_body_bytes))
+        .and_then(merge_passengers);
+
+    let from_mrz = passengers_path
+        .and(warp::path("from-mrz"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(common(Permission::PassengerWrite))
+        .and(json_body(config().max_body_bytes))
+        .and_then(create_passenger_from_mrz);
+
     let get = passengers_path
         .and(warp::get())
-        .and(common.clone())
+        .and(common(Permission::PassengerRead))
         .and(warp::path::param())
         .and_then(get_passenger);
 
     let create = passengers_path
         .and(warp::post())
-        .and(common.clone())
-        .and(warp::body::json())
+        .and(warp::path::end())
+        .and(common(Permission::PassengerWrite))
+        .and(idempotency_key())
+        .and(json_body(config().max_body_bytes))
         .and_then(create_passenger);
 
+    let create_bulk = passengers_path
+        .and(warp::path("bulk"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(common(Permission::PassengerWrite))
+        .and(idempotency_key())
+        .and(json_body(config().max_bulk_body_bytes))
+        .and_then(create_passengers_bulk);
+
     let update = passengers_path
         .and(warp::patch())
-        .and(common.clone())
+        .and(common(Permission::PassengerWrite))
         .and(warp::path::param())
-        .and(warp::body::json())
+        .and(json_body(config().max_body_bytes))
         .and_then(update_passenger);
 
     let delete = passengers_path
         .and(warp::delete())
-        .and(common.clone())
+        .and(common(Permission::PassengerDelete))
         .and(warp::path::param())
         .and_then(delete_passenger);
 
-    list.or(get).or(create).or(update).or(delete)
+    list.or(search)
+        .or(duplicates)
+        .or(merge)
+        .or(from_mrz)
+        .or(get)
+        .or(create)
+        .or(create_bulk)
+        .or(update)
+        .or(delete)
 }
 
 /// List passengers
@@ -60,18 +117,133 @@ pub fn handlers(
     path = "/api/passengers",
     params (
         ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        PassengerFilter,
     ),
     responses (
         (status = 200, description = "List of passengers", body = [Passenger]),
-    )
+        (status = 403, description = "Missing passenger:read permission"),
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
 )]
 // endregion: Swagger LIST passengers `GET /passengers`
-pub async fn list_passengers(db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+pub async fn list_passengers(db: Arc<Db>, utx: UserCtx, filter: PassengerFilter) -> Result<Json, warp::Rejection> {
     // FIXME: Add proper error handling
-    let passengers = PassengerDao::list(&db, &utx).await?;
+    let passengers = PassengerDao::list(&db, &utx, &filter).await?;
     json_response(passengers)
 }
 
+/// Search passengers
+///
+/// By first and last names, the most relevant first: whole words, then starts of words, then
+/// names close to the query words (typos). Case and accents are ignored.
+// region: Swagger SEARCH passengers `GET /passengers/search?q=`
+#[utoipa::path(
+    get,
+    path = "/api/passengers/search",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        PassengerSearch,
+    ),
+    responses (
+        (status = 200, description = "Matching passengers, with their score and highlights", body = [PassengerSearchHit]),
+        (status = 400, description = "Missing or invalid q, or invalid limit"),
+        (status = 403, description = "Missing passenger:read permission"),
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
+)]
+// endregion: Swagger SEARCH passengers `GET /passengers/search?q=`
+pub async fn search_passengers(db: Arc<Db>, utx: UserCtx, search: PassengerSearch) -> Result<Json, warp::Rejection> {
+    let hits = PassengerDao::search(&db, &utx, &search).await?;
+    json_response(hits)
+}
+
+/// Find duplicate passengers
+///
+/// Pairs of passengers that may be the same person, the most likely first. Names are compared
+/// without case nor accents, tolerating typos and swapped first and last names.
+// region: Swagger DUPLICATES passengers `GET /passengers/duplicates`
+#[utoipa::path(
+    get,
+    path = "/api/passengers/duplicates",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        DuplicateFilter,
+    ),
+    responses (
+        (status = 200, description = "Duplicate candidates, with their score and reasons", body = [PassengerDuplicate]),
+        (status = 400, description = "Invalid threshold or limit"),
+        (status = 403, description = "Missing passenger:read permission"),
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
+)]
+// endregion: Swagger DUPLICATES passengers `GET /passengers/duplicates`
+pub async fn passenger_duplicates(db: Arc<Db>, utx: UserCtx, filter: DuplicateFilter) -> Result<Json, warp::Rejection> {
+    let duplicates = PassengerDao::duplicates(&db, &utx, &filter).await?;
+    json_response(duplicates)
+}
+
+/// Merge passengers
+///
+/// Keeps one passenger (the survivor) and deletes the others, in one transaction. Each field of
+/// the survivor keeps its value unless it has none, then takes the one of the most recently
+/// updated passenger; the merge is recorded for audit.
+// region: Swagger MERGE passengers `POST /passengers/merge with body PassengerMergeRequest`
+#[utoipa::path(
+    post,
+    path = "/api/passengers/merge",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body=PassengerMergeRequest,
+    responses(
+        (status = 200, description = "Passengers merged", body = PassengerMerge),
+        (status = 400, description = "Invalid ids or survivor, or passengers of different tenants"),
+        (status = 403, description = "Missing passenger:delete permission"),
+        (status = 404, description = "Passenger not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:delete"]))
+)]
+// endregion: Swagger MERGE passengers `POST /passengers/merge with body PassengerMergeRequest`
+pub async fn merge_passengers(
+    db: Arc<Db>,
+    utx: UserCtx,
+    request: PassengerMergeRequest,
+) -> Result<Json, warp::Rejection> {
+    let merge = PassengerDao::merge(&db, &utx, &request).await?;
+    json_response(merge)
+}
+
+/// Create passenger from MRZ
+///
+/// Reads the machine readable zone of a passport, ID card or visa, and returns the passenger
+/// holding the document, else the one with the same names and date of birth (the document is
+/// then added to it), else a new passenger with the document.
+// region: Swagger CREATE passenger `POST /passengers/from-mrz with body PassengerMrzRequest`
+#[utoipa::path(
+    post,
+    path = "/api/passengers/from-mrz",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = PassengerMrzRequest,
+    responses(
+        (status = 200, description = "Passenger found or created, with the document", body = PassengerFromMrz),
+        (status = 400, description = "Not a TD1, TD2 or TD3 MRZ, or invalid field (date, country...)"),
+        (status = 403, description = "Missing passenger:write permission"),
+        (status = 422, description = "Check digits not matching, the fields in details.check_digits"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
+)]
+// endregion: Swagger CREATE passenger `POST /passengers/from-mrz with body PassengerMrzRequest`
+pub async fn create_passenger_from_mrz(
+    db: Arc<Db>,
+    utx: UserCtx,
+    request: PassengerMrzRequest,
+) -> Result<Json, warp::Rejection> {
+    let passenger = PassengerDao::from_mrz(&db, &utx, &request).await?;
+    json_response(passenger)
+}
+
 /// Get passenger
 ///
 // region: Swagger GET passenger `GET /passengers/100`
@@ -86,8 +258,10 @@ pub async fn list_passengers(db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Re
         (status = 200, description = "Delete successful", body = Passenger),
         (status = 400, description = "Missing Auth Token request header"),
         (status = 401, description = "Unauthorized to fetch a passenger"),
+        (status = 403, description = "Missing passenger:read permission"),
         (status = 404, description = "Passenger not found"),
-    )
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
 )]
 // endregion: Swagger GET passenger `GET /passengers/100`
 async fn get_passenger(db: Arc<Db>, utx: UserCtx, id: String) -> Result<Json, warp::Rejection> {
@@ -103,17 +277,74 @@ async fn get_passenger(db: Arc<Db>, utx: UserCtx, id: String) -> Result<Json, wa
     path = "/api/passengers",
     params (
         ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Key making retries safe, the response of the first request is replayed"),
     ),
     request_body=Passenger,
     responses(
         (status = 200, description = "Passenger created successfully", body = Passenger),
-        (status = 409, description = "Passenger already exists")
-    )
+        (status = 400, description = "Invalid JSON body, with the path and reason in details"),
+        (status = 403, description = "Missing passenger:write permission"),
+        (status = 409, description = "Passenger already exists, or Idempotency-Key request in progress"),
+        (status = 413, description = "Body larger than MAX_BODY_BYTES"),
+        (status = 422, description = "Idempotency-Key already used for another request"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
 )]
 // endregion: CREATE passenger `POST /passengers with body PassengerPatch`
-async fn create_passenger(db: Arc<Db>, utx: UserCtx, patch: PassengerPatch) -> Result<Json, warp::Rejection> {
-    let passenger = PassengerDao::create(&db, &utx, patch).await?;
-    json_response(passenger)
+async fn create_passenger(
+    db: Arc<Db>,
+    utx: UserCtx,
+    idempotency_key: Option<String>,
+    patch: PassengerPatch,
+) -> Result<Response, warp::Rejection> {
+    idempotent(&db, &utx, idempotency_key, "POST /passengers", &patch, || async {
+        let passenger = PassengerDao::create(&db, &utx, patch.clone()).await?;
+        json_response(passenger)
+    })
+    .await
+}
+
+/// Create passengers
+///
+/// Creates all the passengers, or none of them.
+// region: CREATE passengers `POST /passengers/bulk with body [PassengerPatch]`
+#[utoipa::path(
+    post,
+    path = "/api/passengers/bulk",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Key making retries safe, the response of the first request is replayed"),
+    ),
+    request_body=[Passenger],
+    responses(
+        (status = 200, description = "Passengers created successfully", body = [Passenger]),
+        (status = 400, description = "Invalid JSON body, with the path and reason in details"),
+        (status = 403, description = "Missing passenger:write permission"),
+        (status = 409, description = "Idempotency-Key request in progress"),
+        (status = 413, description = "Body larger than MAX_BULK_BODY_BYTES"),
+        (status = 422, description = "Idempotency-Key already used for another request"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
+)]
+// endregion: CREATE passengers `POST /passengers/bulk with body [PassengerPatch]`
+async fn create_passengers_bulk(
+    db: Arc<Db>,
+    utx: UserCtx,
+    idempotency_key: Option<String>,
+    patches: Vec<PassengerPatch>,
+) -> Result<Response, warp::Rejection> {
+    idempotent(
+        &db,
+        &utx,
+        idempotency_key,
+        "POST /passengers/bulk",
+        &patches,
+        || async {
+            let passengers = PassengerDao::create_bulk(&db, &utx, patches.clone()).await?;
+            json_response(passengers)
+        },
+    )
+    .await
 }
 
 /// Update passenger
@@ -129,10 +360,13 @@ async fn create_passenger(db: Arc<Db>, utx: UserCtx, patch: PassengerPatch) -> R
     request_body=Passenger,
     responses(
         (status = 200, description = "Passenger updated successfully", body = Passenger),
-        (status = 400, description = "Missing Auth Token request header"),
+        (status = 400, description = "Missing Auth Token request header, or invalid JSON body (unknown fields included)"),
         (status = 401, description = "Unauthorized to update a passenger"),
+        (status = 403, description = "Missing passenger:write permission"),
         (status = 404, description = "Passenger not found"),
-    )
+        (status = 413, description = "Body larger than MAX_BODY_BYTES"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
 )]
 // endregion: UPDATE passenger `PATCH /passengers/100 with body PassengerPatch`
 async fn update_passenger(
@@ -160,8 +394,10 @@ async fn update_passenger(
         (status = 200, description = "Delete successful", body = Passenger),
         (status = 400, description = "Missing Auth Token request header"),
         (status = 401, description = "Unauthorized to delete a passenger"),
+        (status = 403, description = "Missing passenger:delete permission"),
         (status = 404, description = "Passenger not found"),
-    )
+    ),
+    security(("X-Auth-Token" = ["passenger:delete"]))
 )]
 // endregion: DELETE passenger `DELETE /passengers/100`
 async fn delete_passenger(db: Arc<Db>, utx: UserCtx, id: String) -> Result<Json, warp::Rejection> {
@@ -170,7 +406,7 @@ async fn delete_passenger(db: Arc<Db>, utx: UserCtx, id: String) -> Result<Json,
 }
 
 // region: Utils
-fn json_response<D: Serialize>(data: D) -> Result<Json, warp::Rejection> {
+pub(super) fn json_response<D: Serialize>(data: D) -> Result<Json, warp::Rejection> {
     let response = json!({ "data": data });
     Ok(warp::reply::json(&response))
 }
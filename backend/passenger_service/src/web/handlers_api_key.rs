@@ -0,0 +1,120 @@
+use super::filter_auth::require;
+use super::filter_utils::{json_body, with_db};
+use super::handlers::json_response;
+use crate::{
+    config::config,
+    model::{ApiKeyDao, ApiKeyForCreate, Db},
+    security::{Permission, UserCtx},
+};
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let api_keys_path = warp::path(base_path)
+        .and(warp::path("admin"))
+        .and(warp::path("api-keys"));
+    // API keys are managed by admins only
+    let common = with_db(db.clone()).and(require(db, Permission::Admin));
+
+    let list = api_keys_path
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(list_api_keys);
+
+    let create = api_keys_path
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body(config().max_body_bytes))
+        .and_then(create_api_key);
+
+    let revoke = api_keys_path
+        .and(warp::delete())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and_then(revoke_api_key);
+
+    list.or(create).or(revoke)
+}
+
+/// List API keys
+///
+// region: Swagger LIST api keys `GET /admin/api-keys`
+#[utoipa::path(
+    get,
+    path = "/api/admin/api-keys",
+    tag = "Admin",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "List of API keys (without their secret)", body = [ApiKey]),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger LIST api keys `GET /admin/api-keys`
+pub async fn list_api_keys(db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let api_keys = ApiKeyDao::list(&db, &utx).await?;
+    json_response(api_keys)
+}
+
+/// Create API key
+///
+/// The returned token is shown only once, only its salted hash is stored.
+// region: Swagger CREATE api key `POST /admin/api-keys with body ApiKeyForCreate`
+#[utoipa::path(
+    post,
+    path = "/api/admin/api-keys",
+    tag = "Admin",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = ApiKeyForCreate,
+    responses (
+        (status = 200, description = "API key created successfully", body = ApiKeyCreated),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger CREATE api key `POST /admin/api-keys with body ApiKeyForCreate`
+pub async fn create_api_key(db: Arc<Db>, utx: UserCtx, data: ApiKeyForCreate) -> Result<Json, warp::Rejection> {
+    let api_key = ApiKeyDao::create(&db, &utx, data).await?;
+    json_response(api_key)
+}
+
+/// Revoke API key
+///
+// region: Swagger REVOKE api key `DELETE /admin/api-keys/{id}`
+#[utoipa::path(
+    delete,
+    path = "/api/admin/api-keys/{id}",
+    tag = "Admin",
+    params (
+        ("id" = String, Path, description = "API key's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "API key revoked", body = ApiKey),
+        (status = 403, description = "Missing admin permission"),
+        (status = 404, description = "API key not found or already revoked"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger REVOKE api key `DELETE /admin/api-keys/{id}`
+pub async fn revoke_api_key(db: Arc<Db>, utx: UserCtx, id: String) -> Result<Json, warp::Rejection> {
+    let api_key = ApiKeyDao::revoke(&db, &utx, id).await?;
+    json_response(api_key)
+}
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_tests/web_handlers_api_key.rs"]
+mod tests;
+
+// endregion: Tests
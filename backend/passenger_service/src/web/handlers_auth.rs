@@ -0,0 +1,182 @@
+use super::filter_auth::do_auth;
+use super::filter_utils::{json_body, with_db};
+use super::handlers::json_response;
+use crate::{
+    config::config,
+    model::Db,
+    security::{
+        auth::{self, LoginPayload, RefreshPayload},
+        UserCtx,
+    },
+};
+use sqlx::types::Uuid;
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let auth_path = warp::path(base_path).and(warp::path("auth"));
+
+    let login = auth_path
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(json_body(config().max_body_bytes))
+        .and_then(login);
+
+    let refresh = auth_path
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(json_body(config().max_body_bytes))
+        .and_then(refresh);
+
+    let logout = auth_path
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(do_auth(db.clone()))
+        .and_then(logout);
+
+    let sessions_path = auth_path.and(warp::path("sessions"));
+    let list_sessions = sessions_path
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(do_auth(db.clone()))
+        .and_then(list_sessions);
+
+    let revoke_session = sessions_path
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(do_auth(db))
+        .and_then(revoke_session);
+
+    login.or(refresh).or(logout).or(list_sessions).or(revoke_session)
+}
+
+/// Login
+///
+/// Checks the username and password, and returns new access and refresh tokens.
+// region: Swagger LOGIN `POST /auth/login with body LoginPayload`
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "Auth",
+    request_body = LoginPayload,
+    responses (
+        (status = 200, description = "Logged in", body = AuthTokens),
+        (status = 401, description = "Wrong username or password, or account locked"),
+    )
+)]
+// endregion: Swagger LOGIN `POST /auth/login with body LoginPayload`
+pub async fn login(db: Arc<Db>, payload: LoginPayload) -> Result<Json, warp::Rejection> {
+    let tokens = auth::login(&db, payload).await?;
+    json_response(tokens)
+}
+
+/// Refresh tokens
+///
+/// Exchanges a refresh token, which can be used only once, for new tokens.
+// region: Swagger REFRESH `POST /auth/refresh with body RefreshPayload`
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    tag = "Auth",
+    request_body = RefreshPayload,
+    responses (
+        (status = 200, description = "New tokens", body = AuthTokens),
+        (status = 401, description = "Invalid, expired or revoked refresh token"),
+    )
+)]
+// endregion: Swagger REFRESH `POST /auth/refresh with body RefreshPayload`
+pub async fn refresh(db: Arc<Db>, payload: RefreshPayload) -> Result<Json, warp::Rejection> {
+    let tokens = auth::refresh(&db, payload).await?;
+    json_response(tokens)
+}
+
+/// Logout
+///
+/// Revokes the session of the access token, its refresh token can not be used anymore.
+// region: Swagger LOGOUT `POST /auth/logout`
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "Auth",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Revoked session, null without session", body = Option<UserSession>),
+        (status = 401, description = "Invalid token"),
+    ),
+    security(("X-Auth-Token" = []))
+)]
+// endregion: Swagger LOGOUT `POST /auth/logout`
+pub async fn logout(db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let session = auth::logout(&db, &utx).await?;
+    json_response(session)
+}
+
+/// List sessions
+///
+/// Lists the active sessions of the authenticated user.
+// region: Swagger LIST sessions `GET /auth/sessions`
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    tag = "Auth",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Active sessions, newest first", body = [UserSession]),
+        (status = 401, description = "Invalid token"),
+    ),
+    security(("X-Auth-Token" = []))
+)]
+// endregion: Swagger LIST sessions `GET /auth/sessions`
+pub async fn list_sessions(db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let sessions = auth::list_sessions(&db, &utx).await?;
+    json_response(sessions)
+}
+
+/// Revoke session
+///
+/// Revokes a session of the authenticated user: its refresh token and access tokens are rejected.
+// region: Swagger REVOKE session `DELETE /auth/sessions/{id}`
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions/{id}",
+    tag = "Auth",
+    params (
+        ("id" = String, Path, description = "Session id"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Revoked session", body = UserSession),
+        (status = 401, description = "Invalid token"),
+        (status = 404, description = "Session not found, or already revoked"),
+    ),
+    security(("X-Auth-Token" = []))
+)]
+// endregion: Swagger REVOKE session `DELETE /auth/sessions/{id}`
+pub async fn revoke_session(id: Uuid, db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let session = auth::revoke_session(&db, &utx, id).await?;
+    json_response(session)
+}
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_tests/web_handlers_auth.rs"]
+mod tests;
+
+// endregion: Tests
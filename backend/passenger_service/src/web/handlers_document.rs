@@ -0,0 +1,218 @@
+use super::filter_auth::require;
+use super::filter_utils::{json_body, with_db};
+use super::handlers::json_response;
+use crate::{
+    config::config,
+    model::{Db, PassengerDocumentDao, PassengerDocumentForCreate, PassengerDocumentPatch},
+    security::{Permission, UserCtx},
+};
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+/// The travel documents of a passenger, `/passengers/{id}/documents`. Chained before the
+/// passenger routes, which match any path below `/passengers/{id}`.
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let documents_path = warp::path(base_path)
+        .and(warp::path("passengers"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("documents"));
+    let common = |permission: Permission| with_db(db.clone()).and(require(db.clone(), permission));
+
+    let list = documents_path
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(common(Permission::PassengerRead))
+        .and_then(list_documents);
+
+    let create = documents_path
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(common(Permission::PassengerWrite))
+        .and(json_body(config().max_body_bytes))
+        .and_then(create_document);
+
+    let get = documents_path
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(common(Permission::PassengerRead))
+        .and_then(get_document);
+
+    let update = documents_path
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::patch())
+        .and(common(Permission::PassengerWrite))
+        .and(json_body(config().max_body_bytes))
+        .and_then(update_document);
+
+    let delete = documents_path
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(common(Permission::PassengerDelete))
+        .and_then(delete_document);
+
+    list.or(create).or(get).or(update).or(delete)
+}
+
+/// List passenger documents
+///
+/// With their `expired` or `expires_soon` warnings.
+// region: Swagger LIST documents `GET /passengers/{id}/documents`
+#[utoipa::path(
+    get,
+    path = "/api/passengers/{id}/documents",
+    tag = "Passengers",
+    params (
+        ("id" = String, Path, description = "Passenger's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Documents of the passenger", body = [PassengerDocument]),
+        (status = 403, description = "Missing passenger:read permission"),
+        (status = 404, description = "Passenger not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
+)]
+// endregion: Swagger LIST documents `GET /passengers/{id}/documents`
+pub async fn list_documents(passenger_id: String, db: Arc<Db>, utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let documents = PassengerDocumentDao::list(&db, &utx, passenger_id).await?;
+    json_response(documents)
+}
+
+/// Create passenger document
+///
+// region: Swagger CREATE document `POST /passengers/{id}/documents with body PassengerDocumentForCreate`
+#[utoipa::path(
+    post,
+    path = "/api/passengers/{id}/documents",
+    tag = "Passengers",
+    params (
+        ("id" = String, Path, description = "Passenger's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = PassengerDocumentForCreate,
+    responses (
+        (status = 200, description = "Document created successfully", body = PassengerDocument),
+        (status = 400, description = "Invalid type, number or issuing country, or document already held by the passenger"),
+        (status = 403, description = "Missing passenger:write permission"),
+        (status = 404, description = "Passenger not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
+)]
+// endregion: Swagger CREATE document `POST /passengers/{id}/documents with body PassengerDocumentForCreate`
+pub async fn create_document(
+    passenger_id: String,
+    db: Arc<Db>,
+    utx: UserCtx,
+    data: PassengerDocumentForCreate,
+) -> Result<Json, warp::Rejection> {
+    let document = PassengerDocumentDao::create(&db, &utx, passenger_id, data).await?;
+    json_response(document)
+}
+
+/// Get passenger document
+///
+// region: Swagger GET document `GET /passengers/{id}/documents/{doc_id}`
+#[utoipa::path(
+    get,
+    path = "/api/passengers/{id}/documents/{doc_id}",
+    tag = "Passengers",
+    params (
+        ("id" = String, Path, description = "Passenger's UUID"),
+        ("doc_id" = String, Path, description = "Document's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Document", body = PassengerDocument),
+        (status = 403, description = "Missing passenger:read permission"),
+        (status = 404, description = "Passenger or document not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:read"]))
+)]
+// endregion: Swagger GET document `GET /passengers/{id}/documents/{doc_id}`
+pub async fn get_document(
+    passenger_id: String,
+    id: String,
+    db: Arc<Db>,
+    utx: UserCtx,
+) -> Result<Json, warp::Rejection> {
+    let document = PassengerDocumentDao::get(&db, &utx, passenger_id, id).await?;
+    json_response(document)
+}
+
+/// Update passenger document
+///
+/// Only the fields present are changed.
+// region: Swagger UPDATE document `PATCH /passengers/{id}/documents/{doc_id} with body PassengerDocumentPatch`
+#[utoipa::path(
+    patch,
+    path = "/api/passengers/{id}/documents/{doc_id}",
+    tag = "Passengers",
+    params (
+        ("id" = String, Path, description = "Passenger's UUID"),
+        ("doc_id" = String, Path, description = "Document's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = PassengerDocumentPatch,
+    responses (
+        (status = 200, description = "Document updated successfully", body = PassengerDocument),
+        (status = 400, description = "Invalid type, number or issuing country, or document already held by the passenger"),
+        (status = 403, description = "Missing passenger:write permission"),
+        (status = 404, description = "Passenger or document not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:write"]))
+)]
+// endregion: Swagger UPDATE document `PATCH /passengers/{id}/documents/{doc_id} with body PassengerDocumentPatch`
+pub async fn update_document(
+    passenger_id: String,
+    id: String,
+    db: Arc<Db>,
+    utx: UserCtx,
+    data: PassengerDocumentPatch,
+) -> Result<Json, warp::Rejection> {
+    let document = PassengerDocumentDao::update(&db, &utx, passenger_id, id, data).await?;
+    json_response(document)
+}
+
+/// Delete passenger document
+///
+// region: Swagger DELETE document `DELETE /passengers/{id}/documents/{doc_id}`
+#[utoipa::path(
+    delete,
+    path = "/api/passengers/{id}/documents/{doc_id}",
+    tag = "Passengers",
+    params (
+        ("id" = String, Path, description = "Passenger's UUID"),
+        ("doc_id" = String, Path, description = "Document's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Delete successful", body = PassengerDocument),
+        (status = 403, description = "Missing passenger:delete permission"),
+        (status = 404, description = "Passenger or document not found"),
+    ),
+    security(("X-Auth-Token" = ["passenger:delete"]))
+)]
+// endregion: Swagger DELETE document `DELETE /passengers/{id}/documents/{doc_id}`
+pub async fn delete_document(
+    passenger_id: String,
+    id: String,
+    db: Arc<Db>,
+    utx: UserCtx,
+) -> Result<Json, warp::Rejection> {
+    let document = PassengerDocumentDao::delete(&db, &utx, passenger_id, id).await?;
+    json_response(document)
+}
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_tests/web_handlers_document.rs"]
+mod tests;
+
+// endregion: Tests
@@ -0,0 +1,161 @@
+use super::filter_auth::{require_or_query, token_or_query};
+use super::handlers_health::shutting_down;
+use crate::config::config;
+use crate::model::{passenger_events, Db, PassengerEvent, PassengerEvents, Replay};
+use crate::security::{utx_from_token, Permission, UserCtx};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use warp::sse::Event;
+use warp::Filter;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path(base_path)
+        .and(warp::path("passengers"))
+        .and(warp::path("events"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(require_or_query(db.clone(), Permission::PassengerRead))
+        .and(token_or_query())
+        .and(warp::header::optional::<String>("last-event-id"))
+        .map(
+            move |utx: UserCtx, token: Option<String>, last_event_id: Option<String>| {
+                // a malformed id is not where the client was, it gets a reset
+                let last_event_id = last_event_id.map(|id| id.trim().parse::<u64>().unwrap_or_default());
+                passenger_events_stream(db.clone(), token, utx, last_event_id)
+            },
+        )
+}
+
+/// Passenger change feed
+///
+/// Server-Sent Events of the passenger changes: `created`, `updated` or `deleted`, with the
+/// passenger as data. Resumes after the `Last-Event-ID`, or sends a `reset` event when the
+/// missed events are no longer kept, the client then reloads the passengers. The stream ends when
+/// the token expires or is revoked, the client then reconnects with a fresh one.
+///
+// region: Swagger passenger events `GET /passengers/events`
+#[utoipa::path(
+    get,
+    path = "/api/passengers/events",
+    params (
+        ("X-Auth-Token" = Option<String>, Header, description = "Authentication token"),
+        ("access_token" = Option<String>, Query, description = "Authentication token, for the EventSource clients"),
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last event received"),
+    ),
+    responses (
+        (status = 200, description = "Stream of the passenger events", content_type = "text/event-stream"),
+        (status = 400, description = "Missing Auth Token"),
+        (status = 401, description = "Unauthorized to read the passengers"),
+        (status = 403, description = "Missing passenger:read permission"),
+    ),
+    tag = "Passengers"
+)]
+// endregion: Swagger passenger events `GET /passengers/events`
+pub fn passenger_events_stream(
+    db: Arc<Db>,
+    token: Option<String>,
+    utx: UserCtx,
+    last_event_id: Option<u64>,
+) -> impl warp::Reply {
+    let expires_at = utx.expires_at;
+    let end = async move {
+        tokio::select! {
+            _ = shutting_down() => {}
+            _ = token_ended(db, token, expires_at, KEEP_ALIVE) => {}
+        }
+    };
+    let events = events(passenger_events(), utx, last_event_id).take_until(end);
+    warp::sse::reply(warp::sse::keep_alive().interval(KEEP_ALIVE).stream(events))
+}
+
+// Resolves when the token expires, or, checked every `interval` as the WebSocket heartbeat does,
+// is no longer valid (logout, revoked session or API key).
+async fn token_ended(db: Arc<Db>, token: Option<String>, expires_at: Option<DateTime<Utc>>, interval: Duration) {
+    let expired = async {
+        match expires_at {
+            Some(expires_at) => tokio::time::sleep((expires_at - Utc::now()).to_std().unwrap_or_default()).await,
+            None => future::pending().await,
+        }
+    };
+    let invalid = async {
+        let mut check = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            check.tick().await;
+            match &token {
+                Some(token) if utx_from_token(&db, token).await.is_ok() => {}
+                _ => return,
+            }
+        }
+    };
+    tokio::select! {
+        _ = expired => {}
+        _ = invalid => {}
+    }
+}
+
+enum Item {
+    Event(Arc<PassengerEvent>),
+    /// Events were missed, with the id to resume from, when known.
+    Reset(Option<u64>),
+}
+
+// The missed events then the new ones, of the passengers in the scope of the user.
+fn events(
+    hub: &PassengerEvents,
+    utx: UserCtx,
+    last_event_id: Option<u64>,
+) -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
+    let (replay, receiver) = hub.subscribe(last_event_id);
+    let replay: Vec<Item> = match replay {
+        Replay::Events(events) => events.into_iter().map(Item::Event).collect(),
+        Replay::Lost { last_id } => vec![Item::Reset(Some(last_id))],
+    };
+    stream::iter(replay).chain(live(receiver)).filter_map(move |item| {
+        let event = match item {
+            Item::Event(event) if config().passenger_scope.allows(&utx, &event.passenger) => Some(sse_event(&event)),
+            Item::Event(_) => None,
+            Item::Reset(last_id) => Some(reset_event(last_id)),
+        };
+        future::ready(event.map(Ok))
+    })
+}
+
+// The events as they are published, a reset when some were missed (too slow a client).
+fn live(receiver: broadcast::Receiver<Arc<PassengerEvent>>) -> impl Stream<Item = Item> {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(event) => Some((Item::Event(event), receiver)),
+            Err(RecvError::Lagged(_)) => Some((Item::Reset(None), receiver)),
+            Err(RecvError::Closed) => None,
+        }
+    })
+}
+
+fn sse_event(event: &PassengerEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .event(event.kind.as_str())
+        .json_data(&event.passenger)
+        .unwrap_or_else(|_| reset_event(None))
+}
+
+fn reset_event(last_id: Option<u64>) -> Event {
+    let event = Event::default().event("reset").data("");
+    match last_id {
+        Some(last_id) => event.id(last_id.to_string()),
+        None => event,
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_handlers_events.rs"]
+mod tests;
@@ -0,0 +1,135 @@
+use super::filter_utils::with_db;
+use crate::config::config;
+use crate::model::{migration_state, Db};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Notify;
+use warp::http::StatusCode;
+use warp::Filter;
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN: Notify = Notify::const_new();
+
+/// Makes `/readyz` not ready, so the load balancer stops sending requests while draining.
+pub fn set_shutting_down() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    SHUTDOWN.notify_waiters();
+}
+
+/// Completes once shutting down, so the endless responses (event streams) end and let the
+/// connections drain.
+pub async fn shutting_down() {
+    let notified = SHUTDOWN.notified();
+    if !SHUTTING_DOWN.load(Ordering::SeqCst) {
+        notified.await;
+    }
+}
+
+pub fn handlers(db: Arc<Db>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let healthz = warp::path("healthz")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(healthz);
+    let readyz = warp::path("readyz")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_db(db))
+        .then(readyz);
+
+    healthz.or(readyz)
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    ok: bool,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl Check {
+    fn new(started_at: Instant) -> Self {
+        Check {
+            ok: true,
+            latency_ms: started_at.elapsed().as_secs_f64() * 1000.0,
+            error: None,
+            details: None,
+        }
+    }
+
+    fn fail(mut self, error: impl Into<String>) -> Self {
+        self.ok = false;
+        self.error = Some(error.into());
+        self
+    }
+
+    fn details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+// The process is alive, it does not check the dependencies.
+fn healthz() -> impl warp::Reply {
+    let checks = json!({ "process": Check::new(Instant::now()) });
+    warp::reply::json(&json!({ "status": "ok", "checks": checks }))
+}
+
+// Ready when the database answers in time, the schema is there and not shutting down. 503 otherwise.
+async fn readyz(db: Arc<Db>) -> impl warp::Reply {
+    let checks = [
+        ("database", check_database(&db).await),
+        ("migrations", check_migrations()),
+        ("shutdown", check_shutdown()),
+    ];
+    let ready = checks.iter().all(|(_, check)| check.ok);
+    let (status, code) = match ready {
+        true => ("ready", StatusCode::OK),
+        false => ("not_ready", StatusCode::SERVICE_UNAVAILABLE),
+    };
+    let checks: serde_json::Map<String, Value> = checks
+        .into_iter()
+        .map(|(name, check)| (name.to_string(), json!(check)))
+        .collect();
+    warp::reply::with_status(warp::reply::json(&json!({ "status": status, "checks": checks })), code)
+}
+
+async fn check_database(db: &Db) -> Check {
+    let started_at = Instant::now();
+    let timeout = config().readiness_db_timeout;
+    let result = tokio::time::timeout(timeout, sqlx::query("SELECT 1").execute(db)).await;
+    let check = Check::new(started_at);
+    match result {
+        Ok(Ok(_)) => check,
+        Ok(Err(ex)) => check.fail(ex.to_string()),
+        Err(_) => check.fail(format!("no answer within {} ms", timeout.as_millis())),
+    }
+}
+
+fn check_migrations() -> Check {
+    let check = Check::new(Instant::now());
+    match migration_state() {
+        None => check.fail("not run"),
+        Some(state) if state.failed_statements > 0 => check
+            .fail(format!("{} failed statements", state.failed_statements))
+            .details(json!(state)),
+        Some(state) => check.details(json!(state)),
+    }
+}
+
+fn check_shutdown() -> Check {
+    let check = Check::new(Instant::now());
+    match SHUTTING_DOWN.load(Ordering::SeqCst) {
+        true => check.fail("shutting down"),
+        false => check,
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_handlers_health.rs"]
+mod tests;
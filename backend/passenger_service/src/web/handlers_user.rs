@@ -0,0 +1,50 @@
+use super::filter_auth::require;
+use super::filter_utils::{json_body, with_db};
+use super::handlers::json_response;
+use crate::{
+    config::config,
+    model::{Db, UserDao, UserForCreate},
+    security::{Permission, UserCtx},
+};
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let users_path = warp::path(base_path).and(warp::path("admin")).and(warp::path("users"));
+    // Users are managed by admins only
+    let common = with_db(db.clone()).and(require(db, Permission::Admin));
+
+    users_path
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common)
+        .and(json_body(config().max_body_bytes))
+        .and_then(create_user)
+}
+
+/// Create user
+///
+// region: Swagger CREATE user `POST /admin/users with body UserForCreate`
+#[utoipa::path(
+    post,
+    path = "/api/admin/users",
+    tag = "Admin",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = UserForCreate,
+    responses (
+        (status = 200, description = "User created successfully", body = User),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger CREATE user `POST /admin/users with body UserForCreate`
+pub async fn create_user(db: Arc<Db>, utx: UserCtx, data: UserForCreate) -> Result<Json, warp::Rejection> {
+    let user = UserDao::create(&db, &utx, data).await?;
+    json_response(user)
+}
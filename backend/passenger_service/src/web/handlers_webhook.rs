@@ -0,0 +1,279 @@
+use super::filter_auth::require;
+use super::filter_utils::{json_body, with_db};
+use super::handlers::json_response;
+use crate::{
+    config::config,
+    model::{Db, WebhookDao, WebhookDeliveryDao, WebhookDeliveryFilter, WebhookForCreate, WebhookPatch},
+    security::{Permission, UserCtx},
+};
+use std::sync::Arc;
+use warp::reply::Json;
+use warp::Filter;
+
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let webhooks_path = warp::path(base_path).and(warp::path("webhooks"));
+    // webhooks are managed by admins only
+    let common = with_db(db.clone()).and(require(db, Permission::Admin));
+
+    let list = webhooks_path
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(list_webhooks);
+
+    let create = webhooks_path
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body(config().max_body_bytes))
+        .and_then(create_webhook);
+
+    let get = webhooks_path
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(get_webhook);
+
+    let update = webhooks_path
+        .and(warp::patch())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(json_body(config().max_body_bytes))
+        .and_then(update_webhook);
+
+    let delete = webhooks_path
+        .and(warp::delete())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(delete_webhook);
+
+    let list_deliveries = webhooks_path
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path("deliveries"))
+        .and(warp::path::end())
+        .and(warp::query::<WebhookDeliveryFilter>())
+        .and_then(list_webhook_deliveries);
+
+    let retry_delivery = webhooks_path
+        .and(warp::post())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path("deliveries"))
+        .and(warp::path::param())
+        .and(warp::path("retry"))
+        .and(warp::path::end())
+        .and_then(retry_webhook_delivery);
+
+    list.or(create)
+        .or(get)
+        .or(update)
+        .or(delete)
+        .or(list_deliveries)
+        .or(retry_delivery)
+}
+
+/// List webhooks
+///
+// region: Swagger LIST webhooks `GET /webhooks`
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    tag = "Webhooks",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "List of webhooks (without their secret)", body = [Webhook]),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger LIST webhooks `GET /webhooks`
+pub async fn list_webhooks(db: Arc<Db>, _utx: UserCtx) -> Result<Json, warp::Rejection> {
+    let webhooks = WebhookDao::list(&db).await?;
+    json_response(webhooks)
+}
+
+/// Create webhook
+///
+/// The passenger events are POSTed to the URL, signed with the returned secret, which is
+/// shown only once. See `X-Webhook-Signature` in the README.
+// region: Swagger CREATE webhook `POST /webhooks with body WebhookForCreate`
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    tag = "Webhooks",
+    params (
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = WebhookForCreate,
+    responses (
+        (status = 200, description = "Webhook created successfully", body = WebhookCreated),
+        (status = 400, description = "Invalid URL, event type or secret"),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger CREATE webhook `POST /webhooks with body WebhookForCreate`
+pub async fn create_webhook(db: Arc<Db>, utx: UserCtx, data: WebhookForCreate) -> Result<Json, warp::Rejection> {
+    let webhook = WebhookDao::create(&db, &utx, data).await?;
+    json_response(webhook)
+}
+
+/// Get webhook
+///
+// region: Swagger GET webhook `GET /webhooks/{id}`
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}",
+    tag = "Webhooks",
+    params (
+        ("id" = String, Path, description = "Webhook's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Webhook found", body = Webhook),
+        (status = 403, description = "Missing admin permission"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger GET webhook `GET /webhooks/{id}`
+pub async fn get_webhook(db: Arc<Db>, _utx: UserCtx, id: String) -> Result<Json, warp::Rejection> {
+    let webhook = WebhookDao::get(&db, id).await?;
+    json_response(webhook)
+}
+
+/// Update webhook
+///
+/// `active: false` pauses the deliveries, they resume once active again.
+// region: Swagger UPDATE webhook `PATCH /webhooks/{id} with body WebhookPatch`
+#[utoipa::path(
+    patch,
+    path = "/api/webhooks/{id}",
+    tag = "Webhooks",
+    params (
+        ("id" = String, Path, description = "Webhook's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    request_body = WebhookPatch,
+    responses (
+        (status = 200, description = "Webhook updated successfully", body = Webhook),
+        (status = 400, description = "Invalid URL or event type"),
+        (status = 403, description = "Missing admin permission"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger UPDATE webhook `PATCH /webhooks/{id} with body WebhookPatch`
+pub async fn update_webhook(
+    db: Arc<Db>,
+    _utx: UserCtx,
+    id: String,
+    data: WebhookPatch,
+) -> Result<Json, warp::Rejection> {
+    let webhook = WebhookDao::update(&db, id, data).await?;
+    json_response(webhook)
+}
+
+/// Delete webhook
+///
+/// Its deliveries, sent or not, are deleted too.
+// region: Swagger DELETE webhook `DELETE /webhooks/{id}`
+#[utoipa::path(
+    delete,
+    path = "/api/webhooks/{id}",
+    tag = "Webhooks",
+    params (
+        ("id" = String, Path, description = "Webhook's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Webhook deleted", body = Webhook),
+        (status = 403, description = "Missing admin permission"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger DELETE webhook `DELETE /webhooks/{id}`
+pub async fn delete_webhook(db: Arc<Db>, _utx: UserCtx, id: String) -> Result<Json, warp::Rejection> {
+    let webhook = WebhookDao::delete(&db, id).await?;
+    json_response(webhook)
+}
+
+/// List webhook deliveries
+///
+/// The delivery log, newest first, `status=dead` for the dead letters.
+// region: Swagger LIST webhook deliveries `GET /webhooks/{id}/deliveries`
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries",
+    tag = "Webhooks",
+    params (
+        ("id" = String, Path, description = "Webhook's UUID"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+        WebhookDeliveryFilter,
+    ),
+    responses (
+        (status = 200, description = "List of deliveries", body = [WebhookDelivery]),
+        (status = 403, description = "Missing admin permission"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger LIST webhook deliveries `GET /webhooks/{id}/deliveries`
+pub async fn list_webhook_deliveries(
+    db: Arc<Db>,
+    _utx: UserCtx,
+    id: String,
+    filter: WebhookDeliveryFilter,
+) -> Result<Json, warp::Rejection> {
+    let deliveries = WebhookDeliveryDao::list(&db, id, filter.status).await?;
+    json_response(deliveries)
+}
+
+/// Retry webhook delivery
+///
+/// Sends the delivery again now, e.g. a dead one once the receiver is fixed. The attempts made
+/// are kept, a dead delivery gets another `WEBHOOK_MAX_ATTEMPTS` ones.
+// region: Swagger RETRY webhook delivery `POST /webhooks/{id}/deliveries/{delivery_id}/retry`
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/{id}/deliveries/{delivery_id}/retry",
+    tag = "Webhooks",
+    params (
+        ("id" = String, Path, description = "Webhook's UUID"),
+        ("delivery_id" = i64, Path, description = "Delivery's id"),
+        ("X-Auth-Token" = String, Header, description = "Authentication token"),
+    ),
+    responses (
+        (status = 200, description = "Delivery pending again", body = WebhookDelivery),
+        (status = 403, description = "Missing admin permission"),
+        (status = 404, description = "Delivery not found"),
+    ),
+    security(("X-Auth-Token" = ["admin"]))
+)]
+// endregion: Swagger RETRY webhook delivery `POST /webhooks/{id}/deliveries/{delivery_id}/retry`
+pub async fn retry_webhook_delivery(
+    db: Arc<Db>,
+    _utx: UserCtx,
+    id: String,
+    delivery_id: i64,
+) -> Result<Json, warp::Rejection> {
+    let delivery = WebhookDeliveryDao::retry(&db, id, delivery_id).await?;
+    json_response(delivery)
+}
+
+// region:    Tests
+#[cfg(test)]
+#[path = "../_tests/web_handlers_webhook.rs"]
+mod tests;
+
+// endregion: Tests
@@ -0,0 +1,486 @@
+use super::filter_auth::token_or_query;
+use super::filter_utils::with_db;
+use super::handlers_health::shutting_down;
+use crate::config::config;
+use crate::model::{
+    self, passenger_events, Db, Passenger, PassengerDao, PassengerEvent, PassengerFilter, PassengerPatch,
+};
+use crate::security::{self, utx_from_token, Permission, UserCtx};
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Semaphore};
+use tracing::Instrument;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::Filter;
+
+// WebSocket close codes (RFC 6455)
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// `GET /api/ws`, live passenger events and JSON-RPC 2.0 commands over a WebSocket.
+///
+/// The client authenticates with the `X-Auth-Token` header or the `access_token` query
+/// parameter, or with an `auth` request first. Then:
+/// - `subscribe` `{"status"?, "id"?}` gives a subscription id, and `passenger.event`
+///   notifications for the matching passengers. A `passenger.reset` notification tells some
+///   were dropped (too slow a client), the client reloads the passengers.
+/// - `unsubscribe` `{"subscription"}`
+/// - `passenger.list` (same params as the list query), `passenger.get` `{"id"}`,
+///   `passenger.create` (a patch), `passenger.update` `{"id", "data"}`, `passenger.delete` `{"id"}`
+/// - `ping`, answered `"pong"`
+///
+/// The server pings every `WS_PING_INTERVAL_SECS` and closes the silent connections.
+pub fn handlers(
+    base_path: &'static str,
+    db: Arc<Db>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path(base_path)
+        .and(warp::path("ws"))
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(token_or_query())
+        .and(with_db(db))
+        .map(|ws: Ws, token: Option<String>, db: Arc<Db>| {
+            ws.max_message_size(config().max_body_bytes as usize)
+                .on_upgrade(move |socket| connection(socket, db, token))
+        })
+}
+
+// region:    JSON-RPC
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    /// None for a notification, which gets no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: &str) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    fn parse_error() -> Self {
+        RpcError::new(-32700, "parse error")
+    }
+
+    fn invalid_request() -> Self {
+        RpcError::new(-32600, "invalid request")
+    }
+
+    fn method_not_found() -> Self {
+        RpcError::new(-32601, "method not found")
+    }
+
+    fn invalid_params(path: String, reason: String) -> Self {
+        RpcError {
+            data: Some(json!({ "path": path, "reason": reason })),
+            ..RpcError::new(-32602, "invalid params")
+        }
+    }
+
+    fn unauthenticated() -> Self {
+        RpcError::new(-32001, "not authenticated")
+    }
+
+    fn forbidden(permission: Permission) -> Self {
+        RpcError {
+            data: Some(json!({ "permission": permission })),
+            ..RpcError::new(-32003, "permission denied")
+        }
+    }
+
+    fn not_found() -> Self {
+        RpcError::new(-32004, "not found")
+    }
+
+    fn too_many(what: &str) -> Self {
+        RpcError::new(-32005, &format!("too many {}", what))
+    }
+
+    fn server_error() -> Self {
+        RpcError::new(-32000, "server error")
+    }
+}
+
+impl From<model::Error> for RpcError {
+    fn from(other: model::Error) -> Self {
+        match other {
+            // out of scope rows are not found as well
+            model::Error::EntityNotFound(..) => RpcError::not_found(),
+            other => {
+                tracing::warn!(error = %other, "rpc failed");
+                RpcError::server_error()
+            }
+        }
+    }
+}
+
+impl From<security::Error> for RpcError {
+    fn from(other: security::Error) -> Self {
+        match other {
+            security::Error::Model(other) => other.into(),
+            _ => RpcError::unauthenticated(),
+        }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Message {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    Message::text(response.to_string())
+}
+
+fn notification(method: &str, params: Value) -> Message {
+    Message::text(json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string())
+}
+
+// The params of the method, the error tells where they are wrong.
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+    serde_path_to_error::deserialize(params)
+        .map_err(|ex| RpcError::invalid_params(ex.path().to_string(), ex.inner().to_string()))
+}
+
+fn to_value<T: Serialize>(data: T) -> Result<Value, RpcError> {
+    serde_json::to_value(data).map_err(|_| RpcError::server_error())
+}
+// endregion: JSON-RPC
+
+// region:    Params
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthParams {
+    token: String,
+}
+
+/// Passengers of a subscription, all of them without criteria.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Subscription {
+    id: Option<String>,
+    status: Option<String>,
+}
+
+impl Subscription {
+    fn matches(&self, passenger: &Passenger) -> bool {
+        self.id.as_ref().is_none_or(|id| passenger.id.to_string() == *id)
+            && self
+                .status
+                .as_ref()
+                .is_none_or(|status| passenger.status.as_ref() == Some(status))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UnsubscribeParams {
+    subscription: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateParams {
+    id: String,
+    data: PassengerPatch,
+}
+// endregion: Params
+
+// region:    Connection
+struct Connection {
+    db: Arc<Db>,
+    token: Option<String>,
+    utx: Option<UserCtx>,
+    subscriptions: HashMap<u64, Subscription>,
+    next_subscription: u64,
+    out: mpsc::Sender<Message>,
+    in_flight: Arc<Semaphore>,
+    // events were dropped for a full send buffer, the subscriptions get a reset
+    reset_pending: bool,
+}
+
+async fn connection(socket: WebSocket, db: Arc<Db>, token: Option<String>) {
+    let config = config();
+    let (mut sink, mut stream) = socket.split();
+    let (out, mut out_rx) = mpsc::channel::<Message>(config.ws_send_buffer.max(1));
+    // the only writer, the requests in flight send their responses through `out` too
+    let writer = tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let mut conn = Connection {
+        db,
+        token: None,
+        utx: None,
+        subscriptions: HashMap::new(),
+        next_subscription: 1,
+        out,
+        in_flight: Arc::new(Semaphore::new(config.ws_max_in_flight.max(1))),
+        reset_pending: false,
+    };
+    let close = match token {
+        Some(token) if conn.authenticate(&token).await.is_err() => Some((CLOSE_POLICY_VIOLATION, "invalid token")),
+        _ => conn.run(&mut stream).await,
+    };
+    if let Some((code, reason)) = close {
+        let _ = conn.out.send(Message::close_with(code, reason)).await;
+    }
+    drop(conn);
+    let _ = writer.await;
+}
+
+impl Connection {
+    // Until the client or the server goes away, the close code and reason when the server closes.
+    async fn run(&mut self, stream: &mut futures::stream::SplitStream<WebSocket>) -> Option<(u16, &'static str)> {
+        let interval = config().ws_ping_interval;
+        let (_, mut events) = passenger_events().subscribe(None);
+        let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        let mut last_seen = Instant::now();
+        let shutdown = shutting_down();
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(message)) => {
+                        last_seen = Instant::now();
+                        if message.is_close() {
+                            return None;
+                        }
+                        self.handle(message).await;
+                    }
+                    _ => return None,
+                },
+                event = events.recv() => match event {
+                    Ok(event) => self.notify(&event),
+                    Err(RecvError::Lagged(_)) => self.reset_pending = true,
+                    Err(RecvError::Closed) => return Some((CLOSE_GOING_AWAY, "server shutting down")),
+                },
+                _ = heartbeat.tick() => {
+                    if last_seen.elapsed() > interval * 2 {
+                        return Some((CLOSE_GOING_AWAY, "heartbeat timeout"));
+                    }
+                    // logged out, revoked or expired tokens end the connection
+                    match &self.token {
+                        None => return Some((CLOSE_POLICY_VIOLATION, "authentication required")),
+                        Some(token) => {
+                            let token = token.clone();
+                            if self.authenticate(&token).await.is_err() {
+                                return Some((CLOSE_POLICY_VIOLATION, "invalid token"));
+                            }
+                        }
+                    }
+                    let _ = self.out.try_send(Message::ping(Vec::new()));
+                }
+                _ = &mut shutdown => return Some((CLOSE_GOING_AWAY, "server shutting down")),
+            }
+        }
+    }
+
+    async fn authenticate(&mut self, token: &str) -> Result<Value, RpcError> {
+        let utx = utx_from_token(&self.db, token).await?;
+        let user_id = utx.user_id.clone();
+        self.utx = Some(utx);
+        self.token = Some(token.to_string());
+        Ok(json!({ "user_id": user_id }))
+    }
+
+    async fn handle(&mut self, message: Message) {
+        let text = match message.to_str() {
+            Ok(text) => text,
+            // pings and pongs are only for the heartbeat
+            Err(_) if !message.is_binary() => return,
+            Err(_) => return self.send(response(Value::Null, Err(RpcError::invalid_request()))).await,
+        };
+        let request = match serde_json::from_str::<Value>(text) {
+            Ok(request) => request,
+            Err(_) => return self.send(response(Value::Null, Err(RpcError::parse_error()))).await,
+        };
+        let request = match serde_json::from_value::<RpcRequest>(request) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            _ => return self.send(response(Value::Null, Err(RpcError::invalid_request()))).await,
+        };
+        let RpcRequest { id, method, params, .. } = request;
+
+        let result = match method.as_str() {
+            "ping" => Ok(json!("pong")),
+            "auth" => match self::params::<AuthParams>(params) {
+                Ok(params) => self.authenticate(&params.token).await,
+                Err(error) => Err(error),
+            },
+            "subscribe" => self.subscribe(params),
+            "unsubscribe" => self.unsubscribe(params),
+            method if method.starts_with("passenger.") => return self.spawn_call(id, method.to_string(), params),
+            _ => Err(RpcError::method_not_found()),
+        };
+        if let Some(id) = id {
+            self.send(response(id, result)).await;
+        }
+    }
+
+    fn subscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        self.require(Permission::PassengerRead)?;
+        let subscription = self::params::<Subscription>(params)?;
+        if self.subscriptions.len() >= config().ws_max_subscriptions {
+            return Err(RpcError::too_many("subscriptions"));
+        }
+        let id = self.next_subscription;
+        self.next_subscription += 1;
+        self.subscriptions.insert(id, subscription);
+        Ok(json!({ "subscription": id }))
+    }
+
+    fn unsubscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        let params = self::params::<UnsubscribeParams>(params)?;
+        Ok(json!(self.subscriptions.remove(&params.subscription).is_some()))
+    }
+
+    // The passenger commands run aside, so the events keep flowing meanwhile.
+    fn spawn_call(&mut self, id: Option<Value>, method: String, params: Value) {
+        let permit = match self.in_flight.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if let Some(id) = id {
+                    let _ = self
+                        .out
+                        .try_send(response(id, Err(RpcError::too_many("requests in flight"))));
+                }
+                return;
+            }
+        };
+        let (db, utx, out) = (self.db.clone(), self.utx.clone(), self.out.clone());
+        let span = tracing::info_span!("rpc", method = %method, user_id = utx.as_ref().map(|utx| utx.user_id.as_str()));
+        tokio::spawn(
+            async move {
+                let result = match utx {
+                    Some(utx) => call(&db, &utx, &method, params).await,
+                    None => Err(RpcError::unauthenticated()),
+                };
+                if let Some(id) = id {
+                    let _ = out.send(response(id, result)).await;
+                }
+                drop(permit);
+            }
+            .instrument(span),
+        );
+    }
+
+    fn require(&self, permission: Permission) -> Result<&UserCtx, RpcError> {
+        match &self.utx {
+            None => Err(RpcError::unauthenticated()),
+            Some(utx) if utx.has_permission(permission) => Ok(utx),
+            Some(_) => Err(RpcError::forbidden(permission)),
+        }
+    }
+
+    // Sends the event to the matching subscriptions, dropped (and a reset later) when the client
+    // does not keep up.
+    fn notify(&mut self, event: &PassengerEvent) {
+        let utx = match &self.utx {
+            Some(utx) if config().passenger_scope.allows(utx, &event.passenger) => utx,
+            _ => return,
+        };
+        if !utx.has_permission(Permission::PassengerRead) {
+            return;
+        }
+        if self.reset_pending && !self.send_resets() {
+            return;
+        }
+        for (subscription, _) in self
+            .subscriptions
+            .iter()
+            .filter(|(_, sub)| sub.matches(&event.passenger))
+        {
+            let params = json!({ "subscription": subscription, "event": event });
+            if let Err(TrySendError::Full(_)) = self.out.try_send(notification("passenger.event", params)) {
+                self.reset_pending = true;
+                return;
+            }
+        }
+    }
+
+    // True once all the subscriptions got their reset.
+    fn send_resets(&mut self) -> bool {
+        if self.out.capacity() < self.subscriptions.len() {
+            return false;
+        }
+        for subscription in self.subscriptions.keys() {
+            let _ = self
+                .out
+                .try_send(notification("passenger.reset", json!({ "subscription": subscription })));
+        }
+        self.reset_pending = false;
+        true
+    }
+
+    async fn send(&self, message: Message) {
+        let _ = self.out.send(message).await;
+    }
+}
+
+async fn call(db: &Db, utx: &UserCtx, method: &str, params: Value) -> Result<Value, RpcError> {
+    let permission = match method {
+        "passenger.list" | "passenger.get" => Permission::PassengerRead,
+        "passenger.create" | "passenger.update" => Permission::PassengerWrite,
+        "passenger.delete" => Permission::PassengerDelete,
+        _ => return Err(RpcError::method_not_found()),
+    };
+    if !utx.has_permission(permission) {
+        return Err(RpcError::forbidden(permission));
+    }
+    match method {
+        "passenger.list" => {
+            let filter = self::params::<PassengerFilter>(params)?;
+            to_value(PassengerDao::list(db, utx, &filter).await?)
+        }
+        "passenger.get" => to_value(PassengerDao::get(db, utx, self::params::<IdParams>(params)?.id).await?),
+        "passenger.create" => to_value(PassengerDao::create(db, utx, self::params::<PassengerPatch>(params)?).await?),
+        "passenger.update" => {
+            let UpdateParams { id, data } = self::params(params)?;
+            to_value(PassengerDao::update(db, utx, id, data).await?)
+        }
+        _ => to_value(PassengerDao::delete(db, utx, self::params::<IdParams>(params)?.id).await?),
+    }
+}
+// endregion: Connection
+
+#[cfg(test)]
+#[path = "../_tests/web_handlers_ws.rs"]
+mod tests;
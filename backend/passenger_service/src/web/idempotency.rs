@@ -0,0 +1,232 @@
+use super::Error;
+use crate::config::config;
+use crate::model::{Db, IdempotencyKey, IdempotencyKeyDao};
+use crate::security::UserCtx;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use warp::http::{HeaderValue, StatusCode};
+use warp::hyper::body::{to_bytes, Body};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const HEADER_IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+const HEADER_REPLAYED: &str = "Idempotent-Replayed";
+const MAX_KEY_LEN: usize = 255;
+// How long a duplicate waits for the first request, when in progress on another replica.
+const IN_PROGRESS_WAIT: Duration = Duration::from_secs(10);
+const IN_PROGRESS_POLL: Duration = Duration::from_millis(100);
+
+/// Extracts the `Idempotency-Key` header, if any.
+pub fn idempotency_key() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(HEADER_IDEMPOTENCY_KEY).and_then(|key: Option<String>| async move {
+        match key {
+            Some(key) if key.is_empty() || key.len() > MAX_KEY_LEN => {
+                Err::<_, Rejection>(Error::FailIdempotencyKeyFormat(MAX_KEY_LEN).into())
+            }
+            key => Ok(key),
+        }
+    })
+}
+
+/// Runs the request once per user and key, see the `Idempotency-Key` header.
+///
+/// The first successful response is stored with the key and a hash of the request (`route` and
+/// `body`), for `idempotency_ttl`. A replay of the same request returns the stored response,
+/// with an `Idempotent-Replayed` header. A replay with another request is rejected with 422.
+/// Failed requests are not stored, so they can be retried with the same key; neither are the
+/// ones dropped before their response (client gone, handler timeout).
+///
+/// Duplicates sent at the same time are serialized: they wait for the first one to complete.
+/// A first request still in progress after `handler_timeout` is abandoned (process crashed),
+/// a duplicate takes its key over.
+pub async fn idempotent<B, F, Fut, R>(
+    db: &Db,
+    utx: &UserCtx,
+    key: Option<String>,
+    route: &str,
+    body: &B,
+    run: F,
+) -> Result<Response, Rejection>
+where
+    B: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<R, Rejection>>,
+    R: Reply,
+{
+    let Some(key) = key else {
+        return Ok(run().await?.into_response());
+    };
+    let request_hash = request_hash(route, body);
+
+    // duplicates in this process wait here, the ones from other replicas in `wait_for`
+    let lock = key_lock(&utx.user_id, &key);
+    let _guard = lock.lock().await;
+
+    let lease = chrono::Duration::from_std(config().handler_timeout).unwrap_or(config().idempotency_ttl);
+    let claim = loop {
+        let expires_at = Utc::now() + config().idempotency_ttl;
+        if let Some(claimed) = IdempotencyKeyDao::claim(db, &utx.user_id, &key, &request_hash, expires_at).await? {
+            break Claim {
+                db: db.clone(),
+                user_id: utx.user_id.clone(),
+                key,
+                claimed_at: claimed.created_at,
+                settled: false,
+            };
+        }
+        match wait_for(db, utx, &key, lease).await? {
+            Some(stored) if stored.request_hash != request_hash => {
+                return Err(Error::FailIdempotencyKeyReused(key).into());
+            }
+            Some(stored) => {
+                let status = stored.status.and_then(|status| u16::try_from(status).ok());
+                let status = status.and_then(|status| StatusCode::from_u16(status).ok());
+                let mut response = Response::new(Body::from(stored.response.unwrap_or_default()));
+                *response.status_mut() = status.unwrap_or(StatusCode::OK);
+                let headers = response.headers_mut();
+                headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+                headers.insert(HEADER_REPLAYED, HeaderValue::from_static("true"));
+                return Ok(response);
+            }
+            // expired, abandoned, or released by a failed request, claim it again
+            None => continue,
+        }
+    };
+
+    let response = match run().await {
+        Ok(reply) => reply.into_response(),
+        Err(rejection) => {
+            claim.release().await?;
+            return Err(rejection);
+        }
+    };
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body).await.map_err(|_| Error::FailIdempotencyKeyStore)?;
+    claim
+        .complete(parts.status.as_u16(), &String::from_utf8_lossy(&body))
+        .await?;
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+// The key claimed by a request. Released when the request is dropped before its response is
+// stored, so a retry does not wait for the lease.
+struct Claim {
+    db: Db,
+    user_id: String,
+    key: String,
+    claimed_at: DateTime<Utc>,
+    // completed or released
+    settled: bool,
+}
+
+impl Claim {
+    async fn complete(mut self, status: u16, response: &str) -> Result<(), Rejection> {
+        IdempotencyKeyDao::complete(&self.db, &self.user_id, &self.key, self.claimed_at, status, response).await?;
+        self.settled = true;
+        Ok(())
+    }
+
+    async fn release(mut self) -> Result<(), Rejection> {
+        self.settled = true;
+        IdempotencyKeyDao::release(&self.db, &self.user_id, &self.key, self.claimed_at).await?;
+        Ok(())
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (db, user_id, key, claimed_at) = (
+            self.db.clone(),
+            std::mem::take(&mut self.user_id),
+            std::mem::take(&mut self.key),
+            self.claimed_at,
+        );
+        runtime.spawn(async move {
+            if let Err(ex) = IdempotencyKeyDao::release(&db, &user_id, &key, claimed_at).await {
+                tracing::warn!(cause = ?ex, "idempotency - release of a dropped request failed");
+            }
+        });
+    }
+}
+
+/// Deletes the expired keys every `idempotency_gc` in the background.
+pub fn start_gc(db: Arc<Db>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config().idempotency_gc);
+        loop {
+            interval.tick().await;
+            match IdempotencyKeyDao::delete_expired(&db).await {
+                Ok(0) => (),
+                Ok(deleted) => tracing::info!(deleted, "idempotency gc - expired keys deleted"),
+                Err(ex) => tracing::error!(cause = ?ex, "idempotency gc failed"),
+            }
+        }
+    });
+}
+
+// region:    Utils
+// Waits for the stored response of a key claimed by another request.
+// Returns None when the key is free again (expired, abandoned after its lease, or released).
+async fn wait_for(
+    db: &Db,
+    utx: &UserCtx,
+    key: &str,
+    lease: chrono::Duration,
+) -> Result<Option<IdempotencyKey>, Rejection> {
+    let started_at = Instant::now();
+    loop {
+        let stored = IdempotencyKeyDao::get(db, &utx.user_id, key).await?;
+        match stored {
+            Some(stored) if stored.is_expired() || stored.is_abandoned(lease) => {
+                IdempotencyKeyDao::release_stale(db, &utx.user_id, key, lease).await?;
+                return Ok(None);
+            }
+            Some(stored) if stored.response.is_none() => {
+                if started_at.elapsed() >= IN_PROGRESS_WAIT {
+                    return Err(Error::FailIdempotencyKeyInProgress(key.to_string()).into());
+                }
+                tokio::time::sleep(IN_PROGRESS_POLL).await;
+            }
+            stored => return Ok(stored),
+        }
+    }
+}
+
+fn request_hash<B: Serialize>(route: &str, body: &B) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(route.as_bytes());
+    hasher.update(serde_json::to_vec(body).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+// One lock per user and key, dropped with the last request using it.
+fn key_lock(user_id: &str, key: &str) -> Arc<tokio::sync::Mutex<()>> {
+    static LOCKS: OnceLock<Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+    let mut locks = LOCKS.get_or_init(Default::default).lock().unwrap();
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock_key = format!("{}:{}", user_id, key);
+    match locks.get(&lock_key).and_then(Weak::upgrade) {
+        Some(lock) => lock,
+        None => {
+            let lock = Arc::new(tokio::sync::Mutex::new(()));
+            locks.insert(lock_key, Arc::downgrade(&lock));
+            lock
+        }
+    }
+}
+// endregion: Utils
+
+#[cfg(test)]
+#[path = "../_tests/web_idempotency.rs"]
+mod tests;
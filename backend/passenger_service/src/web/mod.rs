@@ -3,7 +3,13 @@
 
 use crate::model::{self, Db, Passenger};
 use crate::security;
+use crate::security::{auth, Permission, Role};
+use crate::{config, metrics};
+use futures::FutureExt;
+use server::{Tls, TlsFiles};
 use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
 use std::{path::Path, sync::Arc};
 use utoipa::{
     openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
@@ -11,18 +17,39 @@ use utoipa::{
 };
 use warp::{
     http::Uri,
-    hyper::{Response, StatusCode},
-    path::{FullPath, Tail},
+    hyper::{Body, Response, StatusCode},
+    path::{FullPath, Peek, Tail},
     Filter, Rejection, Reply,
 };
 
 use std::string::String;
 
+use filter_rate_limit::{rate_limit, with_rate_limit_headers, RateLimited, RateLimiter};
+use filter_utils::with_db;
 use serde_json::json;
 use utoipa_swagger_ui::Config;
 mod filter_auth;
+mod filter_compression;
+mod filter_cors;
+mod filter_rate_limit;
+mod filter_security_headers;
 mod filter_utils;
 mod handlers;
+mod handlers_api_key;
+mod handlers_auth;
+mod handlers_document;
+mod handlers_events;
+mod handlers_health;
+mod handlers_user;
+mod handlers_webhook;
+mod handlers_ws;
+mod idempotency;
+mod server;
+mod trace;
+
+pub use filter_rate_limit::Quota;
+pub use idempotency::start_gc as start_idempotency_gc;
+pub use server::{BindAddr, TlsClientAuth};
 
 struct SecurityAddon;
 
@@ -31,12 +58,22 @@ impl Modify for SecurityAddon {
         let components = openapi.components.as_mut().unwrap(); // we can unwrap safely since there already is components registered.
         components.add_security_scheme(
             "X-Auth-Token",
-            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("123"))),
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-Auth-Token",
+                "User token, or API key (psk_...) for service to service calls",
+            ))),
         )
     }
 }
 
-pub async fn start_web_server(folder: &str, port: u16, db: Arc<Db>) -> Result<(), Error> {
+/// Serves the API and the web folder until `shutdown` completes, then drains the in-flight
+/// requests (see `trace::serve`).
+pub async fn start_web_server(
+    folder: &str,
+    port: u16,
+    db: Arc<Db>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error> {
     // validate the web folder
     if !Path::new(folder).exists() {
         return Err(Error::FailStartWebFolderNotFound(folder.to_string()));
@@ -47,15 +84,75 @@ pub async fn start_web_server(folder: &str, port: u16, db: Arc<Db>) -> Result<()
     #[derive(OpenApi)]
     #[openapi(
         paths(
-            handlers::list_passengers, 
+            handlers::list_passengers,
+            handlers::search_passengers,
+            handlers::passenger_duplicates,
+            handlers::merge_passengers,
+            handlers::create_passenger_from_mrz,
             handlers::get_passenger,
             handlers::create_passenger,
+            handlers::create_passengers_bulk,
             handlers::update_passenger,
             handlers::delete_passenger,
+            handlers_document::list_documents,
+            handlers_document::create_document,
+            handlers_document::get_document,
+            handlers_document::update_document,
+            handlers_document::delete_document,
+            handlers_events::passenger_events_stream,
+            handlers_api_key::create_api_key,
+            handlers_api_key::list_api_keys,
+            handlers_api_key::revoke_api_key,
+            handlers_auth::login,
+            handlers_auth::refresh,
+            handlers_auth::logout,
+            handlers_auth::list_sessions,
+            handlers_auth::revoke_session,
+            handlers_user::create_user,
+            handlers_webhook::list_webhooks,
+            handlers_webhook::create_webhook,
+            handlers_webhook::get_webhook,
+            handlers_webhook::update_webhook,
+            handlers_webhook::delete_webhook,
+            handlers_webhook::list_webhook_deliveries,
+            handlers_webhook::retry_webhook_delivery,
         ),
-        components(schemas(Passenger)),
+        components(schemas(
+            Passenger,
+            model::PassengerSearchHit,
+            model::Highlight,
+            model::PassengerDuplicate,
+            model::PassengerMergeRequest,
+            model::PassengerMerge,
+            model::PassengerMrzRequest,
+            model::PassengerFromMrz,
+            model::CheckDigitFailure,
+            model::PassengerDocument,
+            model::PassengerDocumentForCreate,
+            model::PassengerDocumentPatch,
+            model::ApiKey,
+            model::ApiKeyForCreate,
+            model::ApiKeyCreated,
+            model::User,
+            model::UserForCreate,
+            model::UserSession,
+            model::Webhook,
+            model::WebhookForCreate,
+            model::WebhookPatch,
+            model::WebhookCreated,
+            model::WebhookDelivery,
+            Permission,
+            Role,
+            auth::LoginPayload,
+            auth::RefreshPayload,
+            auth::AuthTokens
+        )),
+        modifiers(&SecurityAddon),
         tags(
-            (name = "Passengers", description = "Passengers items management API")
+            (name = "Passengers", description = "Passengers items management API"),
+            (name = "Auth", description = "Login and sessions API"),
+            (name = "Admin", description = "Administration API"),
+            (name = "Webhooks", description = "Passenger events delivery API")
         )
     )]
     struct ApiDoc;
@@ -72,7 +169,20 @@ pub async fn start_web_server(folder: &str, port: u16, db: Arc<Db>) -> Result<()
         .and_then(serve_swagger);
 
     // // Passengers routes
-    let apis = handlers::handlers("api", db);
+    let apis = handlers_events::handlers("api", db.clone())
+        .or(handlers_document::handlers("api", db.clone()))
+        .or(handlers::handlers("api", db.clone()))
+        .or(handlers_api_key::handlers("api", db.clone()))
+        .or(handlers_auth::handlers("api", db.clone()))
+        .or(handlers_user::handlers("api", db.clone()))
+        .or(handlers_ws::handlers("api", db.clone()))
+        .or(handlers_webhook::handlers("api", db.clone()));
+    // Rate limiting of the API, per client
+    let rate_limiter = RateLimiter::from_config().map(Arc::new);
+    let apis = is_api()
+        .and(rate_limit(db.clone(), rate_limiter))
+        .and(apis)
+        .map(with_rate_limit_headers);
     // Static content -- index.html and all other files
     let content = warp::fs::dir(folder.to_string());
     let root_index = warp::get()
@@ -80,19 +190,92 @@ pub async fn start_web_server(folder: &str, port: u16, db: Arc<Db>) -> Result<()
         .and(warp::fs::file(format!("{}/index.html", folder))); // = localhost:port/index.html
     let static_site = content.or(root_index);
 
+    // Prometheus metrics
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(is_enabled(config::config().metrics_enabled))
+        .and(with_db(db.clone()))
+        .then(|db: Arc<Db>| async move { metrics::metrics().gather(&db).await });
+
+    // Liveness and readiness probes
+    let health = handlers_health::handlers(db.clone());
+
     // Combine all routes
     let routes = api_doc
+        .or(health)
+        .or(metrics)
         .or(swagger_ui)
         .or(apis)
         .or(static_site)
-        .recover(handle_rejection);
+        .recover(handle_rejection)
+        .map(Reply::into_response);
 
-    println!("Start 127.0.0.1:{} at {}", port, folder);
-    warp::serve(routes).run(([127, 0, 0, 1], port)).await;
+    tracing::info!(folder, "serving static content");
+    // bind all the listeners and load the certificates first, so a wrong config fails the start
+    let config = config::config();
+    let addrs: Vec<SocketAddr> = config.bind_addrs.iter().map(|addr| addr.socket_addr(port)).collect();
+    let listeners = server::bind(&addrs)?;
+    let tls = match TlsFiles::from_config() {
+        Some(files) => {
+            let tls = Arc::new(Tls::load(files)?);
+            server::start_reload_on_sighup(tls.clone())?;
+            Some(tls)
+        }
+        None => None,
+    };
+    // the compression sees the CORS headers, the security headers go on every response
+    let routes = filter_cors::with_cors(filter_cors::Cors::from_config(), routes);
+    let routes = filter_compression::with_compression(config.compression_enabled, routes)
+        .with(filter_security_headers::security_headers(tls.is_some()));
+    let redirect_listeners = match config.https_redirect_addr {
+        Some(addr) => server::bind(&[addr])?,
+        None => Vec::new(),
+    };
+    let https_port = addrs[0].port();
 
+    // not ready first, so no new requests get routed here while draining
+    let shutdown = async move {
+        shutdown.await;
+        handlers_health::set_shutting_down();
+    }
+    .shared();
+    let drain_timeout = config.shutdown_drain_timeout;
+    let redirect = async {
+        if !redirect_listeners.is_empty() {
+            let service = warp::service(server::https_redirect(https_port));
+            server::serve(redirect_listeners, None, service, shutdown.clone(), drain_timeout).await;
+        }
+    };
+    let main = server::serve(listeners, tls, warp::service(routes), shutdown.clone(), drain_timeout);
+    tokio::join!(main, redirect);
     Ok(())
 }
 
+// Passes when the feature is enabled, not found otherwise.
+fn is_enabled(enabled: bool) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::any()
+        .and_then(move || async move {
+            match enabled {
+                true => Ok(()),
+                false => Err(warp::reject::not_found()),
+            }
+        })
+        .untuple_one()
+}
+
+// Matches the `/api/...` paths, without consuming the `api` segment.
+fn is_api() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path::peek()
+        .and_then(|peek: Peek| async move {
+            match peek.segments().next() == Some("api") {
+                true => Ok(()),
+                false => Err(warp::reject::not_found()),
+            }
+        })
+        .untuple_one()
+}
+
 async fn serve_swagger(
     full_path: FullPath,
     tail: Tail,
@@ -123,59 +306,172 @@ async fn serve_swagger(
     }
 }
 
-async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
-    // Print to server side
-    println!("Server ERROR: {:?}", err);
-    // TODO - Call log API for capture and store
+/// The JSON error body, `{"errorMessage": <type>}` with the `details` if any.
+pub fn error_response(user_message: &str, status: StatusCode, details: Option<serde_json::Value>) -> Response<Body> {
+    let mut result = json!({ "errorMessage": user_message });
+    if let Some(details) = details {
+        result["details"] = details;
+    }
+    warp::reply::with_status(warp::reply::json(&result), status).into_response()
+}
 
-	// Build user message
-	let user_message = match err.find::<WebErrorMessage>() {
-		Some(err) => err.typ.to_string(),
-		None => "Unknown".to_string(),
-	};
+async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+    // Log to server side
+    match err.is_not_found() {
+        true => tracing::debug!(rejection = ?err, "request rejected"),
+        false => tracing::warn!(rejection = ?err, "request rejected"),
+    }
 
-	let result = json!({ "errorMessage": user_message });
-	let result = warp::reply::json(&result);
+    // Build user message
+    let (user_message, status, details) = match (err.find::<WebErrorMessage>(), err.find::<RateLimited>()) {
+        (Some(err), _) => (err.typ, err.status, err.details.clone()),
+        (None, Some(_)) => ("web::Error", StatusCode::TOO_MANY_REQUESTS, None),
+        (None, None) => ("Unknown", StatusCode::BAD_REQUEST, None),
+    };
 
-	Ok(warp::reply::with_status(result, warp::http::StatusCode::BAD_REQUEST))
+    let mut response = error_response(user_message, status, details);
+    if let Some(limited) = err.find::<RateLimited>() {
+        limited.add_headers(response.headers_mut());
+    }
+    Ok(response)
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Web server failed to start because web-folder '{0}' not found.")]
     FailStartWebFolderNotFound(String),
 
+    #[error("Web server failed to start - {0}")]
+    FailStartServer(String),
+
+    #[error("Web server TLS config failed - {0}")]
+    FailTls(String),
+
     #[error("Fail authentication missing X-Auth-Token header.")]
     FailAuthMissingXAuth,
+
+    #[error("Fail authorization missing permission '{0}'.")]
+    FailAuthPermission(&'static str),
+
+    #[error("Fail Idempotency-Key header must have 1 to {0} characters.")]
+    FailIdempotencyKeyFormat(usize),
+
+    #[error("Fail Idempotency-Key '{0}' already used for another request.")]
+    FailIdempotencyKeyReused(String),
+
+    #[error("Fail Idempotency-Key '{0}' request still in progress.")]
+    FailIdempotencyKeyInProgress(String),
+
+    #[error("Fail to store the response of the Idempotency-Key request.")]
+    FailIdempotencyKeyStore,
+
+    #[error("Fail request body larger than {0} bytes.")]
+    FailBodyTooLarge(u64),
+
+    #[error("Fail to read the request body - {0}")]
+    FailBodyRead(String),
+
+    #[error("Fail JSON body at '{path}' - {reason}")]
+    FailJsonBody { path: String, reason: String },
+
+    #[error("Fail request not handled within {0:?}.")]
+    FailTimeout(std::time::Duration),
 }
 
 // region:    Warp Custom Error
 #[derive(Debug)]
 pub struct WebErrorMessage {
     pub typ: &'static str,
+    #[allow(dead_code)]
     pub message: String,
+    pub status: StatusCode,
+    /// Sent to the client along the type, when set.
+    pub details: Option<serde_json::Value>,
 }
 impl warp::reject::Reject for WebErrorMessage {}
 
 impl WebErrorMessage {
-    pub fn rejection(typ: &'static str, message: String) -> warp::Rejection {
-        warp::reject::custom(WebErrorMessage { typ, message })
+    fn new(typ: &'static str, message: String, status: StatusCode) -> Self {
+        WebErrorMessage {
+            typ,
+            message,
+            status,
+            details: None,
+        }
+    }
+
+    pub fn rejection(typ: &'static str, message: String, status: StatusCode) -> warp::Rejection {
+        warp::reject::custom(WebErrorMessage::new(typ, message, status))
     }
 }
 
 impl From<self::Error> for warp::Rejection {
     fn from(other: self::Error) -> Self {
-        WebErrorMessage::rejection("web::Error", format!("{}", other))
+        match other {
+            Error::FailAuthMissingXAuth => count_auth_failure("missing_token"),
+            Error::FailAuthPermission(_) => count_auth_failure("permission_denied"),
+            _ => (),
+        }
+        let status = match other {
+            Error::FailAuthPermission(_) => StatusCode::FORBIDDEN,
+            Error::FailIdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::FailIdempotencyKeyInProgress(_) => StatusCode::CONFLICT,
+            Error::FailIdempotencyKeyStore => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::FailBodyTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::FailTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        // the client can fix its body with these, they hold nothing of the server
+        let details = match &other {
+            Error::FailJsonBody { path, reason } => Some(json!({ "path": path, "reason": reason })),
+            Error::FailBodyTooLarge(limit) => Some(json!({ "limit": limit })),
+            _ => None,
+        };
+        let mut message = WebErrorMessage::new("web::Error", format!("{}", other), status);
+        message.details = details;
+        warp::reject::custom(message)
     }
 }
 impl From<model::Error> for warp::Rejection {
     fn from(other: model::Error) -> Self {
-        WebErrorMessage::rejection("model::Error", format!("{}", other))
+        // out of scope rows are not found as well, so their existence does not leak
+        let status = match other {
+            model::Error::EntityNotFound(..) => StatusCode::NOT_FOUND,
+            // all the connections busy, retrying later may succeed
+            model::Error::Sqlx(sqlx::Error::PoolTimedOut) => StatusCode::SERVICE_UNAVAILABLE,
+            model::Error::CheckDigits(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        // the fields of the MRZ to read again
+        let details = match &other {
+            model::Error::CheckDigits(failures) => Some(json!({ "check_digits": failures })),
+            _ => None,
+        };
+        let mut message = WebErrorMessage::new("model::Error", format!("{}", other), status);
+        message.details = details;
+        warp::reject::custom(message)
     }
 }
 impl From<security::Error> for warp::Rejection {
     fn from(other: security::Error) -> Self {
-        WebErrorMessage::rejection("security::Error", format!("{}", other))
+        match other {
+            security::Error::Model(other) => other.into(),
+            other => {
+                if let Some(reason) = other.auth_failure_reason() {
+                    count_auth_failure(reason);
+                }
+                WebErrorMessage::rejection("security::Error", format!("{}", other), StatusCode::UNAUTHORIZED)
+            }
+        }
     }
 }
+
+fn count_auth_failure(reason: &'static str) {
+    metrics::metrics().auth_failures.with_label_values(&[reason]).inc();
+}
 // endregion: Warp Custom Error
+
+#[cfg(test)]
+#[path = "../_tests/web.rs"]
+mod tests;
@@ -0,0 +1,394 @@
+use super::trace::traced;
+use super::Error;
+use crate::config::config;
+use crate::metrics::metrics;
+use futures::future::join_all;
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use socket2::{Domain, Socket, Type};
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio_rustls::TlsAcceptor;
+use warp::http::{Request, Response, StatusCode};
+use warp::hyper::server::conn::Http;
+use warp::hyper::service::{service_fn, Service};
+use warp::hyper::Body;
+use warp::path::FullPath;
+use warp::{Filter, Rejection, Reply};
+
+const LISTEN_BACKLOG: i32 = 1024;
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// on accept errors (e.g. out of file descriptors), before accepting again
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+// region:    Bind
+/// Address to listen on (`127.0.0.1`, `::`, `0.0.0.0:8080`, `[::1]:8080`). Without port,
+/// the port given on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindAddr {
+    ip: IpAddr,
+    port: Option<u16>,
+}
+
+impl BindAddr {
+    pub fn localhost() -> Self {
+        BindAddr {
+            ip: Ipv4Addr::LOCALHOST.into(),
+            port: None,
+        }
+    }
+
+    pub fn socket_addr(&self, default_port: u16) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port.unwrap_or(default_port))
+    }
+}
+
+impl FromStr for BindAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(BindAddr {
+                ip: addr.ip(),
+                port: Some(addr.port()),
+            });
+        }
+        match s.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(ip) => Ok(BindAddr { ip, port: None }),
+            Err(_) => Err(format!("Wrong bind address '{}'", s)),
+        }
+    }
+}
+
+/// Binds the addresses. The IPv6 ones accept only IPv6, so `0.0.0.0` and `::` can be
+/// bound together on the same port.
+pub fn bind(addrs: &[SocketAddr]) -> Result<Vec<TcpListener>, Error> {
+    addrs
+        .iter()
+        .map(|addr| bind_one(*addr).map_err(|ex| Error::FailStartServer(format!("bind {} - {}", addr, ex))))
+        .collect()
+}
+
+fn bind_one(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+// endregion: Bind
+
+// region:    TLS
+/// Whether the clients must present a certificate, when mTLS is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsClientAuth {
+    Required,
+    Optional,
+}
+
+impl FromStr for TlsClientAuth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "required" => Ok(TlsClientAuth::Required),
+            "optional" => Ok(TlsClientAuth::Optional),
+            other => Err(format!("Unknown TLS client auth '{}'", other)),
+        }
+    }
+}
+
+/// The PEM files of the TLS config.
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// CA of the client certificates, mTLS when set.
+    pub client_ca: Option<PathBuf>,
+    pub client_auth: TlsClientAuth,
+}
+
+impl TlsFiles {
+    /// None when TLS is off (no `tls_cert_path`).
+    pub fn from_config() -> Option<Self> {
+        let config = config();
+        Some(TlsFiles {
+            cert: config.tls_cert_path.clone()?,
+            key: config.tls_key_path.clone()?,
+            client_ca: config.tls_client_ca_path.clone(),
+            client_auth: config.tls_client_auth,
+        })
+    }
+
+    fn server_config(&self) -> Result<ServerConfig, Error> {
+        let certs = read_certs(&self.cert)?;
+        let key = PrivateKeyDer::from_pem_file(&self.key).map_err(|ex| fail_tls(&self.key, ex))?;
+        let provider = Arc::new(default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|ex| fail_tls(&self.cert, ex))?;
+        let builder = match &self.client_ca {
+            None => builder.with_no_client_auth(),
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(client_ca)? {
+                    roots.add(cert).map_err(|ex| fail_tls(client_ca, ex))?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match self.client_auth {
+                    TlsClientAuth::Required => verifier,
+                    TlsClientAuth::Optional => verifier.allow_unauthenticated(),
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(|ex| fail_tls(client_ca, ex))?)
+            }
+        };
+        let mut server_config = builder
+            .with_single_cert(certs, key)
+            .map_err(|ex| fail_tls(&self.cert, ex))?;
+        server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(server_config)
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|ex| fail_tls(path, ex))?;
+    match certs.is_empty() {
+        true => Err(fail_tls(path, "no certificate")),
+        false => Ok(certs),
+    }
+}
+
+fn fail_tls(path: &Path, cause: impl Display) -> Error {
+    Error::FailTls(format!("{} - {}", path.display(), cause))
+}
+
+/// The current TLS config. `reload` swaps it, the open connections keep theirs.
+pub struct Tls {
+    files: TlsFiles,
+    current: RwLock<Arc<ServerConfig>>,
+}
+
+impl Tls {
+    pub fn load(files: TlsFiles) -> Result<Self, Error> {
+        let server_config = files.server_config()?;
+        Ok(Tls {
+            files,
+            current: RwLock::new(Arc::new(server_config)),
+        })
+    }
+
+    /// Reads the files again, keeping the current config when they are wrong.
+    pub fn reload(&self) -> Result<(), Error> {
+        let server_config = self.files.server_config()?;
+        *self.current.write().unwrap() = Arc::new(server_config);
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+}
+
+/// Reloads the certificates on SIGHUP, e.g. after their renewal.
+pub fn start_reload_on_sighup(tls: Arc<Tls>) -> Result<(), Error> {
+    let mut sighup = signal(SignalKind::hangup()).map_err(|ex| Error::FailTls(format!("SIGHUP handler - {}", ex)))?;
+    tokio::spawn(async move {
+        while sighup.recv().await.is_some() {
+            match tls.reload() {
+                Ok(()) => tracing::info!("tls - certificates reloaded"),
+                Err(ex) => tracing::error!(cause = %ex, "tls - reload failed, keeping the current certificates"),
+            }
+        }
+    });
+    Ok(())
+}
+// endregion: TLS
+
+// region:    Serve
+/// Serves the (warp) service on the listeners, over TLS when `tls` is set, with a `request`
+/// span per request (see `traced`), until the `shutdown` future completes. Then stops
+/// accepting connections and waits up to `drain_timeout` for the in-flight requests, the
+/// connections still open after are aborted.
+pub async fn serve<S>(
+    listeners: Vec<TcpListener>,
+    tls: Option<Arc<Tls>>,
+    service: S,
+    shutdown: impl Future<Output = ()>,
+    drain_timeout: Duration,
+) where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let connections = Connections::default();
+    let accept_loops: Vec<JoinHandle<()>> = listeners
+        .into_iter()
+        .map(|listener| {
+            let accept_loop = accept_loop(
+                listener,
+                tls.clone(),
+                service.clone(),
+                stop_rx.clone(),
+                connections.clone(),
+            );
+            tokio::spawn(accept_loop)
+        })
+        .collect();
+
+    shutdown.await;
+    let _ = stop_tx.send(true);
+    join_all(accept_loops).await;
+    tracing::info!(in_flight = metrics().http_requests_in_flight.get(), "draining");
+    if !connections.drain(drain_timeout).await {
+        let in_flight = metrics().http_requests_in_flight.get();
+        tracing::warn!(in_flight, "drain timeout, aborting the requests still in flight");
+    }
+}
+
+async fn accept_loop<S>(
+    listener: TcpListener,
+    tls: Option<Arc<Tls>>,
+    service: S,
+    mut stop: watch::Receiver<bool>,
+    connections: Connections,
+) where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let addr = listener.local_addr().ok();
+    tracing::info!(?addr, tls = tls.is_some(), "start listening");
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(ex) => {
+                    tracing::warn!(?addr, cause = %ex, "accept failed");
+                    tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                    continue;
+                }
+            },
+            _ = stopped(&mut stop) => return,
+        };
+        let (tls, service, stop) = (tls.clone(), service.clone(), stop.clone());
+        connections.spawn(async move {
+            let Some(tls) = tls else {
+                return serve_connection(stream, remote_addr, service, stop).await;
+            };
+            match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.acceptor().accept(stream)).await {
+                Ok(Ok(stream)) => serve_connection(stream, remote_addr, service, stop).await,
+                Ok(Err(ex)) => tracing::debug!(%remote_addr, cause = %ex, "tls handshake failed"),
+                Err(_) => tracing::debug!(%remote_addr, "tls handshake timeout"),
+            }
+        });
+    }
+}
+
+// Serves the requests of the connection, closing it once idle after `stop`.
+async fn serve_connection<I, S>(io: I, remote_addr: SocketAddr, service: S, mut stop: watch::Receiver<bool>)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let service = service_fn(move |req| traced(service.clone(), Some(remote_addr), req));
+    let connection = Http::new().serve_connection(io, service).with_upgrades();
+    tokio::pin!(connection);
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = stopped(&mut stop) => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+    if let Err(ex) = result {
+        tracing::debug!(%remote_addr, cause = %ex, "connection error");
+    }
+}
+
+async fn stopped(stop: &mut watch::Receiver<bool>) {
+    // the sender is only dropped once stopped
+    let _ = stop.wait_for(|stop| *stop).await;
+}
+
+// The connection tasks, to wait for them or abort them when draining.
+#[derive(Clone, Default)]
+struct Connections(Arc<Mutex<Vec<JoinHandle<()>>>>);
+
+impl Connections {
+    fn spawn(&self, connection: impl Future<Output = ()> + Send + 'static) {
+        let handle = tokio::spawn(connection);
+        let mut handles = self.0.lock().unwrap();
+        handles.retain(|handle| !handle.is_finished());
+        handles.push(handle);
+    }
+
+    // Waits for the connections to end, aborts them at the timeout. False on timeout.
+    async fn drain(&self, timeout: Duration) -> bool {
+        let handles = std::mem::take(&mut *self.0.lock().unwrap());
+        let abort_handles: Vec<AbortHandle> = handles.iter().map(JoinHandle::abort_handle).collect();
+        match tokio::time::timeout(timeout, join_all(handles)).await {
+            Ok(_) => true,
+            Err(_) => {
+                abort_handles.iter().for_each(AbortHandle::abort);
+                false
+            }
+        }
+    }
+}
+// endregion: Serve
+
+// region:    HTTPS Redirect
+/// Redirects (308) to the same host, path and query on HTTPS, at `https_port`.
+pub fn https_redirect(https_port: u16) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("host")
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .map(move |host: Option<String>, path: FullPath, query: String| {
+            let Some(host) = host else {
+                return StatusCode::BAD_REQUEST.into_response();
+            };
+            let port = match https_port {
+                443 => String::new(),
+                port => format!(":{}", port),
+            };
+            let query = match query.is_empty() {
+                true => query,
+                false => format!("?{}", query),
+            };
+            let location = format!("https://{}{}{}{}", host_without_port(&host), port, path.as_str(), query);
+            warp::reply::with_header(StatusCode::PERMANENT_REDIRECT, "location", location).into_response()
+        })
+}
+
+// `example.com:8080` -> `example.com`, `[::1]:8080` -> `[::1]`
+fn host_without_port(host: &str) -> &str {
+    match host.find(']') {
+        Some(end) => &host[..=end],
+        None => host.split(':').next().unwrap_or(host),
+    }
+}
+// endregion: HTTPS Redirect
+
+#[cfg(test)]
+#[path = "../_tests/web_server.rs"]
+mod tests;
@@ -0,0 +1,115 @@
+use super::{error_response, Error};
+use crate::config::config;
+use crate::metrics::{metrics, route_label};
+use crate::telemetry;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Instrument;
+use warp::http::{HeaderValue, Request, Response, StatusCode};
+use warp::hyper::service::Service;
+use warp::hyper::Body;
+
+pub const HEADER_REQUEST_ID: &str = "X-Request-Id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Address of the client, in the request extensions (`warp::addr::remote` is None with
+/// the custom server, see `server::serve`).
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+/// Runs the request in a `request` span, with the method, path, request id, and once known,
+/// the user id (see `do_auth`), status and latency. The DB queries are its child spans.
+/// The request is counted in the HTTP metrics.
+///
+/// The request id comes from the `X-Request-Id` header, or is generated, and is sent back in
+/// the response.
+pub async fn traced<S>(
+    mut service: S,
+    remote_addr: Option<SocketAddr>,
+    mut req: Request<Body>,
+) -> Result<Response<Body>, Infallible>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+{
+    let request_id = req
+        .headers()
+        .get(HEADER_REQUEST_ID)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.to_str().is_ok())
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()).unwrap());
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id = request_id.to_str().unwrap_or_default(),
+        user_id = Empty,
+        status = Empty,
+        latency_ms = Empty,
+    );
+    telemetry::set_remote_parent(&span, req.headers());
+    if let Some(remote_addr) = remote_addr {
+        req.extensions_mut().insert(ClientAddr(remote_addr));
+    }
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let started_at = Instant::now();
+    let in_flight = InFlight::start();
+    let mut response = timed(config().handler_timeout, service.call(req).instrument(span.clone())).await?;
+    drop(in_flight);
+    let status = response.status();
+    let latency = started_at.elapsed();
+    span.record("status", status.as_u16());
+    span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+    let labels = [method.as_str(), &route_label(&path, status.as_u16()), status.as_str()];
+    metrics().http_requests.with_label_values(&labels).inc();
+    metrics()
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(latency.as_secs_f64());
+    span.in_scope(|| match status.is_server_error() {
+        true => tracing::error!("request failed"),
+        false => tracing::info!("request completed"),
+    });
+    response.headers_mut().insert(HEADER_REQUEST_ID, request_id);
+    Ok(response)
+}
+
+/// The response of `handler`, or a 504 when it does not come within `timeout`. The handler
+/// is then dropped, which cancels its pending DB queries.
+pub async fn timed<F>(timeout: Duration, handler: F) -> Result<Response<Body>, Infallible>
+where
+    F: Future<Output = Result<Response<Body>, Infallible>>,
+{
+    match tokio::time::timeout(timeout, handler).await {
+        Ok(response) => response,
+        Err(_) => {
+            let ex = Error::FailTimeout(timeout);
+            tracing::warn!(error = %ex, "request timed out");
+            Ok(error_response("web::Error", StatusCode::GATEWAY_TIMEOUT, None))
+        }
+    }
+}
+
+// Counts the request in flight until dropped, also when the client goes away mid-request.
+struct InFlight;
+
+impl InFlight {
+    fn start() -> Self {
+        metrics().http_requests_in_flight.inc();
+        InFlight
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        metrics().http_requests_in_flight.dec();
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_trace.rs"]
+mod tests;
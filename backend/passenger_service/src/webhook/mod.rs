@@ -0,0 +1,185 @@
+use crate::config::config;
+use crate::metrics::metrics;
+use crate::model::{self, Db, OutboxDao, WebhookDao, WebhookDelivery, WebhookDeliveryDao};
+use crate::security::webhook::signature;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde_json::json;
+use std::error::Error as StdError;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error as ThisError;
+use tokio::time::MissedTickBehavior;
+
+/// Sends the outbox events to the webhooks. Each round fans out the new events to deliveries,
+/// then POSTs the due deliveries, retried with an exponential backoff until `max_attempts`,
+/// dead after. Deliveries are at least once, receivers dedupe on `X-Webhook-Id`.
+pub struct Dispatcher {
+    client: reqwest::Client,
+    max_attempts: i64,
+    retry_backoff: Duration,
+    retry_max_backoff: Duration,
+    batch_size: i64,
+    // a delivery taken is not due again before, longer than an attempt
+    lease: Duration,
+}
+
+impl Dispatcher {
+    pub fn from_config() -> Result<Self, Error> {
+        let config = config();
+        Dispatcher::new(
+            config.webhook_timeout,
+            config.webhook_max_attempts,
+            config.webhook_retry_backoff,
+            config.webhook_retry_max_backoff,
+            config.webhook_batch_size,
+        )
+    }
+
+    pub fn new(
+        timeout: Duration,
+        max_attempts: i64,
+        retry_backoff: Duration,
+        retry_max_backoff: Duration,
+        batch_size: i64,
+    ) -> Result<Self, Error> {
+        // a redirect is a failure, the URL to call is the one registered
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|ex| Error::FailClient(ex.to_string()))?;
+        Ok(Dispatcher {
+            client,
+            max_attempts,
+            retry_backoff,
+            retry_max_backoff,
+            batch_size,
+            lease: timeout * 2,
+        })
+    }
+
+    /// Runs one round. Returns true when a batch was full, so more may be waiting.
+    pub async fn run_once(&self, db: &Db) -> Result<bool, model::Error> {
+        let events = WebhookDeliveryDao::fan_out(db, self.batch_size).await?;
+        let lease_until = Utc::now() + chrono::Duration::milliseconds(self.lease.as_millis() as i64);
+        let deliveries = WebhookDeliveryDao::claim_due(db, self.batch_size, lease_until).await?;
+        let batch_size = self.batch_size as usize;
+        let full = events == batch_size || deliveries.len() == batch_size;
+        for result in join_all(deliveries.into_iter().map(|delivery| self.deliver(db, delivery))).await {
+            if let Err(ex) = result {
+                tracing::error!(cause = ?ex, "webhook delivery not recorded");
+            }
+        }
+        Ok(full)
+    }
+
+    async fn deliver(&self, db: &Db, delivery: WebhookDelivery) -> Result<(), model::Error> {
+        let webhook = WebhookDao::get(db, delivery.webhook_id.to_string()).await?;
+        let event = OutboxDao::get(db, delivery.outbox_id).await?;
+        let body = json!({
+            "id": event.id,
+            "type": event.event_type,
+            "created_at": event.created_at,
+            "data": event.payload,
+        })
+        .to_string();
+        let timestamp = Utc::now().timestamp();
+        let result = self
+            .client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", event.id.to_string())
+            .header("X-Webhook-Event", &event.event_type)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header(
+                "X-Webhook-Signature",
+                signature(&webhook.secret, timestamp, body.as_bytes()),
+            )
+            .body(body)
+            .send()
+            .await;
+
+        let attempts = delivery.attempts + 1;
+        let (status_code, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                metrics().webhook_deliveries.with_label_values(&["delivered"]).inc();
+                let status_code = response.status().as_u16();
+                return WebhookDeliveryDao::delivered(db, delivery.id, attempts, status_code).await;
+            }
+            Ok(response) => (Some(response.status().as_u16()), format!("HTTP {}", response.status())),
+            Err(ex) => (None, error_chain(&ex)),
+        };
+        let retry_at = self.retry_at(attempts);
+        let outcome = if retry_at.is_some() { "retry" } else { "dead" };
+        metrics().webhook_deliveries.with_label_values(&[outcome]).inc();
+        tracing::warn!(delivery_id = delivery.id, url = %webhook.url, attempts, outcome, error, "webhook delivery failed");
+        WebhookDeliveryDao::failed(db, delivery.id, attempts, status_code, error, retry_at).await
+    }
+
+    // None once out of attempts. A delivery retried by hand keeps its attempts, each retry
+    // opens another window of `max_attempts`.
+    fn retry_at(&self, attempts: i64) -> Option<DateTime<Utc>> {
+        let attempt = (attempts - 1).rem_euclid(self.max_attempts) + 1;
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let exponent = (attempt - 1).clamp(0, 20) as u32;
+        let backoff = self
+            .retry_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.retry_max_backoff);
+        Some(Utc::now() + chrono::Duration::milliseconds(backoff.as_millis() as i64))
+    }
+}
+
+/// Runs the dispatcher every `webhook_poll_interval` in the background, unless disabled.
+pub fn start_dispatcher(db: Arc<Db>) -> Result<(), Error> {
+    let config = config();
+    if !config.webhook_dispatch_enabled {
+        tracing::info!("webhook dispatcher disabled");
+        return Ok(());
+    }
+    let dispatcher = Dispatcher::from_config()?;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.webhook_poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            loop {
+                match dispatcher.run_once(&db).await {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(ex) => {
+                        tracing::error!(cause = ?ex, "webhook dispatch failed");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+// The error with its causes, reqwest only displays the outer one.
+fn error_chain(ex: &dyn StdError) -> String {
+    let mut message = ex.to_string();
+    let mut source = ex.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(" - {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+// region:    Error
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Webhook client failed to start - {0}")]
+    FailClient(String),
+}
+// endregion: Error
+
+#[cfg(test)]
+#[path = "../_tests/webhook.rs"]
+mod tests;
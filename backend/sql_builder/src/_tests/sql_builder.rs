@@ -0,0 +1,47 @@
+use super::{FormatSqlValue, SqlBuilder};
+
+#[test]
+fn sql_builder_string_escapes_quotes() {
+    assert_eq!("'Ada'", "Ada".to_string().format_sql_value());
+    assert_eq!("'O''Brien'", "O'Brien".to_string().format_sql_value());
+    assert_eq!("''''''", "''".to_string().format_sql_value());
+    assert_eq!("'O''Brien'", Some("O'Brien".to_string()).format_sql_value());
+    assert_eq!("NULL", None::<String>.format_sql_value());
+}
+
+#[test]
+fn sql_builder_where_keeps_value_in_literal() {
+    let sql = SqlBuilder::new()
+        .select_from("passenger")
+        .where_clause("first_name = {}", "x' OR '1' = '1".to_string())
+        .and_where("last_name = {}", "'; DROP TABLE passenger; --".to_string())
+        .build();
+    assert_eq!(
+        "SELECT * FROM passenger WHERE first_name = 'x'' OR ''1'' = ''1' \
+         AND last_name = '''; DROP TABLE passenger; --'",
+        sql
+    );
+}
+
+#[test]
+fn sql_builder_insert_and_update_escape_values() {
+    let value = "O'Brien".to_string();
+    let insert = SqlBuilder::new()
+        .insert_into("passenger")
+        .columns(&["last_name"])
+        .values(&[&value])
+        .build();
+    assert_eq!(
+        "INSERT INTO passenger (last_name) VALUES ('O''Brien') RETURNING *",
+        insert
+    );
+    let update = SqlBuilder::new()
+        .update("passenger")
+        .set_columns_and_values(&["last_name"], &[&value])
+        .where_clause("id = {}", "1".to_string())
+        .build();
+    assert_eq!(
+        "UPDATE passenger SET last_name = 'O''Brien'  WHERE id = '1' RETURNING *",
+        update
+    );
+}
@@ -17,7 +17,8 @@ pub trait FormatSqlValue {
 
 impl FormatSqlValue for String {
     fn format_sql_value(&self) -> String {
-        format!("'{}'", self)
+        // escape the single quotes so the value can not terminate the literal
+        format!("'{}'", self.replace('\'', "''"))
     }
 }
 
@@ -27,6 +28,38 @@ impl FormatSqlValue for i64 {
     }
 }
 
+impl<T: FormatSqlValue> FormatSqlValue for Option<T> {
+    fn format_sql_value(&self) -> String {
+        match self {
+            Some(value) => value.format_sql_value(),
+            None => "NULL".to_string(),
+        }
+    }
+}
+
+/// Returns the statement with its string literals replaced by `$1`, `$2`, ..., so it can be
+/// logged without the values.
+pub fn template(sql: &str) -> String {
+    let mut template = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut count = 0;
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            template.push(c);
+            continue;
+        }
+        // skip the literal, '' being an escaped quote
+        while let Some(c) = chars.next() {
+            if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                break;
+            }
+        }
+        count += 1;
+        template.push_str(&format!("${}", count));
+    }
+    template
+}
+
 pub struct SqlBuilder {
     query_type: QueryType,       // The type of SQL query (SELECT, INSERT, etc.)
     table: String,               // The table on which the query will be executed
@@ -37,6 +70,13 @@ pub struct SqlBuilder {
     update_values: Vec<String>,
     where_conditions: Vec<String>,
     order_by_column: Option<String>,
+    limit: Option<i64>,
+}
+
+impl Default for SqlBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SqlBuilder {
@@ -53,6 +93,7 @@ impl SqlBuilder {
             update_values: Vec::new(),
             where_conditions: Vec::new(),
             order_by_column: None,
+            limit: None,
         }
     }
 
@@ -119,7 +160,22 @@ impl SqlBuilder {
         self
     }
 
+    // Appends a formatted condition, starting the WHERE clause if there is none yet
+    pub fn and_where<F: FormatSqlValue>(mut self, clause: &str, value: F) -> Self {
+        if self.where_conditions.is_empty() {
+            return self.where_clause(clause, value);
+        }
+        let formatted_clause = clause.replace("{}", &value.format_sql_value());
+        self.where_conditions.push(format!("AND {}", formatted_clause));
+        self
+    }
+
+    // Appends a raw condition, starting the WHERE clause if there is none yet
     pub fn and(mut self, condition: &str) -> Self {
+        if self.where_conditions.is_empty() {
+            self.where_conditions.push(format!(" WHERE {}", condition));
+            return self;
+        }
         self.where_conditions.push(format!("AND {}", condition));
         self
     }
@@ -129,6 +185,11 @@ impl SqlBuilder {
         self
     }
 
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
     pub fn build(&self) -> String {
         match self.query_type {
             QueryType::Select => self.build_select(),
@@ -155,6 +216,9 @@ impl SqlBuilder {
         if let Some(ref order_by_column) = self.order_by_column {
             query.push_str(&format!(" ORDER BY {}", order_by_column));
         }
+        if let Some(limit) = self.limit {
+            query.push_str(&format!(" LIMIT {}", limit));
+        }
         query
     }
 
@@ -197,3 +261,7 @@ impl SqlBuilder {
         format!("TRUNCATE {};", self.table)
     }
 }
+
+#[cfg(test)]
+#[path = "_tests/sql_builder.rs"]
+mod tests;
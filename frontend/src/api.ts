@@ -1,6 +1,15 @@
 type WebMethod = "GET" | "POST" | "DELETE" | "PATCH";
 
 const API_BASE_PATH = "/api";
+const ACCESS_TOKEN_KEY = "access_token";
+const REFRESH_TOKEN_KEY = "refresh_token";
+
+export interface AuthTokens {
+  access_token: string;
+  access_expires_at: string;
+  refresh_token: string;
+  refresh_expires_at: string;
+}
 
 export async function apiGet(path: string, data?: any) {
   return execute("GET", path, data);
@@ -15,21 +24,119 @@ export async function apiDelete(path: string, data?: any) {
   return execute("DELETE", path, data);
 }
 
+// #region    --- Auth
+export function isLoggedIn(): boolean {
+  return localStorage.getItem(ACCESS_TOKEN_KEY) != null;
+}
+
+export async function login(username: string, pwd: string): Promise<boolean> {
+  const response = await send("POST", "auth/login", { username, pwd });
+  if (!response.ok) {
+    return false;
+  }
+  storeTokens((await response.json()).data);
+  return true;
+}
+
+export async function logout() {
+  if (isLoggedIn()) {
+    await send("POST", "auth/logout");
+  }
+  localStorage.removeItem(ACCESS_TOKEN_KEY);
+  localStorage.removeItem(REFRESH_TOKEN_KEY);
+}
+
+// Gets new tokens with the refresh token, which works only once: used twice, the server revokes
+// the session. So the callers needing a refresh at the same time share the one in flight.
+let refreshing: Promise<boolean> | null = null;
+export function refresh(): Promise<boolean> {
+  if (refreshing == null) {
+    refreshing = doRefresh().finally(() => { refreshing = null; });
+  }
+  return refreshing;
+}
+
+async function doRefresh(): Promise<boolean> {
+  const refresh_token = localStorage.getItem(REFRESH_TOKEN_KEY);
+  if (refresh_token == null) {
+    return false;
+  }
+  const response = await send("POST", "auth/refresh", { refresh_token });
+  if (!response.ok) {
+    localStorage.removeItem(ACCESS_TOKEN_KEY);
+    localStorage.removeItem(REFRESH_TOKEN_KEY);
+    return false;
+  }
+  storeTokens((await response.json()).data);
+  return true;
+}
+
+function storeTokens(tokens: AuthTokens) {
+  localStorage.setItem(ACCESS_TOKEN_KEY, tokens.access_token);
+  localStorage.setItem(REFRESH_TOKEN_KEY, tokens.refresh_token);
+}
+// #endregion --- Auth
+
+
+// #region    --- Events
+// Server-Sent Events of the API. EventSource cannot send headers, the token goes in the query.
+// When the server closes the stream (expired access token), reconnects with refreshed tokens
+// and signals a 'reset', as the events in between are lost.
+export function apiEvents(path: string, types: string[], onEvent: (type: string, data: any) => void): () => void {
+  let source: EventSource | null = null;
+  let closed = false;
+  const open = () => {
+    const accessToken = localStorage.getItem(ACCESS_TOKEN_KEY);
+    if (closed || accessToken == null) {
+      return;
+    }
+    source = new EventSource(`${API_BASE_PATH}/${path}?access_token=${encodeURIComponent(accessToken)}`);
+    for (const type of [...types, 'reset']) {
+      source.addEventListener(type, (evt) => {
+        const data = (evt as MessageEvent).data;
+        onEvent(type, data ? JSON.parse(data) : null);
+      });
+    }
+    source.onerror = async () => {
+      // the browser retries by itself, unless the server refused the stream
+      if (source?.readyState === EventSource.CLOSED && await refresh()) {
+        open();
+        onEvent('reset', null);
+      }
+    };
+  };
+  open();
+  return () => {
+    closed = true;
+    source?.close();
+  };
+}
+// #endregion --- Events
 
 async function execute(httpMethod: WebMethod, path: string, data?: any) {
+  let response = await send(httpMethod, path, data);
+  // the access token is short lived, retry once with a refreshed one
+  if (response.status == 401 && await refresh()) {
+    response = await send(httpMethod, path, data);
+  }
+
+  let res = await response.json();
+  return res.data;
+}
+
+async function send(httpMethod: WebMethod, path: string, data?: any) {
   const url = `${API_BASE_PATH}/${path}`;
+  const headers: Record<string, string> = { 'Content-Type': 'application/json' };
+  const accessToken = localStorage.getItem(ACCESS_TOKEN_KEY);
+  if (accessToken != null) {
+    headers['X-Auth-Token'] = accessToken;
+  }
 
-  const response = await fetch(url, {
+  return fetch(url, {
     method: httpMethod,
     mode: 'same-origin',
     cache: 'no-cache',
-    headers: {
-      'Content-Type': 'application/json',
-      'X-Auth-Token': '123'
-    },
+    headers,
     body: JSON.stringify(data)
   });
-
-  let res = await response.json();
-  return res.data;
 }
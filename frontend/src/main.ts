@@ -1,2 +1,3 @@
+import './ui/login-form.js';
 import './ui/passenger-mvc.js';
 import './ui/commons.js';
\ No newline at end of file
@@ -1,5 +1,5 @@
 import { hub } from 'dom-native';
-import { apiGet, apiPatch, apiDelete, apiPost } from '../api';
+import { apiGet, apiPatch, apiDelete, apiPost, apiEvents } from '../api';
 
 export interface Passenger {
     id: string;
@@ -10,7 +10,25 @@ export interface Passenger {
 
 export type PassengerPatch = Partial<Omit<Passenger, 'id'>>;
 
+// server event types to the dataHub topics
+const EVENT_TOPICS: Record<string, string> = { created: 'create', updated: 'update', deleted: 'delete', reset: 'reset' };
+
 class PassengerMco {
+    #stopWatch: (() => void) | null = null;
+
+    // Publishes the changes of the other operators too, as they come from the server.
+    watch() {
+        this.unwatch();
+        this.#stopWatch = apiEvents('passengers/events', ['created', 'updated', 'deleted'], (type, data) => {
+            hub('dataHub').pub('Passenger', EVENT_TOPICS[type], data);
+        });
+    }
+
+    unwatch() {
+        this.#stopWatch?.();
+        this.#stopWatch = null;
+    }
+
 
     async list(): Promise<Passenger[]> {
         const data = await apiGet('passengers');
@@ -0,0 +1,45 @@
+import { BaseHTMLElement, OnEvent, customElement, html, hub, onEvent, scanChild } from "dom-native";
+import { isLoggedIn, login, logout } from '../api';
+
+@customElement("login-form")
+class LoginForm extends BaseHTMLElement {
+    #usernameEl!: HTMLInputElement;
+    #pwdEl!: HTMLInputElement;
+    init() {
+        this.render();
+    }
+
+    render() {
+        let htmlContent: DocumentFragment = isLoggedIn()
+            ? html`<button class="logout">Logout</button>`
+            : html`
+                <input class="username" type="text" placeholder="Username" />
+                <input class="pwd" type="password" placeholder="Password" />
+                <button class="login">Login</button>
+            `;
+        if (!isLoggedIn()) {
+            [this.#usernameEl, this.#pwdEl] = scanChild(htmlContent, "input.username", "input.pwd");
+        }
+        this.innerHTML = "";
+        this.append(htmlContent);
+    }
+
+    // #region    --- UI Events
+    @onEvent('click', 'button.login')
+    async onLogin(evt: MouseEvent & OnEvent) {
+        const ok = await login(this.#usernameEl.value, this.#pwdEl.value);
+        this.classList.toggle("error", !ok);
+        if (ok) {
+            this.render();
+            hub('dataHub').pub('Auth', 'login', {});
+        }
+    }
+
+    @onEvent('click', 'button.logout')
+    async onLogout(evt: MouseEvent & OnEvent) {
+        await logout();
+        this.render();
+        hub('dataHub').pub('Auth', 'logout', {});
+    }
+    // #endregion --- UI Events
+}
@@ -1,4 +1,5 @@
 import { BaseHTMLElement, OnEvent, customElement, first, html, onEvent, onHub, scanChild } from "dom-native";
+import { isLoggedIn } from '../api';
 import { Passenger, passengerMco } from '../model/passenger-mco';
 
 @customElement("passenger-mvc")
@@ -15,10 +16,13 @@ class PassengercMvc extends BaseHTMLElement {
         [this.#passengerInputEl, this.#passengerListEl] = scanChild(htmlContent, "passenger-input", "passenger-list");
         this.append(htmlContent);
         this.refresh();
+        if (isLoggedIn()) {
+            passengerMco.watch();
+        }
     }
 
     async refresh() {
-        let passengers: Passenger[] = await passengerMco.list();
+        let passengers: Passenger[] = (await passengerMco.list()) ?? [];
         let htmlContent = document.createDocumentFragment();
         for (const passenger of passengers) {
             const el = document.createElement("passenger-item") as PassengerItem;
@@ -54,6 +58,29 @@ class PassengercMvc extends BaseHTMLElement {
     onPassengerCreate(data: Passenger) {
         this.refresh();
     }
+
+    @onHub('dataHub', 'Passenger', 'delete')
+    onPassengerDelete(data: Passenger) {
+        first(`passenger-item.Passenger-${data.id}`)?.remove();
+    }
+
+    // events were missed, reload them all
+    @onHub('dataHub', 'Passenger', 'reset')
+    onPassengerReset() {
+        this.refresh();
+    }
+
+    @onHub('dataHub', 'Auth', 'login')
+    onLogin() {
+        this.refresh();
+        passengerMco.watch();
+    }
+
+    @onHub('dataHub', 'Auth', 'logout')
+    onLogout() {
+        passengerMco.unwatch();
+        this.#passengerListEl.innerHTML = "";
+    }
   // #endregion --- Data Events
 }
 }
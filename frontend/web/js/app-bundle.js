@@ -1,39 +1,39 @@
 (function () {
     'use strict';
 
-    /******************************************************************************
-    Copyright (c) Microsoft Corporation.
-
-    Permission to use, copy, modify, and/or distribute this software for any
-    purpose with or without fee is hereby granted.
-
-    THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
-    REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY
-    AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
-    INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
-    LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
-    OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
-    PERFORMANCE OF THIS SOFTWARE.
-    ***************************************************************************** */
-
-    function __decorate(decorators, target, key, desc) {
-        var c = arguments.length, r = c < 3 ? target : desc === null ? desc = Object.getOwnPropertyDescriptor(target, key) : desc, d;
-        if (typeof Reflect === "object" && typeof Reflect.decorate === "function") r = Reflect.decorate(decorators, target, key, desc);
-        else for (var i = decorators.length - 1; i >= 0; i--) if (d = decorators[i]) r = (c < 3 ? d(r) : c > 3 ? d(target, key, r) : d(target, key)) || r;
-        return c > 3 && r && Object.defineProperty(target, key, r), r;
-    }
-
-    function __classPrivateFieldGet(receiver, state, kind, f) {
-        if (kind === "a" && !f) throw new TypeError("Private accessor was defined without a getter");
-        if (typeof state === "function" ? receiver !== state || !f : !state.has(receiver)) throw new TypeError("Cannot read private member from an object whose class did not declare it");
-        return kind === "m" ? f : kind === "a" ? f.call(receiver) : f ? f.value : state.get(receiver);
-    }
-
-    function __classPrivateFieldSet(receiver, state, value, kind, f) {
-        if (kind === "m") throw new TypeError("Private method is not writable");
-        if (kind === "a" && !f) throw new TypeError("Private accessor was defined without a setter");
-        if (typeof state === "function" ? receiver !== state || !f : !state.has(receiver)) throw new TypeError("Cannot write private member to an object whose class did not declare it");
-        return (kind === "a" ? f.call(receiver, value) : f ? f.value = value : state.set(receiver, value)), value;
+    /******************************************************************************
+    Copyright (c) Microsoft Corporation.
+
+    Permission to use, copy, modify, and/or distribute this software for any
+    purpose with or without fee is hereby granted.
+
+    THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+    REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY
+    AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+    INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+    LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+    OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+    PERFORMANCE OF THIS SOFTWARE.
+    ***************************************************************************** */
+
+    function __decorate(decorators, target, key, desc) {
+        var c = arguments.length, r = c < 3 ? target : desc === null ? desc = Object.getOwnPropertyDescriptor(target, key) : desc, d;
+        if (typeof Reflect === "object" && typeof Reflect.decorate === "function") r = Reflect.decorate(decorators, target, key, desc);
+        else for (var i = decorators.length - 1; i >= 0; i--) if (d = decorators[i]) r = (c < 3 ? d(r) : c > 3 ? d(target, key, r) : d(target, key)) || r;
+        return c > 3 && r && Object.defineProperty(target, key, r), r;
+    }
+
+    function __classPrivateFieldGet(receiver, state, kind, f) {
+        if (kind === "a" && !f) throw new TypeError("Private accessor was defined without a getter");
+        if (typeof state === "function" ? receiver !== state || !f : !state.has(receiver)) throw new TypeError("Cannot read private member from an object whose class did not declare it");
+        return kind === "m" ? f : kind === "a" ? f.call(receiver) : f ? f.value : state.get(receiver);
+    }
+
+    function __classPrivateFieldSet(receiver, state, value, kind, f) {
+        if (kind === "m") throw new TypeError("Private method is not writable");
+        if (kind === "a" && !f) throw new TypeError("Private accessor was defined without a setter");
+        if (typeof state === "function" ? receiver !== state || !f : !state.has(receiver)) throw new TypeError("Cannot write private member to an object whose class did not declare it");
+        return (kind === "a" ? f.call(receiver, value) : f ? f.value = value : state.set(receiver, value)), value;
     }
 
     // --------- Object Utils --------- //
@@ -1122,6 +1122,8 @@
     }
 
     const API_BASE_PATH = "/api";
+    const ACCESS_TOKEN_KEY = "access_token";
+    const REFRESH_TOKEN_KEY = "refresh_token";
     async function apiGet(path, data) {
         return execute("GET", path, data);
     }
@@ -1134,23 +1136,183 @@
     async function apiDelete(path, data) {
         return execute("DELETE", path, data);
     }
+    // #region    --- Auth
+    function isLoggedIn() {
+        return localStorage.getItem(ACCESS_TOKEN_KEY) != null;
+    }
+    async function login(username, pwd) {
+        const response = await send("POST", "auth/login", { username, pwd });
+        if (!response.ok) {
+            return false;
+        }
+        storeTokens((await response.json()).data);
+        return true;
+    }
+    async function logout() {
+        if (isLoggedIn()) {
+            await send("POST", "auth/logout");
+        }
+        localStorage.removeItem(ACCESS_TOKEN_KEY);
+        localStorage.removeItem(REFRESH_TOKEN_KEY);
+    }
+    // Gets new tokens with the refresh token, which works only once: used twice, the server revokes
+    // the session. So the callers needing a refresh at the same time share the one in flight.
+    let refreshing = null;
+    function refresh() {
+        if (refreshing == null) {
+            refreshing = doRefresh().finally(() => { refreshing = null; });
+        }
+        return refreshing;
+    }
+    async function doRefresh() {
+        const refresh_token = localStorage.getItem(REFRESH_TOKEN_KEY);
+        if (refresh_token == null) {
+            return false;
+        }
+        const response = await send("POST", "auth/refresh", { refresh_token });
+        if (!response.ok) {
+            localStorage.removeItem(ACCESS_TOKEN_KEY);
+            localStorage.removeItem(REFRESH_TOKEN_KEY);
+            return false;
+        }
+        storeTokens((await response.json()).data);
+        return true;
+    }
+    function storeTokens(tokens) {
+        localStorage.setItem(ACCESS_TOKEN_KEY, tokens.access_token);
+        localStorage.setItem(REFRESH_TOKEN_KEY, tokens.refresh_token);
+    }
+    // #endregion --- Auth
+    // #region    --- Events
+    // Server-Sent Events of the API. EventSource cannot send headers, the token goes in the query.
+    // When the server closes the stream (expired access token), reconnects with refreshed tokens
+    // and signals a 'reset', as the events in between are lost.
+    function apiEvents(path, types, onEvent) {
+        let source = null;
+        let closed = false;
+        const open = () => {
+            const accessToken = localStorage.getItem(ACCESS_TOKEN_KEY);
+            if (closed || accessToken == null) {
+                return;
+            }
+            source = new EventSource(`${API_BASE_PATH}/${path}?access_token=${encodeURIComponent(accessToken)}`);
+            for (const type of [...types, 'reset']) {
+                source.addEventListener(type, (evt) => {
+                    const data = evt.data;
+                    onEvent(type, data ? JSON.parse(data) : null);
+                });
+            }
+            source.onerror = async () => {
+                // the browser retries by itself, unless the server refused the stream
+                if (source?.readyState === EventSource.CLOSED && await refresh()) {
+                    open();
+                    onEvent('reset', null);
+                }
+            };
+        };
+        open();
+        return () => {
+            closed = true;
+            source?.close();
+        };
+    }
+    // #endregion --- Events
     async function execute(httpMethod, path, data) {
+        let response = await send(httpMethod, path, data);
+        // the access token is short lived, retry once with a refreshed one
+        if (response.status == 401 && await refresh()) {
+            response = await send(httpMethod, path, data);
+        }
+        let res = await response.json();
+        return res.data;
+    }
+    async function send(httpMethod, path, data) {
         const url = `${API_BASE_PATH}/${path}`;
-        const response = await fetch(url, {
+        const headers = { 'Content-Type': 'application/json' };
+        const accessToken = localStorage.getItem(ACCESS_TOKEN_KEY);
+        if (accessToken != null) {
+            headers['X-Auth-Token'] = accessToken;
+        }
+        return fetch(url, {
             method: httpMethod,
             mode: 'same-origin',
             cache: 'no-cache',
-            headers: {
-                'Content-Type': 'application/json',
-                'X-Auth-Token': '123'
-            },
+            headers,
             body: JSON.stringify(data)
         });
-        let res = await response.json();
-        return res.data;
     }
 
+    var _LoginForm_usernameEl, _LoginForm_pwdEl;
+    let LoginForm = class LoginForm extends BaseHTMLElement {
+        constructor() {
+            super(...arguments);
+            _LoginForm_usernameEl.set(this, void 0);
+            _LoginForm_pwdEl.set(this, void 0);
+            // #endregion --- UI Events
+        }
+        init() {
+            this.render();
+        }
+        render() {
+            var _a, _b;
+            let htmlContent = isLoggedIn()
+                ? html `<button class="logout">Logout</button>`
+                : html `
+                <input class="username" type="text" placeholder="Username" />
+                <input class="pwd" type="password" placeholder="Password" />
+                <button class="login">Login</button>
+            `;
+            if (!isLoggedIn()) {
+                _a = this, _b = this, [({ set value(_c) { __classPrivateFieldSet(_a, _LoginForm_usernameEl, _c, "f"); } }).value, ({ set value(_c) { __classPrivateFieldSet(_b, _LoginForm_pwdEl, _c, "f"); } }).value] = scanChild(htmlContent, "input.username", "input.pwd");
+            }
+            this.innerHTML = "";
+            this.append(htmlContent);
+        }
+        // #region    --- UI Events
+        async onLogin(evt) {
+            const ok = await login(__classPrivateFieldGet(this, _LoginForm_usernameEl, "f").value, __classPrivateFieldGet(this, _LoginForm_pwdEl, "f").value);
+            this.classList.toggle("error", !ok);
+            if (ok) {
+                this.render();
+                hub('dataHub').pub('Auth', 'login', {});
+            }
+        }
+        async onLogout(evt) {
+            await logout();
+            this.render();
+            hub('dataHub').pub('Auth', 'logout', {});
+        }
+    };
+    _LoginForm_usernameEl = new WeakMap();
+    _LoginForm_pwdEl = new WeakMap();
+    __decorate([
+        onEvent('click', 'button.login')
+    ], LoginForm.prototype, "onLogin", null);
+    __decorate([
+        onEvent('click', 'button.logout')
+    ], LoginForm.prototype, "onLogout", null);
+    LoginForm = __decorate([
+        customElement("login-form")
+    ], LoginForm);
+
+    var _PassengerMco_stopWatch;
+    // server event types to the dataHub topics
+    const EVENT_TOPICS = { created: 'create', updated: 'update', deleted: 'delete', reset: 'reset' };
     class PassengerMco {
+        constructor() {
+            _PassengerMco_stopWatch.set(this, null);
+        }
+        // Publishes the changes of the other operators too, as they come from the server.
+        watch() {
+            this.unwatch();
+            __classPrivateFieldSet(this, _PassengerMco_stopWatch, apiEvents('passengers/events', ['created', 'updated', 'deleted'], (type, data) => {
+                hub('dataHub').pub('Passenger', EVENT_TOPICS[type], data);
+            }), "f");
+        }
+        unwatch() {
+            __classPrivateFieldGet(this, _PassengerMco_stopWatch, "f")?.call(this);
+            __classPrivateFieldSet(this, _PassengerMco_stopWatch, null, "f");
+        }
         async list() {
             const data = await apiGet('passengers');
             return data;
@@ -1182,6 +1344,7 @@
             return oldData;
         }
     }
+    _PassengerMco_stopWatch = new WeakMap();
     // export as singleton
     const passengerMco = new PassengerMco();
 
@@ -1204,9 +1367,12 @@
             _a = this, _b = this, [({ set value(_c) { __classPrivateFieldSet(_a, _PassengercMvc_passengerInputEl, _c, "f"); } }).value, ({ set value(_c) { __classPrivateFieldSet(_b, _PassengercMvc_passengerListEl, _c, "f"); } }).value] = scanChild(htmlContent, "passenger-input", "passenger-list");
             this.append(htmlContent);
             this.refresh();
+            if (isLoggedIn()) {
+                passengerMco.watch();
+            }
         }
         async refresh() {
-            let passengers = await passengerMco.list();
+            let passengers = (await passengerMco.list()) ?? [];
             let htmlContent = document.createDocumentFragment();
             for (const passenger of passengers) {
                 const el = document.createElement("passenger-item");
@@ -1236,6 +1402,21 @@
         onPassengerCreate(data) {
             this.refresh();
         }
+        onPassengerDelete(data) {
+            first(`passenger-item.Passenger-${data.id}`)?.remove();
+        }
+        // events were missed, reload them all
+        onPassengerReset() {
+            this.refresh();
+        }
+        onLogin() {
+            this.refresh();
+            passengerMco.watch();
+        }
+        onLogout() {
+            passengerMco.unwatch();
+            __classPrivateFieldGet(this, _PassengercMvc_passengerListEl, "f").innerHTML = "";
+        }
     };
     _PassengercMvc_passengerInputEl = new WeakMap();
     _PassengercMvc_passengerListEl = new WeakMap();
@@ -1248,6 +1429,18 @@
     __decorate([
         onHub('dataHub', 'Passenger', 'create')
     ], PassengercMvc.prototype, "onPassengerCreate", null);
+    __decorate([
+        onHub('dataHub', 'Passenger', 'delete')
+    ], PassengercMvc.prototype, "onPassengerDelete", null);
+    __decorate([
+        onHub('dataHub', 'Passenger', 'reset')
+    ], PassengercMvc.prototype, "onPassengerReset", null);
+    __decorate([
+        onHub('dataHub', 'Auth', 'login')
+    ], PassengercMvc.prototype, "onLogin", null);
+    __decorate([
+        onHub('dataHub', 'Auth', 'logout')
+    ], PassengercMvc.prototype, "onLogout", null);
     PassengercMvc = __decorate([
         customElement("passenger-mvc")
     ], PassengercMvc);